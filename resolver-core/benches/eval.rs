@@ -0,0 +1,57 @@
+//! Benchmarks of evaluating documents, to measure changes to the engine
+//! against. Run them with `cargo bench -p resolver-core`; a filter, as in
+//! `cargo bench -p resolver-core -- large`, runs only those whose names
+//! contain it. This is a plain harness, not Criterion: each is timed over a
+//! fixed number of iterations and the mean printed, with no saved baseline.
+
+use std::env;
+use std::hint::black_box;
+use std::time;
+
+use resolver_core::exec::Context;
+use resolver_core::render;
+
+/// A representative document: variables, units, currencies, and comments.
+const BUDGET: &str = include_str!("fixtures/budget.rdl");
+/// How many copies of the representative document make a large one.
+const LARGE: usize = 100;
+/// About how long each benchmark runs for.
+const DURATION: time::Duration = time::Duration::from_secs(1);
+
+/// Run a benchmark repeatedly for about `DURATION`, and print how long it
+/// took each time, on average.
+fn measure<T>(name: &str, mut f: impl FnMut() -> T) {
+  if env::args().skip(1).filter(|a| !a.starts_with('-')).any(|a| !name.contains(&a)) {
+    return;
+  }
+  black_box(f()); // warm up
+  let start = time::Instant::now();
+  let mut n = 0;
+  while start.elapsed() < DURATION {
+    black_box(f());
+    n += 1;
+  }
+  println!("{:<40} {:>12.2?} ({} iterations)", name, start.elapsed() / n, n);
+}
+
+/// Evaluate every line of a document in order, as the editor does when it
+/// draws.
+fn evaluate(cxt: &mut Context, lines: &[&str]) -> usize {
+  lines.iter().map(|l| render(cxt, l).len()).sum()
+}
+
+fn main() {
+  let budget: Vec<&str> = BUDGET.lines().collect();
+  let large = BUDGET.repeat(LARGE);
+  let large: Vec<&str> = large.lines().collect();
+
+  measure("evaluate a document", || evaluate(&mut Context::new(), &budget));
+  measure("evaluate a large document", || evaluate(&mut Context::new(), &large));
+
+  // editing the last line needs only it evaluated again, given the context
+  // as it was after the lines before it
+  let (last, before) = large.split_last().expect("The fixture is empty");
+  let mut cxt = Context::new();
+  evaluate(&mut cxt, before);
+  measure("re-evaluate the line edited", || evaluate(&mut cxt.clone(), &[last]));
+}
@@ -99,6 +99,10 @@ pub enum Error {
   TokenNotMatched,
   InvalidASTNode(String),
   UnboundVariable(String),
+  ReadOnlyVariable(String),
+  StaleReference(String),
+  NoQuote(String),
+  IncompatibleUnits(String, String),
   AssertionFailed(AssertionFailed),
   SyntaxError(SyntaxError),
   ParseFloatError(ParseFloatError),
@@ -136,6 +140,10 @@ impl fmt::Display for Error {
       Self::TokenNotMatched => write!(f, "Token not matched"),
       Self::InvalidASTNode(node) => write!(f, "Invalid AST node: {}", node),
       Self::UnboundVariable(name) => write!(f, "No such variable: {}", name),
+      Self::ReadOnlyVariable(name) => write!(f, "Variable is read-only: {}", name),
+      Self::StaleReference(name) => write!(f, "Stale reference: @{}", name),
+      Self::NoQuote(symbol) => write!(f, "No quote for {}", symbol),
+      Self::IncompatibleUnits(a, b) => write!(f, "Incompatible units: {}, {}", a, b),
       Self::AssertionFailed(err) => err.fmt(f),
       Self::SyntaxError(err) => err.fmt(f),
       Self::ParseFloatError(err) => err.fmt(f),
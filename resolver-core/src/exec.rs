@@ -0,0 +1,701 @@
+use std::fmt;
+use std::sync::OnceLock;
+use std::collections::{HashMap, HashSet};
+
+use crate::unit;
+use crate::error;
+use crate::locale::Locale;
+
+static GLOBALS: OnceLock<Vec<(String, unit::Value)>> = OnceLock::new();
+
+/// Install global definitions, which are included in every context created
+/// with the standard library. Globals can only be installed once.
+pub fn set_globals(vars: Vec<(String, unit::Value)>) {
+  let _ = GLOBALS.set(vars);
+}
+
+#[derive(Clone)]
+pub struct Context {
+  vars: HashMap<String, unit::Value>,
+  readonly: HashSet<String>,
+  refs: HashMap<String, unit::Value>,
+  quotes: HashMap<String, unit::Value>, // prices, by symbol
+  format: unit::Format,
+  locale: Locale,
+  rates: Vec<unit::Rate>,
+  strict: bool,
+  steps: Option<Vec<Step>>, // taken while evaluating, if they're being traced
+}
+
+impl Context {
+  pub fn new() -> Context {
+    Context{
+      vars: HashMap::new(),
+      readonly: HashSet::new(),
+      refs: HashMap::new(),
+      quotes: HashMap::new(),
+      format: unit::Format::default(),
+      locale: Locale::default(),
+      rates: Vec::new(),
+      strict: false,
+      steps: None,
+    }
+  }
+  
+  pub fn new_with_stdlib() -> Context {
+    let mut vars = HashMap::new();
+    vars.insert("pi".to_string(), unit::Value::raw(std::f64::consts::PI));
+    vars.insert("tau".to_string(), unit::Value::raw(std::f64::consts::TAU));
+    vars.insert("E".to_string(), unit::Value::raw(std::f64::consts::E));
+    if let Some(globals) = GLOBALS.get() {
+      for (key, val) in globals {
+        vars.insert(key.clone(), *val);
+      }
+    }
+    Context{
+      vars: vars,
+      readonly: HashSet::new(),
+      refs: HashMap::new(),
+      quotes: HashMap::new(),
+      format: unit::Format::default(),
+      locale: Locale::default(),
+      rates: Vec::new(),
+      strict: false,
+      steps: None,
+    }
+  }
+  
+  /// The format in which results are presented.
+  pub fn format(&self) -> unit::Format {
+    self.format
+  }
+  
+  pub fn set_format(&mut self, format: unit::Format) {
+    self.format = format;
+  }
+  
+  /// The conventions by which expressions are read.
+  pub fn locale(&self) -> &Locale {
+    &self.locale
+  }
+  
+  pub fn set_locale(&mut self, locale: Locale) {
+    self.locale = locale;
+  }
+  
+  /// Find a unit by name. A bare dollar sign is the locale's currency.
+  pub fn unit(&self, name: &str) -> Option<unit::Unit> {
+    match name {
+      "$" => Some(unit::Unit::Money(self.locale.currency)),
+      _   => unit::Unit::from(name),
+    }
+  }
+  
+  /// Set the exchange rates between currencies, in order of precedence.
+  pub fn set_rates(&mut self, rates: Vec<unit::Rate>) {
+    self.rates = rates;
+  }
+  
+  /// In strict mode, arithmetic on values with units that cannot be
+  /// converted to each other is an error rather than discarding the units.
+  pub fn set_strict(&mut self, strict: bool) {
+    self.strict = strict;
+  }
+  
+  pub fn set(&mut self, key: &str, val: unit::Value) {
+    self.vars.insert(key.to_string(), val);
+  }
+  
+  /// Bind a read-only variable, which cannot be reassigned by expressions.
+  pub fn bind(&mut self, key: &str, val: unit::Value) {
+    self.vars.insert(key.to_string(), val);
+    self.readonly.insert(key.to_string());
+  }
+  
+  pub fn is_readonly(&self, key: &str) -> bool {
+    self.readonly.contains(key)
+  }
+  
+  /// Bind a variable defined in another document, which is referenced as
+  /// `@document.name`.
+  pub fn bind_reference(&mut self, doc: &str, name: &str, val: unit::Value) {
+    self.refs.insert(format!("{}.{}", doc, name), val);
+  }
+  
+  pub fn reference(&self, key: &str) -> Option<unit::Value> {
+    self.refs.get(key).copied()
+  }
+  
+  /// Set the price of a share of a stock, or the level of an index, by its
+  /// symbol, as in `150 shares of AAPL`.
+  pub fn set_quote(&mut self, symbol: &str, price: unit::Value) {
+    self.quotes.insert(symbol.to_uppercase(), price);
+  }
+  
+  pub fn quote(&self, symbol: &str) -> Option<unit::Value> {
+    self.quotes.get(symbol).copied()
+  }
+  
+  pub fn get(&self, key: &str) -> Option<unit::Value> {
+    match self.vars.get(key) {
+      Some(v) => Some(*v),
+      None => None,
+    }
+  }
+  
+  /// Record the steps taken to evaluate expressions from now on, or stop
+  /// recording them; see `take_steps`.
+  pub fn trace(&mut self, on: bool) {
+    self.steps = on.then(Vec::new);
+  }
+  
+  /// Take the steps recorded since they were last taken, in the order
+  /// they were taken.
+  pub fn take_steps(&mut self) -> Vec<Step> {
+    self.steps.as_mut().map(std::mem::take).unwrap_or_default()
+  }
+  
+  fn record(&mut self, step: Step) {
+    if let Some(steps) = &mut self.steps {
+      steps.push(step);
+    }
+  }
+  
+  /// Produce every variable binding, ordered by name.
+  pub fn vars(&self) -> Vec<(&str, unit::Value)> {
+    let mut vars: Vec<(&str, unit::Value)> = self.vars.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    vars.sort_by(|a, b| a.0.cmp(b.0));
+    vars
+  }
+}
+
+/// Something evaluating an expression drew on, which shows how its result
+/// was arrived at: the value of a variable or a quote, or a conversion
+/// between units or exchange between currencies made along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+  Variable(String, unit::Value),
+  Reference(String, unit::Value),
+  Quote(String, unit::Value),
+  Conversion(unit::Value, unit::Value),
+  Exchange(unit::Value, unit::Value, unit::Rate),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NType {
+  Ident,
+  Reference,
+  Quote,
+  Number,
+  Assign,
+  Typecast,
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Mod,
+}
+
+impl fmt::Display for NType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NType::Ident    => write!(f, "ident"),
+      NType::Reference => write!(f, "reference"),
+      NType::Quote    => write!(f, "quote"),
+      NType::Number   => write!(f, "value"),
+      NType::Assign   => write!(f, "="),
+      NType::Typecast => write!(f, ":"),
+      NType::Add      => write!(f, "+"),
+      NType::Sub      => write!(f, "-"),
+      NType::Mul      => write!(f, "*"),
+      NType::Div      => write!(f, "/"),
+      NType::Mod      => write!(f, "%"),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Node {
+  ntype: NType,
+  left:  Option<Box<Node>>,
+  right: Option<Box<Node>>,
+  text:  Option<String>,
+  value: Option<f64>,
+}
+
+impl fmt::Display for Node {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.print() {
+      Ok(out)  => write!(f, "{}", out),
+      Err(err) => write!(f, "error: {}", err),
+    }
+  }
+}
+
+impl Node {
+  pub fn new_ident(name: &str) -> Node {
+    Node{
+      ntype: NType::Ident,
+      left: None, right: None,
+      text: Some(name.to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_reference(name: &str) -> Node {
+    Node{
+      ntype: NType::Reference,
+      left: None, right: None,
+      text: Some(name.to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_quote(symbol: &str) -> Node {
+    Node{
+      ntype: NType::Quote,
+      left: None, right: None,
+      text: Some(symbol.to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_number(value: f64) -> Node {
+    Node{
+      ntype: NType::Number,
+      left: None, right: None,
+      text: None,
+      value: Some(value),
+    }
+  }
+  
+  pub fn new_assign(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Assign,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("=".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_typecast(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Typecast,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some(":".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_add(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Add,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("+".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_sub(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Sub,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("-".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_mul(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Mul,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("*".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_div(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Div,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("/".to_string()),
+      value: None,
+    }
+  }
+  
+  pub fn new_mod(left: Node, right: Node) -> Node {
+    Node{
+      ntype: NType::Mod,
+      left: Some(Box::new(left)), right: Some(Box::new(right)),
+      text: Some("%".to_string()),
+      value: None,
+    }
+  }
+  
+  fn text<'a>(&'a self) -> Result<&'a str, error::Error> {
+    match &self.text {
+      Some(text) => Ok(text),
+      None => Err(error::Error::InvalidASTNode(format!("{}: Expected text", self.ntype))),
+    }
+  }
+  
+  fn value(&self) -> Result<unit::Value, error::Error> {
+    match self.value {
+      Some(value) => Ok(unit::Value::raw(value)),
+      None => Err(error::Error::InvalidASTNode(format!("{}: Expected value", self.ntype))),
+    }
+  }
+  
+  fn left<'a>(&'a self) -> Result<&'a Box<Node>, error::Error> {
+    match &self.left {
+      Some(left) => Ok(left),
+      None => Err(error::Error::InvalidASTNode(format!("{}: Expected left child", self.ntype))),
+    }
+  }
+  
+  fn right<'a>(&'a self) -> Result<&'a Box<Node>, error::Error> {
+    match &self.right {
+      Some(right) => Ok(right),
+      None => Err(error::Error::InvalidASTNode(format!("{}: Expected right child", self.ntype))),
+    }
+  }
+  
+  /// Produce every reference to another document made by this node or its
+  /// descendants.
+  pub fn references(&self) -> Vec<&str> {
+    self.texts(NType::Reference)
+  }
+  
+  /// Produce the symbol of every quote this node or its descendants need.
+  pub fn quotes(&self) -> Vec<&str> {
+    self.texts(NType::Quote)
+  }
+  
+  /// Produce the name of the variable this node assigns, if it is an
+  /// assignment.
+  pub fn assigns(&self) -> Option<&str> {
+    match self.ntype {
+      NType::Assign => self.left.as_ref().and_then(|l| l.text.as_deref()),
+      _ => None,
+    }
+  }
+  
+  /// Produce the text of every node of a type, this one or a descendant.
+  fn texts(&self, ntype: NType) -> Vec<&str> {
+    let mut texts = Vec::new();
+    if self.ntype == ntype {
+      if let Some(text) = &self.text {
+        texts.push(text.as_str());
+      }
+    }
+    if let Some(left) = &self.left {
+      texts.append(&mut left.texts(ntype));
+    }
+    if let Some(right) = &self.right {
+      texts.append(&mut right.texts(ntype));
+    }
+    texts
+  }
+  
+  pub fn exec(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    match self.ntype {
+      NType::Ident    => self.exec_ident(cxt),
+      NType::Reference => self.exec_reference(cxt),
+      NType::Quote    => self.exec_quote(cxt),
+      NType::Number   => self.exec_number(cxt),
+      NType::Assign   => self.exec_assign(cxt),
+      NType::Typecast => self.exec_typecast(cxt),
+      NType::Add | NType::Sub | NType::Mul | NType::Div | NType::Mod => self.exec_arith(cxt),
+    }
+  }
+  
+  fn exec_ident(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let name = self.text()?;
+    match cxt.get(&name) {
+      Some(v) => {
+        cxt.record(Step::Variable(name.to_owned(), v));
+        Ok(v)
+      },
+      None => Err(error::Error::UnboundVariable(name.to_owned())),
+    }
+  }
+  
+  fn exec_reference(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let name = self.text()?;
+    match cxt.reference(name) {
+      Some(v) => {
+        cxt.record(Step::Reference(name.to_owned(), v));
+        Ok(v)
+      },
+      None => Err(error::Error::StaleReference(name.to_owned())),
+    }
+  }
+  
+  fn exec_quote(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let symbol = self.text()?;
+    match cxt.quote(symbol) {
+      Some(v) => {
+        cxt.record(Step::Quote(symbol.to_owned(), v));
+        Ok(v)
+      },
+      None => Err(error::Error::NoQuote(symbol.to_owned())),
+    }
+  }
+  
+  fn exec_number(&self, _cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    self.value()
+  }
+  
+  fn exec_assign(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let left = self.left()?;
+    let right = self.right()?;
+    let ident = match left.ntype {
+      NType::Ident => left.text()?,
+      _ => return Err(error::Error::InvalidASTNode(format!("{}: Expected identifier as left child, got: {}", self.ntype, left.ntype))),
+    };
+    if cxt.is_readonly(ident) {
+      return Err(error::Error::ReadOnlyVariable(ident.to_owned()));
+    }
+    let right = match right.exec(cxt) {
+      Ok(right) => right,
+      Err(err) => return Err(error::Error::InvalidASTNode(format!("{}: Could not exec right: {}", self.ntype, err))),
+    };
+    cxt.set(ident, right);
+    Ok(right)
+  }
+  
+  fn exec_typecast(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let left = self.left()?;
+    let right = self.right()?;
+    let tname = match right.ntype {
+      NType::Ident => right.text()?,
+      _ => return Err(error::Error::InvalidASTNode(format!("{}: Expected identifier as right child, got: {}", self.ntype, right.ntype))),
+    };
+    let left = match left.exec(cxt) {
+      Ok(left) => left,
+      Err(err) => return Err(error::Error::InvalidASTNode(format!("{}: Could not exec left: {}", self.ntype, err))),
+    };
+    let to = cxt.unit(tname);
+    if let Some(conv) = left.convert(to) {
+      if left.unit().is_some() && left.unit() != to {
+        cxt.record(Step::Conversion(left, conv));
+      }
+      return Ok(conv);
+    }
+    Ok(match (left.exchange(to, &cxt.rates), left.exchange_rate(to, &cxt.rates)) {
+      (Some(conv), Some(rate)) => {
+        cxt.record(Step::Exchange(left, conv, rate));
+        conv
+      },
+      _ => left,
+    })
+  }
+  
+  fn exec_arith(&self, cxt: &mut Context) -> Result<unit::Value, error::Error> {
+    let left = match self.left()?.exec(cxt) {
+      Ok(left) => left,
+      Err(err) => return Err(error::Error::InvalidASTNode(format!("{}: Could not exec left: {}", self.ntype, err))),
+    };
+    let right = match self.right()?.exec(cxt) {
+      Ok(right) => right,
+      Err(err) => return Err(error::Error::InvalidASTNode(format!("{}: Could not exec right: {}", self.ntype, err))),
+    };
+    let left = match (left.exchange(right.unit(), &cxt.rates), left.exchange_rate(right.unit(), &cxt.rates)) {
+      (Some(conv), Some(rate)) => {
+        cxt.record(Step::Exchange(left, conv, rate));
+        conv
+      },
+      _ => left,
+    };
+    if left.unit().is_some() && right.unit().is_some() && left.unit() != right.unit() {
+      if let Some(conv) = left.convert(right.unit()) {
+        cxt.record(Step::Conversion(left, conv)); // as arithmetic does, to the unit on the right
+      }
+    }
+    if cxt.strict && !left.is_compatible(right.unit()) {
+      return Err(error::Error::IncompatibleUnits(format!("{}", left), format!("{}", right)));
+    }
+    match self.ntype {
+      NType::Add => Ok(left + right),
+      NType::Sub => Ok(left - right),
+      NType::Mul => Ok(left * right),
+      NType::Div => Ok(left / right),
+      NType::Mod => Ok(left % right),
+      _ => Err(error::Error::InvalidASTNode(format!("{}: Unsupported operation", self.ntype))),
+    }
+  }
+  
+  pub fn print(&self) -> Result<String, error::Error> {
+    match self.ntype {
+      NType::Ident    => self.print_ident(),
+      NType::Reference => self.print_reference(),
+      NType::Quote    => self.print_ident(),
+      NType::Number   => self.print_number(),
+      NType::Assign   => self.print_assign(),
+      NType::Typecast => self.print_typecast(),
+      NType::Add | NType::Sub | NType::Mul | NType::Div | NType::Mod => self.print_arith(),
+    }
+  }
+  
+  fn print_ident(&self) -> Result<String, error::Error> {
+    Ok(self.text()?.to_owned())
+  }
+  
+  fn print_reference(&self) -> Result<String, error::Error> {
+    Ok(format!("@{}", self.text()?))
+  }
+  
+  fn print_number(&self) -> Result<String, error::Error> {
+    Ok(format!("{}", self.value()?))
+  }
+  
+  fn print_arith(&self) -> Result<String, error::Error> {
+    Ok(format!("({} {} {})", self.left()?.print()?, self.ntype, self.right()?.print()?))
+  }
+  
+  fn print_assign(&self) -> Result<String, error::Error> {
+    Ok(format!("({} {} {})", self.left()?.print()?, self.ntype, self.right()?.print()?))
+  }
+  
+  fn print_typecast(&self) -> Result<String, error::Error> {
+    Ok(format!("{}({})", self.right()?.print()?, self.left()?.print()?))
+  }
+  
+  /// Write the expression as it would be typed, with the variables it
+  /// refers to replaced by their values in a context, which it hasn't yet
+  /// been evaluated in, formatted as the context formats them. The variable
+  /// an expression assigns is left alone, as are variables which aren't
+  /// defined.
+  pub fn substitute(&self, cxt: &Context) -> Result<String, error::Error> {
+    match self.ntype {
+      NType::Ident => {
+        let name = self.text()?;
+        Ok(cxt.get(name).map_or(name.to_owned(), |v| v.format(&cxt.format())))
+      },
+      NType::Reference => {
+        let name = self.text()?;
+        Ok(cxt.reference(name).map_or(format!("@{}", name), |v| v.format(&cxt.format())))
+      },
+      NType::Quote => {
+        let symbol = self.text()?;
+        Ok(cxt.quote(symbol).map_or(symbol.to_owned(), |v| v.format(&cxt.format())))
+      },
+      NType::Number => self.print_number(),
+      NType::Assign => Ok(format!("{} = {}", self.left()?.text()?, self.right()?.substitute(cxt)?)),
+      NType::Typecast => {
+        let (left, unit) = (self.left()?, self.right()?.text()?);
+        match left.ntype {
+          NType::Number => Ok(format!("{} {}", left.substitute(cxt)?, unit)),
+          _ => Ok(format!("{} in {}", left.substitute_within(self, false, cxt)?, unit)),
+        }
+      },
+      NType::Add | NType::Sub | NType::Mul | NType::Div | NType::Mod => Ok(format!("{} {} {}",
+        self.left()?.substitute_within(self, false, cxt)?,
+        self.ntype,
+        self.right()?.substitute_within(self, true, cxt)?,
+      )),
+    }
+  }
+  
+  /// Substitute a child of another node, in parentheses if it binds less
+  /// tightly than its parent.
+  fn substitute_within(&self, parent: &Node, right: bool, cxt: &Context) -> Result<String, error::Error> {
+    let (mine, theirs) = (self.precedence(), parent.precedence());
+    let text = self.substitute(cxt)?;
+    if mine > 0 && (mine < theirs || (right && mine == theirs)) {
+      Ok(format!("({})", text))
+    }else{
+      Ok(text)
+    }
+  }
+  
+  fn precedence(&self) -> u8 {
+    match self.ntype {
+      NType::Assign => 1,
+      NType::Typecast if self.left().is_ok_and(|l| l.ntype == NType::Number) => 0, // a quantity, as in 5 kg
+      NType::Typecast => 2,
+      NType::Add | NType::Sub => 3,
+      NType::Mul | NType::Div | NType::Mod => 4,
+      NType::Ident | NType::Reference | NType::Quote | NType::Number => 0, // never in parentheses
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn exec_simple() {
+    let mut cxt = Context::new();
+    cxt.set("a", unit::Value::raw(1.0));
+    cxt.set("b", unit::Value::raw(2.0));
+    cxt.set("c", unit::Value::raw(3.0));
+    
+    let n = Node::new_ident("a");
+    assert_eq!(Ok(unit::Value::raw(1.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_number(1.25);
+    assert_eq!(Ok(unit::Value::raw(1.25)), n.exec(&mut cxt));
+    
+    let n = Node::new_add(Node::new_ident("a"), Node::new_ident("b"));
+    assert_eq!(Ok(unit::Value::raw(3.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_sub(Node::new_ident("a"), Node::new_ident("c"));
+    assert_eq!(Ok(unit::Value::raw(-2.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_mul(Node::new_ident("a"), Node::new_ident("c"));
+    assert_eq!(Ok(unit::Value::raw(3.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_div(Node::new_ident("a"), Node::new_ident("b"));
+    assert_eq!(Ok(unit::Value::raw(0.5)), n.exec(&mut cxt));
+    
+    let n = Node::new_mod(Node::new_ident("c"), Node::new_ident("b"));
+    assert_eq!(Ok(unit::Value::raw(1.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_assign(Node::new_ident("d"), Node::new_number(123.0));
+    assert_eq!(Ok(unit::Value::raw(123.0)), n.exec(&mut cxt));
+    
+    let n = Node::new_typecast(Node::new_ident("d"), Node::new_ident("kg"));
+    assert_eq!(Ok(unit::Value::new(123.0, unit::Unit::Kilogram)), n.exec(&mut cxt));
+    
+    cxt.set_locale(Locale{currency: unit::Currency::Chf, ..Locale::default()});
+    let n = Node::new_typecast(Node::new_number(5.0), Node::new_ident("$"));
+    assert_eq!(Ok(unit::Value::new(5.0, unit::Unit::Money(unit::Currency::Chf))), n.exec(&mut cxt));
+  }
+  
+  #[test]
+  fn exec_trace() {
+    let (eur, usd) = (unit::Unit::Money(unit::Currency::Eur), unit::Unit::Money(unit::Currency::Usd));
+    let mut cxt = Context::new();
+    cxt.set("rent", unit::Value::new(1200.0, eur));
+    cxt.set_rates(unit::Rate::parse("EUR/USD = 1.25").into_iter().collect());
+    
+    let n = Node::new_typecast(Node::new_mul(Node::new_ident("rent"), Node::new_number(2.0)), Node::new_ident("USD"));
+    assert_eq!(Ok("1200 EUR * 2 in USD".to_string()), n.substitute(&cxt));
+    cxt.trace(true);
+    assert_eq!(Ok(unit::Value::new(3000.0, usd)), n.exec(&mut cxt));
+    assert_eq!(vec![
+      Step::Variable("rent".to_string(), unit::Value::new(1200.0, eur)),
+      Step::Exchange(unit::Value::new(2400.0, eur), unit::Value::new(3000.0, usd), cxt.rates[0]),
+    ], cxt.take_steps());
+    assert!(cxt.take_steps().is_empty());
+    
+    let n = Node::new_add(Node::new_typecast(Node::new_number(1.0), Node::new_ident("kg")), Node::new_typecast(Node::new_number(500.0), Node::new_ident("g")));
+    assert_eq!(Ok("1 kg + 500 g".to_string()), n.substitute(&cxt));
+    assert_eq!(Ok(unit::Value::new(1500.0, unit::Unit::Gram)), n.exec(&mut cxt));
+    assert_eq!(vec![Step::Conversion(unit::Value::new(1.0, unit::Unit::Kilogram), unit::Value::new(1000.0, unit::Unit::Gram))], cxt.take_steps());
+    
+    let n = Node::new_assign(Node::new_ident("x"), Node::new_mul(Node::new_add(Node::new_number(1.0), Node::new_ident("y")), Node::new_sub(Node::new_number(2.0), Node::new_number(3.0))));
+    assert_eq!(Ok("x = (1 + y) * (2 - 3)".to_string()), n.substitute(&cxt));
+    assert_eq!(Some("x"), n.assigns());
+    let n = Node::new_sub(Node::new_number(1.0), Node::new_sub(Node::new_number(2.0), Node::new_number(3.0)));
+    assert_eq!(Ok("1 - (2 - 3)".to_string()), n.substitute(&cxt));
+    assert_eq!(None, n.assigns());
+    
+    cxt.trace(false);
+    n.exec(&mut cxt).unwrap();
+    assert!(cxt.take_steps().is_empty());
+  }
+  
+}
@@ -0,0 +1,266 @@
+#[cfg(feature = "fs")]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+
+use crate::unit;
+use crate::error;
+use crate::exec::Context;
+use crate::scan::Scanner;
+use crate::parse::Parser;
+
+const DIRECTIVE: &str = "use";
+
+/// Variables imported from external files by `use "file"` directives.
+///
+/// Files are read the first time they are used and cached until they are
+/// explicitly reloaded, so a document can be re-evaluated on every change
+/// without re-reading the files it imports.
+pub struct Imports {
+  base: PathBuf,
+  cache: HashMap<PathBuf, Vec<(String, unit::Value)>>,
+  hits: usize, // how many times a file was found in the cache
+  misses: usize, // and how many times it was read
+}
+
+impl Imports {
+  /// Create a set of imports which resolves relative paths against the
+  /// provided base directory.
+  pub fn new(base: &Path) -> Imports {
+    Imports{
+      base: base.to_owned(),
+      cache: HashMap::new(),
+      hits: 0,
+      misses: 0,
+    }
+  }
+
+  /// Create a set of imports which resolves relative paths against the
+  /// directory containing the provided document.
+  pub fn new_for_document(doc: &Path) -> Imports {
+    match doc.parent() {
+      Some(dir) => Self::new(dir),
+      None => Self::new(Path::new("")),
+    }
+  }
+
+  /// Discard cached files so they are read again the next time they are used.
+  pub fn reload(&mut self) {
+    self.cache.clear();
+  }
+
+  /// If the line of text is a `use` directive, bind every variable in the
+  /// file it names as a read-only variable in the context and produce the
+  /// number of variables bound. If the line is not a directive, produce
+  /// nothing.
+  pub fn apply(&mut self, cxt: &mut Context, text: &str) -> Option<Result<usize, error::Error>> {
+    let path = self.base.join(directive(text)?);
+    Some(self.bind(cxt, path))
+  }
+
+  /// How many times a file that was imported was found in the cache, and
+  /// how many times it had to be read, since the imports were created.
+  pub fn lookups(&self) -> (usize, usize) {
+    (self.hits, self.misses)
+  }
+
+  fn bind(&mut self, cxt: &mut Context, path: PathBuf) -> Result<usize, error::Error> {
+    if self.cache.contains_key(&path) {
+      self.hits += 1;
+    }else{
+      self.misses += 1;
+      let vars = load(&path)?;
+      self.cache.insert(path.clone(), vars);
+    }
+    let vars = &self.cache[&path];
+    for (key, val) in vars {
+      cxt.bind(key, *val);
+    }
+    Ok(vars.len())
+  }
+}
+
+/// Determine whether a line of text is a `use "file"` directive and, if it
+/// is, produce the path it names.
+pub fn directive(text: &str) -> Option<&str> {
+  let rest = text.trim().strip_prefix(DIRECTIVE)?;
+  if !rest.starts_with(char::is_whitespace) {
+    return None;
+  }
+  let path = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+  if path.is_empty() || path.contains('"') {
+    None
+  }else{
+    Some(path)
+  }
+}
+
+#[cfg(feature = "fs")]
+fn read(path: &Path) -> Result<String, error::Error> {
+  match fs::read_to_string(path) {
+    Ok(data) => Ok(data),
+    Err(err) => Err(error::IOError::new(&format!("{}: {}", path.display(), err)).into()),
+  }
+}
+
+#[cfg(not(feature = "fs"))]
+fn read(path: &Path) -> Result<String, error::Error> {
+  Err(error::IOError::new(&format!("{}: Files cannot be read in this build", path.display())).into())
+}
+
+/// Load variables from a TOML, JSON, or .env file. The format is determined
+/// by the file extension. Nested keys are joined with underscores and values
+/// that are not numbers or quantities (e.g., "5 kg") are ignored.
+pub fn load(path: &Path) -> Result<Vec<(String, unit::Value)>, error::Error> {
+  let data = read(path)?;
+  let ext = path.extension().and_then(|e| e.to_str());
+  let name = path.file_name().and_then(|e| e.to_str());
+  let mut vars = match (ext, name) {
+    (Some("toml"), _) => parse_toml(&data)?,
+    (Some("json"), _) => parse_json(&data)?,
+    (Some("env"), _) | (_, Some(".env")) => parse_env(&data),
+    _ => return Err(error::IOError::new(&format!("{}: Unsupported file format", path.display())).into()),
+  };
+  vars.sort_by(|a, b| a.0.cmp(&b.0));
+  Ok(vars)
+}
+
+fn parse_toml(data: &str) -> Result<Vec<(String, unit::Value)>, error::Error> {
+  let table: toml::Value = match data.parse() {
+    Ok(table) => table,
+    Err(err) => return Err(error::IOError::new(&format!("Invalid TOML: {}", err)).into()),
+  };
+  let mut vars = Vec::new();
+  flatten_toml(&mut vars, None, &table);
+  Ok(vars)
+}
+
+fn flatten_toml(vars: &mut Vec<(String, unit::Value)>, key: Option<String>, val: &toml::Value) {
+  match (key, val) {
+    (key, toml::Value::Table(table)) => {
+      for (k, v) in table {
+        flatten_toml(vars, Some(join(&key, k)), v);
+      }
+    },
+    (Some(key), toml::Value::Integer(v)) => vars.push((key, unit::Value::raw(*v as f64))),
+    (Some(key), toml::Value::Float(v)) => vars.push((key, unit::Value::raw(*v))),
+    (Some(key), toml::Value::String(v)) => if let Some(v) = value(v) { vars.push((key, v)) },
+    _ => {},
+  }
+}
+
+fn parse_json(data: &str) -> Result<Vec<(String, unit::Value)>, error::Error> {
+  let obj: serde_json::Value = match serde_json::from_str(data) {
+    Ok(obj) => obj,
+    Err(err) => return Err(error::IOError::new(&format!("Invalid JSON: {}", err)).into()),
+  };
+  let mut vars = Vec::new();
+  flatten_json(&mut vars, None, &obj);
+  Ok(vars)
+}
+
+fn flatten_json(vars: &mut Vec<(String, unit::Value)>, key: Option<String>, val: &serde_json::Value) {
+  match (key, val) {
+    (key, serde_json::Value::Object(obj)) => {
+      for (k, v) in obj {
+        flatten_json(vars, Some(join(&key, k)), v);
+      }
+    },
+    (Some(key), serde_json::Value::Number(v)) => if let Some(v) = v.as_f64() { vars.push((key, unit::Value::raw(v))) },
+    (Some(key), serde_json::Value::String(v)) => if let Some(v) = value(v) { vars.push((key, v)) },
+    _ => {},
+  }
+}
+
+fn parse_env(data: &str) -> Vec<(String, unit::Value)> {
+  let mut vars = Vec::new();
+  for line in data.lines() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    if let Some((key, val)) = line.split_once('=') {
+      let val = val.trim().trim_matches(|c| c == '"' || c == '\'');
+      if let Some(val) = value(val) {
+        vars.push((key.trim().to_string(), val));
+      }
+    }
+  }
+  vars
+}
+
+fn join(prefix: &Option<String>, key: &str) -> String {
+  match prefix {
+    Some(prefix) => format!("{}_{}", prefix, key),
+    None => key.to_string(),
+  }
+}
+
+/// Evaluate a string value as a standalone expression, so that quantities
+/// with units can be imported.
+fn value(text: &str) -> Option<unit::Value> {
+  let mut p = Parser::new(Scanner::new(text));
+  let exp = p.parse().ok()?;
+  if exp.range.start != 0 || exp.range.end < text.trim_end().len() {
+    return None; // only part of the text is an expression
+  }
+  exp.ast.exec(&mut Context::new()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_directive() {
+    assert_eq!(Some("constants.toml"), directive("use \"constants.toml\""));
+    assert_eq!(Some("../shared/rates.json"), directive("  use   \"../shared/rates.json\"  "));
+    assert_eq!(None, directive("use constants.toml"));
+    assert_eq!(None, directive("user \"constants.toml\""));
+    assert_eq!(None, directive("use \"\""));
+    assert_eq!(None, directive("we use \"constants.toml\" here"));
+  }
+
+  #[test]
+  fn parse_formats() {
+    let vars = parse_toml("tax = 0.0875\nname = \"Acme\"\n[team]\nsize = 12\nweight = \"5 kg\"\n").unwrap();
+    let vars: Vec<(&str, String)> = vars.iter().map(|(k, v)| (k.as_str(), v.to_string())).collect();
+    assert_eq!(vec![("tax", "0.0875".to_string()), ("team_size", "12".to_string()), ("team_weight", "5 kg".to_string())], vars);
+
+    let vars = parse_json("{\"tax\": 0.0875, \"team\": {\"size\": 12, \"leads\": [1, 2]}, \"ok\": true}").unwrap();
+    let vars: Vec<(&str, String)> = vars.iter().map(|(k, v)| (k.as_str(), v.to_string())).collect();
+    assert_eq!(vec![("tax", "0.0875".to_string()), ("team_size", "12".to_string())], vars);
+
+    let vars = parse_env("# rates\nTAX=0.0875\nexport HEADCOUNT = \"12\"\nNAME=Acme\n");
+    let vars: Vec<(&str, String)> = vars.iter().map(|(k, v)| (k.as_str(), v.to_string())).collect();
+    assert_eq!(vec![("TAX", "0.0875".to_string()), ("HEADCOUNT", "12".to_string())], vars);
+  }
+
+  #[test]
+  #[cfg(feature = "fs")]
+  fn bind_read_only() {
+    let dir = std::env::temp_dir().join(format!("resolver-import-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("constants.toml"), "tax = 0.5\n").unwrap();
+
+    let mut imports = Imports::new(&dir);
+    let mut cxt = Context::new();
+    assert_eq!(None, imports.apply(&mut cxt, "tax = 2"));
+    assert_eq!(Some(Ok(1)), imports.apply(&mut cxt, "use \"constants.toml\""));
+    assert_eq!(Some(unit::Value::raw(0.5)), cxt.get("tax"));
+    assert!(crate::eval(&mut cxt, "tax = 2")[0].result.is_err());
+
+    fs::write(dir.join("constants.toml"), "tax = 0.25\n").unwrap();
+    imports.apply(&mut cxt, "use \"constants.toml\"");
+    assert_eq!(Some(unit::Value::raw(0.5)), cxt.get("tax")); // cached
+    imports.reload();
+    imports.apply(&mut cxt, "use \"constants.toml\"");
+    assert_eq!(Some(unit::Value::raw(0.25)), cxt.get("tax"));
+    assert_eq!((1, 2), imports.lookups());
+
+    assert!(matches!(imports.apply(&mut cxt, "use \"missing.toml\""), Some(Err(_))));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+}
@@ -0,0 +1,97 @@
+use std::ops;
+
+use serde::Serialize;
+
+use crate::exec;
+
+/// The result of a successfully evaluated expression.
+#[derive(Debug, Serialize)]
+pub struct Output {
+  pub expr: String,
+  pub span: ops::Range<usize>,
+  pub value: f64,
+  pub unit: Option<String>,
+  pub display: String,
+}
+
+/// An error produced while evaluating an expression.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+  pub expr: String,
+  pub span: ops::Range<usize>,
+  pub message: String,
+}
+
+/// The structured output for a single line of input.
+#[derive(Debug, Serialize)]
+pub struct Line {
+  pub line: usize,
+  pub text: String,
+  pub results: Vec<Output>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Line {
+  pub fn has_results(&self) -> bool {
+    !self.results.is_empty()
+  }
+  
+  pub fn has_errors(&self) -> bool {
+    !self.diagnostics.is_empty()
+  }
+  
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).expect("Could not encode line")
+  }
+}
+
+/// Evaluate a line of text and produce its structured results. Spans are
+/// byte offsets into the line text. Results are displayed as they're
+/// rendered, in the context's format and any the line sets.
+pub fn eval_line(cxt: &mut exec::Context, num: usize, text: &str) -> Line {
+  let mut results: Vec<Output> = Vec::new();
+  let mut diagnostics: Vec<Diagnostic> = Vec::new();
+  let (_, fmt) = crate::split_line(cxt, text);
+  for e in crate::eval(cxt, text) {
+    let expr = e.text(text).trim().to_string();
+    match &e.result {
+      Ok(res) => results.push(Output{
+        expr,
+        span: e.range.clone(),
+        value: res.value(),
+        unit: res.unit().map(|u| u.to_string()),
+        display: res.format(&fmt),
+      }),
+      Err(err) => diagnostics.push(Diagnostic{
+        expr,
+        span: e.range.clone(),
+        message: err.to_string(),
+      }),
+    };
+  }
+  Line{
+    line: num,
+    text: text.to_string(),
+    results,
+    diagnostics,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn encode_line() {
+    let mut cxt = exec::Context::new();
+    assert_eq!(
+      r#"{"line":1,"text":"2 cups in tbsp","results":[{"expr":"2 cups in tbsp","span":{"start":0,"end":14},"value":32.0,"unit":"tbsp","display":"32 tbsp"}],"diagnostics":[]}"#,
+      eval_line(&mut cxt, 1, "2 cups in tbsp").to_json()
+    );
+    assert_eq!(
+      r#"{"line":2,"text":"x, 1","results":[{"expr":"1","span":{"start":3,"end":4},"value":1.0,"unit":null,"display":"1"}],"diagnostics":[{"expr":"x","span":{"start":0,"end":1},"message":"No such variable: x"}]}"#,
+      eval_line(&mut cxt, 2, "x, 1").to_json()
+    );
+  }
+  
+}
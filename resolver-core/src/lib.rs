@@ -0,0 +1,271 @@
+//! The engine of Resolver, the notepad calculator: scanning, parsing, and
+//! evaluating expressions with units and currencies, independent of any
+//! interface to them.
+//!
+//! The lines of a document are evaluated in order in one `exec::Context`,
+//! so that a line can refer to the variables defined by those before it.
+//!
+//! ```
+//! use resolver_core::{eval, exec::Context};
+//!
+//! let mut cxt = Context::new();
+//! eval(&mut cxt, "rent = 1200 USD");
+//! let res = eval(&mut cxt, "rent * 12");
+//! assert_eq!("14400 USD", res[0].result.as_ref().unwrap().to_string());
+//! ```
+
+pub mod error;
+pub mod scan;
+pub mod parse;
+pub mod exec;
+pub mod unit;
+pub mod locale;
+pub mod import;
+pub mod json;
+
+use std::ops;
+use std::time;
+
+use scan::Scanner;
+use parse::Parser;
+use exec::Context;
+
+/// Lines which start with this are comments and are never evaluated.
+pub const COMMENT: char = '#';
+
+/// Determine whether a line of text is a comment.
+pub fn is_comment(text: &str) -> bool {
+  text.trim_start().starts_with(COMMENT)
+}
+
+/// Determine whether a line of text should be evaluated at all; comments
+/// and directives are not expressions.
+fn is_inert(text: &str) -> bool {
+  is_comment(text) || import::directive(text).is_some()
+}
+
+/// Determine whether a line of text ends with settings that change how its
+/// results are presented, written after a comment marker as in
+/// `rate * 12 # precision: 2, rounding: half-even`, and if it does, produce
+/// the expression part of the line and the format it sets. The trailing
+/// comment is only treated as settings if every part of it is one.
+///
+/// Decimal places or significant figures set on a line replace both of
+/// those in the base format; the rounding mode replaces only itself. A
+/// line may also ask to be watched (see `line_watch`), which is a setting
+/// but changes nothing about the format.
+fn line_format(text: &str, base: unit::Format) -> Option<(&str, unit::Format)> {
+  let (expr, rest) = text.split_once(COMMENT)?;
+  if expr.trim().is_empty() {
+    return None; // the whole line is a comment
+  }
+  let mut fmt = base;
+  let mut digits = (None, None);
+  for (key, val) in rest.split(',').map(line_setting) {
+    match key.as_str() {
+      "precision" => digits.0 = Some(val.parse().ok().filter(|p| *p <= unit::MAX_PRECISION)?),
+      "figures"   => digits.1 = Some(val.parse().ok().filter(|f| (1..=unit::MAX_FIGURES).contains(f))?),
+      "rounding"  => fmt.rounding = unit::Rounding::from_name(val)?,
+      "watch"     => { Watch::from_name(val)?; },
+      _           => return None,
+    };
+  }
+  if digits != (None, None) {
+    (fmt.precision, fmt.figures) = digits;
+  }
+  Some((expr, fmt))
+}
+
+/// A part of the settings at the end of a line, as its key and value. Only
+/// `watch` may be written without a value.
+fn line_setting(part: &str) -> (String, &str) {
+  let (key, val) = part.split_once(':').unwrap_or((part, ""));
+  (key.trim().to_lowercase(), val.trim())
+}
+
+/// How a line asks for changes to its value to be pointed out: written in
+/// its settings as `watch`, or as `watch: notify` to be told even when
+/// the document isn't being looked at.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Watch {
+  Flash,
+  Notify,
+}
+
+impl Watch {
+  fn from_name(name: &str) -> Option<Watch> {
+    match name.to_lowercase().as_str() {
+      ""       => Some(Watch::Flash),
+      "notify" => Some(Watch::Notify),
+      _        => None,
+    }
+  }
+}
+
+/// Determine whether a line asks to be watched, and how.
+pub fn line_watch(text: &str) -> Option<Watch> {
+  line_format(text, unit::Format::default())?; // only if the comment is all settings
+  let (_, rest) = text.split_once(COMMENT)?;
+  rest.split(',').map(line_setting).find(|(key, _)| key == "watch").and_then(|(_, val)| Watch::from_name(val))
+}
+
+/// Produce the symbols of the stocks and indices a document needs quotes
+/// for, as in `150 shares of AAPL`, each once.
+pub fn quote_symbols(text: &str) -> Vec<String> {
+  let mut symbols: Vec<String> = Vec::new();
+  for line in text.lines().filter(|l| l.contains("share") && !is_inert(l)) {
+    let mut scan = Scanner::new(line);
+    loop {
+      match scan.token() {
+        Ok(tok) if tok.ttype == scan::TType::End => break,
+        Ok(tok) if tok.ttype == scan::TType::Quote && !symbols.contains(&tok.ttext) => symbols.push(tok.ttext),
+        Ok(_) => {},
+        Err(_) => break,
+      }
+    }
+  }
+  symbols
+}
+
+/// Produce the expression part of a line and the format of its results,
+/// which is the context's unless the line sets its own.
+pub fn split_line<'a>(cxt: &Context, text: &'a str) -> (&'a str, unit::Format) {
+  line_format(text, cxt.format()).unwrap_or((text, cxt.format()))
+}
+
+/// The outcome of evaluating a single expression found in a line of text.
+#[derive(Debug)]
+pub struct Eval {
+  pub range: ops::Range<usize>,  // range of the expression in the source text, in bytes
+  pub result: Result<unit::Value, error::Error>,
+}
+
+impl Eval {
+  pub fn text<'a>(&self, text: &'a str) -> &'a str {
+    &text[self.range.clone()]
+  }
+}
+
+/// Evaluate every expression found in the provided text, in order. Text that
+/// cannot be parsed as an expression is skipped; expressions that parse but
+/// fail to execute are produced with their error.
+pub fn eval(cxt: &mut Context, text: &str) -> Vec<Eval> {
+  let mut res: Vec<Eval> = Vec::new();
+  if is_inert(text) {
+    return res;
+  }
+  let (expr, _) = split_line(cxt, text);
+  let mut p = Parser::new(Scanner::new(expr).with_decimal(cxt.locale().decimal));
+  while let Ok(exp) = p.parse() {
+    res.push(Eval{
+      result: exp.ast.exec(cxt),
+      range: exp.range,
+    });
+  }
+  res
+}
+
+/// The formatted result of an expression found in a line of text.
+#[derive(Debug)]
+pub struct Output {
+  pub range: ops::Range<usize>, // range of the expression in the source text, in bytes
+  pub expr: exec::Node,         // the expression, as it was parsed
+  pub text: String,             // the result, formatted
+}
+
+/// Evaluate every expression found in the provided text, in order, and
+/// format their results as the line or the context says to. Expressions
+/// that fail to execute are omitted, except those which refer to lines that
+/// no longer exist; those are flagged as stale.
+pub fn render(cxt: &mut Context, text: &str) -> Vec<Output> {
+  render_timed(cxt, text, None)
+}
+
+/// How long was spent parsing and evaluating expressions.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Timings {
+  pub parse: time::Duration,
+  pub eval: time::Duration,
+}
+
+/// Like `render`, but adding how long was spent parsing and evaluating to
+/// the timings, if there are any. Time is only measured when there are,
+/// since there's no clock on every target the engine builds for.
+pub fn render_timed(cxt: &mut Context, text: &str, mut timings: Option<&mut Timings>) -> Vec<Output> {
+  let mut res: Vec<Output> = Vec::new();
+  if is_inert(text) {
+    return res;
+  }
+  let (expr, fmt) = split_line(cxt, text);
+  let mut p = Parser::new(Scanner::new(expr).with_decimal(cxt.locale().decimal));
+  while let Ok(exp) = timed(timings.as_deref_mut().map(|t| &mut t.parse), || p.parse()) {
+    let out = timed(timings.as_deref_mut().map(|t| &mut t.eval), || exp.ast.exec(cxt));
+    let text = match out {
+      Ok(res) => res.format(&fmt),
+      Err(_)  => match (exp.ast.references().into_iter().find(|r| cxt.reference(r).is_none()), exp.ast.quotes().into_iter().find(|q| cxt.quote(q).is_none())) {
+        (Some(stale), _) => format!("stale @{}", stale), // flag references that no longer resolve
+        (None, Some(symbol)) => format!("no quote for {}", symbol), // and quotes that couldn't be fetched
+        (None, None) => continue,
+      },
+    };
+    res.push(Output{
+      range: exp.range,
+      expr: exp.ast,
+      text,
+    });
+  }
+  res
+}
+
+/// The work behind the result of an expression: the expression with the
+/// variables it refers to replaced by their values, and the steps taken to
+/// evaluate it.
+#[derive(Debug)]
+pub struct Work {
+  pub output: Output,
+  pub substituted: String,
+  pub steps: Vec<exec::Step>,
+}
+
+/// Like `render`, but producing the work behind each result as well, so
+/// that it can be checked. Expressions that fail to execute are omitted.
+pub fn render_work(cxt: &mut Context, text: &str) -> Vec<Work> {
+  let mut res: Vec<Work> = Vec::new();
+  if is_inert(text) {
+    return res;
+  }
+  let (expr, fmt) = split_line(cxt, text);
+  let mut p = Parser::new(Scanner::new(expr).with_decimal(cxt.locale().decimal));
+  cxt.trace(true);
+  while let Ok(exp) = p.parse() {
+    let substituted = exp.ast.substitute(cxt);
+    let out = exp.ast.exec(cxt);
+    let steps = cxt.take_steps();
+    if let (Ok(val), Ok(substituted)) = (out, substituted) {
+      res.push(Work{
+        output: Output{
+          range: exp.range,
+          expr: exp.ast,
+          text: val.format(&fmt),
+        },
+        substituted,
+        steps,
+      });
+    }
+  }
+  cxt.trace(false);
+  res
+}
+
+/// Run a function, adding how long it took to a total if there is one.
+fn timed<T>(total: Option<&mut time::Duration>, f: impl FnOnce() -> T) -> T {
+  match total {
+    Some(total) => {
+      let start = time::Instant::now();
+      let out = f();
+      *total += start.elapsed();
+      out
+    },
+    None => f(),
+  }
+}
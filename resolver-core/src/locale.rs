@@ -0,0 +1,131 @@
+use std::fmt;
+
+use crate::unit::{self, Currency};
+
+/// Names of the locales with built-in conventions.
+pub const NAMES: [&str; 4] = ["en", "de", "fr", "ch"];
+
+/// The order in which the parts of a date are written.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DateOrder {
+  DayMonthYear,
+  MonthDayYear,
+  YearMonthDay,
+}
+
+impl DateOrder {
+  pub fn from_name(name: &str) -> Option<DateOrder> {
+    match name.trim().to_lowercase().as_str() {
+      "dmy" => Some(DateOrder::DayMonthYear),
+      "mdy" => Some(DateOrder::MonthDayYear),
+      "ymd" => Some(DateOrder::YearMonthDay),
+      _     => None,
+    }
+  }
+}
+
+impl fmt::Display for DateOrder {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DateOrder::DayMonthYear => write!(f, "dmy"),
+      DateOrder::MonthDayYear => write!(f, "mdy"),
+      DateOrder::YearMonthDay => write!(f, "ymd"),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Weekday {
+  Monday,
+  Tuesday,
+  Wednesday,
+  Thursday,
+  Friday,
+  Saturday,
+  Sunday,
+}
+
+/// Every day of the week, from Monday.
+pub const WEEKDAYS: [Weekday; 7] = [
+  Weekday::Monday,
+  Weekday::Tuesday,
+  Weekday::Wednesday,
+  Weekday::Thursday,
+  Weekday::Friday,
+  Weekday::Saturday,
+  Weekday::Sunday,
+];
+
+impl Weekday {
+  pub fn from_name(name: &str) -> Option<Weekday> {
+    WEEKDAYS.iter().find(|d| d.to_string().eq_ignore_ascii_case(name.trim())).copied()
+  }
+}
+
+impl fmt::Display for Weekday {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Weekday::Monday    => write!(f, "monday"),
+      Weekday::Tuesday   => write!(f, "tuesday"),
+      Weekday::Wednesday => write!(f, "wednesday"),
+      Weekday::Thursday  => write!(f, "thursday"),
+      Weekday::Friday    => write!(f, "friday"),
+      Weekday::Saturday  => write!(f, "saturday"),
+      Weekday::Sunday    => write!(f, "sunday"),
+    }
+  }
+}
+
+/// Regional conventions for reading and writing values. The default is
+/// neutral: dollars are US dollars, the decimal separator is a point,
+/// digits are not grouped, and dates follow ISO 8601.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+  pub currency: Currency,        // what a bare `$` means
+  pub decimal: char,             // accepted in numbers, in addition to a point
+  pub grouping: Option<char>,    // separates groups of digits in results
+  pub date_order: DateOrder,
+  pub week_start: Weekday,
+}
+
+impl Default for Locale {
+  fn default() -> Locale {
+    Locale{
+      currency: Currency::Usd,
+      decimal: '.',
+      grouping: None,
+      date_order: DateOrder::YearMonthDay,
+      week_start: Weekday::Monday,
+    }
+  }
+}
+
+impl Locale {
+  /// The conventions of one of the locales in `NAMES`.
+  pub fn named(name: &str) -> Option<Locale> {
+    let (currency, decimal, grouping, date_order, week_start) = match name {
+      "en" => (Currency::Usd, '.', ',', DateOrder::MonthDayYear, Weekday::Sunday),
+      "de" => (Currency::Eur, ',', '.', DateOrder::DayMonthYear, Weekday::Monday),
+      "fr" => (Currency::Eur, ',', ' ', DateOrder::DayMonthYear, Weekday::Monday),
+      "ch" => (Currency::Chf, '.', '\'', DateOrder::DayMonthYear, Weekday::Monday),
+      _    => return None,
+    };
+    Some(Locale{
+      currency,
+      decimal,
+      grouping: Some(grouping),
+      date_order,
+      week_start,
+    })
+  }
+
+  /// The format in which results are presented in this locale, with
+  /// digits and rounding left as they are by default.
+  pub fn format(&self) -> unit::Format {
+    unit::Format{
+      decimal: self.decimal,
+      grouping: self.grouping,
+      ..unit::Format::default()
+    }
+  }
+}
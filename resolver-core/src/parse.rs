@@ -1,11 +1,10 @@
 use std::fmt;
 use std::ops;
 
-use crate::rdl;
-use crate::rdl::scan::{self, Scanner, TType};
-use crate::rdl::exec::{Context, Node};
-use crate::rdl::unit;
-use crate::rdl::error;
+use crate::scan::{self, Scanner, TType};
+use crate::exec::Node;
+use crate::unit;
+use crate::error;
 
 #[derive(Debug, PartialEq)]
 pub struct Expr {
@@ -123,7 +122,10 @@ impl<'a> Parser<'a> {
       TType::Verbatim => return Ok(left),
       TType::End      => return Ok(left),
       TType::Ident    => Some(self.parse_primary()?),
+      TType::Reference => Some(self.parse_primary()?),
       TType::Number   => Some(self.parse_primary()?),
+      TType::Currency => Some(self.parse_primary()?),
+      TType::Quote    => Some(self.parse_primary()?),
       TType::LParen   => Some(self.parse_primary()?),
       _               => return Ok(left),
     };
@@ -185,7 +187,10 @@ impl<'a> Parser<'a> {
   fn parse_primary(&mut self) -> Result<Expr, error::Error> {
     let tok = self.scan.expect_token_fn(|tok| {
       tok.ttype == TType::Ident  ||
+      tok.ttype == TType::Reference ||
       tok.ttype == TType::Number ||
+      tok.ttype == TType::Currency ||
+      tok.ttype == TType::Quote ||
       tok.ttype == TType::LParen
     })?;
     
@@ -195,10 +200,25 @@ impl<'a> Parser<'a> {
         range: tok.range,
         ast: Node::new_ident(&tok.ttext),
       },
+      TType::Reference => Expr{
+        range: tok.range,
+        ast: Node::new_reference(&tok.ttext),
+      },
       TType::Number => Expr{
         range: tok.range,
         ast: Node::new_number(tok.ttext.parse::<f64>()?),
       },
+      TType::Quote => Expr{
+        range: tok.range,
+        ast: Node::new_quote(&tok.ttext),
+      },
+      TType::Currency => {
+        let num = self.scan.expect_token(TType::Number)?;
+        return Ok(Expr{
+          range: tok.range.start..num.range.end,
+          ast: Node::new_typecast(Node::new_number(num.ttext.parse::<f64>()?), Node::new_ident(&tok.ttext)),
+        });
+      },
       TType::LParen => {
         let exp = self.parse_expr()?;
         Expr{
@@ -251,6 +271,7 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::exec::Context;
   
   fn parse_expr(t: &str) -> Result<Node, error::Error> {
     let e = Parser::new(Scanner::new(t)).parse()?;
@@ -265,9 +286,9 @@ mod tests {
   }
   
   fn exec_line(text: &str, cxt: &mut Context) -> String {
-    let (_, res) = rdl::render_with_options(cxt, text, 0, 0, None, Some(&rdl::Options{verbose: true, debug: false}));
-    println!("*** [{}] → [{}]", text, res.text());
-    res.text().to_owned()
+    let res = crate::render(cxt, text).iter().map(|o| format!("{} → {}", o.expr, o.text)).collect::<Vec<_>>().join("; ");
+    println!("*** [{}] → [{}]", text, res);
+    res
   }
   
   #[test]
@@ -466,4 +487,31 @@ mod tests {
     assert_eq!("(100 + (b * 100)) → 300; 0 → 0; 0 → 0", &exec_line(t, &mut cxt));
   }
   
+  #[test]
+  fn parse_locale() {
+    use crate::locale::Locale;
+    let mut cxt = Context::new();
+    assert_eq!("$(5) → 5 USD; (€(1.5) + 2) → 3.5 EUR", &exec_line("$5 and €1.5 + 2", &mut cxt));
+    
+    cxt.set_locale(Locale::named("de").unwrap());
+    assert_eq!("($(1.5) * 2) → 3 EUR; 1.5 → 1.5", &exec_line("$1,5 * 2, and 1.5", &mut cxt));
+  }
+  
+  #[test]
+  fn parse_quote() {
+    let mut cxt = Context::new();
+    cxt.set_rates(unit::Rate::parse("EUR/USD = 1.25").into_iter().collect());
+    
+    let n = parse_expr(r#"150 shares of AAPL in EUR"#).expect("Could not parse");
+    assert_eq!(Node::new_typecast(Node::new_mul(Node::new_number(150.0), Node::new_quote("AAPL")), Node::new_ident("EUR")), n);
+    assert_eq!(vec!["AAPL"], n.quotes());
+    assert!(n.exec(&mut cxt).is_err());
+    cxt.set_quote("aapl", unit::Value::new(200.0, unit::Unit::Money(unit::Currency::Usd)));
+    assert_eq!(Ok(unit::Value::new(24000.0, unit::Unit::Money(unit::Currency::Eur))), n.exec(&mut cxt));
+    
+    cxt.set_quote("^SPX", unit::Value::raw(50.0));
+    assert_eq!("(2 * ^SPX) → 100", &exec_line("2 shares of ^spx", &mut cxt));
+    assert_eq!(Err(error::Error::NoQuote("MSFT".to_string())), Node::new_quote("MSFT").exec(&mut cxt));
+  }
+  
 }
@@ -1,13 +1,21 @@
 use std::fmt;
 use std::str;
 use std::ops;
+use std::sync::OnceLock;
 
-use crossterm::style::Stylize;
-
-use crate::rdl::error;
+use crate::error;
+use crate::unit;
 
 const ZERO: char = '\0';
 
+static ALIASES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Install aliases, which are words that stand for other text, such as a
+/// unit or part of an expression. Aliases can only be installed once.
+pub fn set_aliases(aliases: Vec<(String, String)>) {
+  let _ = ALIASES.set(aliases);
+}
+
 pub const ESCAPE: char  = '\\';
 pub const LBRACE: char  = '{';
 pub const RBRACE: char  = '}';
@@ -23,12 +31,14 @@ pub const DIV: char     = '/';
 pub const MUL: char     = '*';
 pub const MOD: char     = '%';
 pub const AT: char      = '@';
+pub const DOLLAR: char  = '$';
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum TType {
   Verbatim,
   Whitespace,
   Ident,
+  Reference,
   Number,
   String,
   Operator,
@@ -37,6 +47,8 @@ pub enum TType {
   LParen,
   RParen,
   Symbol,
+  Currency,
+  Quote,
   End,
 }
 
@@ -55,20 +67,6 @@ impl Token {
       range: range,
     }
   }
-  
-  pub fn _styled(&self) -> Option<String> {
-    let ttext: &str = self.ttext.as_ref();
-    match self.ttype {
-      TType::Verbatim => Some(format!("{}", ttext.reset())),
-      TType::Whitespace => Some(format!("{}", ttext.reset())),
-      TType::Ident => Some(format!("{}", ttext.bold())),
-      TType::Number => Some(format!("{}", ttext.yellow())),
-      TType::String => Some(format!("{}", ttext.cyan())),
-      TType::Operator => Some(format!("{}", ttext.green())),
-      TType::Symbol => Some(format!("{}", ttext.blue())),
-      _ => None,
-    }
-  }
 }
 
 impl fmt::Display for Token {
@@ -84,6 +82,8 @@ pub struct Scanner<'a> {
   tokens: Vec<Token>,
   peek: [char; 2],
   index: usize, // index in text, in bytes
+  decimal: char, // decimal separator accepted in addition to a point
+  aliases: &'a [(String, String)], // words expanded to the text they stand for
 }
 
 impl<'a> fmt::Display for Scanner<'a> {
@@ -100,9 +100,23 @@ impl<'a> Scanner<'a> {
       tokens: Vec::new(),
       peek: [ZERO, ZERO],
       index: 0,
+      decimal: '.',
+      aliases: ALIASES.get().map_or(&[], |a| a.as_slice()),
     }
   }
   
+  /// Expand the provided aliases instead of those installed.
+  pub fn with_aliases(mut self, aliases: &'a [(String, String)]) -> Scanner<'a> {
+    self.aliases = aliases;
+    self
+  }
+  
+  /// Accept another decimal separator in numbers, such as a comma.
+  pub fn with_decimal(mut self, decimal: char) -> Scanner<'a> {
+    self.decimal = decimal;
+    self
+  }
+  
   fn syntax_error(&mut self, m: &str) -> error::Error {
     error::SyntaxError::new(self.text, ops::Range{start: self.index, end: self.index}, m).into()
   }
@@ -326,6 +340,10 @@ impl<'a> Scanner<'a> {
         return self.scan_whitespace();
       }else if Self::is_symbol(c) {
         return self.scan_symbol();
+      }else if c == AT {
+        return self.scan_reference();
+      }else if self.is_currency_prefix() {
+        return self.scan_currency();
       }
     }
     Err(error::Error::TokenNotMatched)
@@ -344,6 +362,10 @@ impl<'a> Scanner<'a> {
           break;
         }else if Self::is_symbol(c) {
           break;
+        }else if c == AT && !buf.is_empty() {
+          break;
+        }else if self.is_currency_prefix() {
+          break;
         }else if c == ESCAPE {
           buf.push_str(&self.escape()?)
         }else{
@@ -365,6 +387,12 @@ impl<'a> Scanner<'a> {
   fn scan_word(&mut self) -> Result<(), error::Error> {
     let idx = self.index;
     let name = self.ident()?;
+    if let Some((_, text)) = self.aliases.iter().find(|(alias, _)| *alias == name) {
+      return self.scan_alias(text, idx..self.index);
+    }
+    if (name == "shares" || name == "share") && self.scan_quote(idx) {
+      return Ok(());
+    }
     self.push(match name.as_ref() {
       "in" | "as" => Token{
         ttype: TType::Typecast,
@@ -380,6 +408,55 @@ impl<'a> Scanner<'a> {
     Ok(())
   }
   
+  /// Scan the rest of shares of a stock or an index, which are written as
+  /// `150 shares of AAPL`, having scanned `shares`. They are multiplied by
+  /// the price quoted for the symbol, which is produced in upper case.
+  /// Produces false, having consumed nothing, unless a symbol follows.
+  fn scan_quote(&mut self, idx: usize) -> bool {
+    let rest = &self.text[self.index..];
+    let of = match rest.trim_start().strip_prefix("of") {
+      Some(of) if rest.starts_with(char::is_whitespace) && of.starts_with(char::is_whitespace) => of,
+      _ => return false,
+    };
+    let sym = of.trim_start();
+    let len = sym.find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '^' || c == '_')).unwrap_or(sym.len());
+    let text = sym[..len].trim_end_matches('.'); // ending a sentence
+    if !text.starts_with(|c: char| c.is_alphabetic() || c == '^') {
+      return false;
+    }
+    let start = self.index + (rest.len() - sym.len());
+    let end = start + text.len();
+    let of_end = self.index + (rest.len() - of.len());
+    while self.index < end {
+      self.skip();
+    }
+    self.push(Token{
+      ttype: TType::Operator,
+      ttext: MUL.to_string(),
+      range: idx..of_end,
+    });
+    self.push(Token{
+      ttype: TType::Quote,
+      ttext: text.to_uppercase(),
+      range: start..end,
+    });
+    true
+  }
+  
+  /// Scan the text an alias stands for in place of the alias. The tokens
+  /// it produces all cover the alias, and aliases within it are not
+  /// themselves expanded.
+  fn scan_alias(&mut self, text: &str, range: ops::Range<usize>) -> Result<(), error::Error> {
+    let mut sub = Scanner::new(text).with_decimal(self.decimal).with_aliases(&[]);
+    while sub.peek().is_some() {
+      sub.scan()?;
+    }
+    for tok in sub.tokens {
+      self.push(Token{range: range.clone(), ..tok});
+    }
+    Ok(())
+  }
+  
   fn scan_ident(&mut self) -> Result<(), error::Error> {
     let idx = self.index;
     let name = self.ident()?;
@@ -391,6 +468,34 @@ impl<'a> Scanner<'a> {
     Ok(())
   }
   
+  /// Scan a reference to a variable defined in another document, which is
+  /// written as `@document.name`. The token text omits the leading `@`.
+  fn scan_reference(&mut self) -> Result<(), error::Error> {
+    if !self.peek_n(1).is_some_and(Self::is_ident_start) {
+      return Err(error::Error::TokenNotMatched); // nothing has been consumed
+    }
+    let idx = self.index;
+    self.assert(AT)?;
+    let mut name = self.ident()?;
+    if self.peek() == Some('.') && self.peek_n(1).is_some_and(Self::is_ident_start) {
+      self.skip();
+      name.push('.');
+      name.push_str(&self.ident()?);
+      self.push(Token{
+        ttype: TType::Reference,
+        ttext: name,
+        range: idx..self.index,
+      });
+    }else{
+      self.push(Token{
+        ttype: TType::Verbatim,
+        ttext: format!("@{}", name),
+        range: idx..self.index,
+      });
+    }
+    Ok(())
+  }
+  
   fn scan_number(&mut self) -> Result<(), error::Error> {
     let idx = self.index;
     let val = self.number()?;
@@ -402,6 +507,19 @@ impl<'a> Scanner<'a> {
     Ok(())
   }
   
+  /// Scan a currency symbol written before an amount, as in `$5`.
+  fn scan_currency(&mut self) -> Result<(), error::Error> {
+    let idx = self.index;
+    if let Some(c) = self.next() {
+      self.push(Token{
+        ttype: TType::Currency,
+        ttext: c.to_string(),
+        range: idx..self.index,
+      });
+    }
+    Ok(())
+  }
+  
   fn scan_operator(&mut self) -> Result<(), error::Error> {
     let idx = self.index;
     let mut buf = String::new();
@@ -492,6 +610,11 @@ impl<'a> Scanner<'a> {
     c == EQUAL || c == LPAREN || c == RPAREN
   }
   
+  /// Determine if the stream is at a currency symbol followed by a number.
+  fn is_currency_prefix(&mut self) -> bool {
+    self.peek_fn(|c| c == DOLLAR || unit::Currency::from_symbol(c).is_some()) && self.peek_n(1).is_some_and(Self::is_number_start)
+  }
+  
   fn ident(&mut self) -> Result<String, error::Error> {
     let mut buf = String::new();
     buf.push(self.assert_fn(|c| { Self::is_ident_start(c) })?);
@@ -524,10 +647,10 @@ impl<'a> Scanner<'a> {
     let mut buf = String::new();
     buf.push_str(&self.integer()?);
     if let Some(c1) = self.peek_n(0) {
-      if c1 == '.' {
+      if c1 == '.' || c1 == self.decimal {
         if let Some(c2) = self.peek_n(1) {
           if c2.is_digit(10) {
-            buf.push(c1);
+            buf.push('.');
             self.skip();
             buf.push_str(&self.integer()?);
           }
@@ -753,4 +876,61 @@ mod tests {
     assert_eq!(Ok(Token::new(TType::Whitespace, " ", 7..8)), t.token());
     assert_eq!(Ok(Token::new(TType::Ident, "g", 8..9)), t.token());
   }
+  
+  #[test]
+  fn scan_quote() {
+    let s = "150 shares of aapl in EUR";
+    let mut t = Scanner::new(s);
+    assert_eq!(Ok(Token::new(TType::Number, "150", 0..3)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 3..4)), t.token());
+    assert_eq!(Ok(Token::new(TType::Operator, "*", 4..13)), t.token());
+    assert_eq!(Ok(Token::new(TType::Quote, "AAPL", 14..18)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 18..19)), t.token());
+    assert_eq!(Ok(Token::new(TType::Typecast, "in", 19..21)), t.token());
+    
+    let mut t = Scanner::new("1 share of BRK.B.");
+    t.token().unwrap();
+    t.token().unwrap();
+    assert_eq!(Ok(Token::new(TType::Operator, "*", 2..10)), t.token());
+    assert_eq!(Ok(Token::new(TType::Quote, "BRK.B", 11..16)), t.token());
+    
+    let mut t = Scanner::new("shares of 3");
+    assert_eq!(Ok(Token::new(TType::Ident, "shares", 0..6)), t.token());
+    let mut t = Scanner::new("sharesof AAPL");
+    assert_eq!(Ok(Token::new(TType::Ident, "sharesof", 0..8)), t.token());
+  }
+  
+  #[test]
+  fn scan_reference() {
+    let s = "@savings.total + 1".to_string();
+    let mut t = Scanner::new(&s);
+    assert_eq!(Ok(Token::new(TType::Reference, "savings.total", 0..14)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 14..15)), t.token());
+    
+    let s = "mail @bob now".to_string();
+    let mut t = Scanner::new(&s);
+    assert_eq!(Ok(Token::new(TType::Ident, "mail", 0..4)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 4..5)), t.token());
+    assert_eq!(Ok(Token::new(TType::Verbatim, "@bob", 5..9)), t.token());
+    
+    let s = "@ 1".to_string();
+    let mut t = Scanner::new(&s);
+    assert_eq!(Ok(Token::new(TType::Verbatim, "@ ", 0..2)), t.token());
+  }
+  
+  #[test]
+  fn scan_alias() {
+    let aliases = vec![("tbl".to_string(), "tbsp".to_string()), ("twice".to_string(), "2 * twice".to_string())];
+    let mut t = Scanner::new("3 tbl + twice").with_aliases(&aliases);
+    assert_eq!(Ok(Token::new(TType::Number, "3", 0..1)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 1..2)), t.token());
+    assert_eq!(Ok(Token::new(TType::Ident, "tbsp", 2..5)), t.token());
+    t.discard_fn(|t| t != TType::Number);
+    assert_eq!(Ok(Token::new(TType::Number, "2", 8..13)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 8..13)), t.token());
+    assert_eq!(Ok(Token::new(TType::Operator, "*", 8..13)), t.token());
+    assert_eq!(Ok(Token::new(TType::Whitespace, " ", 8..13)), t.token());
+    assert_eq!(Ok(Token::new(TType::Ident, "twice", 8..13)), t.token()); // not expanded again
+  }
+  
 }
@@ -1,7 +1,19 @@
 use std::fmt;
 use std::ops;
+use std::sync::OnceLock;
+use std::collections::HashMap;
 
-use crate::util;
+static CUSTOM: OnceLock<Vec<Custom>> = OnceLock::new();
+
+/// Install the units defined in the configuration, which are known in
+/// every context. Custom units can only be installed once.
+pub fn set_custom(units: Vec<Custom>) {
+  let _ = CUSTOM.set(units);
+}
+
+fn custom(i: usize) -> Option<&'static Custom> {
+  CUSTOM.get().and_then(|u| u.get(i))
+}
 
 const CONVERSION: [[f64; 11]; 11] = [
  //                 Teaspoon,     Tablespoon,         Cup,                 Quart,               Gallon,              Liter,               Deciliter,           Centiliter,        Milliliter,        Gram,      Kilogram,
@@ -33,10 +45,205 @@ pub enum Unit {
   
   Gram,        // base
   Kilogram,    // 1000x grams
+  
+  Money(Currency), // only converts to the same currency
+  Custom(usize),   // defined in the configuration; see `define`
+}
+
+/// Every built-in unit of measure; currencies are in `CURRENCIES`.
+pub const UNITS: [Unit; 11] = [
+  Unit::Teaspoon, Unit::Tablespoon, Unit::Cup, Unit::Quart, Unit::Gallon,
+  Unit::Liter, Unit::Deciliter, Unit::Centiliter, Unit::Milliliter,
+  Unit::Gram, Unit::Kilogram,
+];
+
+/// Every unit of measure which is known, built-in or custom.
+pub fn units() -> Vec<Unit> {
+  let custom = CUSTOM.get().map_or(0, |u| u.len());
+  UNITS.into_iter().chain((0..custom).map(Unit::Custom)).collect()
+}
+
+/// A unit defined in terms of another, as an amount of it, such as
+/// `point = 0.3528 mm`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+  pub name: String,
+  pub amount: f64,
+  pub of: String,
+}
+
+/// A custom unit, which is some multiple of a base unit. The base is
+/// either a built-in unit or a custom one that is only its own multiple,
+/// which is made up for any unit a definition refers to that isn't known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Custom {
+  name: String,
+  base: Unit,
+  factor: f64,
+}
+
+/// Produce custom units from definitions, which may refer to each other in
+/// any order, and describe any that can't be defined.
+pub fn define(defs: &[Definition]) -> (Vec<Custom>, Vec<(String, String)>) {
+  let mut units: Vec<Custom> = Vec::new();
+  let mut index: HashMap<String, usize> = HashMap::new();
+  let mut probs = Vec::new();
+  
+  let mut pending: Vec<&Definition> = Vec::new();
+  for def in defs {
+    if Unit::builtin(&def.name).is_some() {
+      probs.push((def.name.clone(), "already a unit".to_string()));
+    }else if def.amount <= 0.0 || !def.amount.is_finite() {
+      probs.push((def.name.clone(), format!("expected a positive amount, got {}", def.amount)));
+    }else{
+      pending.push(def);
+    }
+  }
+  
+  let names: Vec<String> = pending.iter().map(|d| d.name.clone()).collect();
+  loop { // until no more definitions can be resolved
+    let n = pending.len();
+    pending.retain(|def| {
+      let (base, factor) = if let Some(unit) = Unit::builtin(&def.of) {
+        (unit, 1.0)
+      }else if let Some(i) = index.get(&def.of) {
+        (units[*i].base, units[*i].factor)
+      }else if names.contains(&def.of) {
+        return true; // not yet defined, or can't be
+      }else{
+        let i = units.len(); // a base unit of its own
+        units.push(Custom{name: def.of.clone(), base: Unit::Custom(i), factor: 1.0});
+        index.insert(def.of.clone(), i);
+        (Unit::Custom(i), 1.0)
+      };
+      index.insert(def.name.clone(), units.len());
+      units.push(Custom{name: def.name.clone(), base, factor: def.amount * factor});
+      false
+    });
+    if pending.is_empty() || pending.len() == n {
+      break;
+    }
+  }
+  for def in pending {
+    probs.push((def.name.clone(), format!("refers to itself by way of {}", def.of)));
+  }
+  
+  (units, probs)
+}
+
+/// A currency. Amounts in different currencies can only be converted to
+/// each other at an exchange rate that has been set; see `Rate`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Currency {
+  Usd,
+  Eur,
+  Gbp,
+  Jpy,
+  Chf,
+}
+
+/// Every currency, in the order they are listed.
+pub const CURRENCIES: [Currency; 5] = [Currency::Usd, Currency::Eur, Currency::Gbp, Currency::Jpy, Currency::Chf];
+
+impl Currency {
+  /// Find a currency by its ISO 4217 code, in any case.
+  pub fn from_code(code: &str) -> Option<Currency> {
+    CURRENCIES.iter().find(|c| c.code().eq_ignore_ascii_case(code.trim())).copied()
+  }
+  
+  /// Find a currency by its code or its symbol.
+  pub fn from_name(name: &str) -> Option<Currency> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+      (Some(sym), None) => Currency::from_symbol(sym),
+      _ => Currency::from_code(name),
+    }
+  }
+  
+  /// Find the currency written with a symbol. The dollar sign is not one
+  /// of these, since which dollar it means depends on the locale.
+  pub fn from_symbol(sym: char) -> Option<Currency> {
+    match sym {
+      '€' => Some(Currency::Eur),
+      '£' => Some(Currency::Gbp),
+      '¥' => Some(Currency::Jpy),
+      _   => None,
+    }
+  }
+  
+  pub fn code(&self) -> &'static str {
+    match self {
+      Currency::Usd => "USD",
+      Currency::Eur => "EUR",
+      Currency::Gbp => "GBP",
+      Currency::Jpy => "JPY",
+      Currency::Chf => "CHF",
+    }
+  }
+}
+
+/// An exchange rate between two currencies, which converts amounts in
+/// either direction. Rates are set by the configuration or a document, or
+/// fetched from a provider.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rate {
+  pub from: Currency,
+  pub to: Currency,
+  pub rate: f64, // the amount in `to` of one unit of `from`
+}
+
+impl Rate {
+  /// Produce the rate for a pair of currencies written as `EUR/USD`.
+  pub fn new(pair: &str, rate: f64) -> Option<Rate> {
+    let (from, to) = pair.split_once('/')?;
+    let (from, to) = (Currency::from_code(from)?, Currency::from_code(to)?);
+    if from == to || rate <= 0.0 || !rate.is_finite() {
+      return None;
+    }
+    Some(Rate{from, to, rate})
+  }
+  
+  /// Parse a rate written as `EUR/USD = 1.09`, meaning one euro is 1.09
+  /// dollars.
+  pub fn parse(text: &str) -> Option<Rate> {
+    let (pair, rate) = text.split_once('=')?;
+    Rate::new(pair, rate.trim().parse().ok()?)
+  }
+  
+  /// The factor by which to multiply an amount in one currency to exchange
+  /// it for another, if this is the rate between them.
+  fn factor(&self, from: Currency, to: Currency) -> Option<f64> {
+    if (self.from, self.to) == (from, to) {
+      Some(self.rate)
+    }else if (self.from, self.to) == (to, from) {
+      Some(1.0 / self.rate)
+    }else{
+      None
+    }
+  }
+}
+
+impl fmt::Display for Rate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}/{} = {}", self.from.code(), self.to.code(), self.rate)
+  }
+}
+
+impl fmt::Display for Currency {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.code())
+  }
 }
 
 impl Unit {
   pub fn from(name: &str) -> Option<Unit> {
+    Unit::builtin(name).or_else(|| {
+      let name = name.trim();
+      CUSTOM.get()?.iter().position(|u| u.name == name).map(Unit::Custom)
+    })
+  }
+  
+  fn builtin(name: &str) -> Option<Unit> {
     match name.to_owned().trim().to_lowercase().as_str() {
       "tsp" | "tsps"       => Some(Unit::Teaspoon),
       "tbsp" | "tbsps"     => Some(Unit::Tablespoon),
@@ -52,28 +259,54 @@ impl Unit {
       "g"                  => Some(Unit::Gram),
       "kg"                 => Some(Unit::Kilogram),
       
-      _                    => None,
+      name                 => Currency::from_name(name).map(Unit::Money),
     }
   }
   
-  pub fn ordinal(&self) -> usize {
+  /// The index of a unit of measure in the conversion table; currencies are
+  /// not in it.
+  fn ordinal(&self) -> Option<usize> {
     match self {
-      Unit::Teaspoon   => 0,
-      Unit::Tablespoon => 1,
-      Unit::Cup        => 2,
-      Unit::Quart      => 3,
-      Unit::Gallon     => 4,
+      Unit::Teaspoon   => Some(0),
+      Unit::Tablespoon => Some(1),
+      Unit::Cup        => Some(2),
+      Unit::Quart      => Some(3),
+      Unit::Gallon     => Some(4),
       
-      Unit::Liter      => 5,
-      Unit::Deciliter  => 6,
-      Unit::Centiliter => 7,
-      Unit::Milliliter => 8,
+      Unit::Liter      => Some(5),
+      Unit::Deciliter  => Some(6),
+      Unit::Centiliter => Some(7),
+      Unit::Milliliter => Some(8),
       
-      Unit::Gram       => 9,
-      Unit::Kilogram   => 10,
+      Unit::Gram       => Some(9),
+      Unit::Kilogram   => Some(10),
+      
+      Unit::Money(_)   => None,
+      Unit::Custom(_)  => None,
     }
   }
   
+  /// The unit this one is a multiple of, and the multiple; built-in units
+  /// are their own.
+  fn root(&self) -> (Unit, f64) {
+    match self {
+      Unit::Custom(i) => custom(*i).map_or((*self, 1.0), |u| (u.base, u.factor)),
+      _               => (*self, 1.0),
+    }
+  }
+  
+  /// The factor by which to multiply an amount in this unit to convert it
+  /// to another, or zero if it cannot be converted.
+  fn factor(&self, to: Unit) -> f64 {
+    let ((a, fa), (b, fb)) = (self.root(), to.root());
+    let f = match (a.ordinal(), b.ordinal()) {
+      (Some(a), Some(b)) => CONVERSION[a][b],
+      _ if a == b        => 1.0,
+      _                  => 0.0,
+    };
+    f * fa / fb
+  }
+  
   pub fn up(&self) -> Option<Unit> {
     match self {
       Unit::Teaspoon   => Some(Unit::Tablespoon),
@@ -89,6 +322,9 @@ impl Unit {
       
       Unit::Gram       => Some(Unit::Kilogram),
       Unit::Kilogram   => None,
+      
+      Unit::Money(_)   => None,
+      Unit::Custom(_)  => None,
     }
   }
   
@@ -107,6 +343,9 @@ impl Unit {
       
       Unit::Gram       => Unit::Gram,
       Unit::Kilogram   => Unit::Gram,
+      
+      Unit::Money(_)   => *self,
+      Unit::Custom(_)  => *self,
     }
   }
   
@@ -125,11 +364,14 @@ impl Unit {
       
       Unit::Gram       => Unit::Kilogram,
       Unit::Kilogram   => Unit::Kilogram,
+      
+      Unit::Money(_)   => *self,
+      Unit::Custom(_)  => *self,
     }
   }
   
   pub fn is_convertable(&self, to: Unit) -> bool {
-    CONVERSION[self.ordinal()][to.ordinal()] != 0.0
+    self.factor(to) != 0.0
   }
 }
 
@@ -149,6 +391,9 @@ impl fmt::Display for Unit {
       
       Self::Gram       => write!(f, "{}", "g"),
       Self::Kilogram   => write!(f, "{}", "kg"),
+      
+      Self::Money(c)   => write!(f, "{}", c),
+      Self::Custom(i)  => write!(f, "{}", custom(*i).map_or("?", |u| u.name.as_str())),
     }
   }
 }
@@ -218,7 +463,7 @@ impl Value {
     if from == to {
       return Some(*self);
     }
-    let factor = CONVERSION[from.ordinal()][to.ordinal()];
+    let factor = from.factor(to);
     if factor == 0.0 {
       None // cannot convert
     }else{
@@ -226,6 +471,26 @@ impl Value {
     }
   }
   
+  /// Exchange an amount of money for another currency, at the first of the
+  /// provided rates which is between them.
+  pub fn exchange(&self, to: Option<Unit>, rates: &[Rate]) -> Option<Value> {
+    match (self.unit, to) {
+      (Some(Unit::Money(a)), Some(Unit::Money(b))) if a != b => {
+        rates.iter().find_map(|r| r.factor(a, b)).map(|f| Value::new(self.value * f, Unit::Money(b)))
+      },
+      _ => None,
+    }
+  }
+  
+  /// The rate at which `exchange` would exchange an amount of money for
+  /// another currency, if there is one.
+  pub fn exchange_rate(&self, to: Option<Unit>, rates: &[Rate]) -> Option<Rate> {
+    match (self.unit, to) {
+      (Some(Unit::Money(a)), Some(Unit::Money(b))) if a != b => rates.iter().find(|r| r.factor(a, b).is_some()).copied(),
+      _ => None,
+    }
+  }
+  
   fn base(&self) -> Value {
     match self.unit {
       None       => *self,
@@ -257,7 +522,7 @@ impl Value {
 }
 
 fn operands(left: Value, right: Value) -> (Option<Unit>, Value, Value) {
-  let target = util::coalesce(right.unit, left.unit);
+  let target = right.unit.or(left.unit);
   let left = match left.convert(target) {
     Some(conv) => conv,
     None => left.untype(),
@@ -345,6 +610,136 @@ impl fmt::Display for Value {
   }
 }
 
+/// The largest fixed number of decimal places.
+pub const MAX_PRECISION: usize = 10;
+/// The largest fixed number of significant figures; a double has no more.
+pub const MAX_FIGURES: usize = 15;
+
+/// How values are rounded when they are presented with fewer digits.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rounding {
+  HalfUp,    // halves away from zero
+  HalfEven,  // halves to the even neighbor
+  Down,      // toward zero
+  Up,        // away from zero
+}
+
+/// Every rounding mode, in the order they are listed.
+pub const ROUNDINGS: [Rounding; 4] = [Rounding::HalfUp, Rounding::HalfEven, Rounding::Down, Rounding::Up];
+
+impl Rounding {
+  pub fn from_name(name: &str) -> Option<Rounding> {
+    ROUNDINGS.iter().find(|r| r.to_string().eq_ignore_ascii_case(name.trim())).copied()
+  }
+  
+  /// Round to an integer.
+  fn round(&self, n: f64) -> f64 {
+    let n = (n * 1e9).round() / 1e9; // so 2.675 * 100 is a half, as written
+    match self {
+      Rounding::HalfUp   => n.round(),
+      Rounding::HalfEven => if (n - n.trunc()).abs() == 0.5 { 2.0 * (n / 2.0).round() } else { n.round() },
+      Rounding::Down     => n.trunc(),
+      Rounding::Up       => if n.fract() == 0.0 { n } else { n.trunc() + n.signum() },
+    }
+  }
+}
+
+impl fmt::Display for Rounding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Rounding::HalfUp   => write!(f, "half-up"),
+      Rounding::HalfEven => write!(f, "half-even"),
+      Rounding::Down     => write!(f, "down"),
+      Rounding::Up       => write!(f, "up"),
+    }
+  }
+}
+
+/// How values are presented: the number of decimal places or significant
+/// figures, if either is fixed, how to round to them, and the separators
+/// used for the decimal point and digit groups. Decimal places take
+/// precedence over significant figures when both are set.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Format {
+  pub precision: Option<usize>,
+  pub figures: Option<usize>,
+  pub rounding: Rounding,
+  pub decimal: char,
+  pub grouping: Option<char>,
+}
+
+impl Default for Format {
+  fn default() -> Format {
+    Format{
+      precision: None,
+      figures: None,
+      rounding: Rounding::HalfUp,
+      decimal: '.',
+      grouping: None,
+    }
+  }
+}
+
+impl Format {
+  /// The number of decimal places to which a number is rounded, which is
+  /// negative to round to tens, hundreds, and so on; none if it isn't.
+  fn places(&self, n: f64) -> Option<i32> {
+    match (self.precision, self.figures) {
+      (Some(p), _) => Some(p as i32),
+      (None, Some(f)) if n == 0.0 || !n.is_finite() => Some(f.max(1) as i32 - 1),
+      (None, Some(f)) => Some(f.max(1) as i32 - 1 - n.abs().log10().floor() as i32),
+      (None, None) => None,
+    }
+  }
+}
+
+impl Value {
+  /// Format the value for display. The default format produces the same
+  /// text as `Display`.
+  pub fn format(&self, fmt: &Format) -> String {
+    let num = match fmt.places(self.value) {
+      Some(p) => {
+        let scale = 10f64.powi(p);
+        let n = fmt.rounding.round(self.value * scale) / scale;
+        format!("{:.*}", p.max(0) as usize, if n == 0.0 { 0.0 } else { n }) // no negative zero
+      },
+      None => format!("{}", self.value),
+    };
+    let (int, frac) = match num.split_once('.') {
+      Some((int, frac)) => (int, Some(frac)),
+      None => (num.as_str(), None),
+    };
+    let mut out = match fmt.grouping {
+      Some(sep) => group(int, sep),
+      None      => int.to_string(),
+    };
+    if let Some(frac) = frac {
+      out.push(fmt.decimal);
+      out.push_str(frac);
+    }
+    match self.unit {
+      Some(unit) => format!("{} {}", out, unit),
+      None       => out,
+    }
+  }
+}
+
+/// Separate the digits of an integer into groups of three.
+fn group(int: &str, sep: char) -> String {
+  let (sign, digits) = match int.strip_prefix('-') {
+    Some(digits) => ("-", digits),
+    None => ("", int),
+  };
+  let mut out = String::from(sign);
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i) % 3 == 0 {
+      out.push(sep);
+    }
+    out.push(c);
+  }
+  out
+}
+
 fn to_fraction(n: f64) -> Option<String> {
   if n == 0.125 {
     Some("1/8".to_string())
@@ -471,6 +866,31 @@ mod tests {
     assert_eq!("2 kg", &format!("{:#}", Value::new(2.0, Unit::Kilogram).pack()));
   }
   
+  #[test]
+  fn to_format() {
+    assert_eq!("1234.5 g", &Value::new(1234.5, Unit::Gram).format(&Format::default()));
+    assert_eq!("0.1", &Value::raw(0.1).format(&Format::default()));
+    assert_eq!("3.14", &Value::raw(std::f64::consts::PI).format(&Format{precision: Some(2), ..Format::default()}));
+    assert_eq!("1,234,567.50", &Value::raw(1234567.5).format(&Format{precision: Some(2), grouping: Some(','), ..Format::default()}));
+    assert_eq!("-1.234,5 kg", &Value::new(-1234.5, Unit::Kilogram).format(&Format{decimal: ',', grouping: Some('.'), ..Format::default()}));
+    assert_eq!("123", &Value::raw(123.0).format(&Format{decimal: ',', grouping: Some('.'), ..Format::default()}));
+    
+    let figures = |f, rounding| Format{figures: Some(f), rounding, ..Format::default()};
+    assert_eq!("12000", &Value::raw(12345.0).format(&figures(2, Rounding::HalfUp)));
+    assert_eq!("0.00123", &Value::raw(0.0012345).format(&figures(3, Rounding::HalfUp)));
+    assert_eq!("1.50", &Value::raw(1.5).format(&figures(3, Rounding::HalfUp)));
+    assert_eq!("0.0", &Value::raw(0.0).format(&figures(2, Rounding::HalfUp)));
+    assert_eq!("3.1", &Value::raw(3.14729).format(&Format{precision: Some(1), figures: Some(4), ..Format::default()}));
+    
+    let places = |rounding| Format{precision: Some(2), rounding, ..Format::default()};
+    assert_eq!("2.68", &Value::raw(2.675).format(&places(Rounding::HalfUp)));
+    assert_eq!("2.68", &Value::raw(2.675).format(&places(Rounding::HalfEven)));
+    assert_eq!("2.62", &Value::raw(2.625).format(&places(Rounding::HalfEven)));
+    assert_eq!("-2.67", &Value::raw(-2.679).format(&places(Rounding::Down)));
+    assert_eq!("-2.68", &Value::raw(-2.671).format(&places(Rounding::Up)));
+    assert_eq!("0.00", &Value::raw(-0.001).format(&places(Rounding::HalfUp)));
+  }
+  
   #[test]
   fn convert() {
     assert_eq!(Some(Value::raw(1.0)), Value::new(1.0, Unit::Tablespoon).convert(None));
@@ -482,6 +902,42 @@ mod tests {
     assert_eq!(Some(Value::new(15.0, Unit::Teaspoon)), Value::new(5.0, Unit::Tablespoon).convert(Some(Unit::Teaspoon)));
     assert_eq!(Some(Value::new(1.0, Unit::Cup)), Value::new(16.0, Unit::Tablespoon).convert(Some(Unit::Cup)));
     assert_eq!(Some(Value::new(0.236588395339208, Unit::Liter)), Value::new(16.0, Unit::Tablespoon).convert(Some(Unit::Liter)));
+    
+    let eur = Unit::Money(Currency::Eur);
+    assert_eq!(Some(eur), Unit::from("eur"));
+    assert_eq!(Some(eur), Unit::from("€"));
+    assert_eq!(None, Unit::from("$"));
+    assert_eq!(Some(Value::new(5.0, eur)), Value::new(5.0, eur).convert(Some(eur)));
+    assert_eq!(None, Value::new(5.0, eur).convert(Some(Unit::Money(Currency::Usd))));
+    assert_eq!(None, Value::new(5.0, eur).convert(Some(Unit::Gram)));
+    assert_eq!(Value::new(5.0, eur), Value::new(5.0, eur).pack());
+  }
+  
+  #[test]
+  fn custom_units() {
+    let def = |name: &str, amount, of: &str| Definition{name: name.to_string(), amount, of: of.to_string()};
+    let (units, probs) = define(&[
+      def("pica", 12.0, "point"),
+      def("point", 0.3528, "mm"),
+      def("story_point", 4.0, "hours"),
+      def("pinch", 0.0625, "tsp"),
+      def("cup", 2.0, "l"),
+      def("foo", 2.0, "bar"),
+      def("bar", 0.5, "foo"),
+    ]);
+    assert_eq!(vec![
+      ("cup".to_string(), "already a unit".to_string()),
+      ("foo".to_string(), "refers to itself by way of bar".to_string()),
+      ("bar".to_string(), "refers to itself by way of foo".to_string()),
+    ], probs);
+    set_custom(units);
+    
+    let (pica, mm, pinch) = (Unit::from("pica").unwrap(), Unit::from("mm").unwrap(), Unit::from("pinch").unwrap());
+    assert_eq!("pica", pica.to_string());
+    assert!((Value::new(1.0, pica).convert(Some(mm)).unwrap().value() - 4.2336).abs() < 1e-9);
+    assert_eq!(Some(Value::new(1.0, Unit::Teaspoon)), Value::new(16.0, pinch).convert(Some(Unit::Teaspoon)));
+    assert_eq!(None, Value::new(1.0, pica).convert(Unit::from("story_point")));
+    assert_eq!(None, Unit::from("foo"));
   }
   
   #[test]
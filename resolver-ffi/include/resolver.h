@@ -0,0 +1,40 @@
+/* C interface to the evaluation engine of Resolver, the notepad calculator. */
+
+#ifndef RESOLVER_H
+#define RESOLVER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Evaluates lines of a document in order, so a line can refer to the
+ * variables defined by those before it. */
+typedef struct Evaluator resolver_evaluator;
+
+/* The formatted results of a line and the errors of the expressions in it
+ * which could not be evaluated. */
+typedef struct Line resolver_line;
+
+/* No function unwinds into its caller: should evaluation fail outright,
+ * those producing pointers produce null, counts are zero, and
+ * resolver_evaluator_set_locale produces false. */
+
+resolver_evaluator *resolver_evaluator_new(void);
+void resolver_evaluator_free(resolver_evaluator *ev);
+bool resolver_evaluator_set_locale(resolver_evaluator *ev, const char *name);
+
+resolver_line *resolver_evaluate(resolver_evaluator *ev, const char *text);
+size_t resolver_line_result_count(const resolver_line *line);
+const char *resolver_line_result(const resolver_line *line, size_t index);
+size_t resolver_line_diagnostic_count(const resolver_line *line);
+const char *resolver_line_diagnostic(const resolver_line *line, size_t index);
+void resolver_line_free(resolver_line *line);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
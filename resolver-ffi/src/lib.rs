@@ -0,0 +1,198 @@
+use std::ptr;
+use std::panic::{self, AssertUnwindSafe};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use resolver_core as rdl;
+use resolver_core::exec;
+use resolver_core::locale::Locale;
+
+/// Evaluates lines of a document in order, so a line can refer to the
+/// variables defined by those before it.
+pub struct Evaluator {
+  cxt: exec::Context,
+}
+
+/// The results of a line, formatted for presentation, and the errors of the
+/// expressions in it which could not be evaluated.
+pub struct Line {
+  results: Vec<CString>,
+  diagnostics: Vec<CString>,
+}
+
+impl Line {
+  fn new(cxt: &mut exec::Context, text: &str) -> Line {
+    let (_, fmt) = rdl::split_line(cxt, text);
+    let mut results = Vec::new();
+    let mut diagnostics = Vec::new();
+    for e in rdl::eval(cxt, text) {
+      match &e.result {
+        Ok(res) => results.push(cstring(res.format(&fmt))),
+        Err(err) => diagnostics.push(cstring(format!("{}: {}", e.text(text).trim(), err))),
+      };
+    }
+    Line{
+      results,
+      diagnostics,
+    }
+  }
+}
+
+fn cstring(text: String) -> CString {
+  CString::new(text.replace('\0', "")).expect("Nul bytes were removed")
+}
+
+/// Run the body of an entry point, producing a fallback if it panics, since
+/// a panic must not unwind into the caller.
+fn guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+  panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+/// Create an evaluator, with the standard library defined. Free it with
+/// `resolver_evaluator_free`. Produces null if it can't be created.
+#[no_mangle]
+pub extern "C" fn resolver_evaluator_new() -> *mut Evaluator {
+  guard(ptr::null_mut(), || Box::into_raw(Box::new(Evaluator{
+    cxt: exec::Context::new_with_stdlib(),
+  })))
+}
+
+/// Free an evaluator.
+///
+/// # Safety
+/// The evaluator must have been produced by `resolver_evaluator_new` and
+/// not freed already, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_evaluator_free(ev: *mut Evaluator) {
+  if !ev.is_null() {
+    guard((), || drop(Box::from_raw(ev)));
+  }
+}
+
+/// Set the conventions of a locale, such as "de", for the lines evaluated
+/// from now on. Produces false if there is no such locale.
+///
+/// # Safety
+/// The evaluator must be valid and the name a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_evaluator_set_locale(ev: *mut Evaluator, name: *const c_char) -> bool {
+  if ev.is_null() || name.is_null() {
+    return false;
+  }
+  guard(false, || match Locale::named(&CStr::from_ptr(name).to_string_lossy()) {
+    Some(locale) => {
+      (*ev).cxt.set_locale(locale);
+      true
+    },
+    None => false,
+  })
+}
+
+/// Evaluate a line of text, which must be UTF-8, and produce its results.
+/// Free them with `resolver_line_free`. Produces null if either argument is,
+/// or if the line can't be evaluated at all.
+///
+/// # Safety
+/// The evaluator must be valid and the text a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_evaluate(ev: *mut Evaluator, text: *const c_char) -> *mut Line {
+  if ev.is_null() || text.is_null() {
+    return ptr::null_mut();
+  }
+  guard(ptr::null_mut(), || {
+    let text = CStr::from_ptr(text).to_string_lossy();
+    Box::into_raw(Box::new(Line::new(&mut (*ev).cxt, &text)))
+  })
+}
+
+/// The number of results of a line.
+///
+/// # Safety
+/// The line must have been produced by `resolver_evaluate`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_line_result_count(line: *const Line) -> usize {
+  guard(0, || line.as_ref().map_or(0, |l| l.results.len()))
+}
+
+/// A result of a line, formatted, or null if there is no such result. The
+/// string belongs to the line and is valid until it is freed.
+///
+/// # Safety
+/// The line must have been produced by `resolver_evaluate`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_line_result(line: *const Line, index: usize) -> *const c_char {
+  guard(ptr::null(), || line.as_ref().and_then(|l| l.results.get(index)).map_or(ptr::null(), |r| r.as_ptr()))
+}
+
+/// The number of expressions in a line which could not be evaluated.
+///
+/// # Safety
+/// The line must have been produced by `resolver_evaluate`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_line_diagnostic_count(line: *const Line) -> usize {
+  guard(0, || line.as_ref().map_or(0, |l| l.diagnostics.len()))
+}
+
+/// A description of why an expression in a line could not be evaluated, or
+/// null if there is no such expression. The string belongs to the line and
+/// is valid until it is freed.
+///
+/// # Safety
+/// The line must have been produced by `resolver_evaluate`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_line_diagnostic(line: *const Line, index: usize) -> *const c_char {
+  guard(ptr::null(), || line.as_ref().and_then(|l| l.diagnostics.get(index)).map_or(ptr::null(), |d| d.as_ptr()))
+}
+
+/// Free the results of a line.
+///
+/// # Safety
+/// The line must have been produced by `resolver_evaluate` and not freed
+/// already, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn resolver_line_free(line: *mut Line) {
+  if !line.is_null() {
+    guard((), || drop(Box::from_raw(line)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn evaluate_lines() {
+    let text = |s: *const c_char| unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+    unsafe {
+      let ev = resolver_evaluator_new();
+      let line = resolver_evaluate(ev, c"rate = 12.5 USD".as_ptr());
+      assert_eq!(1, resolver_line_result_count(line));
+      resolver_line_free(line);
+
+      let line = resolver_evaluate(ev, c"rate * 2, nope # precision: 2".as_ptr());
+      assert_eq!(1, resolver_line_result_count(line));
+      assert_eq!("25.00 USD", text(resolver_line_result(line, 0)));
+      assert!(resolver_line_result(line, 1).is_null());
+      assert_eq!(1, resolver_line_diagnostic_count(line));
+      assert_eq!("nope: No such variable: nope", text(resolver_line_diagnostic(line, 0)));
+      resolver_line_free(line);
+
+      assert!(resolver_evaluator_set_locale(ev, c"de".as_ptr()));
+      assert!(!resolver_evaluator_set_locale(ev, c"xx".as_ptr()));
+      let line = resolver_evaluate(ev, c"1,5 * 2".as_ptr());
+      assert_eq!("3", text(resolver_line_result(line, 0)));
+      resolver_line_free(line);
+
+      assert!(resolver_evaluate(ev, ptr::null()).is_null());
+      assert_eq!(0, resolver_line_result_count(ptr::null()));
+      resolver_evaluator_free(ev);
+    }
+  }
+
+  #[test]
+  fn contain_panics() {
+    assert_eq!(Some(1), guard(Some(1), || panic!("unwinding")));
+    assert_eq!(Some(2), guard(None, || Some(2)));
+  }
+
+}
@@ -0,0 +1,17 @@
+use wasm_bindgen::prelude::*;
+
+use resolver_core::exec;
+use resolver_core::json;
+
+/// Evaluate every line of a document in order, as the editor does, and
+/// produce an array with the results of each line. Lines have the same
+/// fields as the command line's JSON output. Files named by `use`
+/// directives cannot be read here, so they import nothing.
+#[wasm_bindgen]
+pub fn evaluate(document: &str) -> Result<JsValue, JsValue> {
+  let mut cxt = exec::Context::new_with_stdlib();
+  let lines: Vec<json::Line> = document.lines().enumerate()
+    .map(|(i, text)| json::eval_line(&mut cxt, i + 1, text))
+    .collect();
+  Ok(serde_wasm_bindgen::to_value(&lines)?)
+}
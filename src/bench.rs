@@ -0,0 +1,61 @@
+// Benchmarks of drawing the editor, to measure changes to it against.
+// They're ignored tests, since the editor is part of the binary; run them
+// with `cargo test --release bench -- --ignored --nocapture`. Those of the
+// engine are in `resolver-core/benches`. Neither uses Criterion, which the
+// project doesn't depend on; each is timed with `std::time::Instant` over a
+// fixed number of iterations, without its statistics or saved baselines.
+
+use std::hint::black_box;
+use std::time;
+
+use crossterm::style::Color;
+
+use crate::editor::driver::Driver;
+use crate::text::attrs::{self, Attributes, Span};
+
+/// A representative document, which the engine's benchmarks use too.
+const BUDGET: &str = include_str!("../resolver-core/benches/fixtures/budget.rdl");
+/// How many copies of the representative document make a large one.
+const LARGE: usize = 100;
+/// About how long each benchmark runs for.
+const DURATION: time::Duration = time::Duration::from_secs(1);
+/// The size of the screen drawn, in columns and rows.
+const SCREEN: (usize, usize) = (160, 50);
+
+/// Run a benchmark repeatedly for about `DURATION`, and print how long it
+/// took each time, on average.
+fn measure<T>(name: &str, mut f: impl FnMut() -> T) {
+  black_box(f()); // warm up
+  let start = time::Instant::now();
+  let mut n = 0;
+  while start.elapsed() < DURATION {
+    black_box(f());
+    n += 1;
+  }
+  println!("{:<40} {:>12.2?} ({} iterations)", name, start.elapsed() / n, n);
+}
+
+#[test]
+#[ignore]
+fn bench_merge_spans() {
+  let plain = Attributes{bold: false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+  let syntax: Vec<Span> = (0..200).map(|i| Span::new(i * 5..i * 5 + 3, Attributes{color: Some(Color::Blue), ..plain.clone()})).collect();
+  let overlaps: Vec<Span> = (0..100).map(|i| Span::new(i * 10 + 2..i * 10 + 8, Attributes{bold: true, ..plain.clone()})).collect();
+  measure("merge spans", || attrs::merge(syntax.clone(), overlaps.clone()));
+}
+
+#[test]
+#[ignore]
+fn bench_draw_frame() {
+  let mut driver = Driver::new(SCREEN);
+  driver.set_text(&BUDGET.repeat(LARGE));
+  measure("draw a frame of a large document", || driver.draw());
+}
+
+#[test]
+#[ignore]
+fn bench_open_document() {
+  let large = BUDGET.repeat(LARGE);
+  let mut driver = Driver::new(SCREEN);
+  measure("open a large document", || driver.set_text(&large));
+}
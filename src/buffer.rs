@@ -4,15 +4,33 @@ use std::str;
 
 pub struct Buffer {
   data: String,
+  captured: Option<String>, // what has been flushed, kept instead of written to the terminal
 }
 
 impl Buffer {
   pub fn new() -> Self {
     Buffer{
       data: String::new(),
+      captured: None,
     }
   }
   
+  /// A buffer which keeps what is flushed instead of writing it to the
+  /// terminal.
+  #[cfg(test)]
+  pub fn capturing() -> Self {
+    Buffer{
+      data: String::new(),
+      captured: Some(String::new()),
+    }
+  }
+  
+  /// Take what has been flushed since this was last called.
+  #[cfg(test)]
+  pub fn take_captured(&mut self) -> String {
+    self.captured.as_mut().map(std::mem::take).unwrap_or_default()
+  }
+  
   pub fn _text(&self) -> &str {
     &self.data
   }
@@ -46,6 +64,11 @@ impl io::Write for Buffer {
   }
   
   fn flush(&mut self) -> io::Result<()> {
+    if let Some(captured) = &mut self.captured {
+      captured.push_str(&self.data);
+      self.clear();
+      return Ok(());
+    }
     let out = write!(stdout(), "{}", self.data);
     stdout().flush()?;
     self.clear();
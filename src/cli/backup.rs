@@ -0,0 +1,23 @@
+use std::path;
+use std::io;
+
+use crate::error;
+use crate::document::backup::Backups;
+
+/// Restore a document from one of its backups.
+pub fn restore(doc: &str, n: usize, keep: usize) -> Result<(), error::Error> {
+  let doc = path::Path::new(doc);
+  let backups = Backups::in_state_dir(keep)?;
+  find(&backups, doc, n)?;
+  backups.restore(doc, n)?;
+  Ok(())
+}
+
+/// Find the Nth most recent backup of a document.
+pub fn find(backups: &Backups, doc: &path::Path, n: usize) -> io::Result<path::PathBuf> {
+  let list = backups.list(doc);
+  match list.get(n.wrapping_sub(1)) {
+    Some(path) => Ok(path.clone()),
+    None => Err(io::Error::new(io::ErrorKind::NotFound, format!("No backup {} of {}; {} available", n, doc.display(), list.len()))),
+  }
+}
@@ -0,0 +1,237 @@
+use std::fs;
+use std::path;
+use std::process;
+use std::io::{self, Write, IsTerminal};
+
+use crossterm::style::Color;
+
+use crate::error;
+use crate::document;
+use crate::rates::cache;
+use crate::rdl;
+use crate::rdl::import::Imports;
+use crate::text::{attrs, width};
+use crate::util::diff;
+use crate::config::file::Config;
+use crate::document::backup::Backups;
+use crate::document::settings;
+use crate::cli::backup;
+use crate::cli::pipe;
+
+const MAX_TEXT: usize = 40; // columns of a line shown, beyond which it is cut short
+const SEPARATOR: &str = " │ ";
+
+/// How a line differs between two versions of a document.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Change {
+  Same,    // neither the line nor its results changed
+  Results, // the line is the same but its results changed, because of a line above it
+  Edited,  // the line was changed
+  Removed,
+  Added,
+}
+
+impl Change {
+  fn marker(&self) -> char {
+    match self {
+      Change::Same    => ' ',
+      Change::Results => '~',
+      Change::Edited  => '!',
+      Change::Removed => '-',
+      Change::Added   => '+',
+    }
+  }
+}
+
+/// A line of one version of a document, and its results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Side {
+  pub line: usize,
+  pub text: String,
+  pub results: String,
+}
+
+/// A line of the old version of a document beside its counterpart in the
+/// new version, if they have one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+  pub change: Change,
+  pub old: Option<Side>,
+  pub new: Option<Side>,
+}
+
+/// Show how a document differs from one of its backups.
+pub fn run_backup(doc: &str, n: usize, keep: usize, conf: &Config) -> Result<(), error::Error> {
+  let doc = path::Path::new(doc);
+  let backups = Backups::in_state_dir(keep)?;
+  let old = fs::read_to_string(backup::find(&backups, doc, n)?)?;
+  run(doc, &old, conf)
+}
+
+/// Show how a document differs from a git revision of it, such as `HEAD`
+/// or `main~3`.
+pub fn run_revision(doc: &str, rev: &str, conf: &Config) -> Result<(), error::Error> {
+  let doc = path::Path::new(doc);
+  let old = at_revision(doc, rev)?;
+  run(doc, &old, conf)
+}
+
+fn run(doc: &path::Path, old: &str, conf: &Config) -> Result<(), error::Error> {
+  let new = document::load(doc)?;
+  let rows = compare(old, &new, conf, &mut Imports::new_for_document(doc));
+  let mut stdout = io::stdout().lock();
+  let color = stdout.is_terminal();
+  write!(stdout, "{}", render(&rows, color))?;
+  Ok(())
+}
+
+/// Read a document as it was at a git revision.
+fn at_revision(doc: &path::Path, rev: &str) -> io::Result<String> {
+  let dir = doc.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(path::Path::new("."));
+  let name = doc.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file", doc.display())))?;
+  let out = process::Command::new("git")
+    .arg("-C").arg(dir)
+    .arg("show")
+    .arg(format!("{}:./{}", rev, name.to_string_lossy()))
+    .output()?;
+  if !out.status.success() {
+    return Err(io::Error::other(String::from_utf8_lossy(&out.stderr).trim().to_string()));
+  }
+  String::from_utf8(out.stdout).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Evaluate a document, producing the results of each line, joined.
+fn evaluate(text: &str, conf: &Config, imports: &mut Imports) -> Vec<String> {
+  let mut defaults = conf.defaults();
+  defaults.rates.extend(cache::load().map(|(c, _)| c.rates.rates).unwrap_or_default()); // so both versions are exchanged at the same rates
+  let mut cxt = settings::context(text, Some((&conf.locale, &defaults)));
+  text.lines().enumerate().map(|(i, line)| {
+    pipe::import(imports, &mut cxt, i + 1, line);
+    rdl::render(&mut cxt, line).iter().map(|r| r.text.as_str()).collect::<Vec<&str>>().join(", ")
+  }).collect()
+}
+
+/// Compare two versions of a document line by line, along with the results
+/// of each line, which change when a line they depend on does. Lines
+/// removed and added in the same place are paired as edits.
+pub fn compare(old: &str, new: &str, conf: &Config, imports: &mut Imports) -> Vec<Row> {
+  let a: Vec<&str> = old.lines().collect();
+  let b: Vec<&str> = new.lines().collect();
+  let ra = evaluate(old, conf, imports);
+  let rb = evaluate(new, conf, imports);
+  let side = |lines: &[&str], results: &[String], i: usize| Side{line: i + 1, text: lines[i].to_string(), results: results[i].clone()};
+
+  let mut rows = Vec::new();
+  let (mut removed, mut added): (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+  let pairs = diff::align(&a, &b);
+  for (n, pair) in pairs.iter().enumerate() {
+    match *pair {
+      (Some(i), None) => removed.push(i),
+      (None, Some(j)) => added.push(j),
+      _ => {},
+    };
+    if !matches!(pairs.get(n + 1), Some((Some(_), None) | (None, Some(_)))) { // the end of a change
+      for k in 0..removed.len().max(added.len()) {
+        let (old, new) = (removed.get(k).map(|i| side(&a, &ra, *i)), added.get(k).map(|j| side(&b, &rb, *j)));
+        let change = match (&old, &new) {
+          (Some(_), Some(_)) => Change::Edited,
+          (Some(_), None) => Change::Removed,
+          _ => Change::Added,
+        };
+        rows.push(Row{change, old, new});
+      }
+      removed.clear();
+      added.clear();
+    }
+    if let (Some(i), Some(j)) = *pair {
+      let change = if ra[i] == rb[j] { Change::Same }else{ Change::Results };
+      rows.push(Row{change, old: Some(side(&a, &ra, i)), new: Some(side(&b, &rb, j))});
+    }
+  }
+  rows
+}
+
+/// Write out the lines which changed, or whose results did, with each
+/// line of the old version beside its counterpart in the new, followed by
+/// how many changed. Changes are highlighted in color if asked to be.
+pub fn render(rows: &[Row], color: bool) -> String {
+  let changed: Vec<&Row> = rows.iter().filter(|r| r.change != Change::Same).collect();
+  let ((otw, orw), (ntw, nrw)) = (widths(changed.iter().filter_map(|r| r.old.as_ref())), widths(changed.iter().filter_map(|r| r.new.as_ref())));
+  let num = changed.iter().flat_map(|r| r.old.iter().chain(r.new.iter())).map(|s| s.line.to_string().len()).max().unwrap_or(1);
+
+  let paint = |text: String, style: Option<Color>, bold: bool| match (color, style) {
+    (true, Some(c)) => attrs::Attributes{bold, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(c), background: None, link: None}.render(&text),
+    _ => text,
+  };
+  let cell = |side: Option<&Side>, tw: usize, rw: usize, text_color: Option<Color>, res_color: Option<Color>| match side {
+    Some(s) => {
+      let text = clip(&s.text, tw);
+      format!("{:>num$}  {}{}  {}{}",
+        s.line,
+        paint(text.to_string(), text_color, false), " ".repeat(tw - width::str_width(text)),
+        " ".repeat(rw - width::str_width(&s.results)), paint(s.results.clone(), res_color, true),
+        num = num)
+    },
+    None => " ".repeat(num + 2 + tw + 2 + rw),
+  };
+
+  let mut out = String::new();
+  for r in &changed {
+    let results = r.old.as_ref().map(|s| &s.results) != r.new.as_ref().map(|s| &s.results);
+    let (otc, ntc) = match r.change {
+      Change::Removed => (Some(Color::Red), None),
+      Change::Added => (None, Some(Color::Green)),
+      Change::Edited => (Some(Color::Yellow), Some(Color::Yellow)),
+      _ => (None, None),
+    };
+    let (orc, nrc) = if results { (Some(Color::Red), Some(Color::Green)) }else{ (None, None) };
+    let line = format!("{} {}{}{}", r.change.marker(), cell(r.old.as_ref(), otw, orw, otc, orc), SEPARATOR, cell(r.new.as_ref(), ntw, nrw, ntc, nrc));
+    out.push_str(line.trim_end());
+    out.push('\n');
+  }
+  let lines = changed.iter().filter(|r| r.change != Change::Results).count();
+  let results = changed.iter().filter(|r| matches!((&r.old, &r.new), (Some(o), Some(n)) if o.results != n.results)).count();
+  out.push_str(&format!("{} {} changed, {} {} changed\n", lines, if lines == 1 { "line" }else{ "lines" }, results, if results == 1 { "result" }else{ "results" }));
+  out
+}
+
+/// The widest any of some lines is, and any of their results, in columns.
+fn widths<'a>(sides: impl Iterator<Item = &'a Side>) -> (usize, usize) {
+  sides.fold((0, 0), |(tw, rw), s| (tw.max(width::str_width(&s.text).min(MAX_TEXT)), rw.max(width::str_width(&s.results))))
+}
+
+/// Truncate text to at most the provided width, in columns.
+fn clip(text: &str, max: usize) -> &str {
+  let mut n = 0;
+  for (i, c) in text.char_indices() {
+    n += width::char_width(c);
+    if n > max {
+      return &text[..i];
+    }
+  }
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compare_versions() {
+    let old = "rent = 1200\nfood = 400\ntip = 20\ntotal = rent + food\ntotal * 12\n";
+    let new = "rent = 1300\nfood = 400\ntotal = rent + food\ntotal * 12\nspare = 5000 - total\n";
+    let rows = compare(old, new, &Config::default(), &mut Imports::new(path::Path::new("")));
+    assert_eq!(vec![Change::Edited, Change::Same, Change::Removed, Change::Results, Change::Results, Change::Added], rows.iter().map(|r| r.change).collect::<Vec<_>>());
+    assert_eq!(Some(("total * 12", "20400")), rows[4].new.as_ref().map(|s| (s.text.as_str(), s.results.as_str())));
+    assert_eq!(
+      "! 1  rent = 1200           1200 │ 1  rent = 1300            1300\n\
+       - 3  tip = 20                20 │\n\
+       ~ 4  total = rent + food   1600 │ 3  total = rent + food    1700\n\
+       ~ 5  total * 12           19200 │ 4  total * 12            20400\n\
+       +                               │ 5  spare = 5000 - total   3300\n\
+       3 lines changed, 3 results changed\n",
+      render(&rows, false),
+    );
+  }
+
+}
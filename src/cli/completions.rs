@@ -0,0 +1,193 @@
+use clap::{ArgEnum, CommandFactory};
+
+use crate::error;
+use crate::options::Options;
+use crate::rdl::unit;
+
+/// The shells completions can be generated for.
+#[derive(ArgEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Shell {
+  Bash,
+  Zsh,
+  Fish,
+}
+
+/// What the value of an option is completed with.
+#[derive(Debug, Clone, PartialEq)]
+enum Complete {
+  Nothing, // the option takes no value
+  Files,
+  Words(Vec<String>),
+  Anything,
+}
+
+/// An option of the command line, as it is completed.
+#[derive(Debug, Clone, PartialEq)]
+struct Flag {
+  long: String,
+  short: Option<char>,
+  help: String,
+  value: Complete,
+}
+
+/// Print a script which completes the command line in a shell.
+pub fn run(shell: Shell) -> Result<(), error::Error> {
+  print!("{}", generate(shell, &flags(), &words()));
+  Ok(())
+}
+
+/// The words an expression is made of which the engine knows: the names of
+/// units and the codes of currencies.
+fn words() -> Vec<String> {
+  unit::units().iter().map(|u| u.to_string()).chain(unit::CURRENCIES.iter().map(|c| c.code().to_string())).collect()
+}
+
+/// The options of the command line, as clap knows them.
+fn flags() -> Vec<Flag> {
+  let mut cmd = Options::command();
+  cmd.build();
+  cmd.get_arguments().filter(|a| !a.is_positional() && !a.is_hide_set()).filter_map(|arg| {
+    let value = if !arg.is_takes_value_set() {
+      Complete::Nothing
+    }else if let Some(values) = arg.get_possible_values() {
+      Complete::Words(values.iter().map(|v| v.get_name().to_string()).collect())
+    }else if arg.get_id() == "expr" {
+      Complete::Words(words())
+    }else{
+      match arg.get_value_names().and_then(|n| n.first()) {
+        Some(&"DOC") | Some(&"FILE") | Some(&"CONNECTION_FILE") => Complete::Files,
+        _ => Complete::Anything,
+      }
+    };
+    Some(Flag{
+      long: arg.get_long()?.to_string(),
+      short: arg.get_short(),
+      help: arg.get_help().unwrap_or("").to_string(),
+      value,
+    })
+  }).collect()
+}
+
+fn generate(shell: Shell, flags: &[Flag], words: &[String]) -> String {
+  match shell {
+    Shell::Bash => bash(flags),
+    Shell::Zsh  => zsh(flags, words),
+    Shell::Fish => fish(flags),
+  }
+}
+
+fn bash(flags: &[Flag]) -> String {
+  let mut out = String::from("_resolver() {\n  local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n  case \"$prev\" in\n");
+  for flag in flags {
+    let names = match flag.short {
+      Some(s) => format!("-{}|--{}", s, flag.long),
+      None => format!("--{}", flag.long),
+    };
+    match &flag.value {
+      Complete::Nothing => {},
+      Complete::Files => out.push_str(&format!("    {}) COMPREPLY=($(compgen -f -- \"$cur\")); return;;\n", names)),
+      Complete::Words(words) => out.push_str(&format!("    {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return;;\n", names, words.join(" "))),
+      Complete::Anything => out.push_str(&format!("    {}) return;;\n", names)),
+    }
+  }
+  out.push_str("  esac\n  if [[ \"$cur\" == -* ]]; then\n");
+  let names: Vec<String> = flags.iter().flat_map(|f| f.short.map(|s| format!("-{}", s)).into_iter().chain([format!("--{}", f.long)])).collect();
+  out.push_str(&format!("    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n", names.join(" ")));
+  out.push_str("  else\n    COMPREPLY=($(compgen -f -- \"$cur\"))\n  fi\n}\ncomplete -o filenames -F _resolver resolver\n");
+  out
+}
+
+fn zsh(flags: &[Flag], words: &[String]) -> String {
+  let mut out = format!("#compdef resolver\n\n_resolver() {{\n  local -a units=({})\n  _arguments -s \\\n", words.join(" "));
+  for flag in flags {
+    let help = flag.help.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]");
+    let names = match flag.short {
+      Some(s) => format!("'(-{} --{})'{{-{},--{}}}'", s, flag.long, s, flag.long),
+      None => format!("'--{}", flag.long),
+    };
+    let value = match &flag.value {
+      Complete::Nothing => String::new(),
+      Complete::Files => ":file:_files".to_string(),
+      Complete::Words(w) if w == words => ":expression:($units)".to_string(),
+      Complete::Words(w) => format!(":value:({})", w.join(" ")),
+      Complete::Anything => ":value: ".to_string(),
+    };
+    out.push_str(&format!("    {}[{}]{}' \\\n", names, help, value));
+  }
+  out.push_str("    '::document:_files'\n}\n\n_resolver \"$@\"\n");
+  out
+}
+
+fn fish(flags: &[Flag]) -> String {
+  let mut out = String::new();
+  for flag in flags {
+    out.push_str(&format!("complete -c resolver -l {}", flag.long));
+    if let Some(s) = flag.short {
+      out.push_str(&format!(" -s {}", s));
+    }
+    match &flag.value {
+      Complete::Nothing => {},
+      Complete::Files => out.push_str(" -r -F"),
+      Complete::Words(words) => out.push_str(&format!(" -x -a '{}'", words.join(" "))),
+      Complete::Anything => out.push_str(" -x"),
+    }
+    out.push_str(&format!(" -d '{}'\n", flag.help.replace('\\', "\\\\").replace('\'', "\\'")));
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn generate_completions() {
+    let flags = flags();
+    let find = |long: &str| flags.iter().find(|f| f.long == long).unwrap_or_else(|| panic!("No flag --{}", long));
+    assert_eq!(Some('e'), find("eval").short);
+    assert_eq!(Complete::Words(words()), find("eval").value);
+    assert_eq!(Complete::Words(vec!["text".to_string(), "json".to_string()]), find("format").value);
+    assert_eq!(Complete::Files, find("watch").value);
+    assert_eq!(Complete::Nothing, find("help").value);
+    assert!(words().contains(&"tbsp".to_string()) && words().contains(&"EUR".to_string()));
+
+    let flags = vec![
+      Flag{long: "eval".to_string(), short: Some('e'), help: "Evaluate an [expr]".to_string(), value: Complete::Words(vec!["kg".to_string(), "USD".to_string()])},
+      Flag{long: "watch".to_string(), short: None, help: "Watch a doc's changes".to_string(), value: Complete::Files},
+    ];
+    let words = vec!["kg".to_string(), "USD".to_string()];
+    assert_eq!("\
+_resolver() {
+  local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"
+  case \"$prev\" in
+    -e|--eval) COMPREPLY=($(compgen -W \"kg USD\" -- \"$cur\")); return;;
+    --watch) COMPREPLY=($(compgen -f -- \"$cur\")); return;;
+  esac
+  if [[ \"$cur\" == -* ]]; then
+    COMPREPLY=($(compgen -W \"-e --eval --watch\" -- \"$cur\"))
+  else
+    COMPREPLY=($(compgen -f -- \"$cur\"))
+  fi
+}
+complete -o filenames -F _resolver resolver
+", generate(Shell::Bash, &flags, &words));
+    assert_eq!("\
+#compdef resolver
+
+_resolver() {
+  local -a units=(kg USD)
+  _arguments -s \\
+    '(-e --eval)'{-e,--eval}'[Evaluate an \\[expr\\]]:expression:($units)' \\
+    '--watch[Watch a doc'\\''s changes]:file:_files' \\
+    '::document:_files'
+}
+
+_resolver \"$@\"
+", generate(Shell::Zsh, &flags, &words));
+    assert_eq!("\
+complete -c resolver -l eval -s e -x -a 'kg USD' -d 'Evaluate an [expr]'
+complete -c resolver -l watch -r -F -d 'Watch a doc\\'s changes'
+", generate(Shell::Fish, &flags, &words));
+  }
+
+}
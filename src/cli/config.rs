@@ -0,0 +1,35 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crate::error;
+use crate::config::file::Config;
+use crate::config::overrides::Overrides;
+use crate::config::plugins::Plugin;
+use crate::document::settings::Settings;
+
+/// Print the effective value of each setting which can be overridden, for
+/// a document if one is provided, and where each value comes from.
+pub fn show(conf: &Config, over: &Overrides, doc: Option<&str>) -> Result<(), error::Error> {
+  let settings = match doc {
+    Some(doc) => Settings::parse(&fs::read_to_string(doc)?),
+    None => Settings::default(),
+  };
+  let mut out = io::stdout();
+  for (key, val, source) in over.resolve(conf, &settings) {
+    writeln!(out, "{:<12}{:<14}{}", key, val, source)?;
+  }
+  Ok(())
+}
+
+/// Print each enabled plugin and where it was loaded from, or why it
+/// could not be.
+pub fn plugins(plugins: &[(String, Result<Plugin, String>)]) -> Result<(), error::Error> {
+  let mut out = io::stdout();
+  for (name, res) in plugins {
+    match res {
+      Ok(plugin) => writeln!(out, "{}", plugin)?,
+      Err(err) => writeln!(out, "{}: {}", name, err)?,
+    };
+  }
+  Ok(())
+}
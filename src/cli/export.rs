@@ -0,0 +1,89 @@
+use std::path;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::error;
+use crate::document::{self, settings};
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::rdl::json;
+use crate::cli::pipe;
+
+/// A variable defined by a document.
+#[derive(Debug, Serialize)]
+pub struct Variable {
+  pub value: f64,
+  pub unit: Option<String>,
+  pub display: String,
+}
+
+/// Everything computed by a document: the variables defined once it has
+/// been evaluated in full, and the results of every line.
+#[derive(Debug, Serialize)]
+pub struct Export {
+  pub variables: BTreeMap<String, Variable>,
+  pub lines: Vec<json::Line>,
+}
+
+impl Export {
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).expect("Could not encode export")
+  }
+}
+
+/// Evaluate a document and write its variables and results to standard
+/// output as a single JSON object.
+pub fn run(doc: &str) -> Result<(), error::Error> {
+  let path = path::Path::new(doc);
+  let text = document::load(path)?;
+  let mut imports = Imports::new_for_document(path);
+  writeln!(io::stdout(), "{}", export(&text, &mut imports).to_json())?;
+  Ok(())
+}
+
+/// Evaluate a document and produce its export. Standard library constants
+/// are only included if the document redefines them.
+pub fn export(text: &str, imports: &mut Imports) -> Export {
+  let stdlib = exec::Context::new_with_stdlib();
+  let mut cxt = settings::context(text, None);
+  let mut lines: Vec<json::Line> = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    pipe::import(imports, &mut cxt, i + 1, line);
+    lines.push(json::eval_line(&mut cxt, i + 1, line));
+  }
+  let fmt = cxt.format();
+  let variables = cxt.vars().into_iter()
+    .filter(|(k, v)| stdlib.get(k) != Some(*v))
+    .map(|(k, v)| (k.to_string(), Variable{
+      value: v.value(),
+      unit: v.unit().map(|u| u.to_string()),
+      display: v.format(&fmt),
+    }))
+    .collect();
+  Export{
+    variables,
+    lines,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn export_document() {
+    let text = "rent = 1200\nfood = 400\nmonthly_total = rent + food\n";
+    let exp = export(text, &mut Imports::new(path::Path::new("")));
+    assert_eq!(vec!["food", "monthly_total", "rent"], exp.variables.keys().collect::<Vec<_>>());
+    assert_eq!(1600.0, exp.variables["monthly_total"].value);
+    assert_eq!(3, exp.lines.len());
+    assert_eq!("1600", &exp.lines[2].results[0].display);
+    
+    let exp = export(&format!("# precision: 2\n{}", text), &mut Imports::new(path::Path::new(""))); // as the document is displayed
+    assert_eq!("1600.00", &exp.lines[3].results[0].display);
+    assert_eq!("1600.00", &exp.variables["monthly_total"].display);
+  }
+  
+}
@@ -0,0 +1,392 @@
+pub mod zmtp;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::config::file::Config;
+use crate::error;
+use crate::logging;
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::snippet::Snippet;
+use super::serve;
+use zmtp::{Connection, Kind};
+
+/// Separates the routing identities of a message from the rest of it.
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+/// The version of the Jupyter messaging protocol spoken.
+const PROTOCOL: &str = "5.3";
+/// The name of the kernel, as Jupyter lists it.
+const NAME: &str = "resolver";
+
+/// Where Jupyter says to listen, and the key messages are signed with, as
+/// read from the connection file it starts the kernel with.
+#[derive(Debug, Deserialize)]
+struct Connect {
+  ip: String,
+  #[serde(default = "tcp")]
+  transport: String,
+  shell_port: u16,
+  control_port: u16,
+  iopub_port: u16,
+  stdin_port: u16,
+  hb_port: u16,
+  #[serde(default)]
+  key: String,
+  #[serde(default = "hmac_sha256")]
+  signature_scheme: String,
+}
+
+fn tcp() -> String {
+  "tcp".to_string()
+}
+
+fn hmac_sha256() -> String {
+  "hmac-sha256".to_string()
+}
+
+/// A message of the Jupyter protocol, with the identities of whoever it
+/// is routed to.
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+  ids: Vec<Vec<u8>>,
+  header: Value,
+  parent: Value,
+  metadata: Value,
+  content: Value,
+}
+
+impl Message {
+  /// Parse a message from its frames, checking its signature.
+  fn parse(frames: &[Vec<u8>], key: &[u8]) -> Result<Message, String> {
+    let delim = frames.iter().position(|f| f == DELIMITER).ok_or("message has no delimiter")?;
+    let parts = &frames[delim + 1..];
+    if parts.len() < 5 {
+      return Err(format!("message has {} parts after the delimiter, expected 5", parts.len()));
+    }
+    if !key.is_empty() && parts[0] != sign(key, &parts[1..5]).as_bytes() {
+      return Err("message has an invalid signature".to_string());
+    }
+    let json = |part: &Vec<u8>| serde_json::from_slice::<Value>(part).map_err(|err| err.to_string());
+    Ok(Message{
+      ids: frames[..delim].to_vec(),
+      header: json(&parts[1])?,
+      parent: json(&parts[2])?,
+      metadata: json(&parts[3])?,
+      content: json(&parts[4])?,
+    })
+  }
+
+  /// Encode a message as frames, signed.
+  fn frames(&self, key: &[u8]) -> Vec<Vec<u8>> {
+    let parts: Vec<Vec<u8>> = [&self.header, &self.parent, &self.metadata, &self.content].iter().map(|v| v.to_string().into_bytes()).collect();
+    let mut frames = self.ids.clone();
+    frames.push(DELIMITER.to_vec());
+    frames.push(if key.is_empty() { Vec::new() }else{ sign(key, &parts).into_bytes() });
+    frames.extend(parts);
+    frames
+  }
+
+  fn msg_type(&self) -> &str {
+    self.header["msg_type"].as_str().unwrap_or("")
+  }
+}
+
+/// The HMAC-SHA256 of the parts of a message, in hexadecimal.
+fn sign(key: &[u8], parts: &[Vec<u8>]) -> String {
+  let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+  for part in parts {
+    mac.update(part);
+  }
+  mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// What persists between the cells a notebook executes: what they define,
+/// and how many have been executed.
+struct Session {
+  cxt: exec::Context,
+  imports: Imports,
+  count: usize,
+}
+
+struct Kernel {
+  key: Vec<u8>,
+  session: String,
+  state: Mutex<Session>,
+  iopub: Mutex<Vec<Connection>>,
+  serial: AtomicUsize,
+}
+
+impl Kernel {
+  /// A message in reply to another, or published because of it.
+  fn reply(&self, parent: &Message, msg_type: &str, content: Value) -> Message {
+    let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+    let date = logging::timestamp(time::SystemTime::now()).replace(' ', "T") + "Z";
+    Message{
+      ids: parent.ids.clone(),
+      header: json!({
+        "msg_id": format!("{}-{}", self.session, serial),
+        "session": self.session,
+        "username": NAME,
+        "date": date,
+        "msg_type": msg_type,
+        "version": PROTOCOL,
+      }),
+      parent: parent.header.clone(),
+      metadata: json!({}),
+      content,
+    }
+  }
+
+  /// Publish a message to every frontend listening, forgetting those which
+  /// have gone.
+  fn publish(&self, parent: &Message, msg_type: &str, content: Value) {
+    let mut msg = self.reply(parent, msg_type, content);
+    msg.ids = vec![msg_type.as_bytes().to_vec()]; // the topic
+    let frames = msg.frames(&self.key);
+    if let Ok(mut subs) = self.iopub.lock() {
+      subs.retain_mut(|conn| conn.send(&frames).is_ok());
+    }
+  }
+
+  /// Handle a request on the shell or control channel, producing the
+  /// reply, if it has one.
+  fn handle(&self, req: &Message) -> Option<Message> {
+    self.publish(req, "status", json!({"execution_state": "busy"}));
+    let reply = match req.msg_type() {
+      "kernel_info_request" => Some(self.reply(req, "kernel_info_reply", kernel_info())),
+      "execute_request" => Some(self.execute(req)),
+      "is_complete_request" => Some(self.reply(req, "is_complete_reply", json!({"status": "complete"}))),
+      "complete_request" => {
+        let pos = req.content["cursor_pos"].as_u64().unwrap_or(0);
+        Some(self.reply(req, "complete_reply", json!({"status": "ok", "matches": [], "cursor_start": pos, "cursor_end": pos, "metadata": {}})))
+      },
+      "inspect_request" => Some(self.reply(req, "inspect_reply", json!({"status": "ok", "found": false, "data": {}, "metadata": {}}))),
+      "history_request" => Some(self.reply(req, "history_reply", json!({"status": "ok", "history": []}))),
+      "comm_info_request" => Some(self.reply(req, "comm_info_reply", json!({"status": "ok", "comms": {}}))),
+      "interrupt_request" => Some(self.reply(req, "interrupt_reply", json!({"status": "ok"}))),
+      "shutdown_request" => Some(self.reply(req, "shutdown_reply", json!({"status": "ok", "restart": req.content["restart"].as_bool().unwrap_or(false)}))),
+      other => {
+        log::debug!("ignored a {} message", other);
+        None
+      },
+    };
+    self.publish(req, "status", json!({"execution_state": "idle"}));
+    reply
+  }
+
+  /// Evaluate a cell in what the cells before it defined, and publish
+  /// its lines and their results.
+  fn execute(&self, req: &Message) -> Message {
+    let code = req.content["code"].as_str().unwrap_or("");
+    let silent = req.content["silent"].as_bool().unwrap_or(false);
+    let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+    let state = &mut *state;
+    if !silent {
+      state.count += 1;
+      self.publish(req, "execute_input", json!({"code": code, "execution_count": state.count}));
+    }
+    let snip = Snippet::evaluate(&mut state.cxt, code, 0..usize::MAX, &mut state.imports);
+    if !silent && !code.trim().is_empty() {
+      self.publish(req, "execute_result", json!({
+        "execution_count": state.count,
+        "data": {
+          "text/plain": snip.ansi().trim_end(),
+          "text/html": snip.html(),
+        },
+        "metadata": {},
+      }));
+    }
+    self.reply(req, "execute_reply", json!({"status": "ok", "execution_count": state.count, "user_expressions": {}}))
+  }
+}
+
+fn kernel_info() -> Value {
+  json!({
+    "status": "ok",
+    "protocol_version": PROTOCOL,
+    "implementation": NAME,
+    "implementation_version": env!("CARGO_PKG_VERSION"),
+    "language_info": {
+      "name": NAME,
+      "version": env!("CARGO_PKG_VERSION"),
+      "mimetype": "text/x-resolver",
+      "file_extension": ".rdl",
+    },
+    "banner": format!("resolver {}", env!("CARGO_PKG_VERSION")),
+    "help_links": [],
+  })
+}
+
+/// Run as a Jupyter kernel, listening where a connection file says, until
+/// a frontend asks it to shut down.
+pub fn run(conf: &Config, file: &str) -> Result<(), error::Error> {
+  let connect: Connect = serde_json::from_str(&fs::read_to_string(file)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", file, err)))?;
+  if connect.transport != "tcp" {
+    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("transport {} is not supported; only tcp is", connect.transport)).into());
+  }
+  if !connect.key.is_empty() && connect.signature_scheme != "hmac-sha256" {
+    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("signature scheme {} is not supported; only hmac-sha256 is", connect.signature_scheme)).into());
+  }
+  let listen = |port: u16| TcpListener::bind((connect.ip.as_str(), port));
+  let (shell, control, iopub, stdin, hb) = (listen(connect.shell_port)?, listen(connect.control_port)?, listen(connect.iopub_port)?, listen(connect.stdin_port)?, listen(connect.hb_port)?);
+  let kernel = Arc::new(Kernel{
+    key: connect.key.into_bytes(),
+    session: format!("{:x}-{:x}", process::id(), time::SystemTime::now().duration_since(time::UNIX_EPOCH).map_or(0, |d| d.as_nanos())),
+    state: Mutex::new(Session{
+      cxt: serve::context(conf),
+      imports: Imports::new(&env::current_dir()?),
+      count: 0,
+    }),
+    iopub: Mutex::new(Vec::new()),
+    serial: AtomicUsize::new(0),
+  });
+  log::info!("kernel listening on {}", connect.ip);
+
+  spawn(hb, Kind::Rep, |mut conn| {
+    while let Ok(msg) = conn.recv() {
+      if conn.send(&msg).is_err() {
+        break;
+      }
+    }
+  });
+  spawn(stdin, Kind::Router, |mut conn| while conn.recv().is_ok() {}); // input is never requested
+  let k = kernel.clone();
+  spawn(iopub, Kind::Pub, move |mut conn| {
+    if let Ok(clone) = conn.try_clone() {
+      if let Ok(mut subs) = k.iopub.lock() {
+        subs.push(clone);
+      }
+    }
+    while conn.recv().is_ok() {} // subscriptions; everything is published to everyone
+  });
+  let k = kernel.clone();
+  spawn(control, Kind::Router, move |conn| serve_requests(&k, conn));
+  for stream in shell.incoming() {
+    let k = kernel.clone();
+    accept(stream, Kind::Router, move |conn| serve_requests(&k, conn));
+  }
+  Ok(())
+}
+
+/// Accept connections to a listener in the background, handling each in a
+/// thread of its own.
+fn spawn(listener: TcpListener, kind: Kind, handle: impl Fn(Connection) + Send + Sync + Clone + 'static) {
+  thread::spawn(move || for stream in listener.incoming() {
+    accept(stream, kind, handle.clone());
+  });
+}
+
+fn accept(stream: io::Result<TcpStream>, kind: Kind, handle: impl FnOnce(Connection) + Send + 'static) {
+  let conn = stream.and_then(|s| Connection::accept(s, kind));
+  match conn {
+    Ok(conn) => {
+      thread::spawn(move || handle(conn));
+    },
+    Err(err) => log::warn!("could not accept a connection: {}", err),
+  }
+}
+
+/// Answer requests on a shell or control connection until it closes.
+fn serve_requests(kernel: &Kernel, mut conn: Connection) {
+  while let Ok(frames) = conn.recv() {
+    let req = match Message::parse(&frames, &kernel.key) {
+      Ok(req) => req,
+      Err(err) => {
+        log::warn!("dropped a message: {}", err);
+        continue;
+      },
+    };
+    log::info!("{}", req.msg_type());
+    if let Some(reply) = kernel.handle(&req) {
+      if conn.send(&reply.frames(&kernel.key)).is_err() {
+        break;
+      }
+    }
+    if req.msg_type() == "shutdown_request" {
+      process::exit(0);
+    }
+  }
+}
+
+/// Install the kernel where Jupyter finds kernels, so notebooks can be
+/// created with it, and produce where it was installed.
+pub fn install() -> Result<path::PathBuf, error::Error> {
+  let data = match env::var_os("JUPYTER_DATA_DIR").filter(|d| !d.is_empty()) {
+    Some(dir) => path::PathBuf::from(dir),
+    None => match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+      Some(dir) => path::PathBuf::from(dir).join("jupyter"),
+      None => env::var_os("HOME").map(|home| path::PathBuf::from(home).join(".local").join("share").join("jupyter"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory is available; set JUPYTER_DATA_DIR"))?,
+    },
+  };
+  let dir = data.join("kernels").join(NAME);
+  fs::create_dir_all(&dir)?;
+  fs::write(dir.join("kernel.json"), kernel_spec(&env::current_exe()?))?;
+  Ok(dir)
+}
+
+/// The description of the kernel Jupyter reads to start it.
+fn kernel_spec(exe: &path::Path) -> String {
+  serde_json::to_string_pretty(&json!({
+    "argv": [exe, "--kernel", "{connection_file}"],
+    "display_name": "Resolver",
+    "language": NAME,
+  })).expect("Could not encode kernel spec")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sign_and_parse_messages() {
+    let kernel = Kernel{
+      key: b"secret".to_vec(),
+      session: "s".to_string(),
+      state: Mutex::new(Session{cxt: exec::Context::new(), imports: Imports::new(path::Path::new("")), count: 0}),
+      iopub: Mutex::new(Vec::new()),
+      serial: AtomicUsize::new(0),
+    };
+    let req = Message{
+      ids: vec![b"client".to_vec()],
+      header: json!({"msg_id": "1", "msg_type": "execute_request"}),
+      parent: json!({}),
+      metadata: json!({}),
+      content: json!({"code": "rent = 1200\nrent * 2"}),
+    };
+    let frames = req.frames(&kernel.key);
+    assert_eq!(b"client".to_vec(), frames[0]);
+    assert_eq!(DELIMITER, &frames[1][..]);
+    assert_eq!(Ok(req.clone()), Message::parse(&frames, &kernel.key));
+    assert_eq!(Err("message has an invalid signature".to_string()), Message::parse(&frames, b"other"));
+
+    let reply = kernel.handle(&req).unwrap();
+    assert_eq!("execute_reply", reply.msg_type());
+    assert_eq!(json!({"status": "ok", "execution_count": 1, "user_expressions": {}}), reply.content);
+    assert_eq!(req.header, reply.parent);
+    assert_eq!(req.ids, reply.ids);
+    let next = Message{content: json!({"code": "rent / 4"}), ..req};
+    kernel.handle(&next);
+    let mut state = kernel.state.lock().unwrap();
+    assert_eq!(2, state.count);
+    let state = &mut *state;
+    assert!(Snippet::evaluate(&mut state.cxt, "rent", 0..1, &mut state.imports).ansi().contains("1200"));
+
+    assert!(kernel_spec(path::Path::new("/bin/resolver")).contains("\"--kernel\",\n    \"{connection_file}\""));
+  }
+
+}
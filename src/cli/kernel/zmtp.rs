@@ -0,0 +1,162 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// The largest frame accepted, in bytes.
+const MAX_FRAME: u64 = 16 << 20;
+
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+/// The kinds of socket the kernel offers, as ZeroMQ names them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+  Router,
+  Pub,
+  Rep,
+}
+
+impl Kind {
+  fn name(&self) -> &'static str {
+    match self {
+      Kind::Router => "ROUTER",
+      Kind::Pub    => "PUB",
+      Kind::Rep    => "REP",
+    }
+  }
+}
+
+/// A connection from a ZeroMQ peer, speaking ZMTP 3.0 with the NULL security
+/// mechanism, which is what Jupyter uses over TCP; messages are
+/// authenticated by signing them instead. Messages are sent and received as
+/// a list of frames.
+pub struct Connection {
+  stream: TcpStream,
+}
+
+impl Connection {
+  /// Greet a peer which has connected, and agree on the socket types.
+  pub fn accept(mut stream: TcpStream, kind: Kind) -> io::Result<Connection> {
+    stream.write_all(&greeting())?;
+    let mut peer = [0; 64];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "peer does not speak ZMTP 3"));
+    }
+    if &peer[12..16] != b"NULL" {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "peer wants a security mechanism other than NULL"));
+    }
+    stream.write_all(&frame(&ready(kind), COMMAND))?;
+    let mut conn = Connection{
+      stream,
+    };
+    match conn.read_frame()? {
+      (flags, body) if flags & COMMAND != 0 && body.starts_with(b"\x05READY") => Ok(conn),
+      _ => Err(io::Error::new(io::ErrorKind::InvalidData, "peer did not send READY")),
+    }
+  }
+
+  /// Another handle to the same connection, for reading and writing it from
+  /// different threads.
+  pub fn try_clone(&self) -> io::Result<Connection> {
+    Ok(Connection{
+      stream: self.stream.try_clone()?,
+    })
+  }
+
+  /// Receive the next message, skipping any commands.
+  pub fn recv(&mut self) -> io::Result<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    loop {
+      let (flags, body) = self.read_frame()?;
+      if flags & COMMAND != 0 {
+        continue;
+      }
+      frames.push(body);
+      if flags & MORE == 0 {
+        return Ok(frames);
+      }
+    }
+  }
+
+  /// Send a message.
+  pub fn send(&mut self, frames: &[Vec<u8>]) -> io::Result<()> {
+    let mut out = Vec::new();
+    for (i, body) in frames.iter().enumerate() {
+      out.extend(frame(body, if i + 1 < frames.len() { MORE }else{ 0 }));
+    }
+    self.stream.write_all(&out)
+  }
+
+  fn read_frame(&mut self) -> io::Result<(u8, Vec<u8>)> {
+    let mut flags = [0; 1];
+    self.stream.read_exact(&mut flags)?;
+    let size = if flags[0] & LONG != 0 {
+      let mut size = [0; 8];
+      self.stream.read_exact(&mut size)?;
+      u64::from_be_bytes(size)
+    }else{
+      let mut size = [0; 1];
+      self.stream.read_exact(&mut size)?;
+      size[0] as u64
+    };
+    if size > MAX_FRAME {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is too large", size)));
+    }
+    let mut body = vec![0; size as usize];
+    self.stream.read_exact(&mut body)?;
+    Ok((flags[0], body))
+  }
+}
+
+/// The greeting which opens a connection: the signature, version 3.0, the
+/// NULL mechanism, and that we aren't a server of a mechanism.
+fn greeting() -> [u8; 64] {
+  let mut out = [0; 64];
+  out[0] = 0xff;
+  out[9] = 0x7f;
+  out[10] = 3;
+  out[12..16].copy_from_slice(b"NULL");
+  out
+}
+
+/// The body of the READY command, naming the type of our socket.
+fn ready(kind: Kind) -> Vec<u8> {
+  let mut out = b"\x05READY".to_vec();
+  let (name, value) = ("Socket-Type", kind.name());
+  out.push(name.len() as u8);
+  out.extend(name.as_bytes());
+  out.extend((value.len() as u32).to_be_bytes());
+  out.extend(value.as_bytes());
+  out
+}
+
+/// Encode a frame, which is short or long depending on its size.
+fn frame(body: &[u8], flags: u8) -> Vec<u8> {
+  let mut out = Vec::with_capacity(body.len() + 9);
+  if body.len() > 255 {
+    out.push(flags | LONG);
+    out.extend((body.len() as u64).to_be_bytes());
+  }else{
+    out.push(flags);
+    out.push(body.len() as u8);
+  }
+  out.extend(body);
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encode_frames() {
+    assert_eq!(b"\x01\x03abc".to_vec(), frame(b"abc", MORE));
+    let long = frame(&[7; 300], 0);
+    assert_eq!(&[LONG, 0, 0, 0, 0, 0, 0, 1, 44], &long[..9]);
+    assert_eq!(309, long.len());
+    assert_eq!(b"\x05READY\x0bSocket-Type\x00\x00\x00\x06ROUTER".to_vec(), ready(Kind::Router));
+    assert_eq!((0xff, 0x7f, 3, &b"NULL"[..]), (greeting()[0], greeting()[9], greeting()[10], &greeting()[12..16]));
+  }
+
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::path;
+use std::io::{self, Write, IsTerminal};
+
+use crossterm::terminal;
+
+use crate::error;
+use crate::document;
+use crate::editor::writer::{self, Placement};
+use crate::text::{attrs, width};
+
+/// Print a document as the editor draws it at a width, with attributes
+/// written as markup, for comparing against golden files; see
+/// `writer::render_markup`.
+pub fn run(doc: &str, width: usize) -> Result<(), error::Error> {
+  let text = document::load(path::Path::new(doc))?;
+  write!(io::stdout(), "{}", writer::render_markup(&text, width, Placement::Column))?;
+  Ok(())
+}
+
+/// Print a file of markup, such as a golden fixture, styled as it would be
+/// in the terminal. Lines wider than the terminal are cut short rather
+/// than wrapped, so the columns of a fixture stay lined up, and tabs are
+/// expanded to stops every so many columns, so its styles line up too.
+pub fn view(file: &str, tab_width: usize) -> Result<(), error::Error> {
+  let markup = fs::read_to_string(file)?;
+  let mut lines = attrs::AttributedLines::from(attrs::parse_markup(&markup));
+  if lines.num_lines() > 0 && lines.line(lines.num_lines() - 1).is_some_and(|l| l.text().is_empty()) {
+    lines.remove(lines.num_lines() - 1); // the newline a file ends with doesn't start another line
+  }
+  let mut stdout = io::stdout().lock();
+  let cols = if stdout.is_terminal() { terminal::size().ok().map(|(w, _)| w as usize) }else{ None };
+  let lines: attrs::AttributedLines = lines.iter().map(|l| {
+    let mut l = l.clone();
+    l.expand_tabs(tab_width);
+    l.slice(0..cols.map_or(l.len(), |cols| fit(l.text(), cols)))
+  }).collect();
+  lines.render_to(&mut stdout, 0..lines.num_lines(), attrs::Mode::Terminal)?;
+  Ok(())
+}
+
+/// The length in bytes of as much of a line as fits in some columns.
+fn fit(line: &str, cols: usize) -> usize {
+  let mut n = 0;
+  for (i, c) in line.char_indices() {
+    n += width::char_width(c);
+    if n > cols {
+      return i;
+    }
+  }
+  line.len()
+}
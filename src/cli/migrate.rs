@@ -0,0 +1,20 @@
+use std::fs;
+use std::path;
+use std::io::{self, Write};
+
+use crate::error;
+use crate::text::sanitize;
+use crate::document::migrate::{self, Dialect};
+
+/// Convert a document from another calculator app and write it to standard
+/// output. The dialect is inferred from the file extension if not provided.
+pub fn run(file: &str, dialect: Option<Dialect>) -> Result<(), error::Error> {
+  let path = path::Path::new(file);
+  let dialect = match dialect.or_else(|| Dialect::from_path(path)) {
+    Some(dialect) => dialect,
+    None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Cannot infer the format of {}; specify one with --import-from", file)).into()),
+  };
+  let text = fs::read_to_string(path)?;
+  write!(io::stdout(), "{}", migrate::convert(&sanitize::clean(&text), dialect))?;
+  Ok(())
+}
@@ -0,0 +1,17 @@
+pub mod backup;
+pub mod compare;
+pub mod completions;
+pub mod config;
+pub mod export;
+pub mod kernel;
+pub mod markup;
+pub mod migrate;
+pub mod oneshot;
+pub mod pipe;
+pub mod print;
+pub mod provenance;
+pub mod quick;
+pub mod send;
+pub mod serve;
+pub mod snippet;
+pub mod watch;
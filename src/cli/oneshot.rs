@@ -0,0 +1,64 @@
+use std::io::{self, Write};
+
+use crate::error;
+use crate::options;
+use crate::rdl;
+use crate::rdl::exec;
+use crate::rdl::json;
+
+/// Evaluate a single expression and write its result to standard output.
+/// If the text contains no expression or any expression fails to evaluate,
+/// the error is written to standard error instead and false is returned so
+/// the caller can exit with a nonzero status.
+pub fn run(text: &str, format: options::Format) -> Result<bool, error::Error> {
+  let mut cxt = exec::Context::new_with_stdlib();
+  if format == options::Format::Json {
+    let line = json::eval_line(&mut cxt, 1, text);
+    writeln!(io::stdout(), "{}", line.to_json())?;
+    return Ok(line.has_results() && !line.has_errors());
+  }
+  match eval(&mut cxt, text) {
+    Ok(res) => {
+      writeln!(io::stdout(), "{}", res)?;
+      Ok(true)
+    },
+    Err(err) => {
+      writeln!(io::stderr(), "resolver: {}", err)?;
+      Ok(false)
+    },
+  }
+}
+
+/// Evaluate the provided text, producing the results of every expression it
+/// contains or the first error encountered.
+pub fn eval(cxt: &mut exec::Context, text: &str) -> Result<String, rdl::error::Error> {
+  let mut out = String::new();
+  for e in rdl::eval(cxt, text) {
+    let res = e.result?;
+    if !out.is_empty() {
+      out.push_str("; ");
+    }
+    out.push_str(&res.to_string());
+  }
+  if out.is_empty() {
+    Err(rdl::error::Error::EndOfInput)
+  }else{
+    Ok(out)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn eval_oneshot() {
+    let mut cxt = exec::Context::new();
+    assert_eq!(Ok("50".to_string()), eval(&mut cxt, "10 + 40"));
+    assert_eq!(Ok("2; 3".to_string()), eval(&mut cxt, "a = 2; a + 1"));
+    assert_eq!(Ok("3 tbsp".to_string()), eval(&mut cxt, "9 tsp in tbsp"));
+    assert_eq!(Err(rdl::error::Error::UnboundVariable("b".to_string())), eval(&mut cxt, "a, b"));
+    assert_eq!(Err(rdl::error::Error::EndOfInput), eval(&mut cxt, ""));
+  }
+  
+}
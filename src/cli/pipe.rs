@@ -0,0 +1,87 @@
+use std::path;
+use std::io::{self, BufRead, Write};
+
+use crate::error;
+use crate::options;
+use crate::rdl;
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::rdl::json;
+use crate::text::attrs;
+
+/// Read lines from standard input, evaluate each, and write the results to
+/// standard output, one line of output per line of input. Lines colored by
+/// the program they come from are evaluated without their colors. In JSON format
+/// each line of output is a self-contained JSON object. Variables assigned
+/// in earlier lines remain available to later lines. Imported files are
+/// resolved relative to the working directory.
+pub fn run(opts: &options::Options) -> Result<(), error::Error> {
+  let stdin = io::stdin();
+  let mut stdout = io::stdout().lock();
+  let mut cxt = exec::Context::new_with_stdlib();
+  let mut imports = Imports::new(path::Path::new(""));
+  for (i, line) in stdin.lock().lines().enumerate() {
+    let line = attrs::parse_ansi(&line?);
+    write_line(&mut stdout, &mut cxt, &mut imports, i + 1, line.text(), opts)?;
+  }
+  Ok(())
+}
+
+/// Evaluate a single line and write its results in the configured format.
+/// If the line is a `use` directive, the file it names is imported first.
+pub fn write_line<W: Write>(w: &mut W, cxt: &mut exec::Context, imports: &mut Imports, num: usize, text: &str, opts: &options::Options) -> Result<(), error::Error> {
+  import(imports, cxt, num, text);
+  match opts.format {
+    options::Format::Text => writeln!(w, "{}", format_line(cxt, text, opts.result_only))?,
+    options::Format::Json => writeln!(w, "{}", json::eval_line(cxt, num, text).to_json())?,
+  };
+  Ok(())
+}
+
+/// Import the file a line names, if it's a `use` directive, reporting on
+/// standard error if it can't be, since printed results have no place for
+/// it. The line is numbered from one.
+pub fn import(imports: &mut Imports, cxt: &mut exec::Context, num: usize, text: &str) {
+  if let Some(err) = rdl::import(imports, cxt, text) {
+    eprintln!("resolver: line {}: {}", num, err);
+  }
+}
+
+/// Evaluate a line and format its results as `expression = result`, or just
+/// `result` if requested. Expressions that fail to evaluate are omitted.
+pub fn format_line(cxt: &mut exec::Context, text: &str, result_only: bool) -> String {
+  let mut out = String::new();
+  let (_, fmt) = rdl::split_line(cxt, text);
+  for e in rdl::eval(cxt, text) {
+    let res = match &e.result {
+      Ok(res) => res,
+      Err(_)  => continue,
+    };
+    if !out.is_empty() {
+      out.push_str("; ");
+    }
+    if result_only {
+      out.push_str(&res.format(&fmt));
+    }else{
+      out.push_str(&format!("{} = {}", e.text(text).trim(), res.format(&fmt)));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn format_lines() {
+    let mut cxt = exec::Context::new();
+    assert_eq!("10 + 40 = 50", &format_line(&mut cxt, "Such as 10 + 40, for example", false));
+    assert_eq!("50", &format_line(&mut cxt, "Such as 10 + 40, for example", true));
+    assert_eq!("tax = 0.5 = 0.5", &format_line(&mut cxt, "if tax = 0.5 then", false));
+    assert_eq!("100 * tax = 50; 2 = 2", &format_line(&mut cxt, "the total is 100 * tax, or 2", false));
+    assert_eq!("", &format_line(&mut cxt, "Nothing to see here", false));
+    assert_eq!("", &format_line(&mut cxt, "", true));
+  }
+  
+}
@@ -0,0 +1,160 @@
+use std::path;
+use std::io::{self, Write};
+
+use crate::error;
+use crate::document;
+use crate::rdl;
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::cli::pipe;
+use crate::text::layout;
+use crate::text::width::{self, str_width};
+use crate::document::settings;
+
+const FORM_FEED: char = '\x0c';
+const RULE: char = '─';
+const ELLIPSIS: char = '…';
+
+/// Print a document, paginated for a printer or for archiving.
+pub fn run(doc: &str, width: usize, height: usize) -> Result<(), error::Error> {
+  let path = path::Path::new(doc);
+  let title = match path.file_name() {
+    Some(name) => name.to_string_lossy().to_string(),
+    None => doc.to_string(),
+  };
+  let text = document::load(path)?;
+  let mut imports = Imports::new_for_document(path);
+  write!(io::stdout(), "{}", render(&title, &text, width, height, &mut imports))?;
+  Ok(())
+}
+
+struct Row {
+  text: String,
+  totals: Vec<String>, // totals in effect as of this row
+}
+
+/// Render a document as plain text paginated into pages of a fixed width
+/// and height. Results are right-aligned in a column beside the text they
+/// belong to. Every page has a header with the title and page number, and
+/// a footer which repeats the totals as they stand at the end of that page.
+/// Pages are separated by a form feed.
+///
+/// A total is any variable whose name contains "total".
+pub fn render(title: &str, text: &str, width: usize, height: usize, imports: &mut Imports) -> String {
+  let rw = width / 3;                 // result column width
+  let tw = width.saturating_sub(rw + 1); // text column width
+  let body = height.saturating_sub(4).max(1);
+  
+  let mut cxt = settings::context(text, None);
+  let mut rows: Vec<Row> = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    pipe::import(imports, &mut cxt, i + 1, line);
+    let (_, res) = rdl::render_with_options(&mut cxt, line, 0, 0, None, None);
+    let totals = totals(&cxt);
+    let segs: Vec<&str> = layout::layout(line, tw).iter().map(|l| l.text(line)).collect();
+    if segs.is_empty() {
+      rows.push(Row{text: String::new(), totals});
+      continue;
+    }
+    for (i, seg) in segs.iter().enumerate() {
+      let res = if i == 0 { fit(res.text(), rw) } else { String::new() };
+      let row = format!("{}{}", pad(seg, tw + 1), lpad(&res, rw));
+      rows.push(Row{text: row.trim_end().to_string(), totals: totals.clone()});
+    }
+  }
+  
+  let pages: Vec<&[Row]> = if rows.is_empty() { vec![&rows[..]] } else { rows.chunks(body).collect() };
+  let mut out = String::new();
+  for (i, page) in pages.iter().enumerate() {
+    if i > 0 {
+      out.push(FORM_FEED);
+    }
+    let num = format!("Page {} of {}", i + 1, pages.len());
+    let title = fit(title, width.saturating_sub(num.len() + 1));
+    out.push_str(&pad(&title, width.saturating_sub(num.len())));
+    out.push_str(&num);
+    out.push('\n');
+    out.push_str(&RULE.to_string().repeat(width));
+    out.push('\n');
+    for row in page.iter() {
+      out.push_str(&row.text);
+      out.push('\n');
+    }
+    for _ in page.len()..body {
+      out.push('\n');
+    }
+    out.push_str(&RULE.to_string().repeat(width));
+    out.push('\n');
+    if let Some(last) = page.last() {
+      out.push_str(&fit(&last.totals.join("; "), width));
+    }
+    out.push('\n');
+  }
+  out
+}
+
+fn totals(cxt: &exec::Context) -> Vec<String> {
+  cxt.vars().iter()
+    .filter(|(k, _)| k.to_lowercase().contains("total"))
+    .map(|(k, v)| format!("{} = {}", k, v))
+    .collect()
+}
+
+/// Truncate text to the provided width, in columns, marking the truncation.
+fn fit(text: &str, width: usize) -> String {
+  if str_width(text) <= width {
+    text.to_string()
+  }else if width > 0 {
+    let mut s = String::new();
+    for c in text.chars() {
+      if str_width(&s) + width::char_width(c) > width - 1 {
+        break;
+      }
+      s.push(c);
+    }
+    s.push(ELLIPSIS);
+    s
+  }else{
+    String::new()
+  }
+}
+
+fn pad(text: &str, width: usize) -> String {
+  format!("{}{}", text, " ".repeat(width.saturating_sub(str_width(text))))
+}
+
+fn lpad(text: &str, width: usize) -> String {
+  format!("{}{}", " ".repeat(width.saturating_sub(str_width(text))), text)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn render_pages() {
+    let text = "Rent is 1200 per month\nsubtotal = 1200 * 12\n\nFood is 400 * 12\ntotal = subtotal + 4800";
+    let rule = "─".repeat(28);
+    let expect = vec![
+      "budget.res       Page 1 of 2",
+      &rule,
+      "Rent is 1200 per        1200",
+      "month",
+      "subtotal = 1200 *      14400",
+      "12",
+      &rule,
+      "subtotal = 14400",
+      "\x0cbudget.res       Page 2 of 2",
+      &rule,
+      "",
+      "Food is 400 * 12        4800",
+      "total = subtotal       19200",
+      "+ 4800",
+      &rule,
+      "subtotal = 14400; total = 1…",
+      "",
+    ].join("\n");
+    assert_eq!(expect, render("budget.res", text, 28, 8, &mut Imports::new(path::Path::new(""))));
+  }
+  
+}
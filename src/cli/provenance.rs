@@ -0,0 +1,194 @@
+use std::path;
+use std::time;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::error;
+use crate::document;
+use crate::logging;
+use crate::options;
+use crate::rates::{self, cache, quotes};
+use crate::rdl;
+use crate::rdl::{exec, unit};
+use crate::rdl::import::Imports;
+use crate::config::file::Config;
+use crate::document::settings::{self, Settings};
+use crate::cli::pipe;
+
+/// Where an exchange rate came from, in order of precedence.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+  Document,
+  Config,
+  Fetched{provider: String, fetched: time::SystemTime, published: Option<String>},
+}
+
+impl Source {
+  fn describe(&self) -> String {
+    match self {
+      Source::Document => "set in the document".to_string(),
+      Source::Config => "configured".to_string(),
+      Source::Fetched{provider, fetched, published} => fetched_from(provider, *fetched, published.as_deref()),
+    }
+  }
+}
+
+fn fetched_from(provider: &str, fetched: time::SystemTime, published: Option<&str>) -> String {
+  match published {
+    Some(date) => format!("fetched from {} at {} UTC, published {}", provider, logging::timestamp(fetched), date),
+    None => format!("fetched from {} at {} UTC", provider, logging::timestamp(fetched)),
+  }
+}
+
+/// A step taken to arrive at a result, written out.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Detail {
+  Variable{name: String, value: String},
+  Quote{symbol: String, price: String, source: String},
+  Conversion{from: String, to: String},
+  Exchange{from: String, to: String, rate: String, source: String},
+}
+
+/// The work behind a result: the expression, as written and with its
+/// variables substituted, the result, and how it was arrived at.
+#[derive(Debug, Serialize)]
+pub struct Work {
+  pub line: usize,
+  pub expr: String,
+  pub substituted: String,
+  pub result: String,
+  pub steps: Vec<Detail>,
+}
+
+/// Evaluate a document and write the work behind each of its results to
+/// standard output, for checking an invoice or an expense report. Rates
+/// and quotes fetched from providers are those the editor last fetched;
+/// none are fetched here.
+pub fn run(doc: &str, conf: &Config, format: options::Format) -> Result<(), error::Error> {
+  let path = path::Path::new(doc);
+  let text = document::load(path)?;
+  let mut imports = Imports::new_for_document(path);
+  let configured = |specs: &[String], name: &str| specs.iter().any(|p| p.split(':').next() == Some(name));
+  let fetched = cache::load().map(|(current, _)| current).filter(|c| configured(&conf.providers, &c.provider));
+  let quoted = quotes::cache::load().map(|(current, _)| current).filter(|c| configured(&conf.quotes, &c.provider));
+  let work = show(&text, conf, fetched.as_ref(), quoted.as_ref(), &mut imports);
+  match format {
+    options::Format::Json => writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&work).expect("Could not encode work"))?,
+    options::Format::Text => write!(io::stdout(), "{}", render(&work))?,
+  }
+  Ok(())
+}
+
+/// Evaluate a document, with the configured rates and those fetched, and
+/// the quotes fetched, and produce the work behind every result.
+pub fn show(text: &str, conf: &Config, fetched: Option<&rates::Current>, quoted: Option<&quotes::Current>, imports: &mut Imports) -> Vec<Work> {
+  let doc = Settings::parse(text);
+  let mut defaults = conf.defaults();
+  defaults.rates.extend_from_slice(fetched.map_or(&[], |c| &c.rates.rates)); // as the editor does, after those configured
+  let mut cxt = settings::context(text, Some((&conf.locale, &defaults)));
+  for (symbol, price) in quoted.map_or(&[][..], |c| &c.quotes.quotes) {
+    cxt.set_quote(symbol, *price);
+  }
+  let source = |rate: &unit::Rate| if doc.rates.contains(rate) {
+    Source::Document
+  }else if conf.rates.contains(rate) {
+    Source::Config
+  }else{
+    match fetched {
+      Some(c) => Source::Fetched{provider: c.provider.clone(), fetched: c.fetched, published: c.rates.published.clone()},
+      None => Source::Config,
+    }
+  };
+
+  let mut out = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    pipe::import(imports, &mut cxt, i + 1, line);
+    for work in rdl::render_work(&mut cxt, line) {
+      let fmt = cxt.format();
+      let mut steps: Vec<Detail> = Vec::new();
+      for step in work.steps {
+        let detail = match step {
+          exec::Step::Variable(name, val) => Detail::Variable{name, value: val.format(&fmt)},
+          exec::Step::Reference(name, val) => Detail::Variable{name: format!("@{}", name), value: val.format(&fmt)},
+          exec::Step::Quote(symbol, price) => Detail::Quote{symbol, price: price.format(&fmt), source: quoted.map_or(String::new(), |c| fetched_from(&c.provider, c.fetched, c.quotes.published.as_deref()))},
+          exec::Step::Conversion(from, to) => Detail::Conversion{from: from.format(&fmt), to: to.format(&fmt)},
+          exec::Step::Exchange(from, to, rate) => Detail::Exchange{from: from.format(&fmt), to: to.format(&fmt), rate: rate.to_string(), source: source(&rate).describe()},
+        };
+        if !steps.contains(&detail) {
+          steps.push(detail);
+        }
+      }
+      out.push(Work{
+        line: i + 1,
+        expr: line[work.output.range.clone()].trim().to_string(),
+        substituted: work.substituted,
+        result: work.output.text,
+        steps,
+      });
+    }
+  }
+  out
+}
+
+/// Write out the work behind results as plain text, one result after
+/// another.
+pub fn render(work: &[Work]) -> String {
+  let mut out = String::new();
+  for (i, w) in work.iter().enumerate() {
+    if i > 0 {
+      out.push('\n');
+    }
+    out.push_str(&format!("line {}: {}\n", w.line, w.expr));
+    if w.substituted != w.expr && w.substituted != w.result {
+      out.push_str(&format!("  = {}\n", w.substituted));
+    }
+    out.push_str(&format!("  = {}\n", w.result));
+    for step in &w.steps {
+      let text = match step {
+        Detail::Variable{name, value} => format!("where {} = {}", name, value),
+        Detail::Quote{symbol, price, source} => format!("where {} is quoted at {}, {}", symbol, price, source),
+        Detail::Conversion{from, to} => format!("converted {} to {}", from, to),
+        Detail::Exchange{from, to, rate, source} => format!("exchanged {} for {} at {}, {}", from, to, rate, source),
+      };
+      out.push_str(&format!("    {}\n", text));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn show_work() {
+    let text = "# rate: EUR/USD = 1.25\nrent = 1200 EUR\n2 * rent in USD\n1 kg + 500 g in lb\nrent in GBP\n";
+    let conf = Config{rates: unit::Rate::parse("EUR/GBP = 0.85").into_iter().collect(), ..Config::default()};
+    let work = show(text, &conf, None, None, &mut Imports::new(path::Path::new("")));
+    assert_eq!(vec![2, 3, 4, 5], work.iter().map(|w| w.line).collect::<Vec<_>>());
+    assert_eq!("2 * 1200 EUR in USD", &work[1].substituted);
+    assert_eq!("3000 USD", &work[1].result);
+    assert_eq!(vec![
+      Detail::Variable{name: "rent".to_string(), value: "1200 EUR".to_string()},
+      Detail::Exchange{from: "2400 EUR".to_string(), to: "3000 USD".to_string(), rate: "EUR/USD = 1.25".to_string(), source: "set in the document".to_string()},
+    ], work[1].steps);
+    assert!(matches!(&work[2].steps[0], Detail::Conversion{from, to} if from == "1 kg" && to == "1000 g"));
+    assert!(matches!(&work[3].steps[1], Detail::Exchange{source, ..} if source == "configured"));
+
+    let fetched = rates::Current{
+      provider: "ecb".to_string(),
+      fetched: time::UNIX_EPOCH + time::Duration::from_secs(1_700_000_000),
+      rates: rates::Fetched{rates: unit::Rate::parse("EUR/CHF = 0.95").into_iter().collect(), published: Some("2023-11-14".to_string())},
+    };
+    let quoted = quotes::Current{
+      provider: "stooq".to_string(),
+      fetched: fetched.fetched,
+      quotes: quotes::Fetched{quotes: vec![("SAP.DE".to_string(), unit::Value::new(20.0, unit::Unit::Money(unit::Currency::Eur)))], published: None},
+    };
+    let work = show("2 shares of SAP.DE in CHF", &Config::default(), Some(&fetched), Some(&quoted), &mut Imports::new(path::Path::new("")));
+    assert_eq!("line 1: 2 shares of SAP.DE in CHF\n  = 2 * 20 EUR in CHF\n  = 38 CHF\n    where SAP.DE is quoted at 20 EUR, fetched from stooq at 2023-11-14 22:13:20 UTC\n    exchanged 40 EUR for 38 CHF at EUR/CHF = 0.95, fetched from ecb at 2023-11-14 22:13:20 UTC, published 2023-11-14\n", render(&work));
+  }
+
+}
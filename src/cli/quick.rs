@@ -0,0 +1,197 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crossterm::event;
+use crossterm::queue;
+use crossterm::cursor;
+use crossterm::style;
+use crossterm::terminal;
+
+use crate::Reader;
+use crate::error;
+use crate::rdl;
+use crate::rdl::exec;
+use crate::state;
+
+const HISTORY: &str = "quick";
+const MAX_HISTORY: usize = 500;
+const ANS: &str = "ans";
+const PROMPT: &str = "> ";
+
+/// A quick calculation session: expressions are entered one line at a time,
+/// the result of the last one is available as `ans`, and previously entered
+/// lines can be recalled.
+pub struct Session {
+  cxt: exec::Context,
+  history: Vec<String>, // oldest first
+  recall: usize,        // position in the history while recalling; its length otherwise
+}
+
+impl Session {
+  pub fn new(history: Vec<String>) -> Session {
+    let recall = history.len();
+    Session{
+      cxt: exec::Context::new_with_stdlib(),
+      history,
+      recall,
+    }
+  }
+
+  /// Load a session with the history persisted in the state directory. A
+  /// missing or unreadable history is treated as empty.
+  pub fn load() -> Session {
+    match state::file(HISTORY).and_then(fs::read_to_string) {
+      Ok(text) => Session::new(text.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect()),
+      Err(_) => Session::new(Vec::new()),
+    }
+  }
+
+  pub fn save(&self) -> io::Result<()> {
+    let start = self.history.len().saturating_sub(MAX_HISTORY);
+    let mut text = self.history[start..].join("\n");
+    text.push('\n');
+    fs::write(state::file(HISTORY)?, text)
+  }
+
+  /// Evaluate a line, record it in the history, and bind the last result it
+  /// produces as `ans`.
+  pub fn eval(&mut self, text: &str) -> Result<String, rdl::error::Error> {
+    if !text.trim().is_empty() && self.history.last().map(|l| l.as_str()) != Some(text) {
+      self.history.push(text.to_string());
+    }
+    self.recall = self.history.len();
+    let res = eval(&mut self.cxt, text)?;
+    if let Some(last) = res.last() {
+      self.cxt.set(ANS, *last);
+    }
+    Ok(res.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; "))
+  }
+
+  /// Produce what the line would evaluate to, without evaluating it.
+  pub fn preview(&self, text: &str) -> Option<String> {
+    let res = eval(&mut self.cxt.clone(), text).ok()?;
+    Some(res.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; "))
+  }
+
+  /// Recall the line entered before the one being recalled.
+  pub fn previous(&mut self) -> Option<&str> {
+    if self.recall == 0 {
+      return None;
+    }
+    self.recall -= 1;
+    self.history.get(self.recall).map(|l| l.as_str())
+  }
+
+  /// Recall the line entered after the one being recalled. Produces none
+  /// once past the most recent line, where a new line is entered.
+  pub fn next(&mut self) -> Option<&str> {
+    self.recall = (self.recall + 1).min(self.history.len());
+    self.history.get(self.recall).map(|l| l.as_str())
+  }
+}
+
+fn eval(cxt: &mut exec::Context, text: &str) -> Result<Vec<rdl::unit::Value>, rdl::error::Error> {
+  let mut res = Vec::new();
+  for e in rdl::eval(cxt, text) {
+    res.push(e.result?);
+  }
+  if res.is_empty() {
+    Err(rdl::error::Error::EndOfInput)
+  }else{
+    Ok(res)
+  }
+}
+
+/// Run a one-line prompt which evaluates each line as it is entered, for
+/// use as a popup calculator. Results scroll up the terminal like a shell.
+/// Escape, or Control-C or Control-D, ends the session.
+pub fn run() -> Result<(), error::Error> {
+  let mut session = Session::load();
+  terminal::enable_raw_mode()?;
+  let res = prompt(&mut session);
+  terminal::disable_raw_mode()?;
+  let _ = session.save(); // failing to record history is not worth reporting
+  res
+}
+
+fn prompt(session: &mut Session) -> Result<(), error::Error> {
+  let mut out = io::stdout();
+  let mut line = String::new();
+  loop {
+    draw(&mut out, session, &line)?;
+    let evt = Reader.read_key()?;
+    match (evt.code, evt.modifiers) {
+      (event::KeyCode::Esc, _) => break,
+      (event::KeyCode::Char('c'), event::KeyModifiers::CONTROL) => break,
+      (event::KeyCode::Char('d'), event::KeyModifiers::CONTROL) => break,
+      (event::KeyCode::Char('u'), event::KeyModifiers::CONTROL) => line.clear(),
+      (event::KeyCode::Enter, _) => {
+        if line.trim().is_empty() {
+          continue;
+        }
+        let res = match session.eval(&line) {
+          Ok(res) => format!("= {}", res),
+          Err(err) => err.to_string(),
+        };
+        queue!(out, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+        write!(out, "\r\n{}\r\n", res)?;
+        line.clear();
+      },
+      (event::KeyCode::Up, _) => {
+        if let Some(prev) = session.previous() {
+          line = prev.to_string();
+        }
+      },
+      (event::KeyCode::Down, _) => {
+        line = session.next().unwrap_or_default().to_string();
+      },
+      (event::KeyCode::Backspace, _) => {
+        line.pop();
+      },
+      (event::KeyCode::Char(c), event::KeyModifiers::NONE | event::KeyModifiers::SHIFT) => line.push(c),
+      _ => {},
+    };
+  }
+  write!(out, "\r\n")?;
+  out.flush()?;
+  Ok(())
+}
+
+fn draw(out: &mut io::Stdout, session: &Session, line: &str) -> Result<(), error::Error> {
+  write!(out, "\r{}{}", PROMPT, line)?;
+  queue!(out, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+  if let Some(res) = session.preview(line) {
+    queue!(out, cursor::SavePosition, style::SetForegroundColor(style::Color::DarkGrey))?;
+    write!(out, "  = {}", res)?;
+    queue!(out, style::ResetColor, cursor::RestorePosition)?;
+  }
+  out.flush()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quick_session() {
+    let mut s = Session::new(vec!["1 + 1".to_string()]);
+    assert_eq!(Ok("12".to_string()), s.eval("10 + 2"));
+    assert_eq!(Some("24".to_string()), s.preview("ans * 2"));
+    assert_eq!(Ok("6".to_string()), s.eval("ans / 2"));
+    assert!(s.eval("nope").is_err());
+    assert_eq!(Ok("6".to_string()), s.eval("ans"));
+
+    assert_eq!(Some("ans"), s.previous());
+    assert_eq!(Some("nope"), s.previous());
+    assert_eq!(Some("ans"), s.next());
+    assert_eq!(None, s.next());
+    for _ in 0..4 {
+      s.previous();
+    }
+    assert_eq!(Some("1 + 1"), s.previous());
+    assert_eq!(None, s.previous());
+    assert_eq!(vec!["1 + 1", "10 + 2", "ans / 2", "nope", "ans"], s.history);
+  }
+
+}
@@ -0,0 +1,34 @@
+use std::env;
+use std::io::{self, Write};
+
+use crate::control::{self, Request};
+use crate::error;
+
+/// Send a request to the running editor and write its reply to standard
+/// output. If the request can't be parsed, no editor is running, or the
+/// editor couldn't carry it out, the error is written to standard error
+/// instead and false is returned so the caller can exit with a nonzero
+/// status.
+pub fn run(line: &str) -> Result<bool, error::Error> {
+  let req = match Request::parse(line) {
+    Ok(req) => req.resolve(&env::current_dir()?),
+    Err(err) => {
+      writeln!(io::stderr(), "resolver: {}", err)?;
+      return Ok(false);
+    },
+  };
+  match control::send(&req) {
+    Ok(Ok(reply)) => {
+      writeln!(io::stdout(), "{}", reply)?;
+      Ok(true)
+    },
+    Ok(Err(err)) => {
+      writeln!(io::stderr(), "resolver: {}", err)?;
+      Ok(false)
+    },
+    Err(err) => {
+      writeln!(io::stderr(), "resolver: {}", err)?; // not running, most likely
+      Ok(false)
+    },
+  }
+}
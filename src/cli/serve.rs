@@ -0,0 +1,246 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::thread;
+use std::time;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::config::file::Config;
+use crate::rdl::exec;
+use crate::rdl::json;
+use crate::rdl::unit;
+
+/// The largest request body accepted, in bytes.
+const MAX_BODY: usize = 1 << 20;
+
+/// The largest request line and headers accepted, together, in bytes.
+const MAX_HEAD: usize = 8 << 10;
+
+/// How long a client may take to send any part of a request before it's
+/// given up on.
+const READ_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// The hosts requests may be addressed to. Any other is refused, so that a
+/// page which has had its own name pointed at this machine can't reach it.
+const HOSTS: [&str; 2] = ["localhost", "127.0.0.1"];
+
+#[derive(Debug, Deserialize)]
+struct Expr {
+  expr: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Document {
+  text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Rate {
+  from: String,
+  to: String,
+  rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Failure {
+  error: String,
+}
+
+/// A response to a request: its status and a JSON body.
+#[derive(Debug, PartialEq)]
+struct Response {
+  status: u16,
+  body: String,
+}
+
+impl Response {
+  fn ok<T: Serialize>(body: &T) -> Response {
+    Response{
+      status: 200,
+      body: serde_json::to_string(body).expect("Could not encode response"),
+    }
+  }
+
+  fn error(status: u16, msg: &str) -> Response {
+    Response{
+      status,
+      body: serde_json::to_string(&Failure{error: msg.to_string()}).expect("Could not encode response"),
+    }
+  }
+
+  fn reason(&self) -> &'static str {
+    match self.status {
+      200 => "OK",
+      400 => "Bad Request",
+      403 => "Forbidden",
+      404 => "Not Found",
+      405 => "Method Not Allowed",
+      413 => "Payload Too Large",
+      431 => "Request Header Fields Too Large",
+      _   => "Internal Server Error",
+    }
+  }
+}
+
+/// Serve the JSON API on the loopback interface until interrupted. Each
+/// connection is handled on a thread of its own, and each request is
+/// evaluated in a new context with the configured locale and exchange
+/// rates. Requests addressed to hosts other than this one are refused.
+///
+/// - `POST /eval` with `{"expr": "..."}` evaluates an expression
+/// - `POST /document` with `{"text": "..."}` evaluates every line of a document
+/// - `GET /units` lists the units of measure
+/// - `GET /currencies` lists the currencies
+/// - `GET /rates` lists the exchange rates
+pub fn run(conf: &Config, port: u16) -> Result<(), error::Error> {
+  let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+  eprintln!("resolver: serving on http://{}", listener.local_addr()?);
+  thread::scope(|scope| for stream in listener.incoming() {
+    scope.spawn(move || {
+      let res = stream.and_then(|mut stream| handle(&mut stream, conf));
+      if let Err(err) = res {
+        log::warn!("{}", err);
+        eprintln!("resolver: {}", err);
+      }
+    });
+  });
+  Ok(())
+}
+
+fn handle(stream: &mut TcpStream, conf: &Config) -> io::Result<()> {
+  stream.set_read_timeout(Some(READ_TIMEOUT))?;
+  let res = match read_request(&mut *stream)? {
+    Ok((method, path, body)) => {
+      let res = respond(conf, &method, &path, &body);
+      log::info!("{} {} {}", method, path, res.status);
+      res
+    },
+    Err(res) => res,
+  };
+  write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", res.status, res.reason(), res.body.len(), res.body)?;
+  stream.flush()
+}
+
+/// Read the method, path, and body of a request, or produce the response to
+/// a request that can't be read, or which is addressed to another host.
+fn read_request(stream: impl Read) -> io::Result<Result<(String, String, String), Response>> {
+  let mut reader = BufReader::new(stream).take(MAX_HEAD as u64);
+  let mut line = String::new();
+  reader.read_line(&mut line)?;
+  let mut parts = line.split_whitespace();
+  let (method, path) = match (parts.next(), parts.next()) {
+    (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+    _ if reader.limit() == 0 => return Ok(Err(Response::error(431, "request line too long"))),
+    _ => return Ok(Err(Response::error(400, "malformed request"))),
+  };
+  let (mut len, mut host) = (0, None);
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+      if reader.limit() == 0 {
+        return Ok(Err(Response::error(431, "request headers too large")));
+      }
+      break;
+    }
+    if let Some((name, val)) = header.split_once(':') {
+      match name.trim().to_ascii_lowercase().as_str() {
+        "content-length" => len = val.trim().parse().unwrap_or(0),
+        "host" => host = Some(val.trim().to_string()),
+        _ => {},
+      }
+    }
+  }
+  if !host.as_deref().is_some_and(is_local) {
+    return Ok(Err(Response::error(403, "requests must be addressed to localhost")));
+  }
+  if len > MAX_BODY {
+    return Ok(Err(Response::error(413, "request body too large")));
+  }
+  reader.set_limit(len as u64);
+  let mut body = vec![0; len];
+  reader.read_exact(&mut body)?;
+  match String::from_utf8(body) {
+    Ok(body) => Ok(Ok((method, path, body))),
+    Err(_) => Ok(Err(Response::error(400, "request body is not UTF-8"))),
+  }
+}
+
+/// Whether a host, as a request's `Host` header has it, is this one, on
+/// whichever port.
+fn is_local(host: &str) -> bool {
+  let name = host.rsplit_once(':').filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit())).map_or(host, |(name, _)| name);
+  HOSTS.iter().any(|h| name.eq_ignore_ascii_case(h))
+}
+
+/// A context to evaluate in, with the locale and rates configured.
+pub fn context(conf: &Config) -> exec::Context {
+  let mut cxt = exec::Context::new_with_stdlib();
+  cxt.set_locale(conf.locale.clone());
+  cxt.set_rates(conf.rates.clone());
+  cxt
+}
+
+/// Produce the response to a request.
+fn respond(conf: &Config, method: &str, path: &str, body: &str) -> Response {
+  let path = path.split('?').next().unwrap_or(path);
+  let expect = match path {
+    "/eval" | "/document" => "POST",
+    "/units" | "/currencies" | "/rates" => "GET",
+    _ => return Response::error(404, &format!("no such endpoint: {}", path)),
+  };
+  if method != expect {
+    return Response::error(405, &format!("expected {} {}", expect, path));
+  }
+  match path {
+    "/eval" => match serde_json::from_str::<Expr>(body) {
+      Ok(req) => Response::ok(&json::eval_line(&mut context(conf), 1, &req.expr)),
+      Err(err) => Response::error(400, &err.to_string()),
+    },
+    "/document" => match serde_json::from_str::<Document>(body) {
+      Ok(req) => {
+        let mut cxt = context(conf);
+        let lines: Vec<json::Line> = req.text.lines().enumerate().map(|(i, text)| json::eval_line(&mut cxt, i + 1, text)).collect();
+        Response::ok(&lines)
+      },
+      Err(err) => Response::error(400, &err.to_string()),
+    },
+    "/units" => Response::ok(&unit::units().iter().map(|u| u.to_string()).collect::<Vec<_>>()),
+    "/currencies" => Response::ok(&unit::CURRENCIES.iter().map(|c| c.code()).collect::<Vec<_>>()),
+    _ => Response::ok(&conf.rates.iter().map(|r| Rate{from: r.from.code().to_string(), to: r.to.code().to_string(), rate: r.rate}).collect::<Vec<_>>()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn respond_to_requests() {
+    let conf = Config{rates: unit::Rate::parse("EUR/USD = 1.25").into_iter().collect(), ..Config::default()};
+    let res = respond(&conf, "POST", "/eval", r#"{"expr": "4 EUR in USD"}"#);
+    assert_eq!(200, res.status);
+    assert!(res.body.contains(r#""display":"5 USD""#), "{}", res.body);
+    let res = respond(&conf, "POST", "/document", r#"{"text": "a = 2\na * 3"}"#);
+    assert!(res.body.contains(r#""line":2"#) && res.body.contains(r#""display":"6""#), "{}", res.body);
+    assert_eq!(Response{status: 200, body: r#"[{"from":"EUR","to":"USD","rate":1.25}]"#.to_string()}, respond(&conf, "GET", "/rates", ""));
+    assert_eq!(Response{status: 200, body: r#"["USD","EUR","GBP","JPY","CHF"]"#.to_string()}, respond(&conf, "GET", "/currencies?all", ""));
+    assert_eq!(400, respond(&conf, "POST", "/eval", r#"{"text": "1"}"#).status);
+    assert_eq!(405, respond(&conf, "GET", "/eval", "").status);
+    assert_eq!(404, respond(&conf, "GET", "/", "").status);
+  }
+
+  #[test]
+  fn read_requests() {
+    let body = r#"{"expr": "1"}"#;
+    let req = format!("POST /eval HTTP/1.1\r\nHost: localhost:7070\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+    assert_eq!(Ok(("POST".to_string(), "/eval".to_string(), body.to_string())), read_request(req.as_bytes()).unwrap());
+    let status = |req: &str| read_request(req.as_bytes()).unwrap().err().map(|res| res.status);
+    assert_eq!(None, status("GET /units HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n"));
+    assert_eq!(Some(403), status("GET /units HTTP/1.1\r\nHost: rebound.example:7070\r\n\r\n"));
+    assert_eq!(Some(403), status("GET /units HTTP/1.1\r\n\r\n"));
+    assert_eq!(Some(431), status(&format!("GET /units HTTP/1.1\r\nHost: localhost\r\nX-Padding: {}\r\n\r\n", "x".repeat(MAX_HEAD))));
+    assert_eq!(Some(431), status(&format!("GET /{} HTTP/1.1\r\n\r\n", "x".repeat(MAX_HEAD))));
+  }
+
+}
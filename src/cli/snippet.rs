@@ -0,0 +1,28 @@
+use std::path;
+use std::io::{self, Write, IsTerminal};
+
+use crate::error;
+use crate::document;
+use crate::snippet::{self, Snippet};
+use crate::rdl::import::Imports;
+use crate::document::settings;
+
+/// Write a snippet of a document to standard output as an SVG image, as
+/// Markdown, or as text, styled with ANSI escape sequences unless it is
+/// piped elsewhere.
+pub fn run(doc: &str, lines: Option<&str>, svg: bool, markdown: bool) -> Result<(), error::Error> {
+  let path = path::Path::new(doc);
+  let text = document::load(path)?;
+  let lines = match lines {
+    Some(spec) => match snippet::parse_lines(spec) {
+      Some(lines) => lines,
+      None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid line range: {}", spec)).into()),
+    },
+    None => 0..usize::MAX,
+  };
+  let snip = Snippet::new(settings::context(&text, None), &text, lines, &mut Imports::new_for_document(path));
+  let mut stdout = io::stdout().lock();
+  let out = if svg { snip.svg() } else if markdown { snip.markdown() } else if stdout.is_terminal() { snip.ansi() } else { snip.plain() };
+  write!(stdout, "{}", out)?;
+  Ok(())
+}
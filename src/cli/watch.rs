@@ -0,0 +1,75 @@
+use std::fs;
+use std::path;
+use std::sync::mpsc;
+use std::time;
+use std::io::{self, Write, IsTerminal};
+
+use crossterm::execute;
+use crossterm::cursor;
+use crossterm::terminal;
+use notify::{Watcher, RecursiveMode};
+
+use crate::error;
+use crate::document;
+use crate::options;
+use crate::rdl::import::Imports;
+use crate::cli::pipe;
+use crate::document::settings;
+
+const DEBOUNCE: time::Duration = time::Duration::from_millis(50);
+
+/// Evaluate a document and print its results, then evaluate it again every
+/// time it changes. This runs until the process is interrupted.
+///
+/// The parent directory is watched rather than the file itself, since many
+/// editors save by replacing the file, which would end a watch placed on it.
+pub fn run(doc: &str, opts: &options::Options) -> Result<(), error::Error> {
+  let file = fs::canonicalize(doc)?;
+  let dir = match file.parent() {
+    Some(dir) => dir.to_owned(),
+    None => path::PathBuf::from("/"),
+  };
+  
+  let (tx, rx) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(tx)?;
+  watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+  
+  write_document(&file, opts)?;
+  loop {
+    let evt = match rx.recv() {
+      Ok(evt) => evt?,
+      Err(_)  => return Ok(()), // watcher is gone
+    };
+    if !affects(&evt, &file) {
+      continue;
+    }
+    while rx.recv_timeout(DEBOUNCE).is_ok() {} // coalesce bursts of events from a single save
+    write_document(&file, opts)?;
+  }
+}
+
+fn affects(evt: &notify::Event, file: &path::Path) -> bool {
+  if evt.kind.is_access() {
+    return false;
+  }
+  evt.paths.iter().any(|p| p == file)
+}
+
+fn write_document(file: &path::Path, opts: &options::Options) -> Result<(), error::Error> {
+  let text = match document::load(file) { // before clearing, so the last results stay up until there are new ones
+    Ok(text) => text,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()), // mid-replace; wait for the next event
+    Err(err) => return Err(err.into()),
+  };
+  let mut stdout = io::stdout().lock();
+  if stdout.is_terminal() {
+    execute!(stdout, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+  }
+  let mut cxt = settings::context(&text, None);
+  let mut imports = Imports::new_for_document(file); // imported files are re-read every time the document changes
+  for (i, line) in text.lines().enumerate() {
+    pipe::write_line(&mut stdout, &mut cxt, &mut imports, i + 1, line, opts)?;
+  }
+  stdout.flush()?;
+  Ok(())
+}
@@ -0,0 +1,140 @@
+use std::env;
+use std::io::{self, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether text may be copied through the terminal when there is no
+/// clipboard to reach.
+static OSC52: AtomicBool = AtomicBool::new(true);
+
+/// The most text, once encoded, that terminals commonly accept in an OSC 52
+/// sequence. Longer text isn't copied at all rather than copied in part.
+const OSC52_LIMIT: usize = 74_994;
+
+/// Clipboard commands to try, in order. The first one that can be run is
+/// used. These cover macOS, Wayland, X11, and WSL.
+const COMMANDS: &[&[&str]] = &[
+  &["pbcopy"],
+  &["wl-copy"],
+  &["xclip", "-selection", "clipboard"],
+  &["xsel", "--clipboard", "--input"],
+  &["clip.exe"],
+];
+
+/// Clipboard commands to try, in order, to copy RTF, which word processors
+/// and mail paste with its formatting. There's no way to copy it through the
+/// terminal. `pbcopy` knows RTF when it sees it.
+const RTF_COMMANDS: &[&[&str]] = &[
+  &["pbcopy"],
+  &["wl-copy", "--type", "text/rtf"],
+  &["xclip", "-selection", "clipboard", "-t", "text/rtf"],
+];
+
+/// Allow or forbid copying through the terminal from now on.
+pub fn set_osc52(enabled: bool) {
+  OSC52.store(enabled, Ordering::Relaxed);
+}
+
+/// Copy text to the system clipboard. Where there is none to reach, as in
+/// a session over SSH, the terminal is asked to copy it instead, with an
+/// OSC 52 escape sequence, unless that has been forbidden.
+pub fn copy(text: &str) -> io::Result<()> {
+  let osc52 = OSC52.load(Ordering::Relaxed);
+  let mut last = io::Error::new(io::ErrorKind::NotFound, "No clipboard is available");
+  if !(osc52 && is_remote()) { // a clipboard on the remote machine is no use to anyone
+    for cmd in COMMANDS {
+      match copy_with(cmd, text) {
+        Ok(_) => return Ok(()),
+        Err(err) => {
+          log::debug!("{}: {}", cmd[0], err);
+          last = err;
+        },
+      };
+    }
+  }
+  if osc52 {
+    log::debug!("copying through the terminal");
+    copy_osc52(text)
+  }else{
+    Err(last)
+  }
+}
+
+/// Copy RTF to the system clipboard, or, if it can't be, copy the same text
+/// without its formatting, as `copy` copies it.
+pub fn copy_rtf(rtf: &str, text: &str) -> io::Result<()> {
+  if !(OSC52.load(Ordering::Relaxed) && is_remote()) {
+    for cmd in RTF_COMMANDS {
+      match copy_with(cmd, rtf) {
+        Ok(_) => return Ok(()),
+        Err(err) => log::debug!("{}: {}", cmd[0], err),
+      };
+    }
+  }
+  copy(text)
+}
+
+/// Whether we're running on a remote machine, over SSH.
+fn is_remote() -> bool {
+  env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some()
+}
+
+fn copy_osc52(text: &str) -> io::Result<()> {
+  let encoded = base64(text.as_bytes());
+  if encoded.len() > OSC52_LIMIT {
+    return Err(io::Error::other(format!("{} bytes is too much to copy through the terminal", text.len())));
+  }
+  let mut out = io::stdout();
+  write!(out, "\x1b]52;c;{}\x07", encoded)?;
+  out.flush()
+}
+
+/// Encode bytes as standard, padded base64.
+pub fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+      }else{
+        out.push('=');
+      }
+    }
+  }
+  out
+}
+
+fn copy_with(cmd: &[&str], text: &str) -> io::Result<()> {
+  let mut child = process::Command::new(cmd[0])
+    .args(&cmd[1..])
+    .stdin(process::Stdio::piped())
+    .stdout(process::Stdio::null())
+    .stderr(process::Stdio::null())
+    .spawn()?;
+  if let Some(mut stdin) = child.stdin.take() {
+    stdin.write_all(text.as_bytes())?;
+  }
+  let status = child.wait()?;
+  if status.success() {
+    Ok(())
+  }else{
+    Err(io::Error::other(format!("{} failed: {}", cmd[0], status)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encode_base64() {
+    assert_eq!("", base64(b""));
+    assert_eq!("Zg==", base64(b"f"));
+    assert_eq!("Zm8=", base64(b"fo"));
+    assert_eq!("Zm9v", base64(b"foo"));
+    assert_eq!("NSBFVVIg4oKs", base64("5 EUR €".as_bytes()));
+  }
+
+}
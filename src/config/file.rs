@@ -0,0 +1,561 @@
+use std::fs;
+use std::io;
+use std::fmt;
+use std::path;
+use std::time;
+use std::collections::BTreeMap;
+
+use crate::config;
+use crate::document::settings::{self, Settings};
+use crate::theme;
+use crate::rates;
+use crate::util::suggest;
+use crate::rdl::unit::{self, Currency};
+use crate::rdl::locale::{DateOrder, Locale, Weekday, WEEKDAYS};
+use crate::editor::keymap::{self, Keymap};
+use crate::editor::status::Status;
+use crate::editor::cursor::{Cursors, Shape, Style, SHAPES};
+use crate::config::plugins::{self, Plugins};
+use crate::editor::writer::{Placement, PLACEMENTS};
+use crate::text::width::Ambiguous;
+use crate::i18n::{Language, LANGUAGES};
+
+const FILE: &str = "config.toml";
+
+/// The settings the configuration file may contain.
+const KEYS: [&str; 18] = ["theme", "placement", "ambiguous_width", "language", "status", "osc52", "locale", "precision", "figures", "rounding", "keymap", "providers", "quotes", "rates", "aliases", "units", "plugins", "cursor"];
+/// The conventions a locale table may set.
+const LOCALE_KEYS: [&str; 6] = ["name", "currency", "decimal", "grouping", "date_order", "week_start"];
+/// The settings of the cursor, which may also be set for each mode.
+const CURSOR_KEYS: [&str; 3] = ["shape", "blink", "delete"];
+/// The settings a plugins table may contain, besides tables of settings for each plugin.
+const PLUGIN_KEYS: [&str; 2] = ["enabled", "paths"];
+
+/// A problem with the configuration file. Problems with a single value
+/// name the key; the value is ignored and its default used instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+  pub key: Option<String>,
+  pub message: String,
+}
+
+impl Problem {
+  fn new(key: &str, message: String) -> Problem {
+    Problem{
+      key: Some(key.to_string()),
+      message,
+    }
+  }
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.key {
+      Some(key) => write!(f, "{}: {}: {}", FILE, key, self.message),
+      None      => write!(f, "{}: {}", FILE, self.message),
+    }
+  }
+}
+
+/// User configuration, from `config.toml` in the configuration directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+  pub theme: Option<String>,                    // none to choose one to suit the terminal
+  pub placement: Placement,                     // where results are drawn
+  pub ambiguous_width: Option<Ambiguous>,       // none to guess from the locale
+  pub status: Status,                           // what the status bar shows
+  pub language: Option<Language>,               // of the interface; none to choose one from the locale
+  pub osc52: bool,                              // whether to copy through the terminal without a clipboard
+  pub locale: Locale,                           // for documents that don't set one
+  pub precision: Option<usize>,                 // likewise
+  pub figures: Option<usize>,                   // likewise
+  pub rounding: Option<unit::Rounding>,         // likewise
+  pub keymap: Keymap,
+  pub providers: Vec<String>,                   // data providers, in order of preference
+  pub quotes: Vec<String>,                      // providers of quotes for stocks and indices, likewise
+  pub units: Vec<unit::Custom>,                 // in addition to the built-in units
+  pub rates: Vec<unit::Rate>,                   // exchange rates, which documents may override
+  pub aliases: Vec<(String, String)>,           // words, and the text they stand for
+  pub plugins: Plugins,
+  pub cursor: Cursors,
+}
+
+impl Default for Config {
+  fn default() -> Config {
+    Config{
+      theme: None,
+      placement: Placement::Column,
+      ambiguous_width: None,
+      status: Status::default(),
+      language: None,
+      osc52: true,
+      locale: Locale::default(),
+      precision: None,
+      figures: None,
+      rounding: None,
+      keymap: Keymap::default(),
+      providers: Vec::new(),
+      quotes: Vec::new(),
+      units: Vec::new(),
+      rates: Vec::new(),
+      aliases: Vec::new(),
+      plugins: Plugins::default(),
+      cursor: Cursors::default(),
+    }
+  }
+}
+
+impl Config {
+  /// Settings that apply to documents unless they override them.
+  pub fn defaults(&self) -> Settings {
+    Settings{
+      precision: self.precision,
+      figures: self.figures,
+      rounding: self.rounding,
+      rates: self.rates.clone(),
+      ..Settings::default()
+    }
+  }
+
+  /// Determine whether a change from this configuration to another can be
+  /// applied while running. Providers, units, aliases, and plugins are only
+  /// set up at startup.
+  pub fn is_live(&self, other: &Config) -> bool {
+    self.providers == other.providers && self.quotes == other.quotes && self.units == other.units && self.aliases == other.aliases && self.plugins == other.plugins
+  }
+}
+
+/// The path to the configuration file, if there is a configuration
+/// directory.
+pub fn path() -> Option<path::PathBuf> {
+  config::dir().map(|d| d.join(FILE))
+}
+
+/// Load the configuration file. A missing file is not an error; values
+/// which are invalid are reported and replaced by their defaults.
+pub fn load() -> (Config, Vec<Problem>) {
+  let path = match path() {
+    Some(path) => path,
+    None => return (Config::default(), Vec::new()),
+  };
+  match fs::read_to_string(&path) {
+    Ok(text) => parse(&text),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => (Config::default(), Vec::new()),
+    Err(err) => (Config::default(), vec![Problem{key: None, message: format!("Could not read {}: {}", path.display(), err)}]),
+  }
+}
+
+/// Parse the text of a configuration file.
+pub fn parse(text: &str) -> (Config, Vec<Problem>) {
+  let mut conf = Config::default();
+  let mut probs = Vec::new();
+  let table = match text.parse::<toml::Value>() {
+    Ok(toml::Value::Table(table)) => table,
+    Ok(_) => return (conf, probs),
+    Err(err) => return (conf, vec![Problem{key: None, message: err.to_string()}]),
+  };
+  for (key, val) in &table {
+    match key.as_str() {
+      "theme" => {
+        let themes = theme::names();
+        match val.as_str() {
+          Some(theme::AUTO) => conf.theme = None,
+          Some(name) if themes.iter().any(|t| t == name) => conf.theme = Some(name.to_string()),
+          _ => probs.push(Problem::new(key, format!("expected {} or one of {}, got {}", theme::AUTO, themes.join(", "), val))),
+        };
+      },
+      "placement" => match val.as_str().and_then(Placement::from_name) {
+        Some(placement) => conf.placement = placement,
+        None => probs.push(Problem::new(key, format!("expected one of {}, got {}", PLACEMENTS.map(|p| p.to_string()).join(", "), val))),
+      },
+      "ambiguous_width" => match val.as_str() {
+        Some(theme::AUTO) => conf.ambiguous_width = None,
+        Some(name) if Ambiguous::from_name(name).is_some() => conf.ambiguous_width = Ambiguous::from_name(name),
+        _ => probs.push(Problem::new(key, format!("expected {}, {}, or {}, got {}", theme::AUTO, Ambiguous::Narrow, Ambiguous::Wide, val))),
+      },
+      "language" => match val.as_str() {
+        Some(theme::AUTO) => conf.language = None,
+        Some(name) if Language::from_name(name).is_some() => conf.language = Language::from_name(name),
+        _ => probs.push(Problem::new(key, format!("expected {} or one of {}, got {}", theme::AUTO, LANGUAGES.map(|l| l.to_string()).join(", "), val))),
+      },
+      "status" => match val.as_str().map(Status::parse) {
+        Some(Ok(status)) => conf.status = status,
+        Some(Err(err)) => probs.push(Problem::new(key, err)),
+        None => probs.push(Problem::new(key, format!("expected a template, such as \"{{doc}} {{cursor}}\", got {}", val))),
+      },
+      "osc52" => match val.as_bool() {
+        Some(osc52) => conf.osc52 = osc52,
+        None => probs.push(Problem::new(key, format!("expected true or false, got {}", val))),
+      },
+      "locale" => match val {
+        toml::Value::String(name) => match Locale::named(name) {
+          Some(locale) => conf.locale = locale,
+          None => probs.push(Problem::new(key, format!("expected one of {}, got {}", settings::LOCALES.join(", "), val))),
+        },
+        toml::Value::Table(table) => conf.locale = parse_locale(table, &mut probs),
+        _ => probs.push(Problem::new(key, format!("expected a locale name or a table of conventions, got {}", val))),
+      },
+      "precision" => match val.as_integer() {
+        Some(n) if n >= 0 && n as usize <= settings::MAX_PRECISION => conf.precision = Some(n as usize),
+        _ => probs.push(Problem::new(key, format!("expected a number of decimal places from 0 to {}, got {}", settings::MAX_PRECISION, val))),
+      },
+      "figures" => match val.as_integer() {
+        Some(n) if n >= 1 && n as usize <= settings::MAX_FIGURES => conf.figures = Some(n as usize),
+        _ => probs.push(Problem::new(key, format!("expected a number of significant figures from 1 to {}, got {}", settings::MAX_FIGURES, val))),
+      },
+      "rounding" => match val.as_str().and_then(unit::Rounding::from_name) {
+        Some(rounding) => conf.rounding = Some(rounding),
+        None => probs.push(Problem::new(key, format!("expected one of {}, got {}", unit::ROUNDINGS.map(|r| r.to_string()).join(", "), val))),
+      },
+      "keymap" => match val.as_table() {
+        Some(table) => {
+          let mut custom = BTreeMap::new();
+          for (cmd, spec) in table {
+            match spec.as_str().and_then(keymap::parse_key) {
+              Some(evt) => {
+                custom.insert(cmd.to_string(), evt);
+              },
+              None => probs.push(Problem::new(&format!("keymap.{}", cmd), format!("expected a key, such as \"ctrl-s\", got {}", spec))),
+            };
+          }
+          let (map, errs) = Keymap::new(&custom);
+          conf.keymap = map;
+          probs.extend(errs.into_iter().map(|e| Problem::new(key, e)));
+        },
+        None => probs.push(Problem::new(key, format!("expected a table of commands and keys, got {}", val))),
+      },
+      "providers" => match val.as_array().and_then(|a| a.iter().map(|v| v.as_str().map(|s| s.to_string())).collect::<Option<Vec<_>>>()) {
+        Some(providers) => {
+          for spec in &providers {
+            if let Err(err) = rates::provider(spec) {
+              probs.push(Problem::new(key, err));
+            }
+          }
+          conf.providers = providers;
+        },
+        None => probs.push(Problem::new(key, format!("expected a list of provider names, got {}", val))),
+      },
+      "quotes" => match val.as_array().and_then(|a| a.iter().map(|v| v.as_str().map(|s| s.to_string())).collect::<Option<Vec<_>>>()) {
+        Some(providers) => {
+          for spec in &providers {
+            if let Err(err) = rates::quotes::provider(spec) {
+              probs.push(Problem::new(key, err));
+            }
+          }
+          conf.quotes = providers;
+        },
+        None => probs.push(Problem::new(key, format!("expected a list of quote provider names, got {}", val))),
+      },
+      "rates" => match val.as_table() {
+        Some(table) => for (pair, rate) in table {
+          match rate.as_float().or_else(|| rate.as_integer().map(|n| n as f64)).and_then(|r| unit::Rate::new(pair, r)) {
+            Some(rate) => conf.rates.push(rate),
+            None => probs.push(Problem::new(&format!("rates.{}", pair), format!("expected a pair of different currencies, such as \"EUR/USD\", and a positive rate, got {}", rate))),
+          };
+        },
+        None => probs.push(Problem::new(key, format!("expected a table of exchange rates, got {}", val))),
+      },
+      "aliases" => match val.as_table() {
+        Some(table) => for (alias, text) in table {
+          match text.as_str().map(str::trim) {
+            Some(_) if !is_name(alias) => probs.push(Problem::new(&format!("aliases.{}", alias), "aliases may only contain letters, digits, and underscores".to_string())),
+            Some(text) if !text.is_empty() => conf.aliases.push((alias.to_string(), text.to_string())),
+            _ => probs.push(Problem::new(&format!("aliases.{}", alias), format!("expected the text it stands for, such as \"19% tax\", got {}", text))),
+          };
+        },
+        None => probs.push(Problem::new(key, format!("expected a table of aliases, got {}", val))),
+      },
+      "units" => match val.as_table() {
+        Some(table) => conf.units = parse_units(table, &mut probs),
+        None => probs.push(Problem::new(key, format!("expected a table of unit definitions, got {}", val))),
+      },
+      "cursor" => match val.as_table() {
+        Some(table) => conf.cursor = parse_cursor(table, &mut probs),
+        None => probs.push(Problem::new(key, format!("expected a table of cursor settings, got {}", val))),
+      },
+      "plugins" => match val.as_table() {
+        Some(table) => conf.plugins = parse_plugins(table, &mut probs),
+        None => probs.push(Problem::new(key, format!("expected a table of plugins, got {}", val))),
+      },
+      _ => probs.push(Problem::new(key, unknown(key, &KEYS))),
+    };
+  }
+  (conf, probs)
+}
+
+/// Parse a `[units]` table, which defines each unit as an amount of
+/// another, such as `point = "0.3528 mm"`.
+fn parse_units(table: &toml::value::Table, probs: &mut Vec<Problem>) -> Vec<unit::Custom> {
+  let mut defs = Vec::new();
+  for (name, val) in table {
+    let key = format!("units.{}", name);
+    let def = val.as_str().and_then(|s| s.trim().split_once(char::is_whitespace)).and_then(|(amount, of)| {
+      Some(unit::Definition{name: name.to_string(), amount: amount.parse().ok()?, of: of.trim().to_string()})
+    });
+    match def {
+      Some(def) if is_name(name) && is_name(&def.of) => defs.push(def),
+      Some(_) => probs.push(Problem::new(&key, "unit names may only contain letters, digits, and underscores".to_string())),
+      None => probs.push(Problem::new(&key, format!("expected an amount of another unit, such as \"0.3528 mm\", got {}", val))),
+    };
+  }
+  let (units, errs) = unit::define(&defs);
+  probs.extend(errs.into_iter().map(|(name, e)| Problem::new(&format!("units.{}", name), e)));
+  units
+}
+
+/// Determine whether text can be written as a unit in an expression.
+fn is_name(text: &str) -> bool {
+  text.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_') && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Parse a `[cursor]` table, which sets the style of the cursor and may
+/// set a different one for delete mode in `[cursor.delete]`.
+fn parse_cursor(table: &toml::value::Table, probs: &mut Vec<Problem>) -> Cursors {
+  let base = Style{shape: Shape::Block, blink: true};
+  let normal = parse_cursor_style("cursor", table, base, probs);
+  let delete = match table.get("delete") {
+    Some(toml::Value::Table(table)) => parse_cursor_style("cursor.delete", table, normal.unwrap_or(base), probs).or(normal),
+    Some(val) => {
+      probs.push(Problem::new("cursor.delete", format!("expected a table of cursor settings, got {}", val)));
+      normal
+    },
+    None => normal,
+  };
+  Cursors{normal, delete}
+}
+
+/// Parse the style of the cursor from a table, starting from a base style.
+/// Produces none if the table doesn't set one.
+fn parse_cursor_style(path: &str, table: &toml::value::Table, base: Style, probs: &mut Vec<Problem>) -> Option<Style> {
+  let mut style = None;
+  for (key, val) in table {
+    let key_path = format!("{}.{}", path, key);
+    match key.as_str() {
+      "shape" => match val.as_str().and_then(Shape::from_name) {
+        Some(shape) => style = Some(Style{shape, ..style.unwrap_or(base)}),
+        None => probs.push(Problem::new(&key_path, format!("expected one of {}, got {}", SHAPES.map(|s| s.to_string()).join(", "), val))),
+      },
+      "blink" => match val.as_bool() {
+        Some(blink) => style = Some(Style{blink, ..style.unwrap_or(base)}),
+        None => probs.push(Problem::new(&key_path, format!("expected true or false, got {}", val))),
+      },
+      "delete" if path == "cursor" => {},
+      _ => probs.push(Problem::new(&key_path, unknown(key, &CURSOR_KEYS))),
+    };
+  }
+  style
+}
+
+/// Parse a `[plugins]` table: the plugins to enable, where to look for them,
+/// and a table of settings for any of them.
+fn parse_plugins(table: &toml::value::Table, probs: &mut Vec<Problem>) -> Plugins {
+  let mut conf = Plugins::default();
+  let strings = |val: &toml::Value| val.as_array().and_then(|a| a.iter().map(|v| v.as_str().map(|s| s.to_string())).collect::<Option<Vec<_>>>());
+  for (key, val) in table {
+    let path = format!("plugins.{}", key);
+    match (key.as_str(), val) {
+      ("enabled", _) => match strings(val) {
+        Some(names) => for name in names {
+          if plugins::is_name(&name) {
+            conf.enabled.push(name);
+          }else{
+            probs.push(Problem::new(&path, format!("plugin names may only contain letters, digits, hyphens, and underscores, got {:?}", name)));
+          }
+        },
+        None => probs.push(Problem::new(&path, format!("expected a list of plugin names, got {}", val))),
+      },
+      ("paths", _) => match strings(val) {
+        Some(paths) => conf.paths = paths.iter().map(|p| plugins::expand(p)).collect(),
+        None => probs.push(Problem::new(&path, format!("expected a list of directories, got {}", val))),
+      },
+      (name, toml::Value::Table(settings)) => {
+        conf.settings.insert(name.to_string(), settings.clone());
+      },
+      _ => probs.push(Problem::new(&path, unknown(key, &PLUGIN_KEYS))),
+    };
+  }
+  for name in conf.settings.keys().filter(|n| !conf.enabled.contains(n)) {
+    probs.push(Problem::new(&format!("plugins.{}", name), "settings for a plugin which is not enabled".to_string()));
+  }
+  conf
+}
+
+/// Parse a `[locale]` table. It may name a locale to start from and then
+/// override any of its conventions.
+/// Describe a setting which doesn't exist, suggesting one which does if it
+/// looks like a typo.
+fn unknown(key: &str, known: &[&str]) -> String {
+  match suggest::closest(key, known) {
+    Some(near) => format!("unknown setting; did you mean {}?", near),
+    None => "unknown setting".to_string(),
+  }
+}
+
+fn parse_locale(table: &toml::value::Table, probs: &mut Vec<Problem>) -> Locale {
+  let mut locale = Locale::default();
+  if let Some(val) = table.get("name") {
+    match val.as_str().and_then(Locale::named) {
+      Some(named) => locale = named,
+      None => probs.push(Problem::new("locale.name", format!("expected one of {}, got {}", settings::LOCALES.join(", "), val))),
+    };
+  }
+  for (key, val) in table {
+    let path = format!("locale.{}", key);
+    match key.as_str() {
+      "name" => {},
+      "currency" => match val.as_str().and_then(Currency::from_code) {
+        Some(currency) => locale.currency = currency,
+        None => probs.push(Problem::new(&path, format!("expected one of {}, got {}", unit::CURRENCIES.map(|c| c.code()).join(", "), val))),
+      },
+      "decimal" => match val.as_str().and_then(separator) {
+        Some(Some(c)) if c.is_ascii_punctuation() => locale.decimal = c,
+        _ => probs.push(Problem::new(&path, format!("expected a punctuation character, such as \".\" or \",\", got {}", val))),
+      },
+      "grouping" => match val.as_str().and_then(separator) {
+        Some(c) if !c.is_some_and(|c| c.is_alphanumeric()) => locale.grouping = c,
+        _ => probs.push(Problem::new(&path, format!("expected a single character, or \"\" for none, got {}", val))),
+      },
+      "date_order" => match val.as_str().and_then(DateOrder::from_name) {
+        Some(order) => locale.date_order = order,
+        None => probs.push(Problem::new(&path, format!("expected dmy, mdy, or ymd, got {}", val))),
+      },
+      "week_start" => match val.as_str().and_then(Weekday::from_name) {
+        Some(day) => locale.week_start = day,
+        None => probs.push(Problem::new(&path, format!("expected a day of the week, such as {} or {}, got {}", WEEKDAYS[0], WEEKDAYS[6], val))),
+      },
+      _ => probs.push(Problem::new(&path, unknown(key, &LOCALE_KEYS))),
+    };
+  }
+  if locale.grouping == Some(locale.decimal) {
+    probs.push(Problem::new("locale.grouping", format!("the same as the decimal separator, {:?}; digits will not be grouped", locale.decimal)));
+    locale.grouping = None;
+  }
+  locale
+}
+
+/// Parse a separator, which is a single character or nothing at all.
+fn separator(text: &str) -> Option<Option<char>> {
+  let mut chars = text.chars();
+  match (chars.next(), chars.next()) {
+    (None, _) => Some(None),
+    (Some(c), None) => Some(Some(c)),
+    _ => None,
+  }
+}
+
+/// Watches the configuration file for changes.
+pub struct Watch {
+  path: Option<path::PathBuf>,
+  mtime: Option<time::SystemTime>,
+}
+
+impl Watch {
+  pub fn new() -> Watch {
+    let path = path();
+    let mtime = path.as_deref().and_then(modified);
+    Watch{
+      path,
+      mtime,
+    }
+  }
+
+  /// Load the configuration again if the file has changed since it was
+  /// last loaded, including if it has been created or removed.
+  pub fn changed(&mut self) -> Option<(Config, Vec<Problem>)> {
+    let mtime = self.path.as_deref().and_then(modified);
+    if mtime == self.mtime {
+      return None;
+    }
+    self.mtime = mtime;
+    Some(load())
+  }
+}
+
+fn modified(path: &path::Path) -> Option<time::SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_config() {
+    use crate::editor::command::Command;
+    let (conf, probs) = parse("theme = \"dark\"\nlanguage = \"fr\"\nplacement = \"below\"\nambiguous_width = \"wide\"\nlocale = \"de\"\nprecision = 2\nfigures = 3\nrounding = \"half-even\"\nstatus = \"\"\nosc52 = false\nproviders = [\"ecb\", \"file\"]\nquotes = [\"stooq\", \"yahoo\"]\n[keymap]\nsave = \"ctrl-w\"\nkeybindings = \"F2\"\n[rates]\n\"EUR/USD\" = 1.09\n\"EUR/EUR\" = 1\n[units]\npoint = \"0.3528 mm\"\nheap = \"lots\"\n[aliases]\nmwst = \"19% tax\"\n\"km/h\" = \"kmh\"\n");
+    assert_eq!(vec![
+      "config.toml: aliases.km/h: aliases may only contain letters, digits, and underscores",
+      "config.toml: keymap: ctrl-w is bound to both save and next-word; next-word is unbound",
+      "config.toml: providers: file needs a path, as in \"file:~/rates.toml\"",
+      "config.toml: quotes: expected one of stooq, file, got yahoo",
+      "config.toml: rates.EUR/EUR: expected a pair of different currencies, such as \"EUR/USD\", and a positive rate, got 1",
+      "config.toml: units.heap: expected an amount of another unit, such as \"0.3528 mm\", got \"lots\"",
+    ], probs.iter().map(|p| p.to_string()).collect::<Vec<_>>());
+    assert_eq!(unit::Rate::parse("EUR/USD = 1.09").into_iter().collect::<Vec<_>>(), conf.rates);
+    assert_eq!(2, conf.units.len());
+    assert_eq!(vec![("mwst".to_string(), "19% tax".to_string())], conf.aliases); // the point, and the millimeter it is defined by
+    assert_eq!(Placement::Below, conf.placement);
+    assert_eq!(Some(Ambiguous::Wide), conf.ambiguous_width);
+    assert!(!conf.status.is_visible());
+    assert!(!conf.osc52);
+    assert_eq!(Some(Language::French), conf.language);
+    assert_eq!(Some(2), conf.precision);
+    assert_eq!(Some(3), conf.figures);
+    assert_eq!(Some(unit::Rounding::HalfEven), conf.rounding);
+    assert_eq!(Locale::named("de"), Some(conf.locale));
+    assert_eq!(vec!["ecb".to_string(), "file".to_string()], conf.providers);
+    assert_eq!(vec!["stooq".to_string(), "yahoo".to_string()], conf.quotes);
+    assert_eq!(Some(&Command::Save), conf.keymap.lookup(&keymap::parse_key("ctrl-w").unwrap()));
+    assert_eq!(None, conf.keymap.key(&Command::NextWord));
+
+    let (conf, probs) = parse("theme = \"neon\"\nlanguage = \"tlh\"\nambiguous_width = 2\nplacement = \"left\"\nprecision = -1\nrounding = \"sideways\"\nplacment = \"inline\"\nosc52 = \"no\"\nstatus = \"{doc} {line}\"\ncolour = 1\n[keymap]\nsave = \"hyper-s\"\n");
+    assert_eq!(Config::default(), conf);
+    let probs: Vec<String> = probs.iter().map(|p| p.to_string()).collect();
+    assert_eq!(vec![
+      "config.toml: ambiguous_width: expected auto, narrow, or wide, got 2",
+      "config.toml: colour: unknown setting",
+      "config.toml: keymap.save: expected a key, such as \"ctrl-s\", got \"hyper-s\"",
+      "config.toml: language: expected auto or one of en, de, fr, got \"tlh\"",
+      "config.toml: osc52: expected true or false, got \"no\"",
+      "config.toml: placement: expected one of column, inline, below, got \"left\"",
+      "config.toml: placment: unknown setting; did you mean placement?",
+      "config.toml: precision: expected a number of decimal places from 0 to 10, got -1",
+      "config.toml: rounding: expected one of half-up, half-even, down, up, got \"sideways\"",
+      "config.toml: status: unknown segment {line}; expected one of mode, cursor, doc, rates_age, quotes_age, precision",
+      "config.toml: theme: expected auto or one of dark, light, mono, got \"neon\"",
+    ], probs);
+
+    let (conf, probs) = parse("[locale]\nname = \"en\"\ncurrency = \"chf\"\ndecimal = \",\"\ngrouping = \",\"\nweek_start = \"Monday\"\ndate_order = \"dym\"\n");
+    let probs: Vec<String> = probs.iter().map(|p| p.to_string()).collect();
+    assert_eq!(vec![
+      "config.toml: locale.date_order: expected dmy, mdy, or ymd, got \"dym\"",
+      "config.toml: locale.grouping: the same as the decimal separator, ','; digits will not be grouped",
+    ], probs);
+    assert_eq!(Locale{currency: Currency::Chf, decimal: ',', grouping: None, date_order: DateOrder::MonthDayYear, week_start: Weekday::Monday}, conf.locale);
+    
+    let (conf, probs) = parse("[plugins]\nenabled = [\"stocks\", \"../x\"]\npaths = [\"/opt/resolver\"]\nautoload = true\n[plugins.stocks]\nsymbol = \"ACME\"\n[plugins.weather]\ncity = \"Oslo\"\n");
+    let probs: Vec<String> = probs.iter().map(|p| p.to_string()).collect();
+    assert_eq!(vec![
+      "config.toml: plugins.autoload: unknown setting",
+      "config.toml: plugins.enabled: plugin names may only contain letters, digits, hyphens, and underscores, got \"../x\"",
+      "config.toml: plugins.weather: settings for a plugin which is not enabled",
+    ], probs);
+    assert_eq!(vec!["stocks".to_string()], conf.plugins.enabled);
+    assert_eq!(vec![path::PathBuf::from("/opt/resolver")], conf.plugins.paths);
+    assert_eq!(Some(&toml::Value::String("ACME".to_string())), conf.plugins.settings["stocks"].get("symbol"));
+
+    let (conf, probs) = parse("[cursor]\nshape = \"bar\"\nblnk = true\n[cursor.delete]\nshape = \"underline\"\nblink = false\n");
+    let probs: Vec<String> = probs.iter().map(|p| p.to_string()).collect();
+    assert_eq!(vec!["config.toml: cursor.blnk: unknown setting; did you mean blink?"], probs);
+    assert_eq!(Some(Style{shape: Shape::Bar, blink: true}), conf.cursor.normal);
+    assert_eq!(Some(Style{shape: Shape::Underline, blink: false}), conf.cursor.delete);
+    let (conf, probs) = parse("[cursor]\nshape = \"beam\"\n");
+    assert_eq!("config.toml: cursor.shape: expected one of block, bar, underline, got \"beam\"", probs[0].to_string());
+    assert_eq!(Cursors::default(), conf.cursor);
+
+    let (_, probs) = parse("precision = ");
+    assert_eq!(1, probs.len());
+    assert_eq!(None, probs[0].key);
+  }
+
+}
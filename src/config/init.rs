@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::fmt;
+
+use crate::config;
+use crate::rdl;
+use crate::rdl::unit;
+use crate::rdl::exec;
+
+const FILE: &str = "init.rsv";
+
+/// A problem encountered while evaluating the init file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.line > 0 {
+      write!(f, "{}:{}: {}", FILE, self.line, self.message)
+    }else{
+      write!(f, "{}: {}", FILE, self.message)
+    }
+  }
+}
+
+/// The outcome of evaluating the init file: the definitions it makes, which
+/// are available to every document, and any problems encountered.
+#[derive(Debug, Default)]
+pub struct Init {
+  pub vars: Vec<(String, unit::Value)>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Load and evaluate the init file from the configuration directory. A
+/// missing file is not an error.
+pub fn load() -> Init {
+  let path = match config::dir() {
+    Some(dir) => dir.join(FILE),
+    None => return Init::default(),
+  };
+  match fs::read_to_string(&path) {
+    Ok(text) => eval(&text),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => Init::default(),
+    Err(err) => Init{
+      vars: Vec::new(),
+      diagnostics: vec![Diagnostic{line: 0, message: format!("Could not read {}: {}", path.display(), err)}],
+    },
+  }
+}
+
+/// Evaluate the text of an init file. Every line is evaluated in order, as
+/// in a document; the variables defined by the end are its definitions.
+pub fn eval(text: &str) -> Init {
+  let stdlib = exec::Context::new_with_stdlib();
+  let mut cxt = exec::Context::new_with_stdlib();
+  let mut diagnostics = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    for e in rdl::eval(&mut cxt, line) {
+      if let Err(err) = &e.result {
+        diagnostics.push(Diagnostic{
+          line: i + 1,
+          message: format!("{}: {}", e.text(line).trim(), err),
+        });
+      }
+    }
+  }
+  let vars = cxt.vars().into_iter()
+    .filter(|(k, v)| stdlib.get(k) != Some(*v))
+    .map(|(k, v)| (k.to_string(), v))
+    .collect();
+  Init{
+    vars,
+    diagnostics,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn eval_init() {
+    let init = eval("# Shared constants\ntax = 0.0875\nrate = tax * 2\nbad = missing + 1\n");
+    assert_eq!(vec!["rate", "tax"], init.vars.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>());
+    assert_eq!(1, init.diagnostics.len());
+    assert_eq!(4, init.diagnostics[0].line);
+    assert!(init.diagnostics[0].to_string().starts_with("init.rsv:4: bad = missing + 1: "));
+  }
+  
+}
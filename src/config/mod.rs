@@ -0,0 +1,21 @@
+pub mod init;
+pub mod file;
+pub mod overrides;
+pub mod plugins;
+
+use std::env;
+use std::path;
+
+const APP: &str = "resolver";
+
+/// The directory in which user configuration is kept. This follows the XDG
+/// convention: `$XDG_CONFIG_HOME/resolver`, falling back to
+/// `~/.config/resolver` when the variable is not set.
+pub fn dir() -> Option<path::PathBuf> {
+  if let Some(base) = env::var_os("XDG_CONFIG_HOME") {
+    if !base.is_empty() {
+      return Some(path::PathBuf::from(base).join(APP));
+    }
+  }
+  env::var_os("HOME").map(|home| path::PathBuf::from(home).join(".config").join(APP))
+}
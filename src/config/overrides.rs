@@ -0,0 +1,215 @@
+use std::env;
+use std::fmt;
+use std::collections::BTreeMap;
+
+use crate::config::file::Config;
+use crate::document::settings::{self, Settings};
+use crate::rdl::unit;
+use crate::rdl::locale::{self, Locale};
+use crate::theme;
+use crate::util::suggest;
+
+/// The prefix of environment variables which override settings, as in
+/// `RESOLVER_PRECISION=2`.
+pub const ENV_PREFIX: &str = "RESOLVER_";
+
+pub const THEME: &str = "theme";
+
+/// The settings which can be overridden.
+pub const KEYS: [&str; 6] = [settings::PRECISION, settings::FIGURES, settings::ROUNDING, settings::LOCALE, settings::CURRENCY, THEME];
+
+/// Where the effective value of a setting comes from, from the least to the
+/// most specific.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Source {
+  Default,
+  File,
+  Document,
+  Environment,
+  Flag,
+}
+
+impl fmt::Display for Source {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Source::Default     => write!(f, "default"),
+      Source::File        => write!(f, "config.toml"),
+      Source::Document    => write!(f, "front matter"),
+      Source::Environment => write!(f, "environment"),
+      Source::Flag        => write!(f, "command line"),
+    }
+  }
+}
+
+/// Settings given in the environment or on the command line, which take
+/// precedence over those of documents and the configuration file. Those
+/// on the command line take precedence over those in the environment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+  pub settings: Settings,
+  pub theme: Option<String>,
+  sources: BTreeMap<&'static str, Source>, // of each setting overridden
+}
+
+impl Overrides {
+  /// Collect overrides from the environment and from `--set` flags, written
+  /// as `key=value`, and describe any problems with them.
+  pub fn load(flags: &[String]) -> (Overrides, Vec<String>) {
+    Overrides::new(env::vars(), flags)
+  }
+
+  fn new(vars: impl Iterator<Item=(String, String)>, flags: &[String]) -> (Overrides, Vec<String>) {
+    let mut over = Overrides::default();
+    let mut probs = Vec::new();
+    for (var, val) in vars {
+      if let Some(key) = var.strip_prefix(ENV_PREFIX) {
+        if let Err(err) = over.set(&key.to_lowercase(), &val, Source::Environment) {
+          probs.push(format!("{}: {}", var, err));
+        }
+      }
+    }
+    for flag in flags {
+      let res = match flag.split_once('=') {
+        Some((key, val)) => over.set(key.trim(), val.trim(), Source::Flag),
+        None => Err("expected key=value".to_string()),
+      };
+      if let Err(err) = res {
+        probs.push(format!("--set {}: {}", flag, err));
+      }
+    }
+    (over, probs)
+  }
+
+  fn set(&mut self, key: &str, val: &str, source: Source) -> Result<(), String> {
+    let key = match KEYS.iter().find(|k| **k == key) {
+      Some(key) => *key,
+      None => return Err(match suggest::closest(key, &KEYS) {
+        Some(near) => format!("no such setting; did you mean {}?", near),
+        None => format!("no such setting; expected one of {}", KEYS.join(", ")),
+      }),
+    };
+    if key == THEME {
+      let themes = theme::names();
+      if val != theme::AUTO && !themes.iter().any(|t| t == val) {
+        return Err(format!("expected {} or one of {}, got {}", theme::AUTO, themes.join(", "), val));
+      }
+      self.theme = Some(val.to_string());
+    }else{
+      let mut settings = self.settings.clone(); // unchanged if the value is invalid
+      if !settings.set(key, val) {
+        return Err(format!("invalid value {}", val));
+      }
+      self.settings = settings;
+    }
+    // places and figures go together, so one replaces the other from a less specific source
+    let replaced = |other| self.sources.get(other).is_some_and(|s| *s < source);
+    if key == settings::PRECISION && replaced(settings::FIGURES) {
+      self.settings.figures = None;
+      self.sources.remove(settings::FIGURES);
+    }else if key == settings::FIGURES && replaced(settings::PRECISION) {
+      self.settings.precision = None;
+      self.sources.remove(settings::PRECISION);
+    }
+    self.sources.insert(key, source);
+    Ok(())
+  }
+
+  /// Apply overrides to a document's settings, which have the
+  /// configuration's filled in already.
+  pub fn apply(&self, settings: Settings) -> Settings {
+    self.settings.clone().or(&settings)
+  }
+
+  /// Apply overrides to the configuration.
+  pub fn configure(&self, conf: &mut Config) {
+    match self.theme.as_deref() {
+      Some(theme::AUTO) => conf.theme = None,
+      Some(name) => conf.theme = Some(name.to_string()),
+      None => {},
+    };
+  }
+
+  /// Describe the effective value of every setting which can be
+  /// overridden, for a document with the provided settings of its own, and
+  /// where each value comes from.
+  pub fn resolve(&self, conf: &Config, doc: &Settings) -> Vec<(&'static str, String, Source)> {
+    let file = conf.defaults();
+    let over = |key| self.sources.get(key).copied();
+    // decimal places and significant figures are taken together from the most specific place either is set
+    let digits = match (over(settings::PRECISION).max(over(settings::FIGURES)), doc.precision.or(doc.figures), file.precision.or(file.figures)) {
+      (Some(source), _, _)  => (&self.settings, source),
+      (None, Some(_), _)    => (doc, Source::Document),
+      (None, None, Some(_)) => (&file, Source::File),
+      (None, None, None)    => (&file, Source::Default),
+    };
+    let pick = |key, over: Option<String>, doc: Option<String>, file: Option<String>, default: String| match (self.sources.get(key), over, doc, file) {
+      (Some(source), Some(v), _, _) => (v, *source),
+      (_, _, Some(v), _)            => (v, Source::Document),
+      (_, _, _, Some(v))            => (v, Source::File),
+      _                             => (default, Source::Default),
+    };
+    let file_locale = (conf.locale != Locale::default()).then(|| {
+      locale::NAMES.iter().find(|n| Locale::named(n).as_ref() == Some(&conf.locale)).map(|n| n.to_string()).unwrap_or_else(|| "custom".to_string())
+    });
+    let auto = || "automatic".to_string();
+    let rounding = pick(settings::ROUNDING, self.settings.rounding.map(|r| r.to_string()), doc.rounding.map(|r| r.to_string()), file.rounding.map(|r| r.to_string()), unit::Rounding::HalfUp.to_string());
+    let locale = pick(settings::LOCALE, self.settings.locale.clone(), doc.locale.clone(), file_locale, "none".to_string());
+    // without a currency of its own, what a bare `$` means follows the locale
+    let currency = match (self.settings.currency.clone(), doc.currency.clone()) {
+      (Some(code), _)    => (code, over(settings::CURRENCY).unwrap_or(Source::Flag)),
+      (None, Some(code)) => (code, Source::Document),
+      (None, None)       => (Locale::named(&locale.0).map_or(conf.locale.currency, |l| l.currency).code().to_string(), locale.1),
+    };
+    let theme = pick(THEME, self.theme.clone(), None, conf.theme.clone(), theme::AUTO.to_string());
+    vec![
+      (settings::PRECISION, digits.0.precision.map(|p| p.to_string()).unwrap_or_else(auto), digits.1),
+      (settings::FIGURES, digits.0.figures.map(|f| f.to_string()).unwrap_or_else(auto), digits.1),
+      (settings::ROUNDING, rounding.0, rounding.1),
+      (settings::LOCALE, locale.0, locale.1),
+      (settings::CURRENCY, currency.0, currency.1),
+      (THEME, theme.0, theme.1),
+    ]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn override_settings() {
+    let vars = vec![
+      ("RESOLVER_PRECISION".to_string(), "4".to_string()),
+      ("RESOLVER_ROUNDING".to_string(), "down".to_string()),
+      ("RESOLVER_COLOUR".to_string(), "red".to_string()),
+      ("HOME".to_string(), "/home/user".to_string()),
+    ];
+    let (over, _) = Overrides::new(vec![("RESOLVER_PRECISION".to_string(), "4".to_string())].into_iter(), &["figures=2".to_string()]);
+    assert_eq!((None, Some(2)), (over.settings.precision, over.settings.figures));
+
+    let flags = vec!["precision=1".to_string(), "locale = de".to_string(), "figures".to_string(), "currency=XYZ".to_string()];
+    let (over, probs) = Overrides::new(vars.into_iter(), &flags);
+    assert_eq!(vec![
+      "RESOLVER_COLOUR: no such setting; expected one of precision, figures, rounding, locale, currency, theme".to_string(),
+      "--set figures: expected key=value".to_string(),
+      "--set currency=XYZ: invalid value XYZ".to_string(),
+    ], probs);
+
+    let conf = Config{figures: Some(3), ..Config::default()};
+    let doc = Settings::parse("# rounding: up\n# currency: GBP\n# precision: 2\n");
+    let settings = over.apply(doc.clone().or(&conf.defaults()));
+    assert_eq!(Some(1), settings.precision);
+    assert_eq!(Some(unit::Rounding::Down), settings.rounding);
+    assert_eq!(Some("GBP".to_string()), settings.currency);
+    let resolved = over.resolve(&conf, &doc);
+    assert_eq!(vec![
+      (settings::PRECISION, "1".to_string(), Source::Flag),
+      (settings::FIGURES, "automatic".to_string(), Source::Flag),
+      (settings::ROUNDING, "down".to_string(), Source::Environment),
+      (settings::LOCALE, "de".to_string(), Source::Flag),
+      (settings::CURRENCY, "GBP".to_string(), Source::Document),
+      (THEME, "auto".to_string(), Source::Default),
+    ], resolved);
+  }
+
+}
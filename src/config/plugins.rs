@@ -0,0 +1,200 @@
+use std::fs;
+use std::io::{self, Read};
+use std::fmt;
+use std::path;
+use std::collections::BTreeMap;
+
+use crate::config;
+
+/// The directory in the configuration directory which is searched for
+/// plugins after any configured paths.
+const DIR: &str = "plugins";
+/// The manifest every plugin has in its directory.
+const MANIFEST: &str = "plugin.toml";
+/// The WebAssembly module of a plugin whose manifest doesn't name one.
+const MODULE: &str = "plugin.wasm";
+/// The magic number and version which begin a WebAssembly module.
+const WASM_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// What the module of a plugin extends the calculator with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Capability {
+  Functions,
+  Units,
+  Providers,
+}
+
+/// Every capability, in the order they are listed.
+pub const CAPABILITIES: [Capability; 3] = [Capability::Functions, Capability::Units, Capability::Providers];
+
+impl Capability {
+  pub fn from_name(name: &str) -> Option<Capability> {
+    CAPABILITIES.iter().find(|c| c.to_string() == name.trim()).copied()
+  }
+}
+
+impl fmt::Display for Capability {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Capability::Functions => write!(f, "functions"),
+      Capability::Units     => write!(f, "units"),
+      Capability::Providers => write!(f, "providers"),
+    }
+  }
+}
+
+/// The plugins to load, where to find them, and their settings, from the
+/// `[plugins]` section of the configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plugins {
+  pub enabled: Vec<String>,
+  pub paths: Vec<path::PathBuf>, // searched in order, before the plugins directory
+  pub settings: BTreeMap<String, toml::value::Table>, // by plugin name
+}
+
+/// A plugin which was found, and the settings configured for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plugin {
+  pub name: String,
+  pub path: path::PathBuf,
+  pub version: Option<String>,
+  pub description: Option<String>,
+  pub module: Option<path::PathBuf>, // WebAssembly, sandboxed
+  pub provides: Vec<Capability>,     // by the module
+  pub settings: toml::value::Table,
+}
+
+impl fmt::Display for Plugin {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name)?;
+    if let Some(version) = &self.version {
+      write!(f, " {}", version)?;
+    }
+    write!(f, " from {}", self.path.display())?;
+    if !self.provides.is_empty() {
+      write!(f, ", provides {}", self.provides.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+/// Determine whether text can name a plugin.
+pub fn is_name(text: &str) -> bool {
+  !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Expand a configured search path: `~` is the home directory, and relative
+/// paths are relative to the configuration directory.
+pub fn expand(path: &str) -> path::PathBuf {
+  let home = std::env::var_os("HOME").map(path::PathBuf::from);
+  match (path.strip_prefix("~/"), home) {
+    (Some(rest), Some(home)) => home.join(rest),
+    _ => match config::dir() {
+      Some(dir) => dir.join(path), // an absolute path replaces the directory
+      None => path::PathBuf::from(path),
+    },
+  }
+}
+
+impl Plugins {
+  /// Every directory searched for plugins, in order.
+  pub fn search_paths(&self) -> Vec<path::PathBuf> {
+    self.paths.iter().cloned().chain(config::dir().map(|d| d.join(DIR))).collect()
+  }
+
+  /// Find each enabled plugin, producing it or why it could not be loaded.
+  pub fn discover(&self) -> Vec<(String, Result<Plugin, String>)> {
+    let paths = self.search_paths();
+    self.enabled.iter().map(|name| (name.clone(), self.find(name, &paths))).collect()
+  }
+
+  fn find(&self, name: &str, paths: &[path::PathBuf]) -> Result<Plugin, String> {
+    let dir = match paths.iter().map(|p| p.join(name)).find(|d| d.is_dir()) {
+      Some(dir) => dir,
+      None => return Err(format!("not found in {}", paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "))),
+    };
+    let text = fs::read_to_string(dir.join(MANIFEST)).map_err(|err| format!("{}: {}", MANIFEST, err))?;
+    let manifest = match text.parse::<toml::Value>() {
+      Ok(toml::Value::Table(table)) => table,
+      Ok(_) => toml::value::Table::new(),
+      Err(err) => return Err(format!("{}: {}", MANIFEST, err)),
+    };
+    let field = |key: &str| manifest.get(key).and_then(|v| v.as_str()).map(|v| v.to_string());
+    let module = match field("module") {
+      Some(file) => Some(module(&dir, &file)?),
+      None if dir.join(MODULE).is_file() => Some(module(&dir, MODULE)?),
+      None => None,
+    };
+    let mut provides = Vec::new();
+    for val in manifest.get("provides").and_then(|v| v.as_array()).into_iter().flatten() {
+      match val.as_str().and_then(Capability::from_name) {
+        Some(cap) => provides.push(cap),
+        None => return Err(format!("{}: provides: expected {}, got {}", MANIFEST, CAPABILITIES.map(|c| c.to_string()).join(", "), val)),
+      };
+    }
+    if module.is_none() && !provides.is_empty() {
+      return Err(format!("{}: provides {} without a module", MANIFEST, provides[0]));
+    }
+    Ok(Plugin{
+      name: name.to_string(),
+      path: dir.clone(),
+      version: field("version"),
+      description: field("description"),
+      module,
+      provides,
+      settings: self.settings.get(name).cloned().unwrap_or_default(),
+    })
+  }
+}
+
+/// Find the module of a plugin in its directory, and check that it is a
+/// WebAssembly module.
+fn module(dir: &path::Path, file: &str) -> Result<path::PathBuf, String> {
+  let path = dir.join(file);
+  let mut header = [0; 8];
+  match fs::File::open(&path).and_then(|mut f| f.read_exact(&mut header)) {
+    Ok(()) if header == WASM_HEADER => Ok(path),
+    Ok(()) => Err(format!("{}: not a WebAssembly module", file)),
+    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(format!("{}: not a WebAssembly module", file)),
+    Err(err) => Err(format!("{}: {}", file, err)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn discover_plugins() {
+    let base = std::env::temp_dir().join(format!("resolver-test-plugins-{}", std::process::id()));
+    fs::create_dir_all(base.join("stocks")).unwrap();
+    fs::write(base.join("stocks").join(MANIFEST), "version = \"0.2\"\ndescription = \"Share prices\"\n").unwrap();
+    fs::write(base.join("stocks").join(MODULE), WASM_HEADER).unwrap();
+    fs::create_dir_all(base.join("fx")).unwrap();
+    fs::write(base.join("fx").join(MANIFEST), "module = \"fx.wasm\"\nprovides = [\"providers\"]\n").unwrap();
+    fs::write(base.join("fx").join("fx.wasm"), "(module)").unwrap();
+    fs::create_dir_all(base.join("broken")).unwrap();
+    fs::write(base.join("broken").join(MANIFEST), "version = ").unwrap();
+
+    let mut settings = BTreeMap::new();
+    settings.insert("stocks".to_string(), toml::value::Table::new());
+    let plugins = Plugins{
+      enabled: vec!["stocks".to_string(), "broken".to_string(), "missing".to_string(), "fx".to_string()],
+      paths: vec![base.clone()],
+      settings,
+    };
+    let found = plugins.discover();
+    fs::remove_dir_all(&base).unwrap();
+
+    let stocks = found[0].1.as_ref().unwrap();
+    assert_eq!(Some("0.2".to_string()), stocks.version);
+    assert_eq!(base.join("stocks"), stocks.path);
+    assert_eq!(Some(base.join("stocks").join(MODULE)), stocks.module);
+    assert_eq!(Err("fx.wasm: not a WebAssembly module".to_string()), found[3].1);
+    assert!(found[1].1.as_ref().is_err_and(|e| e.starts_with("plugin.toml: ")));
+    assert!(found[2].1.as_ref().is_err_and(|e| e.starts_with(&format!("not found in {}", base.display()))));
+    assert!(is_name("units-extra"));
+    assert!(!is_name("../etc"));
+  }
+
+}
@@ -0,0 +1,216 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path;
+use std::sync::mpsc;
+use std::thread;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::state;
+
+/// The socket a running editor listens on, in the runtime directory.
+const SOCKET: &str = "resolver.sock";
+
+/// Something a running editor is asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+  Open(path::PathBuf),
+  Eval(String),
+}
+
+impl Request {
+  /// Parse a request as it is written, such as `open budget.rdl` or
+  /// `eval 3 EUR in USD`.
+  pub fn parse(line: &str) -> Result<Request, String> {
+    let line = line.trim();
+    let (cmd, arg) = line.split_once(char::is_whitespace).map_or((line, ""), |(c, a)| (c, a.trim()));
+    match (cmd, arg) {
+      ("open" | "eval", "") => Err(format!("{} needs an argument, as in \"{}\"", cmd, if cmd == "open" { "open budget.rdl" }else{ "eval 3 EUR in USD" })),
+      ("open", path) => Ok(Request::Open(path::PathBuf::from(path))),
+      ("eval", expr) => Ok(Request::Eval(expr.to_string())),
+      _ => Err(format!("expected open or eval, got {}", cmd)),
+    }
+  }
+
+  /// Make the path of a document to open absolute, as it is relative to
+  /// where the request is made rather than to the editor.
+  pub fn resolve(self, dir: &path::Path) -> Request {
+    match self {
+      Request::Open(path) => Request::Open(dir.join(path)),
+      req => req,
+    }
+  }
+
+  fn line(&self) -> String {
+    match self {
+      Request::Open(path) => format!("open {}", path.display()),
+      Request::Eval(expr) => format!("eval {}", expr),
+    }
+  }
+}
+
+/// What a request produced, or why it failed, sent back to whoever made it.
+pub type Reply = Result<String, String>;
+
+/// A request received by the editor, with where to send its reply.
+pub struct Received {
+  pub request: Request,
+  reply: mpsc::Sender<Reply>,
+}
+
+impl Received {
+  pub fn reply(self, reply: Reply) {
+    let _ = self.reply.send(reply); // whoever asked may have gone
+  }
+}
+
+/// Listens for requests from other processes in the background, on a
+/// socket only the user can connect to. The socket is removed once the
+/// listener is dropped.
+pub struct Listener {
+  path: path::PathBuf,
+  requests: mpsc::Receiver<Received>,
+}
+
+impl Listener {
+  /// Take the next request received, if there is one.
+  pub fn receive(&self) -> Option<Received> {
+    self.requests.try_recv().ok()
+  }
+}
+
+impl Drop for Listener {
+  fn drop(&mut self) {
+    let _ = fs::remove_file(&self.path);
+  }
+}
+
+/// The path of the socket: in `$XDG_RUNTIME_DIR` where there is one, and
+/// the state directory otherwise.
+fn socket() -> io::Result<path::PathBuf> {
+  match env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+    Some(dir) => Ok(path::PathBuf::from(dir).join(SOCKET)),
+    None => state::file(SOCKET),
+  }
+}
+
+/// Listen for requests, unless another editor already is, in which case
+/// it goes on receiving them and this produces none.
+pub fn listen() -> io::Result<Option<Listener>> {
+  listen_at(&socket()?)
+}
+
+/// Make a request of a running editor, producing its reply.
+pub fn send(req: &Request) -> io::Result<Reply> {
+  send_to(&socket()?, req)
+}
+
+#[cfg(unix)]
+fn listen_at(path: &path::Path) -> io::Result<Option<Listener>> {
+  if path.exists() {
+    if UnixStream::connect(path).is_ok() {
+      return Ok(None);
+    }
+    fs::remove_file(path)?; // left by an editor which crashed
+  }
+  let listener = UnixListener::bind(path)?;
+  {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+  }
+  let (tx, rx) = mpsc::channel();
+  thread::spawn(move || for stream in listener.incoming().flatten() {
+    let tx = tx.clone();
+    thread::spawn(move || if let Err(err) = answer(stream, tx) {
+      log::warn!("could not answer a request: {}", err);
+    });
+  });
+  Ok(Some(Listener{
+    path: path.to_owned(),
+    requests: rx,
+  }))
+}
+
+/// Read a request from a connection, pass it to the editor, and write
+/// back its reply.
+#[cfg(unix)]
+fn answer(stream: UnixStream, requests: mpsc::Sender<Received>) -> io::Result<()> {
+  let mut line = String::new();
+  BufReader::new(&stream).read_line(&mut line)?;
+  log::info!("received {}", line.trim());
+  let reply = match Request::parse(&line) {
+    Ok(request) => {
+      let (tx, rx) = mpsc::channel();
+      requests.send(Received{request, reply: tx}).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+      rx.recv().unwrap_or_else(|_| Err("the editor exited".to_string()))
+    },
+    Err(err) => Err(err),
+  };
+  let mut stream = stream;
+  match reply {
+    Ok(text) => writeln!(stream, "ok {}", text),
+    Err(err) => writeln!(stream, "error {}", err),
+  }
+}
+
+#[cfg(unix)]
+fn send_to(path: &path::Path, req: &Request) -> io::Result<Reply> {
+  let mut stream = UnixStream::connect(path).map_err(|err| match err.kind() {
+    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => io::Error::new(err.kind(), "no editor is running"),
+    _ => err,
+  })?;
+  writeln!(stream, "{}", req.line())?;
+  let mut line = String::new();
+  BufReader::new(&stream).read_line(&mut line)?;
+  let line = line.trim_end();
+  match line.split_once(' ').unwrap_or((line, "")) {
+    ("ok", text) => Ok(Ok(text.to_string())),
+    ("error", err) => Ok(Err(err.to_string())),
+    _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected reply: {}", line))),
+  }
+}
+
+#[cfg(not(unix))]
+fn listen_at(_path: &path::Path) -> io::Result<Option<Listener>> {
+  Ok(None)
+}
+
+#[cfg(not(unix))]
+fn send_to(_path: &path::Path, _req: &Request) -> io::Result<Reply> {
+  Err(io::Error::new(io::ErrorKind::Unsupported, "requests can only be sent to an editor on Unix"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn send_requests() {
+    assert_eq!(Ok(Request::Open(path::PathBuf::from("budget.rdl"))), Request::parse("open budget.rdl\n"));
+    assert_eq!(Ok(Request::Eval("3 EUR in USD".to_string())), Request::parse("eval  3 EUR in USD"));
+    assert_eq!(Err("open needs an argument, as in \"open budget.rdl\"".to_string()), Request::parse("open"));
+    assert_eq!(Err("expected open or eval, got quit".to_string()), Request::parse("quit now"));
+    assert_eq!(Request::Open(path::PathBuf::from("/home/user/budget.rdl")), Request::Open(path::PathBuf::from("budget.rdl")).resolve(path::Path::new("/home/user")));
+
+    let path = env::temp_dir().join(format!("resolver-test-{}.sock", std::process::id()));
+    let listener = listen_at(&path).unwrap().unwrap();
+    assert!(listen_at(&path).unwrap().is_none()); // one editor listens at a time
+    let editor = thread::spawn(move || {
+      let received = loop {
+        if let Some(received) = listener.receive() {
+          break received;
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+      };
+      assert_eq!(Request::Eval("1 + 2".to_string()), received.request);
+      received.reply(Ok("3".to_string()));
+    });
+    assert_eq!(Ok("3".to_string()), send_to(&path, &Request::Eval("1 + 2".to_string())).unwrap());
+    editor.join().unwrap();
+    assert!(!path.exists());
+    assert_eq!(io::ErrorKind::NotFound, send_to(&path, &Request::Eval("1".to_string())).unwrap_err().kind());
+  }
+
+}
@@ -0,0 +1,149 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, stdout};
+use std::panic;
+use std::path;
+use std::sync::Mutex;
+use std::thread;
+use std::time;
+
+use crossterm::event;
+use crossterm::execute;
+use crossterm::terminal;
+
+use crate::document::{self, Protection};
+use crate::editor::writer::Writer;
+use crate::state;
+
+/// The directory crash reports and recovered documents are written to, in
+/// the state directory.
+const DIR: &str = "crashes";
+/// How many of the most recent commands a crash report lists.
+const COMMANDS: usize = 20;
+
+/// What the editor was doing, kept up to date so the document can be
+/// recovered and the crash described if it panics.
+struct Recovery {
+  doc: Option<path::PathBuf>,
+  text: Option<String>, // none until the first key is pressed
+  protection: Protection,
+  commands: VecDeque<&'static str>, // most recent last
+}
+
+static RECOVERY: Mutex<Recovery> = Mutex::new(Recovery{
+  doc: None,
+  text: None,
+  protection: Protection::None,
+  commands: VecDeque::new(),
+});
+
+fn recovery() -> std::sync::MutexGuard<'static, Recovery> {
+  RECOVERY.lock().unwrap_or_else(|err| err.into_inner()) // a panic elsewhere mustn't stop us recovering
+}
+
+/// Keep the document being edited, so it can be recovered after a crash.
+pub fn track(doc: Option<&path::Path>, text: &str, protection: &Protection) {
+  let mut rec = recovery();
+  rec.doc = doc.map(|d| d.to_owned());
+  rec.text = Some(text.to_string());
+  rec.protection = protection.clone();
+}
+
+/// Note a command which was run, to be listed in a crash report.
+pub fn record(cmd: &'static str) {
+  let mut rec = recovery();
+  if rec.commands.len() >= COMMANDS {
+    rec.commands.pop_front();
+  }
+  rec.commands.push_back(cmd);
+}
+
+/// Handle panics in the editor from now on: the terminal is restored, the
+/// document is written to a recovery file, and a report of the crash is
+/// written which can be attached to a bug report. Panics in background
+/// threads, which the editor outlives, are reported as they were before.
+pub fn install() {
+  let previous = panic::take_hook();
+  panic::set_hook(Box::new(move |info| {
+    if thread::current().name() != Some("main") {
+      return previous(info);
+    }
+    let _ = restore_terminal();
+    log::error!("{}", info);
+    let rec = recovery();
+    let stamp = time::SystemTime::now().duration_since(time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let recovered = rec.text.as_ref().and_then(|text| recover(&rec, text, stamp).ok());
+    let report = report(&info.to_string(), &rec, recovered.as_deref(), &Backtrace::force_capture().to_string());
+    eprintln!("resolver crashed: {}", info);
+    if let Some(path) = &recovered {
+      eprintln!("Your document was recovered to {}", path.display());
+    }
+    match write_report(&report, stamp) {
+      Ok(path) => eprintln!("A crash report was written to {}; please attach it to a bug report.", path.display()),
+      Err(_) => eprintln!("\n{}", report),
+    }
+  }));
+}
+
+fn restore_terminal() -> crossterm::Result<()> {
+  let _ = execute!(stdout(), event::DisableBracketedPaste);
+  terminal::disable_raw_mode()?;
+  execute!(stdout(), terminal::LeaveAlternateScreen)?;
+  Writer::restore_cursor()
+}
+
+/// Write a document to a recovery file, protected as it was when stored.
+fn recover(rec: &Recovery, text: &str, stamp: u64) -> io::Result<path::PathBuf> {
+  let name = rec.doc.as_ref().and_then(|d| d.file_name()).map_or("untitled.rdl".to_string(), |n| n.to_string_lossy().to_string());
+  let dir = state::file(DIR)?;
+  fs::create_dir_all(&dir)?;
+  let path = dir.join(format!("{}-{}", stamp, name));
+  document::save(&path, text, &rec.protection, None)?;
+  Ok(path)
+}
+
+fn write_report(report: &str, stamp: u64) -> io::Result<path::PathBuf> {
+  let dir = state::file(DIR)?;
+  fs::create_dir_all(&dir)?;
+  let path = dir.join(format!("{}-report.txt", stamp));
+  fs::write(&path, report)?;
+  Ok(path)
+}
+
+/// Describe a crash, for a bug report. The document itself is left out.
+fn report(message: &str, rec: &Recovery, recovered: Option<&path::Path>, backtrace: &str) -> String {
+  let mut out = format!("resolver {} crashed\n{}\n\n", env!("CARGO_PKG_VERSION"), message);
+  out.push_str(&format!("Document: {}\n", rec.doc.as_ref().map_or("untitled".to_string(), |d| d.display().to_string())));
+  if let Some(path) = recovered {
+    out.push_str(&format!("Recovered to: {}\n", path.display()));
+  }
+  out.push_str("\nLast commands:\n");
+  if rec.commands.is_empty() {
+    out.push_str("  none\n");
+  }
+  for cmd in &rec.commands {
+    out.push_str(&format!("  {}\n", cmd));
+  }
+  out.push_str(&format!("\nBacktrace:\n{}\n", backtrace));
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn describe_crash() {
+    let rec = Recovery{
+      doc: Some(path::PathBuf::from("/home/user/budget.rdl")),
+      text: Some("rent = 1200".to_string()),
+      protection: Protection::None,
+      commands: VecDeque::from(vec!["save", "copy-result"]),
+    };
+    let report = report("panicked at src/main.rs:1:1:\noops", &rec, Some(path::Path::new("/tmp/1-budget.rdl")), "0: main");
+    assert_eq!(format!("resolver {} crashed\npanicked at src/main.rs:1:1:\noops\n\nDocument: /home/user/budget.rdl\nRecovered to: /tmp/1-budget.rdl\n\nLast commands:\n  save\n  copy-result\n\nBacktrace:\n0: main\n", env!("CARGO_PKG_VERSION")), report);
+    assert!(!report.contains("rent"));
+  }
+
+}
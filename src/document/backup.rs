@@ -0,0 +1,103 @@
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::state;
+
+const DIR: &str = "backups";
+
+/// Rotated backups of documents, kept in a directory of their own. Backup 1
+/// is the most recent. Each document's backups are named for its absolute
+/// path, so documents with the same name in different directories don't
+/// collide.
+pub struct Backups {
+  dir: path::PathBuf,
+  keep: usize,
+}
+
+impl Backups {
+  pub fn new(dir: &path::Path, keep: usize) -> Backups {
+    Backups{
+      dir: dir.to_owned(),
+      keep,
+    }
+  }
+  
+  /// Keep backups in the state directory.
+  pub fn in_state_dir(keep: usize) -> io::Result<Backups> {
+    let dir = state::file(DIR)?;
+    fs::create_dir_all(&dir)?;
+    Ok(Self::new(&dir, keep))
+  }
+  
+  /// The path of the Nth most recent backup of a document.
+  pub fn path(&self, doc: &path::Path, n: usize) -> path::PathBuf {
+    let doc = fs::canonicalize(doc).unwrap_or_else(|_| doc.to_owned());
+    let name = doc.to_string_lossy().replace(path::MAIN_SEPARATOR, "%");
+    self.dir.join(format!("{}.{}", name, n))
+  }
+  
+  /// Produce the paths of every backup of a document, most recent first.
+  pub fn list(&self, doc: &path::Path) -> Vec<path::PathBuf> {
+    (1..=self.keep).map(|n| self.path(doc, n)).take_while(|p| p.exists()).collect()
+  }
+  
+  /// Back up the document as it currently exists, shifting older backups
+  /// down and discarding the oldest once there are more than we keep. A
+  /// document that doesn't exist yet has nothing to back up.
+  pub fn rotate(&self, doc: &path::Path) -> io::Result<()> {
+    if self.keep == 0 || !doc.exists() {
+      return Ok(());
+    }
+    for n in (1..self.keep).rev() {
+      let src = self.path(doc, n);
+      if src.exists() {
+        fs::rename(&src, self.path(doc, n + 1))?;
+      }
+    }
+    fs::copy(doc, self.path(doc, 1))?;
+    Ok(())
+  }
+  
+  /// Restore the Nth most recent backup of a document. The document as it
+  /// stands is backed up first, so a restore can itself be undone.
+  pub fn restore(&self, doc: &path::Path, n: usize) -> io::Result<()> {
+    let data = fs::read(self.path(doc, n))?;
+    self.rotate(doc)?;
+    fs::write(doc, data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn rotate_backups() {
+    let dir = std::env::temp_dir().join(format!("resolver-test-backup-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("Could not create temp dir");
+    let doc = dir.join("budget.res");
+    let backups = Backups::new(&dir.join("backups"), 2);
+    fs::create_dir_all(dir.join("backups")).unwrap();
+    
+    backups.rotate(&doc).expect("Could not rotate");
+    assert_eq!(0, backups.list(&doc).len());
+    
+    for text in ["one", "two", "three"] {
+      fs::write(&doc, text).unwrap();
+      backups.rotate(&doc).expect("Could not rotate");
+    }
+    fs::write(&doc, "four").unwrap();
+    let list = backups.list(&doc);
+    assert_eq!(2, list.len());
+    assert_eq!("three", &fs::read_to_string(&list[0]).unwrap());
+    assert_eq!("two", &fs::read_to_string(&list[1]).unwrap());
+    
+    backups.restore(&doc, 2).expect("Could not restore");
+    assert_eq!("two", &fs::read_to_string(&doc).unwrap());
+    assert_eq!("four", &fs::read_to_string(backups.path(&doc, 1)).unwrap());
+    
+    fs::remove_dir_all(&dir).expect("Could not clean up");
+  }
+  
+}
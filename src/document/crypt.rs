@@ -0,0 +1,57 @@
+use std::io::{self, Read, Write};
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::Secret;
+
+const ARMOR_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+const BINARY_HEADER: &[u8] = b"age-encryption.org/";
+
+/// Determine whether document content is age-encrypted, in either the
+/// armored or binary format.
+pub fn is_encrypted(data: &[u8]) -> bool {
+  data.starts_with(ARMOR_HEADER) || data.starts_with(BINARY_HEADER)
+}
+
+/// Encrypt text with a passphrase. Output is ASCII-armored so encrypted
+/// documents remain plain text files.
+pub fn encrypt(text: &str, passphrase: &str) -> io::Result<Vec<u8>> {
+  let enc = age::Encryptor::with_user_passphrase(Secret::new(passphrase.to_owned()));
+  let mut data: Vec<u8> = Vec::new();
+  let armor = ArmoredWriter::wrap_output(&mut data, Format::AsciiArmor)?;
+  let mut w = enc.wrap_output(armor).map_err(invalid)?;
+  w.write_all(text.as_bytes())?;
+  w.finish()?.finish()?;
+  Ok(data)
+}
+
+/// Decrypt content that was encrypted with a passphrase.
+pub fn decrypt(data: &[u8], passphrase: &str) -> io::Result<String> {
+  let dec = match age::Decryptor::new(ArmoredReader::new(data)).map_err(invalid)? {
+    age::Decryptor::Passphrase(dec) => dec,
+    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Document is not encrypted with a passphrase")),
+  };
+  let mut r = dec.decrypt(&Secret::new(passphrase.to_owned()), None).map_err(invalid)?;
+  let mut text = String::new();
+  r.read_to_string(&mut text)?;
+  Ok(text)
+}
+
+fn invalid<E: ToString>(err: E) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn encrypt_decrypt() {
+    let text = "salary = 100000\n  bonus = salary * 0.1  \n";
+    let data = encrypt(text, "hunter2").expect("Could not encrypt");
+    assert!(is_encrypted(&data));
+    assert!(!is_encrypted(text.as_bytes()));
+    assert_eq!(text, &decrypt(&data, "hunter2").expect("Could not decrypt"));
+    assert!(decrypt(&data, "hunter3").is_err());
+  }
+  
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path;
+use std::process;
+
+/// The instance of the editor which holds the lock on a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holder {
+  pub pid: u32,
+  pub host: String,
+}
+
+impl Holder {
+  /// This instance.
+  fn us() -> Holder {
+    Holder{
+      pid: process::id(),
+      host: hostname(),
+    }
+  }
+
+  fn parse(text: &str) -> Option<Holder> {
+    let (pid, host) = text.trim().split_once(' ')?;
+    Some(Holder{
+      pid: pid.parse().ok()?,
+      host: host.to_string(),
+    })
+  }
+
+  fn line(&self) -> String {
+    format!("{} {}\n", self.pid, self.host)
+  }
+
+  /// Whether the holder has exited without releasing the lock, as it does
+  /// when it crashes. Only holders on this host can be known to have.
+  fn is_gone(&self) -> bool {
+    self.host == hostname() && !is_running(self.pid)
+  }
+}
+
+/// An advisory lock on a document, which tells other instances of the
+/// editor which open it that it is being edited. It is a file beside the
+/// document naming the instance which holds it, and is released when
+/// dropped.
+#[derive(Debug)]
+pub struct Lock {
+  path: path::PathBuf,
+}
+
+impl Lock {
+  /// Whether we still hold the lock, which another instance may have
+  /// taken over since.
+  pub fn is_held(&self) -> bool {
+    holder(&self.path).is_none_or(|h| h == Holder::us()) // a lock which is gone can't be anyone else's
+  }
+}
+
+impl Drop for Lock {
+  fn drop(&mut self) {
+    if self.is_held() {
+      let _ = fs::remove_file(&self.path);
+    }
+  }
+}
+
+/// The path of the lock on a document, a hidden file beside it.
+fn lock_path(doc: &path::Path) -> path::PathBuf {
+  let name = doc.file_name().map_or("document".to_string(), |n| n.to_string_lossy().to_string());
+  doc.with_file_name(format!(".{}.lock", name))
+}
+
+fn holder(path: &path::Path) -> Option<Holder> {
+  fs::read_to_string(path).ok().and_then(|text| Holder::parse(&text))
+}
+
+/// Lock a document, unless another instance which is still running holds
+/// the lock, in which case that instance is produced. A lock left by an
+/// instance which crashed is taken.
+pub fn acquire(doc: &path::Path) -> io::Result<Result<Lock, Holder>> {
+  let path = lock_path(doc);
+  loop {
+    match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+      Ok(mut file) => {
+        file.write_all(Holder::us().line().as_bytes())?;
+        return Ok(Ok(Lock{path}));
+      },
+      Err(err) if err.kind() == io::ErrorKind::AlreadyExists => match holder(&path) {
+        Some(h) if h == Holder::us() => return Ok(Ok(Lock{path})), // we opened it before
+        Some(h) if !h.is_gone() => return Ok(Err(h)),
+        _ => match fs::remove_file(&path) { // stale or unreadable; take it
+          Ok(()) => {},
+          Err(err) if err.kind() == io::ErrorKind::NotFound => {}, // someone else took it first
+          Err(err) => return Err(err),
+        },
+      },
+      Err(err) => return Err(err),
+    }
+  }
+}
+
+/// Lock a document whether or not another instance holds the lock. That
+/// instance sees it has lost the lock when it next saves.
+pub fn take_over(doc: &path::Path) -> io::Result<Lock> {
+  let path = lock_path(doc);
+  fs::write(&path, Holder::us().line())?;
+  Ok(Lock{path})
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+  let mut buf = [0u8; 256];
+  if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+    return "localhost".to_string();
+  }
+  let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+  String::from_utf8_lossy(&buf[..end]).to_string()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+  std::env::var("COMPUTERNAME").unwrap_or_else(|_| "localhost".to_string())
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+  // signal 0 checks the process exists without signalling it; one we may not signal exists too
+  let sent = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+  sent || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+  true // we can't tell, so never take a lock from anyone
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lock_documents() {
+    let dir = std::env::temp_dir().join(format!("resolver-lock-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let doc = dir.join("budget.rdl");
+    let path = dir.join(".budget.rdl.lock");
+
+    let lock = acquire(&doc).unwrap().unwrap();
+    assert_eq!(Some(Holder::us()), holder(&path));
+    assert!(acquire(&doc).unwrap().is_ok()); // ours already
+    drop(lock);
+    assert!(!path.exists());
+
+    let other = Holder{pid: 1, host: hostname()}; // running, always
+    fs::write(&path, other.line()).unwrap();
+    assert_eq!(other, acquire(&doc).unwrap().unwrap_err());
+    let remote = Holder{pid: process::id(), host: "elsewhere".to_string()};
+    fs::write(&path, remote.line()).unwrap();
+    assert_eq!(remote, acquire(&doc).unwrap().unwrap_err());
+
+    let mut child = process::Command::new("true").spawn().unwrap();
+    let gone = Holder{pid: child.id(), host: hostname()};
+    child.wait().unwrap();
+    fs::write(&path, gone.line()).unwrap();
+    let lock = acquire(&doc).unwrap().unwrap(); // stale, so taken
+    assert!(lock.is_held());
+
+    fs::write(&path, other.line()).unwrap(); // taken over by another instance
+    assert!(!lock.is_held());
+    drop(lock);
+    assert_eq!(Some(other), holder(&path)); // which keeps it
+    let lock = take_over(&doc).unwrap();
+    assert!(lock.is_held());
+    drop(lock);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+}
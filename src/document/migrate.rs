@@ -0,0 +1,245 @@
+use std::path;
+
+use clap::ArgEnum;
+
+use crate::rdl;
+use crate::rdl::exec;
+
+/// Calculator apps whose documents can be converted to ours.
+#[derive(ArgEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Dialect {
+  Soulver,
+  Numi,
+}
+
+impl Dialect {
+  /// Infer the dialect of a document from its file extension.
+  pub fn from_path(path: &path::Path) -> Option<Dialect> {
+    match path.extension().and_then(|e| e.to_str()) {
+      Some("soulver") => Some(Dialect::Soulver),
+      Some("numi") => Some(Dialect::Numi),
+      _ => None,
+    }
+  }
+}
+
+const LINE_COMMENT: &str = "//";
+const CURRENCY: [char; 4] = ['$', '€', '£', '¥'];
+
+/// Convert the text of a Soulver or Numi document to our syntax.
+///
+/// Headings and comments are kept as comments. Assignments and arithmetic
+/// carry over once currency symbols and thousands separators are removed.
+/// Totals (`sum`, `total`) and averages (`average`, `avg`) of the lines in
+/// a block become explicit expressions, as do references to the previous
+/// line (`prev`) and, in Soulver, to numbered lines (`line1`). Percentages
+/// are rewritten as arithmetic.
+///
+/// A line is only converted if the result evaluates cleanly; anything else,
+/// including prose, is preserved verbatim as a comment.
+pub fn convert(text: &str, dialect: Dialect) -> String {
+  let mut cxt = exec::Context::new_with_stdlib();
+  let mut out: Vec<String> = Vec::new();
+  let mut lines: Vec<Option<String>> = Vec::new(); // the expression for each source line, if any
+  let mut block: Vec<String> = Vec::new();         // expressions since the last blank line or heading
+
+  for line in text.lines() {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with(rdl::COMMENT) {
+      out.push(line.to_string());
+      lines.push(None);
+      block.clear();
+      continue;
+    }
+    if let Some(comment) = trimmed.strip_prefix(LINE_COMMENT) {
+      out.push(format!("{} {}", rdl::COMMENT, comment.trim()));
+      lines.push(None);
+      continue;
+    }
+
+    let (body, comment) = split_comment(line, dialect);
+    if let Some(comment) = comment {
+      out.push(format!("{} {}", rdl::COMMENT, comment));
+    }
+    let (label, body) = split_label(&body);
+    let prev = lines.last().cloned().flatten();
+
+    let conv = match convert_expr(&body, dialect, &block, prev.as_deref(), &lines) {
+      Some(conv) if evaluates(&mut cxt, &conv) => Some(conv),
+      _ => None,
+    };
+    match conv {
+      Some(conv) => {
+        let value = match conv.split_once('=') {
+          Some((name, _)) => name.trim().to_string(),
+          None => conv.clone(),
+        };
+        match label {
+          Some(label) => out.push(format!("{}: {}", label, conv)),
+          None => out.push(conv),
+        };
+        lines.push(Some(value.clone()));
+        block.push(value);
+      },
+      None => {
+        out.push(format!("{} {}", rdl::COMMENT, line.trim()));
+        lines.push(None);
+      },
+    };
+  }
+
+  let mut text = out.join("\n");
+  text.push('\n');
+  text
+}
+
+/// Separate a trailing `// comment` and, in Soulver, `"quoted"` comments
+/// from a line.
+fn split_comment(line: &str, dialect: Dialect) -> (String, Option<String>) {
+  let (mut body, mut comment) = match line.split_once(LINE_COMMENT) {
+    Some((body, comment)) => (body.to_string(), Some(comment.trim().to_string())),
+    None => (line.to_string(), None),
+  };
+  if dialect == Dialect::Soulver {
+    while let Some(start) = body.find('"') {
+      let end = match body[start+1..].find('"') {
+        Some(end) => start + 1 + end,
+        None => break,
+      };
+      let quoted = body[start+1..end].trim().to_string();
+      comment = match comment {
+        Some(comment) => Some(format!("{} {}", quoted, comment)),
+        None => Some(quoted),
+      };
+      body.replace_range(start..=end, "");
+    }
+  }
+  (body.trim().to_string(), comment)
+}
+
+/// Separate a leading `Label:` from a line.
+fn split_label(line: &str) -> (Option<String>, String) {
+  if let Some((label, rest)) = line.split_once(':') {
+    if !label.is_empty() && label.chars().all(|c| c.is_alphabetic() || c == ' ') {
+      return (Some(label.trim().to_string()), rest.trim().to_string());
+    }
+  }
+  (None, line.to_string())
+}
+
+fn convert_expr(text: &str, dialect: Dialect, block: &[String], prev: Option<&str>, lines: &[Option<String>]) -> Option<String> {
+  let text = strip_numbers(text);
+  match text.to_lowercase().as_str() {
+    "sum" | "total" => return total(block).map(|t| format!("total = {}", t)),
+    "average" | "avg" => return total(block).map(|t| format!("average = ({}) / {}", t, block.len())),
+    _ => {},
+  };
+  let text = replace_words(&text, |word| {
+    let lower = word.to_lowercase();
+    if lower == "prev" {
+      return Some(prev.map(|p| format!("({})", p)));
+    }
+    if dialect == Dialect::Soulver {
+      if let Some(n) = lower.strip_prefix("line").and_then(|n| n.parse::<usize>().ok()) {
+        let expr = n.checked_sub(1).and_then(|n| lines.get(n)).cloned().flatten();
+        return Some(expr.map(|e| format!("({})", e)));
+      }
+    }
+    None
+  })?;
+  Some(percent(&text))
+}
+
+fn total(block: &[String]) -> Option<String> {
+  if block.is_empty() {
+    None
+  }else{
+    Some(block.iter().map(|e| format!("({})", e)).collect::<Vec<_>>().join(" + "))
+  }
+}
+
+/// Remove currency symbols and thousands separators.
+fn strip_numbers(text: &str) -> String {
+  let chars: Vec<char> = text.chars().collect();
+  let mut out = String::new();
+  for (i, c) in chars.iter().enumerate() {
+    if CURRENCY.contains(c) {
+      continue;
+    }
+    if *c == ',' && i > 0 && chars[i-1].is_ascii_digit() && chars.len() > i + 3 && chars[i+1..i+4].iter().all(|c| c.is_ascii_digit()) && !chars.get(i+4).is_some_and(|c| c.is_ascii_digit()) {
+      continue;
+    }
+    out.push(*c);
+  }
+  out.trim().to_string()
+}
+
+/// Replace words in the text. The replacement function produces nothing
+/// to leave a word alone, or an optional replacement, where none means the
+/// word cannot be replaced and the text cannot be converted.
+fn replace_words(text: &str, f: impl Fn(&str) -> Option<Option<String>>) -> Option<String> {
+  let mut out = String::new();
+  let mut word = String::new();
+  for c in text.chars().chain(std::iter::once(' ')) {
+    if c.is_alphanumeric() || c == '_' {
+      word.push(c);
+      continue;
+    }
+    if !word.is_empty() {
+      match f(&word) {
+        Some(Some(repl)) => out.push_str(&repl),
+        Some(None) => return None,
+        None => out.push_str(&word),
+      }
+      word.clear();
+    }
+    out.push(c);
+  }
+  out.pop();
+  Some(out)
+}
+
+/// Rewrite percentages: `X% of Y`, `Y + X%`, and `Y - X%`.
+fn percent(text: &str) -> String {
+  if let Some((pct, rest)) = text.split_once("% of ") {
+    if pct.trim().parse::<f64>().is_ok() {
+      return format!("({}) * {} / 100", rest.trim(), pct.trim());
+    }
+  }
+  if let Some(text) = text.strip_suffix('%') {
+    for op in ['+', '-'] {
+      if let Some((base, pct)) = text.rsplit_once(op) {
+        if pct.trim().parse::<f64>().is_ok() {
+          return format!("({}) * (1 {} {} / 100)", base.trim(), op, pct.trim());
+        }
+      }
+    }
+  }
+  text.to_string()
+}
+
+/// Determine whether text evaluates to at least one result without errors.
+fn evaluates(cxt: &mut exec::Context, text: &str) -> bool {
+  let res = rdl::eval(cxt, text);
+  !res.is_empty() && res.iter().all(|e| e.result.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn convert_soulver() {
+    let text = "# Budget\nRent: $1,200\nfood = $400 // groceries\nsum\n\n20% of 50\n100 + 10%\nline7 * 2\nprev \"doubled\"\nToday + 3 weeks\n";
+    let expect = "# Budget\nRent: 1200\n# groceries\nfood = 400\ntotal = (1200) + (food)\n\n(50) * 20 / 100\n(100) * (1 + 10 / 100)\n((100) * (1 + 10 / 100)) * 2\n# doubled\n(((100) * (1 + 10 / 100)) * 2)\n# Today + 3 weeks\n";
+    assert_eq!(expect, &convert(text, Dialect::Soulver));
+  }
+
+  #[test]
+  fn convert_numi() {
+    let text = "// Costs\na = 2\nb = 4\navg\nline1\n";
+    let expect = "# Costs\na = 2\nb = 4\naverage = ((a) + (b)) / 2\n# line1\n";
+    assert_eq!(expect, &convert(text, Dialect::Numi));
+  }
+
+}
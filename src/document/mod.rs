@@ -0,0 +1,262 @@
+pub mod crypt;
+pub mod lock;
+pub mod backup;
+pub mod migrate;
+pub mod results;
+pub mod settings;
+pub mod sections;
+
+use std::fs;
+use std::io;
+use std::path;
+use std::time;
+
+/// How a document is protected when it is stored.
+#[derive(Clone)]
+pub enum Protection {
+  None,
+  Passphrase(String), // held in memory only; never written anywhere
+}
+
+/// What a document contained when it was last loaded or saved, which is
+/// used to detect changes made to it by other programs.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+  pub text: String,
+  mtime: Option<time::SystemTime>,
+}
+
+impl Snapshot {
+  pub fn new(path: &path::Path, text: &str) -> Snapshot {
+    Snapshot{
+      text: text.to_string(),
+      mtime: modified(path),
+    }
+  }
+  
+  /// Determine whether the document has been touched since the snapshot was
+  /// taken. This is cheap, but does not mean its content has changed.
+  pub fn touched(&self, path: &path::Path) -> bool {
+    modified(path) != self.mtime
+  }
+  
+  /// Acknowledge that the document has been touched, so it is not reported
+  /// as touched again until it next changes.
+  pub fn touch(&mut self, path: &path::Path) {
+    self.mtime = modified(path);
+  }
+  
+  /// Determine whether the content of the document differs from the
+  /// snapshot and, if so, produce its current content. A document which
+  /// has been deleted does not differ, since saving it loses nothing.
+  ///
+  /// The document is read as it is stored, so one that is still plain text
+  /// is compared as such even once it is to be saved with a passphrase.
+  pub fn differs(&self, path: &path::Path, prot: &Protection) -> io::Result<Option<String>> {
+    let stored = fs::read(path).and_then(|data| if crypt::is_encrypted(&data) {
+      decode(data, prot)
+    }else{
+      decode(data, &Protection::None)
+    });
+    match stored {
+      Ok(text) if text == self.text => Ok(None),
+      Ok(text) => Ok(Some(text)),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(err) => Err(err),
+    }
+  }
+}
+
+fn modified(path: &path::Path) -> Option<time::SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Determine whether the document at the provided path is encrypted.
+pub fn is_encrypted(path: &path::Path) -> io::Result<bool> {
+  Ok(crypt::is_encrypted(&fs::read(path)?))
+}
+
+/// Load a document. Documents are plain text and the content is used exactly
+/// as stored, including line endings and trailing whitespace. Loading an
+/// encrypted document this way is an error.
+pub fn load(path: &path::Path) -> io::Result<String> {
+  load_with_protection(path, &Protection::None)
+}
+
+/// Load a document that may be encrypted.
+pub fn load_with_protection(path: &path::Path, prot: &Protection) -> io::Result<String> {
+  decode(fs::read(path)?, prot)
+}
+
+fn decode(data: Vec<u8>, prot: &Protection) -> io::Result<String> {
+  match prot {
+    Protection::Passphrase(pass) => crypt::decrypt(&data, pass),
+    Protection::None => if crypt::is_encrypted(&data) {
+      Err(io::Error::new(io::ErrorKind::InvalidData, "Document is encrypted"))
+    }else{
+      String::from_utf8(data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    },
+  }
+}
+
+/// Save a document. Saving is byte-stable: the text is written verbatim, with
+/// no metadata, reordering, or whitespace normalization, so a document that
+/// is saved without changes is identical to the one that was loaded.
+///
+/// If the file already contains exactly this text it is not written at all,
+/// which keeps its modification time from churning. Otherwise the text is
+/// written to a temporary file which then replaces the original, so a failed
+/// save never leaves a partially-written document behind.
+///
+/// Encryption is not deterministic, so an encrypted document is instead
+/// compared to the existing file by decrypting it.
+///
+/// If backups are provided, the existing file is backed up before it is
+/// replaced.
+///
+/// Returns true if the file was written, false if it was already current.
+pub fn save(path: &path::Path, text: &str, prot: &Protection, backups: Option<&backup::Backups>) -> io::Result<bool> {
+  let curr = match fs::read(path) {
+    Ok(curr) => Some(curr),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+    Err(err) => return Err(err),
+  };
+  match prot {
+    Protection::None => {
+      if let Some(curr) = curr {
+        if curr == text.as_bytes() {
+          return Ok(false);
+        }
+      }
+      if let Some(backups) = backups {
+        backups.rotate(path)?;
+      }
+      write(path, text.as_bytes())?;
+    },
+    Protection::Passphrase(pass) => {
+      if let Some(curr) = curr {
+        if crypt::is_encrypted(&curr) && crypt::decrypt(&curr, pass).ok().as_deref() == Some(text) {
+          return Ok(false);
+        }
+      }
+      let data = crypt::encrypt(text, pass)?;
+      if let Some(backups) = backups {
+        backups.rotate(path)?;
+      }
+      write(path, &data)?;
+    },
+  };
+  Ok(true)
+}
+
+fn write(path: &path::Path, data: &[u8]) -> io::Result<()> {
+  let tmp = temp_path(path);
+  if let Err(err) = fs::write(&tmp, data) {
+    let _ = fs::remove_file(&tmp);
+    return Err(err);
+  }
+  if let Ok(meta) = fs::metadata(path) {
+    let _ = fs::set_permissions(&tmp, meta.permissions());
+  }
+  fs::rename(&tmp, path)
+}
+
+fn temp_path(path: &path::Path) -> path::PathBuf {
+  let name = match path.file_name() {
+    Some(name) => name.to_string_lossy().to_string(),
+    None => "document".to_string(),
+  };
+  path.with_file_name(format!(".{}.resolver~", name))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn temp_dir(name: &str) -> path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("resolver-test-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).expect("Could not create temp dir");
+    dir
+  }
+  
+  #[test]
+  fn save_byte_stable() {
+    let dir = temp_dir("document");
+    let path = dir.join("budget.res");
+    let text = "Budget  \r\n\n\trent = 1200\n  total = rent * 12   \n\n\n";
+    
+    assert!(save(&path, text, &Protection::None, None).expect("Could not save"));
+    assert_eq!(text.as_bytes(), &fs::read(&path).expect("Could not read")[..]);
+    
+    let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+    let loaded = load(&path).expect("Could not load");
+    assert_eq!(text, &loaded);
+    assert!(!save(&path, &loaded, &Protection::None, None).expect("Could not save"));
+    assert_eq!(mtime, fs::metadata(&path).unwrap().modified().unwrap());
+    
+    assert!(save(&path, "Changed", &Protection::None, None).expect("Could not save"));
+    assert_eq!("Changed", &load(&path).expect("Could not load"));
+    assert!(!temp_path(&path).exists());
+    
+    fs::remove_dir_all(&dir).expect("Could not clean up");
+  }
+  
+  #[test]
+  fn detect_changes() {
+    let dir = temp_dir("snapshot");
+    let path = dir.join("budget.res");
+    fs::write(&path, "rent = 1200\n").unwrap();
+    
+    let mut snap = Snapshot::new(&path, "rent = 1200\n");
+    assert!(!snap.touched(&path));
+    assert_eq!(None, snap.differs(&path, &Protection::None).unwrap());
+    
+    std::thread::sleep(time::Duration::from_millis(10));
+    fs::write(&path, "rent = 1300\n").unwrap();
+    assert!(snap.touched(&path));
+    assert_eq!(Some("rent = 1300\n".to_string()), snap.differs(&path, &Protection::None).unwrap());
+    snap.touch(&path);
+    assert!(!snap.touched(&path));
+    
+    fs::remove_file(&path).unwrap();
+    assert_eq!(None, snap.differs(&path, &Protection::None).unwrap());
+    
+    fs::remove_dir_all(&dir).expect("Could not clean up");
+  }
+  
+  #[test]
+  fn save_encrypted() {
+    let dir = temp_dir("encrypted");
+    let path = dir.join("salaries.res");
+    let text = "salary = 100000\n";
+    let prot = Protection::Passphrase("hunter2".to_string());
+    
+    assert!(save(&path, text, &prot, None).expect("Could not save"));
+    assert!(is_encrypted(&path).expect("Could not read"));
+    assert!(load(&path).is_err());
+    assert!(load_with_protection(&path, &Protection::Passphrase("nope".to_string())).is_err());
+    assert_eq!(text, &load_with_protection(&path, &prot).expect("Could not load"));
+    
+    let data = fs::read(&path).unwrap();
+    assert!(!save(&path, text, &prot, None).expect("Could not save"));
+    assert_eq!(data, fs::read(&path).unwrap());
+    
+    fs::remove_dir_all(&dir).expect("Could not clean up");
+  }
+  
+  #[test]
+  fn detect_changes_to_plain_text_being_encrypted() {
+    let dir = temp_dir("encrypting");
+    let path = dir.join("salaries.res");
+    let prot = Protection::Passphrase("hunter2".to_string());
+    fs::write(&path, "salary = 100000\n").unwrap();
+    let snap = Snapshot::new(&path, "salary = 100000\n");
+    
+    assert_eq!(None, snap.differs(&path, &prot).unwrap());
+    fs::write(&path, "salary = 120000\n").unwrap();
+    assert_eq!(Some("salary = 120000\n".to_string()), snap.differs(&path, &prot).unwrap());
+    
+    fs::remove_dir_all(&dir).expect("Could not clean up");
+  }
+  
+}
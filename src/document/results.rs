@@ -0,0 +1,95 @@
+use crate::rdl;
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::text::width::str_width;
+
+pub const DELIMITER: &str = "=>";
+
+const GUTTER: usize = 2; // minimum spaces between the text and the delimiter
+
+/// Write the results of every line into the text itself, after the
+/// delimiter, with the delimiters aligned in a column. Lines without
+/// results are left as they are.
+pub fn annotate(mut cxt: exec::Context, text: &str, delim: &str, imports: &mut Imports) -> String {
+  let mut rows: Vec<(&str, &str, String)> = Vec::new(); // line, line ending, results
+  for line in text.split_inclusive('\n') {
+    let (body, end) = match line.strip_suffix('\n') {
+      Some(body) => (body, "\n"),
+      None => (line, ""),
+    };
+    rdl::import(imports, &mut cxt, body); // only results are written; a file which can't be imported is shown where the line is drawn
+    let (_, res) = rdl::render_with_options(&mut cxt, body, 0, 0, None, None);
+    rows.push((body, end, res.text().to_string()));
+  }
+
+  let width = rows.iter()
+    .filter(|(_, _, res)| !res.is_empty())
+    .map(|(body, _, _)| str_width(body.trim_end()))
+    .max()
+    .unwrap_or(0);
+
+  let mut out = String::new();
+  for (body, end, res) in rows {
+    if res.is_empty() {
+      out.push_str(body);
+    }else{
+      let body = body.trim_end();
+      out.push_str(body);
+      out.push_str(&" ".repeat(width - str_width(body) + GUTTER));
+      out.push_str(&format!("{} {}", delim, res));
+    }
+    out.push_str(end);
+  }
+  out
+}
+
+/// Remove results written into the text by `annotate`, so that what
+/// remains is the text as it was written. Only what `annotate` writes is
+/// removed: a delimiter at least a gutter's width after text which has a
+/// result, and what follows it. A delimiter in a comment or a string is
+/// left alone, since the text before it has no result of its own. Results
+/// aren't compared with what the text evaluates to now, since they change
+/// as rates do.
+pub fn strip(mut cxt: exec::Context, text: &str, delim: &str, imports: &mut Imports) -> String {
+  let mut out = String::new();
+  for line in text.split_inclusive('\n') {
+    let (body, end) = match line.strip_suffix('\n') {
+      Some(body) => (body, "\n"),
+      None => (line, ""),
+    };
+    let kept = match body.rfind(&format!(" {} ", delim)) {
+      Some(idx) if body[..idx + 1].ends_with(&" ".repeat(GUTTER)) && has_result(cxt.clone(), body[..idx].trim_end(), imports) => body[..idx].trim_end(),
+      _ => body,
+    };
+    rdl::import(imports, &mut cxt, kept);
+    let _ = rdl::render_with_options(&mut cxt, kept, 0, 0, None, None); // so later lines see what it defines
+    out.push_str(kept);
+    out.push_str(end);
+  }
+  out
+}
+
+/// Whether a line has a result, as `annotate` would write after it.
+fn has_result(mut cxt: exec::Context, line: &str, imports: &mut Imports) -> bool {
+  rdl::import(imports, &mut cxt, line);
+  !rdl::render_with_options(&mut cxt, line, 0, 0, None, None).1.text().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn annotate_and_strip() {
+    let text = "Budget\nrent = 1200\nfood = 400  \ntotal = rent + food\n";
+    let annotated = annotate(exec::Context::new(), text, DELIMITER, &mut Imports::new(std::path::Path::new("")));
+    assert_eq!("Budget\nrent = 1200          => 1200\nfood = 400           => 400\ntotal = rent + food  => 1600\n", &annotated);
+    let strip = |text: &str, delim: &str| strip(exec::Context::new(), text, delim, &mut Imports::new(std::path::Path::new("")));
+    assert_eq!("Budget\nrent = 1200\nfood = 400\ntotal = rent + food\n", &strip(&annotated, DELIMITER));
+    assert_eq!(annotated, annotate(exec::Context::new(), &strip(&annotated, DELIMITER), DELIMITER, &mut Imports::new(std::path::Path::new(""))));
+    assert_eq!("a = 1\nno results here", &strip("a = 1  -> 1\nno results here", "->"));
+    assert_eq!("# a => b\nrent = 1200\n# a  => b", &strip("# a => b\nrent = 1200  => 1200\n# a  => b", DELIMITER)); // written, rather than results
+    assert_eq!("x = 2 # as in y => 2\nx * 2", &strip("x = 2 # as in y => 2\nx * 2  => 3", DELIMITER)); // stale, after the line before it was kept
+  }
+
+}
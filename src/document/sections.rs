@@ -0,0 +1,39 @@
+use crate::rdl;
+
+/// A section tagged with this style is drawn normally; it ends a styled
+/// section without starting another.
+pub const NONE: &str = "none";
+
+const TAG: &str = "style:";
+
+/// Read the style a line tags the section it starts with, if it is a tag.
+/// A tag is a comment such as `# [style: warning]`, and the section runs
+/// until the next tag. What each style looks like is up to the theme.
+pub fn tag(line: &str) -> Option<String> {
+  if !rdl::is_comment(line) {
+    return None;
+  }
+  let (_, rest) = line.split_once('[')?;
+  let (inner, _) = rest.split_once(']')?;
+  let name = inner.trim().strip_prefix(TAG)?.trim();
+  if name.is_empty() {
+    None
+  }else{
+    Some(name.to_lowercase())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn section_tags() {
+    assert_eq!(Some("warning".to_string()), tag("# Risky assumptions [style: Warning]"));
+    assert_eq!(Some(NONE.to_string()), tag("  #[style:none]"));
+    assert_eq!(None, tag("x = 1 # [style: warning]"));
+    assert_eq!(None, tag("# [style: ]"));
+    assert_eq!(None, tag("# [colour: red]"));
+  }
+
+}
@@ -0,0 +1,262 @@
+use crate::rdl;
+use crate::rdl::unit;
+use crate::rdl::exec;
+use crate::rdl::locale::{self, Locale};
+
+pub const PRECISION: &str = "precision";
+pub const FIGURES: &str = "figures";
+pub const ROUNDING: &str = "rounding";
+pub const LOCALE: &str = "locale";
+pub const CURRENCY: &str = "currency";
+pub const STRICT: &str = "strict";
+pub const RATE: &str = "rate";
+
+pub use unit::{MAX_PRECISION, MAX_FIGURES};
+
+/// Locales whose conventions are supported.
+pub const LOCALES: [&str; 4] = locale::NAMES;
+/// Currencies that can be chosen as the default.
+pub const CURRENCIES: [&str; 5] = ["USD", "EUR", "GBP", "JPY", "CHF"];
+
+/// Settings for a single document, which are kept in its front matter: the
+/// comment lines at the top of the document, as `# key: value`. Comments
+/// in the front matter which are not settings are left alone.
+///
+/// How results are presented can be set in several places, and the most
+/// specific one wins: a line's own settings (see `rdl::split_line`), then
+/// overrides on the command line or in the environment (see
+/// `config::overrides`), then the document's, then the user's configuration. Decimal places and
+/// significant figures are considered together, so that a document which
+/// sets either one replaces both of those from the configuration. Decimal
+/// places take precedence over significant figures set in the same place.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+  pub precision: Option<usize>, // fixed number of decimal places in results
+  pub figures: Option<usize>,   // fixed number of significant figures in results
+  pub rounding: Option<unit::Rounding>,
+  pub locale: Option<String>,   // conventions for numbers, currency, and dates
+  pub currency: Option<String>, // what a bare `$` means, overriding the locale
+  pub strict: bool,             // whether incompatible units are an error
+  pub rates: Vec<unit::Rate>,   // exchange rates, as `# rate: EUR/USD = 1.09`
+}
+
+impl Settings {
+  /// Read the settings from the front matter of a document. Settings with
+  /// values that are not understood are ignored.
+  pub fn parse(text: &str) -> Settings {
+    let mut settings = Settings::default();
+    for (key, val) in text.lines().take_while(|l| rdl::is_comment(l)).filter_map(setting) {
+      settings.set(&key, &val);
+    }
+    settings
+  }
+
+  /// Set a setting by name, as it is written in front matter. Produces
+  /// false if the setting or its value is not understood.
+  pub fn set(&mut self, key: &str, val: &str) -> bool {
+    match key {
+      PRECISION => {
+        self.precision = val.parse().ok().filter(|p| *p <= MAX_PRECISION);
+        self.precision.is_some()
+      },
+      FIGURES => {
+        self.figures = val.parse().ok().filter(|f| (1..=MAX_FIGURES).contains(f));
+        self.figures.is_some()
+      },
+      ROUNDING => {
+        self.rounding = unit::Rounding::from_name(val);
+        self.rounding.is_some()
+      },
+      LOCALE => {
+        self.locale = LOCALES.iter().find(|l| **l == val).map(|l| l.to_string());
+        self.locale.is_some()
+      },
+      CURRENCY => {
+        self.currency = Some(val.to_uppercase());
+        unit::Currency::from_code(val).is_some()
+      },
+      STRICT => {
+        self.strict = matches!(val, "true" | "yes" | "on");
+        true
+      },
+      RATE => match unit::Rate::parse(val) {
+        Some(rate) => {
+          self.rates.push(rate);
+          true
+        },
+        None => false,
+      },
+      _ => false,
+    }
+  }
+
+  /// Fill in settings the document does not set from defaults, such as
+  /// those in the user's configuration. Exchange rates the document sets
+  /// take precedence over those between the same currencies by default.
+  pub fn or(self, defaults: &Settings) -> Settings {
+    let digits = if self.precision.is_some() || self.figures.is_some() { &self } else { defaults };
+    Settings{
+      precision: digits.precision,
+      figures: digits.figures,
+      rounding: self.rounding.or(defaults.rounding),
+      locale: self.locale.or_else(|| defaults.locale.clone()),
+      currency: self.currency.or_else(|| defaults.currency.clone()),
+      strict: self.strict || defaults.strict,
+      rates: self.rates.iter().chain(defaults.rates.iter()).copied().collect(),
+    }
+  }
+
+  /// Produce the text of a document with its front matter updated to these
+  /// settings. Settings which have their default value are omitted.
+  pub fn apply(&self, text: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    if let Some(precision) = self.precision {
+      out.push(format!("{} {}: {}", rdl::COMMENT, PRECISION, precision));
+    }
+    if let Some(figures) = self.figures {
+      out.push(format!("{} {}: {}", rdl::COMMENT, FIGURES, figures));
+    }
+    if let Some(rounding) = self.rounding {
+      out.push(format!("{} {}: {}", rdl::COMMENT, ROUNDING, rounding));
+    }
+    if let Some(locale) = &self.locale {
+      out.push(format!("{} {}: {}", rdl::COMMENT, LOCALE, locale));
+    }
+    if let Some(currency) = &self.currency {
+      out.push(format!("{} {}: {}", rdl::COMMENT, CURRENCY, currency));
+    }
+    if self.strict {
+      out.push(format!("{} {}: true", rdl::COMMENT, STRICT));
+    }
+    for rate in &self.rates {
+      out.push(format!("{} {}: {}", rdl::COMMENT, RATE, rate));
+    }
+    let mut front = true;
+    for line in text.split('\n') {
+      front = front && rdl::is_comment(line);
+      if !front || setting(line).is_none() {
+        out.push(line.to_string());
+      }
+    }
+    out.join("\n")
+  }
+
+  /// Configure a context in which to evaluate the document. A locale or
+  /// currency the document sets replaces the one the context already has,
+  /// such as from the user's configuration.
+  pub fn configure(&self, cxt: &mut exec::Context) {
+    let mut locale = match self.locale.as_deref().and_then(Locale::named) {
+      Some(locale) => locale,
+      None => cxt.locale().clone(),
+    };
+    if let Some(currency) = self.currency.as_deref().and_then(unit::Currency::from_code) {
+      locale.currency = currency;
+    }
+    cxt.set_format(unit::Format{
+      precision: self.precision,
+      figures: self.figures,
+      rounding: self.rounding.unwrap_or(unit::Rounding::HalfUp),
+      ..locale.format()
+    });
+    cxt.set_locale(locale);
+    cxt.set_strict(self.strict);
+    cxt.set_rates(self.rates.clone());
+  }
+}
+
+/// A context in which to evaluate a document outside the editor: the
+/// standard library, configured with the document's settings. Those it
+/// doesn't set are taken from defaults, such as the user's configuration,
+/// if there are any, and it's evaluated in their locale unless it sets one.
+pub fn context(text: &str, defaults: Option<(&Locale, &Settings)>) -> exec::Context {
+  let mut cxt = exec::Context::new_with_stdlib();
+  match defaults {
+    Some((locale, defaults)) => {
+      cxt.set_locale(locale.clone());
+      Settings::parse(text).or(defaults).configure(&mut cxt);
+    },
+    None => Settings::parse(text).configure(&mut cxt),
+  }
+  cxt
+}
+
+/// Determine whether a line is a setting, as in `# precision: 2`, rather
+/// than a comment of some other kind.
+pub fn is_setting(line: &str) -> bool {
+  setting(line).is_some()
+}
+
+/// Parse a line of front matter as a setting, if it is one.
+fn setting(line: &str) -> Option<(String, String)> {
+  let (key, val) = line.trim_start().strip_prefix(rdl::COMMENT)?.split_once(':')?;
+  let key = key.trim().to_lowercase();
+  if [PRECISION, FIGURES, ROUNDING, LOCALE, CURRENCY, STRICT, RATE].contains(&key.as_str()) {
+    Some((key, val.trim().to_string()))
+  }else{
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn front_matter() {
+    let text = "# Budget\n# precision: 2\n# locale: de\n# Strict: yes\nrent = 1200\n# precision: 9\n";
+    let settings = Settings::parse(text);
+    assert_eq!(Settings{precision: Some(2), locale: Some("de".to_string()), strict: true, ..Settings::default()}, settings);
+    let mut cxt = exec::Context::new();
+    settings.configure(&mut cxt);
+    assert_eq!(unit::Format{precision: Some(2), decimal: ',', grouping: Some('.'), ..unit::Format::default()}, cxt.format());
+    assert_eq!(unit::Currency::Eur, cxt.locale().currency);
+
+    let updated = Settings{locale: Some("fr".to_string()), currency: Some("EUR".to_string()), ..Settings::default()};
+    let text = updated.apply(text);
+    assert_eq!("# locale: fr\n# currency: EUR\n# Budget\nrent = 1200\n# precision: 9\n", &text);
+    assert_eq!(updated, Settings::parse(&text));
+    assert_eq!("rent = 1200", &Settings::default().apply("# precision: 3\nrent = 1200"));
+
+    let defaults = Settings{precision: Some(4), locale: Some("en".to_string()), ..Settings::default()};
+    assert_eq!(Settings{precision: Some(4), ..updated.clone()}, updated.or(&defaults));
+  }
+
+  #[test]
+  fn precedence() {
+    let config = Settings{precision: Some(4), rounding: Some(unit::Rounding::Down), ..Settings::default()};
+    let result = |doc: &str, line: &str| {
+      let mut cxt = exec::Context::new();
+      Settings::parse(doc).or(&config).configure(&mut cxt);
+      let (_, res) = rdl::render_with_options(&mut cxt, line, 0, 0, None, None);
+      res.text().to_string()
+    };
+    assert_eq!("3.1415", &result("", "pi = 3.14159265"));
+    assert_eq!("3.141", &result("# figures: 4", "pi = 3.14159265")); // still rounding down
+    assert_eq!("3.14", &result("# figures: 4
+# precision: 2", "pi = 3.14159265"));
+    assert_eq!("3.1416", &result("# rounding: half-up", "pi = 3.14159265"));
+    assert_eq!("3.1", &result("# figures: 4", "pi = 3.14159265 # precision: 1"));
+    assert_eq!("3.15", &result("# precision: 2", "pi = 3.14159265 # figures: 3, rounding: up"));
+    assert_eq!("3.14; 8.00", &result("# precision: 2", "pi = 3.14159265 # not a setting: 8"));
+  }
+
+  #[test]
+  fn exchange_rates() {
+    let config = Settings{rates: vec![unit::Rate::parse("EUR/USD = 1.1").unwrap(), unit::Rate::parse("GBP/USD = 1.25").unwrap()], ..Settings::default()};
+    let doc = "# rate: EUR/USD = 1.5\n# rate: USD/CHF = nope\n";
+    let settings = Settings::parse(doc);
+    assert_eq!(1, settings.rates.len());
+    assert_eq!("# rate: EUR/USD = 1.5\ntotal = 1", &settings.apply("total = 1"));
+    let result = |line: &str| {
+      let mut cxt = exec::Context::new();
+      settings.clone().or(&config).configure(&mut cxt);
+      let (_, res) = rdl::render_with_options(&mut cxt, line, 0, 0, None, None);
+      res.text().to_string()
+    };
+    assert_eq!("150 USD", &result("100 EUR in USD"));
+    assert_eq!("160 USD", &result("€100 + 10 USD"));
+    assert_eq!("10 GBP", &result("12.5 USD in GBP"));
+    assert_eq!("5 CHF", &result("5 CHF in EUR")); // no rate, so left as it is
+  }
+
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::i18n::{fill, tr, Message};
+
+/// A named command, which can be bound to a key or invoked from the
+/// command palette.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Command {
+  Open,
+  Save,
+  Quit,
+  Settings,
+  Palette,
+  Keybindings,
+  CopyResult,
+  CopyLine,
+  CopyMarkdown,
+  CopySnippet,
+  CopyFormatted,
+  DeleteMode,
+  StartOfWord,
+  EndOfWord,
+  NextWord,
+  RefreshRates,
+  ShowLog,
+  ToggleStats,
+  ToggleOutline,
+  EarlierResults,
+  Theme(String),
+}
+
+/// Commands which can be bound to keys, in the order they are listed.
+pub const BINDABLE: [Command; 20] = [
+  Command::Open,
+  Command::Save,
+  Command::Quit,
+  Command::Settings,
+  Command::Palette,
+  Command::Keybindings,
+  Command::CopyResult,
+  Command::CopyLine,
+  Command::CopyMarkdown,
+  Command::CopySnippet,
+  Command::CopyFormatted,
+  Command::DeleteMode,
+  Command::StartOfWord,
+  Command::EndOfWord,
+  Command::NextWord,
+  Command::RefreshRates,
+  Command::ShowLog,
+  Command::ToggleStats,
+  Command::ToggleOutline,
+  Command::EarlierResults,
+];
+
+impl Command {
+  /// Every command, with a command to switch to each of the provided themes.
+  pub fn all(themes: &[String]) -> Vec<Command> {
+    let mut cmds = BINDABLE.to_vec();
+    cmds.extend(themes.iter().map(|t| Command::Theme(t.clone())));
+    cmds
+  }
+
+  /// The name by which a command is bound to a key in the configuration.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Open         => "open",
+      Self::Save         => "save",
+      Self::Quit         => "quit",
+      Self::Settings     => "settings",
+      Self::Palette      => "palette",
+      Self::Keybindings  => "keybindings",
+      Self::CopyResult   => "copy-result",
+      Self::CopyLine     => "copy-line",
+      Self::CopyMarkdown => "copy-markdown",
+      Self::CopySnippet  => "copy-snippet",
+      Self::CopyFormatted => "copy-formatted",
+      Self::DeleteMode   => "delete-mode",
+      Self::StartOfWord  => "start-of-word",
+      Self::EndOfWord    => "end-of-word",
+      Self::NextWord     => "next-word",
+      Self::RefreshRates => "refresh-rates",
+      Self::ShowLog      => "show-log",
+      Self::ToggleStats  => "toggle-stats",
+      Self::ToggleOutline => "toggle-outline",
+      Self::EarlierResults => "earlier-results",
+      Self::Theme(_)     => "theme",
+    }
+  }
+
+  /// Find a command that can be bound to a key by its name.
+  pub fn from_name(name: &str) -> Option<Command> {
+    BINDABLE.iter().find(|c| c.name() == name).cloned()
+  }
+
+  /// Whether the command acts on the selection, which must therefore be
+  /// left alone when it is invoked.
+  pub fn uses_selection(&self) -> bool {
+    matches!(self, Self::CopyResult | Self::CopyLine | Self::CopyMarkdown | Self::CopySnippet | Self::CopyFormatted)
+  }
+}
+
+impl fmt::Display for Command {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Open         => write!(f, "{}", tr(Message::Open)),
+      Self::Save         => write!(f, "{}", tr(Message::Save)),
+      Self::Quit         => write!(f, "{}", tr(Message::Quit)),
+      Self::Settings     => write!(f, "{}", tr(Message::Settings)),
+      Self::Palette      => write!(f, "{}", tr(Message::Palette)),
+      Self::Keybindings  => write!(f, "{}", tr(Message::Keybindings)),
+      Self::CopyResult   => write!(f, "{}", tr(Message::CopyResult)),
+      Self::CopyLine     => write!(f, "{}", tr(Message::CopyLine)),
+      Self::CopyMarkdown => write!(f, "{}", tr(Message::CopyMarkdown)),
+      Self::CopySnippet  => write!(f, "{}", tr(Message::CopySnippet)),
+      Self::CopyFormatted => write!(f, "{}", tr(Message::CopyFormatted)),
+      Self::DeleteMode   => write!(f, "{}", tr(Message::DeleteMode)),
+      Self::StartOfWord  => write!(f, "{}", tr(Message::StartOfWord)),
+      Self::EndOfWord    => write!(f, "{}", tr(Message::EndOfWord)),
+      Self::NextWord     => write!(f, "{}", tr(Message::NextWord)),
+      Self::RefreshRates => write!(f, "{}", tr(Message::RefreshRates)),
+      Self::ShowLog      => write!(f, "{}", tr(Message::ShowLog)),
+      Self::ToggleStats  => write!(f, "{}", tr(Message::ToggleStats)),
+      Self::ToggleOutline => write!(f, "{}", tr(Message::ToggleOutline)),
+      Self::EarlierResults => write!(f, "{}", tr(Message::EarlierResults)),
+      Self::Theme(name)  => write!(f, "{}", fill(Message::ThemeNamed, &[name])),
+    }
+  }
+}
@@ -0,0 +1,39 @@
+/// Format a line together with its result for copying.
+pub fn line_with_result(line: &str, result: &str) -> String {
+  if result.is_empty() {
+    line.trim().to_string()
+  }else{
+    format!("{} = {}", line.trim(), result)
+  }
+}
+
+/// Format lines and their results as a Markdown table for copying.
+pub fn markdown(rows: &[(String, String)]) -> String {
+  let mut out = String::new();
+  out.push_str("| Line | Result |\n");
+  out.push_str("| --- | ---: |\n");
+  for (line, result) in rows {
+    out.push_str(&format!("| {} | {} |\n", escape(line.trim()), escape(result)));
+  }
+  out
+}
+
+fn escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn format_copy() {
+    assert_eq!("Such as 10 + 40 = 50", &line_with_result("Such as 10 + 40 ", "50"));
+    assert_eq!("Nothing here", &line_with_result("Nothing here", ""));
+    assert_eq!(
+      "| Line | Result |\n| --- | ---: |\n| tax = 0.0875 | 0.0875 |\n| a \\| b |  |\n",
+      &markdown(&[("tax = 0.0875".to_string(), "0.0875".to_string()), ("a | b".to_string(), "".to_string())])
+    );
+  }
+  
+}
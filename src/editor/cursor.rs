@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// The sequence which returns the cursor to the terminal's own style.
+pub const RESET: &str = "\x1b[0 q";
+
+/// The shape of the cursor.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Shape {
+  Block,
+  Bar,
+  Underline,
+}
+
+/// Every shape, in the order they are listed in the configuration.
+pub const SHAPES: [Shape; 3] = [Shape::Block, Shape::Bar, Shape::Underline];
+
+impl Shape {
+  pub fn from_name(name: &str) -> Option<Shape> {
+    SHAPES.iter().find(|s| s.to_string() == name.trim().to_lowercase()).copied()
+  }
+}
+
+impl fmt::Display for Shape {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Shape::Block     => write!(f, "block"),
+      Shape::Bar       => write!(f, "bar"),
+      Shape::Underline => write!(f, "underline"),
+    }
+  }
+}
+
+/// How the cursor is drawn.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Style {
+  pub shape: Shape,
+  pub blink: bool,
+}
+
+impl Style {
+  /// The DECSCUSR sequence which sets this style.
+  pub fn sequence(&self) -> String {
+    let n = match self.shape {
+      Shape::Block     => 1,
+      Shape::Underline => 3,
+      Shape::Bar       => 5,
+    };
+    format!("\x1b[{} q", if self.blink { n }else{ n + 1 })
+  }
+}
+
+/// The style of the cursor in each mode of the editor. Modes without a
+/// style leave the cursor as the terminal draws it.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Cursors {
+  pub normal: Option<Style>,
+  pub delete: Option<Style>, // waiting for a movement to delete over
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cursor_sequences() {
+    assert_eq!("\x1b[1 q", Style{shape: Shape::Block, blink: true}.sequence());
+    assert_eq!("\x1b[6 q", Style{shape: Shape::Bar, blink: false}.sequence());
+    assert_eq!("\x1b[4 q", Style{shape: Shape::Underline, blink: false}.sequence());
+    assert_eq!(Some(Shape::Bar), Shape::from_name("Bar"));
+    assert_eq!(None, Shape::from_name("beam"));
+  }
+
+}
@@ -0,0 +1,285 @@
+use clap::Parser;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::editor::Editor;
+use crate::editor::keymap;
+use crate::editor::writer::Writer;
+use crate::options::Options;
+use crate::state::recent::Recent;
+use crate::text::Pos;
+use crate::text::width;
+
+/// Drives an editor without a terminal, for end-to-end tests. Keys are fed
+/// to it as though they were pressed, and what it draws is kept as the grid
+/// of characters a terminal would show instead of being written out. Nothing
+/// a test does is backed up or recorded in the history of documents.
+/// Passphrase prompts read the terminal themselves, and can't be driven.
+pub struct Driver {
+  editor: Editor,
+  screen: Screen,
+  running: bool,
+}
+
+impl Driver {
+  /// Start an editor with a screen of some width and height, in columns
+  /// and rows, and the default configuration.
+  pub fn new(size: (usize, usize)) -> Driver {
+    let opts = Options::parse_from(["resolver"]);
+    let mut editor = Editor::new_with_size(size, opts.clone());
+    editor.writer = Writer::capturing(size, opts);
+    editor.backups = None;
+    editor.recent = Recent::new();
+    let mut driver = Driver{
+      editor,
+      screen: Screen::new(size),
+      running: true,
+    };
+    driver.draw();
+    driver
+  }
+
+  /// Replace the text of the document, as though it had been opened.
+  pub fn set_text(&mut self, text: &str) {
+    self.editor.set_text(text.to_string());
+    self.draw();
+  }
+
+  /// Press a key, written as in the keymap, such as `ctrl-s` or `enter`.
+  /// Produces whether the editor is still running.
+  pub fn press(&mut self, key: &str) -> bool {
+    let evt = keymap::parse_key(key).unwrap_or_else(|| panic!("No such key: {}", key));
+    self.send(Some(Event::Key(evt)))
+  }
+
+  /// Type text a character at a time; a newline presses enter.
+  pub fn type_text(&mut self, text: &str) {
+    for c in text.chars() {
+      let code = if c == '\n' { KeyCode::Enter }else{ KeyCode::Char(c) };
+      self.send(Some(Event::Key(KeyEvent::new(code, KeyModifiers::NONE))));
+    }
+  }
+
+  /// Paste text, as a terminal does with bracketed paste.
+  pub fn paste(&mut self, text: &str) {
+    self.send(Some(Event::Paste(text.to_string())));
+  }
+
+  /// Let time pass without a key being pressed, so anything that happened
+  /// in the background, such as rates arriving, is seen to.
+  pub fn tick(&mut self) {
+    self.send(None);
+  }
+
+  fn send(&mut self, evt: Option<Event>) -> bool {
+    let running = self.editor.handle(evt).expect("Could not handle a key");
+    self.running = self.running && running;
+    self.screen.write(&self.editor.writer.take_output());
+    self.running
+  }
+
+  /// Draw the editor again, as it is after a key is pressed.
+  pub fn draw(&mut self) {
+    self.editor.draw().expect("Could not draw");
+    self.screen.write(&self.editor.writer.take_output());
+  }
+
+  /// The text of the document.
+  pub fn text(&self) -> &str {
+    self.editor.text.text()
+  }
+
+  /// Where the cursor is in the document.
+  pub fn cursor(&self) -> Pos {
+    self.editor.pos
+  }
+
+  /// The characters on a row of the screen, without trailing spaces.
+  pub fn row(&self, y: usize) -> String {
+    self.screen.row(y)
+  }
+
+  /// Whether text appears anywhere on the screen, within a row.
+  pub fn shows(&self, text: &str) -> bool {
+    (0..self.screen.rows.len()).any(|y| self.screen.row(y).contains(text))
+  }
+}
+
+/// What a terminal would show once written to, as a grid of characters.
+/// Only the escape sequences the editor writes are understood, and colors
+/// and other attributes are ignored.
+struct Screen {
+  rows: Vec<Vec<char>>, // with NUL in the second column of wide characters
+  x: usize,
+  y: usize,
+}
+
+impl Screen {
+  fn new(size: (usize, usize)) -> Screen {
+    Screen{
+      rows: vec![vec![' '; size.0]; size.1],
+      x: 0,
+      y: 0,
+    }
+  }
+
+  fn write(&mut self, out: &str) {
+    let mut chars = out.chars();
+    while let Some(c) = chars.next() {
+      match c {
+        '\x1b' => match chars.next() {
+          Some('[') => {
+            let mut params = String::new();
+            for c in chars.by_ref() {
+              if ('@'..='~').contains(&c) {
+                self.control(&params, c);
+                break;
+              }
+              params.push(c);
+            }
+          },
+          Some(']') => while let Some(c) = chars.next() { // up to BEL or ST
+            if c == '\x07' {
+              break;
+            }
+            if c == '\x1b' {
+              chars.next();
+              break;
+            }
+          },
+          _ => {},
+        },
+        '\r' => self.x = 0,
+        '\n' => self.y += 1,
+        c => self.put(c),
+      }
+    }
+  }
+
+  fn control(&mut self, params: &str, cmd: char) {
+    let nums: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+    let num = |i: usize| nums.get(i).copied().unwrap_or(0);
+    let width = self.rows.first().map_or(0, |r| r.len());
+    match cmd {
+      'H' => {
+        self.y = num(0).max(1) - 1;
+        self.x = num(1).max(1) - 1;
+      },
+      'K' => if let Some(row) = self.rows.get_mut(self.y) {
+        let from = if num(0) == 2 { 0 }else{ self.x.min(width) };
+        row[from..].fill(' ');
+      },
+      'J' if num(0) == 2 => self.rows.iter_mut().for_each(|r| r.fill(' ')),
+      _ => {}, // attributes, modes, and the style of the cursor
+    }
+  }
+
+  fn put(&mut self, c: char) {
+    let w = width::char_width(c);
+    if let Some(row) = self.rows.get_mut(self.y) {
+      if w > 0 && self.x + w <= row.len() {
+        row[self.x] = c;
+        if w == 2 {
+          row[self.x + 1] = '\0';
+        }
+      }
+    }
+    self.x += w;
+  }
+
+  fn row(&self, y: usize) -> String {
+    self.rows.get(y).map_or(String::new(), |r| r.iter().filter(|c| **c != '\0').collect::<String>().trim_end().to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::document::{self, Protection};
+
+  #[test]
+  fn drive_editor() {
+    let mut driver = Driver::new((90, 12));
+    driver.type_text("price = 20\nprice * 3");
+    assert_eq!("price = 20\nprice * 3", driver.text());
+    assert_eq!((9, 1), (driver.cursor().x, driver.cursor().y));
+    assert!(driver.row(0).starts_with("   1 ┊price = 20 "));
+    assert!(driver.row(1).ends_with("┊60"));
+
+    driver.press("backspace");
+    driver.type_text("4");
+    assert!(driver.row(1).ends_with("┊80"));
+    driver.paste("\n\x1b[2Jtotal = \u{202e}5\r\n");
+    assert_eq!("price = 20\nprice * 4\ntotal = \u{2426}5\n", driver.text());
+    assert!(driver.row(2).starts_with("   3 ┊total = \u{2426}5 "));
+    assert_eq!((0, 3), (driver.cursor().x, driver.cursor().y));
+
+    driver.press("f1");
+    assert!(driver.shows("Show keybindings"));
+    driver.press("esc"); // dismisses the notice
+    assert!(!driver.shows("Show keybindings"));
+    driver.set_text("# precision: 2\n1/3");
+    assert!(driver.row(1).ends_with("┊0.33"));
+    driver.press("ctrl-p");
+    driver.type_text("performance\n");
+    assert!(driver.row(0).ends_with(" Performance"));
+    assert!(driver.row(2).ends_with("lines evaluated                2"));
+    driver.tick();
+    assert!(!driver.press("ctrl-q"));
+  }
+
+  #[test]
+  fn open_over_unsaved_changes() {
+    let mut driver = Driver::new((90, 12));
+    let missing = std::env::temp_dir().join("resolver-driver-missing.rdl");
+    driver.editor.recent.add(&missing);
+    driver.type_text("rent = 1200");
+    driver.press("ctrl-o");
+    driver.press("enter");
+    assert!(driver.shows("The document has unsaved changes"));
+    driver.press("esc");
+    driver.press("ctrl-o");
+    driver.press("enter"); // again, to discard them
+    assert!(driver.shows("resolver-driver-missing.rdl could not be opened"));
+    assert!(driver.press("esc"));
+    assert_eq!("rent = 1200", driver.text()); // kept, since the other couldn't be opened
+  }
+
+  #[test]
+  fn show_what_cant_be_imported() {
+    let mut driver = Driver::new((90, 12));
+    driver.set_text("use \"resolver-driver-missing.toml\"\nrent = 1200");
+    assert!(driver.row(0).contains("I/O error"));
+    assert!(driver.row(1).contains("1200"));
+  }
+
+  #[test]
+  fn save_where_it_cant_be() {
+    let mut driver = Driver::new((90, 12));
+    driver.editor.doc = Some(std::env::temp_dir().join("resolver-driver-missing").join("budget.rdl"));
+    driver.type_text("rent = 1200");
+    assert!(driver.press("ctrl-s"));
+    assert!(driver.shows("budget.rdl could not be saved"));
+    assert!(driver.press("esc"));
+    assert_eq!("rent = 1200", driver.text());
+  }
+
+  #[test]
+  fn save_plain_text_with_a_passphrase() {
+    let dir = std::env::temp_dir().join(format!("resolver-driver-encrypt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("salaries.rdl");
+    std::fs::write(&path, "salary = 100000").unwrap();
+    let mut driver = Driver::new((90, 12));
+    driver.editor.open(&path).unwrap();
+    driver.editor.protection = Protection::Passphrase("hunter2".to_string()); // as encrypting it does, once asked for one
+    driver.press("end");
+    driver.type_text("0");
+    assert!(driver.press("ctrl-s"));
+    assert!(!driver.shows("could not be saved"));
+    assert!(document::is_encrypted(&path).unwrap());
+    assert_eq!("salary = 1000000", document::load_with_protection(&path, &driver.editor.protection).unwrap());
+    drop(driver); // releasing its lock
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::time;
+
+use crate::util::diff;
+
+/// How many earlier results of each line are kept.
+const KEEP: usize = 5;
+
+/// A result a line evaluated to, with the line as it was written then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Earlier {
+  pub text: String,
+  pub result: String,
+  pub at: time::SystemTime,
+}
+
+/// The results each line of a document has evaluated to during the
+/// session, so that what a line evaluated to before it, or a line above
+/// it, was changed can be looked at without undoing the change. Results
+/// are recorded once typing pauses, so those of half-typed lines aren't.
+#[derive(Debug, Default)]
+pub struct History {
+  lines: Vec<String>, // the paragraphs, as they were when last recorded
+  results: Vec<VecDeque<Earlier>>, // of each paragraph, oldest first; the last is its current result
+  edited: bool, // whether the document has been edited since
+}
+
+impl History {
+  /// Note that the document has been edited, so its results are recorded
+  /// when next asked to be.
+  pub fn touch(&mut self) {
+    self.edited = true;
+  }
+
+  pub fn is_edited(&self) -> bool {
+    self.edited
+  }
+
+  /// Record the results of each paragraph of the document, as it is now.
+  /// Paragraphs are followed to where they moved, and one which replaced
+  /// another where it was keeps the results of the one it replaced, since
+  /// it's most likely the same line, edited.
+  pub fn record(&mut self, rows: &[(String, String)], now: time::SystemTime) {
+    let old: Vec<&str> = self.lines.iter().map(String::as_str).collect();
+    let new: Vec<&str> = rows.iter().map(|(l, _)| l.as_str()).collect();
+    let mut results: Vec<VecDeque<Earlier>> = rows.iter().map(|_| VecDeque::new()).collect();
+    let (mut removed, mut added): (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+    let pairs = diff::align(&old, &new);
+    for (n, pair) in pairs.iter().enumerate() {
+      match *pair {
+        (Some(i), Some(j)) => results[j] = std::mem::take(&mut self.results[i]),
+        (Some(i), None) => removed.push(i),
+        (None, Some(j)) => added.push(j),
+        _ => {},
+      };
+      if !matches!(pairs.get(n + 1), Some((Some(_), None) | (None, Some(_)))) { // the end of a change
+        for (i, j) in removed.drain(..).zip(added.drain(..)) {
+          results[j] = std::mem::take(&mut self.results[i]);
+        }
+      }
+    }
+    for ((text, result), ring) in rows.iter().zip(results.iter_mut()) {
+      if result.is_empty() || ring.back().is_some_and(|e| e.result == *result) {
+        continue;
+      }
+      ring.push_back(Earlier{text: text.clone(), result: result.clone(), at: now});
+      if ring.len() > KEEP + 1 {
+        ring.pop_front();
+      }
+    }
+    self.lines = new.iter().map(|l| l.to_string()).collect();
+    self.results = results;
+    self.edited = false;
+  }
+
+  /// The results a paragraph evaluated to before its current one, most
+  /// recent first.
+  pub fn earlier(&self, paragraph: usize) -> impl Iterator<Item = &Earlier> {
+    self.results.get(paragraph).into_iter().flat_map(|r| r.iter().rev().skip(1))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::editor::driver::Driver;
+
+  #[test]
+  fn earlier_results() {
+    let rows = |doc: &str, results: &[&str]| doc.lines().zip(results).map(|(l, r)| (l.to_string(), r.to_string())).collect::<Vec<_>>();
+    let now = time::SystemTime::now();
+    let mut history = History::default();
+    history.record(&rows("rent = 1200\nfood = 400\nrent + food", &["1200", "400", "1600"]), now);
+    assert_eq!(0, history.earlier(2).count());
+    history.record(&rows("rent = 1300\nfood = 400\nrent + food", &["1300", "400", "1700"]), now);
+    history.record(&rows("# Costs\nrent = 1300\nfood = 400\nrent + food", &["", "1300", "400", "1700"]), now); // moved, and unchanged
+    history.record(&rows("# Costs\nrent = 1300\nfood = 450\nrent + food", &["", "1300", "450", "1750"]), now);
+    let earlier = |h: &History, n: usize| h.earlier(n).map(|e| (e.text.clone(), e.result.clone())).collect::<Vec<_>>();
+    let pairs = |p: &[(&str, &str)]| p.iter().map(|(t, r)| (t.to_string(), r.to_string())).collect::<Vec<_>>();
+    assert_eq!(pairs(&[("rent = 1200", "1200")]), earlier(&history, 1)); // the line edited keeps its results
+    assert_eq!(pairs(&[("rent + food", "1700"), ("rent + food", "1600")]), earlier(&history, 3));
+    assert_eq!(pairs(&[("food = 400", "400")]), earlier(&history, 2));
+    for n in 0..10 {
+      history.record(&rows("# Costs\nrent = 1300\nfood = 450\nrent * 12 + food", &["", "1300", "450", &n.to_string()]), now);
+    }
+    assert_eq!(KEEP, history.earlier(3).count());
+
+    let mut driver = Driver::new((80, 12));
+    driver.set_text("rent = 1200\nrent * 12");
+    driver.tick();
+    driver.press("end");
+    driver.press("backspace");
+    driver.type_text("3");
+    driver.tick();
+    driver.press("down");
+    driver.press("f4");
+    assert!(driver.shows("14400"));
+  }
+
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::editor::command::{Command, BINDABLE};
+use crate::i18n::{tr, Message};
+use crate::util::suggest;
+
+/// The keys which invoke commands. Every command which can be bound has a
+/// default key, which the configuration can change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+  bindings: Vec<(Command, Option<KeyEvent>)>, // in the order of BINDABLE; none if unbound
+}
+
+fn default_key(cmd: &Command) -> Option<KeyEvent> {
+  let ctrl = |c| Some(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+  match cmd {
+    Command::Open         => ctrl('o'),
+    Command::Save         => ctrl('s'),
+    Command::Quit         => ctrl('q'),
+    Command::Settings     => ctrl('g'),
+    Command::Palette      => ctrl('p'),
+    Command::Keybindings  => Some(KeyEvent::new(KeyCode::F(1), KeyModifiers::NONE)),
+    Command::CopyResult   => ctrl('r'),
+    Command::CopyLine     => ctrl('l'),
+    Command::CopyMarkdown => ctrl('k'),
+    Command::CopySnippet  => ctrl('y'),
+    Command::CopyFormatted => None,
+    Command::DeleteMode   => ctrl('d'),
+    Command::StartOfWord  => ctrl('b'),
+    Command::EndOfWord    => ctrl('e'),
+    Command::NextWord     => ctrl('w'),
+    Command::RefreshRates => None,
+    Command::ShowLog      => None,
+    Command::ToggleStats  => None,
+    Command::ToggleOutline => Some(KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE)),
+    Command::EarlierResults => Some(KeyEvent::new(KeyCode::F(4), KeyModifiers::NONE)),
+    Command::Theme(_)     => None,
+  }
+}
+
+impl Default for Keymap {
+  fn default() -> Keymap {
+    Keymap{
+      bindings: BINDABLE.iter().map(|c| (c.clone(), default_key(c))).collect(),
+    }
+  }
+}
+
+impl Keymap {
+  /// Produce the default keymap with the provided bindings of command names
+  /// to keys applied, and describe any problems with them.
+  ///
+  /// Commands that don't exist are ignored. When commands are bound to the
+  /// same key, the one bound by the configuration keeps it, or the first of
+  /// them if several are, and the others are left unbound.
+  pub fn new(custom: &BTreeMap<String, KeyEvent>) -> (Keymap, Vec<String>) {
+    let mut map = Keymap::default();
+    let mut probs = Vec::new();
+    for (name, key) in custom {
+      match Command::from_name(name) {
+        Some(cmd) => map.bind(&cmd, Some(*key)),
+        None => match suggest::closest(name, &BINDABLE.map(|c| c.name())) {
+          Some(near) => probs.push(format!("{}: no such command; did you mean {}?", name, near)),
+          None => probs.push(format!("{}: no such command", name)),
+        },
+      };
+    }
+
+    let explicit = |c: &Command| custom.contains_key(c.name());
+    for i in 0..map.bindings.len() {
+      let key = match map.bindings[i].1 {
+        Some(key) => key,
+        None => continue,
+      };
+      let mut cmds: Vec<usize> = (i..map.bindings.len()).filter(|j| map.bindings[*j].1.is_some_and(|k| same(&k, &key))).collect();
+      if cmds.len() < 2 {
+        continue;
+      }
+      let keep = cmds.iter().position(|j| explicit(&map.bindings[*j].0)).unwrap_or(0);
+      let kept = cmds.remove(keep);
+      let names: Vec<&str> = cmds.iter().map(|j| map.bindings[*j].0.name()).collect();
+      probs.push(format!("{} is bound to both {} and {}; {} {} unbound", format_key(&key), map.bindings[kept].0.name(), names.join(", "), names.join(", "), if names.len() > 1 { "are" } else { "is" }));
+      for j in cmds {
+        map.bindings[j].1 = None;
+      }
+    }
+    (map, probs)
+  }
+
+  fn bind(&mut self, cmd: &Command, key: Option<KeyEvent>) {
+    if let Some(b) = self.bindings.iter_mut().find(|(c, _)| c == cmd) {
+      b.1 = key;
+    }
+  }
+
+  /// Find the command bound to a key.
+  pub fn lookup(&self, evt: &KeyEvent) -> Option<&Command> {
+    self.bindings.iter().find(|(_, k)| k.is_some_and(|k| same(&k, evt))).map(|(c, _)| c)
+  }
+
+  /// Produce the key bound to a command, if any.
+  pub fn key(&self, cmd: &Command) -> Option<KeyEvent> {
+    self.bindings.iter().find(|(c, _)| c == cmd).and_then(|(_, k)| *k)
+  }
+
+  /// Describe every binding, one per line, for display.
+  pub fn describe(&self) -> Vec<String> {
+    self.bindings.iter().map(|(cmd, key)| {
+      let key = key.map(|k| format_key(&k)).unwrap_or_else(|| tr(Message::Unbound).to_string());
+      format!("{:<12}{}", key, cmd)
+    }).collect()
+  }
+}
+
+/// Compare keys by what was pressed, disregarding how it was reported.
+fn same(a: &KeyEvent, b: &KeyEvent) -> bool {
+  a.code == b.code && a.modifiers == b.modifiers
+}
+
+/// Parse a key, such as `ctrl-s`, `alt-x`, `f5`, or `esc`.
+pub fn parse_key(spec: &str) -> Option<KeyEvent> {
+  let mut mods = KeyModifiers::NONE;
+  let mut parts: Vec<&str> = spec.split('-').collect();
+  let name = match parts.pop()? {
+    "" if spec.ends_with('-') => "-", // the minus key itself
+    name => name,
+  };
+  for part in parts.into_iter().filter(|p| !p.is_empty()) {
+    mods |= match part.to_lowercase().as_str() {
+      "ctrl" | "control" => KeyModifiers::CONTROL,
+      "alt" | "meta"     => KeyModifiers::ALT,
+      "shift"            => KeyModifiers::SHIFT,
+      _                  => return None,
+    };
+  }
+  let code = match name.to_lowercase().as_str() {
+    "esc" | "escape"   => KeyCode::Esc,
+    "enter" | "return" => KeyCode::Enter,
+    "tab"              => KeyCode::Tab,
+    "backspace"        => KeyCode::Backspace,
+    "delete" | "del"   => KeyCode::Delete,
+    "up"               => KeyCode::Up,
+    "down"             => KeyCode::Down,
+    "left"             => KeyCode::Left,
+    "right"            => KeyCode::Right,
+    "home"             => KeyCode::Home,
+    "end"              => KeyCode::End,
+    "pageup"           => KeyCode::PageUp,
+    "pagedown"         => KeyCode::PageDown,
+    "space"            => KeyCode::Char(' '),
+    lower => match (lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()), name.chars().count()) {
+      (Some(n), _) if (1..=12).contains(&n) => KeyCode::F(n),
+      (_, 1) => KeyCode::Char(name.chars().next()?.to_ascii_lowercase()),
+      _ => return None,
+    },
+  };
+  Some(KeyEvent::new(code, mods))
+}
+
+/// Describe a key as it would be written in the configuration.
+pub fn format_key(key: &KeyEvent) -> String {
+  let mut out = String::new();
+  for (m, name) in [(KeyModifiers::CONTROL, "ctrl-"), (KeyModifiers::ALT, "alt-"), (KeyModifiers::SHIFT, "shift-")] {
+    if key.modifiers.contains(m) {
+      out.push_str(name);
+    }
+  }
+  match key.code {
+    KeyCode::Esc       => out.push_str("esc"),
+    KeyCode::Enter     => out.push_str("enter"),
+    KeyCode::Tab       => out.push_str("tab"),
+    KeyCode::Backspace => out.push_str("backspace"),
+    KeyCode::Delete    => out.push_str("delete"),
+    KeyCode::Up        => out.push_str("up"),
+    KeyCode::Down      => out.push_str("down"),
+    KeyCode::Left      => out.push_str("left"),
+    KeyCode::Right     => out.push_str("right"),
+    KeyCode::Home      => out.push_str("home"),
+    KeyCode::End       => out.push_str("end"),
+    KeyCode::PageUp    => out.push_str("pageup"),
+    KeyCode::PageDown  => out.push_str("pagedown"),
+    KeyCode::Char(' ') => out.push_str("space"),
+    KeyCode::Char(c)   => out.push(c),
+    KeyCode::F(n)      => out.push_str(&format!("f{}", n)),
+    _                  => out.push('?'),
+  };
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ctrl(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+  }
+
+  #[test]
+  fn remap_keys() {
+    let mut custom = BTreeMap::new();
+    custom.insert("save".to_string(), ctrl('o'));
+    custom.insert("palette".to_string(), parse_key("F2").unwrap());
+    custom.insert("frobnicate".to_string(), ctrl('x'));
+    custom.insert("copy_reslt".to_string(), ctrl('t'));
+    let (map, probs) = Keymap::new(&custom);
+    assert_eq!(vec![
+      "copy_reslt: no such command; did you mean copy-result?".to_string(),
+      "frobnicate: no such command".to_string(),
+      "ctrl-o is bound to both save and open; open is unbound".to_string(),
+    ], probs);
+    assert_eq!(Some(&Command::Save), map.lookup(&ctrl('o')));
+    assert_eq!(None, map.lookup(&ctrl('s')));
+    assert_eq!(None, map.key(&Command::Open));
+    assert_eq!(Some(&Command::Palette), map.lookup(&KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE)));
+    assert!(map.describe().contains(&"unbound     Open document".to_string()));
+  }
+
+  #[test]
+  fn parse_and_format_keys() {
+    for spec in ["ctrl-s", "alt-x", "f5", "esc", "ctrl-shift-up", "space", "-"] {
+      assert_eq!(spec, &format_key(&parse_key(spec).unwrap()));
+    }
+    assert_eq!(Some(ctrl('s')), parse_key("Control-S"));
+    assert_eq!(None, parse_key("hyper-s"));
+    assert_eq!(None, parse_key("f13"));
+  }
+
+}
@@ -1,14 +1,73 @@
 pub mod writer;
+pub mod picker;
+pub mod prompt;
+pub mod copy;
+pub mod settings;
+pub mod command;
+pub mod palette;
+pub mod keymap;
+pub mod status;
+pub mod cursor;
+pub mod stats;
+pub mod watch;
+pub mod outline;
+pub mod history;
+#[cfg(test)]
+pub mod driver;
+
+use std::io;
+use std::ops;
+use std::path;
+use std::time;
 
 use crossterm::event;
 
-use writer::Writer;
+use writer::{Writer, Placement};
+use picker::Picker;
+use settings::Panel;
+use command::Command;
+use palette::Palette;
+use watch::Watched;
+use outline::Outline;
+use history::History;
 
 use crate::Reader;
 use crate::error;
 use crate::text::{self, Text, Pos};
+use crate::text::width;
+use crate::text::sanitize;
 use crate::text::action::{Action, Movement, Operation};
 use crate::options;
+use crate::clipboard;
+use crate::cli::oneshot;
+use crate::control::{self, Request};
+use crate::notify;
+use crate::share;
+use crate::crash;
+use crate::logging;
+use crate::snippet::Snippet;
+use crate::rdl::{self, Watch};
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::workspace::Workspace;
+use crate::theme;
+use crate::rates;
+use crate::rates::quotes;
+use crate::i18n::{self, fill, tr, Message};
+use crate::document::{self, results, Protection, Snapshot};
+use crate::document::lock::{self, Lock, Holder};
+use crate::util::diff;
+use crate::document::backup::Backups;
+use crate::document::settings::Settings;
+use crate::config::file::{self as config, Config};
+use crate::config::overrides::Overrides;
+use crate::state::recent::Recent;
+
+/// How long to wait for a key before seeing to anything that happened in
+/// the background.
+const TICK: time::Duration = time::Duration::from_millis(250);
+/// How many of the last lines of the log are shown.
+const LOG_LINES: usize = 15;
 
 enum Mode {
   Normal,
@@ -22,45 +81,1054 @@ pub struct Editor {
   text: Text,
   mode: Mode,
   pos: Pos,
+  doc: Option<path::PathBuf>,
+  protection: Protection,
+  recent: Recent,
+  picker: Option<Picker>,
+  settings: Option<Panel>,
+  palette: Option<Palette>,
+  outline: Option<Outline>, // while it's shown
+  imports: Imports,
+  workspace: Option<Workspace>,
+  notice: Option<(String, Vec<String>)>,
+  backups: Option<Backups>,
+  disk: Option<Snapshot>,
+  conflict: Option<String>, // the content of the document on disk, when it conflicts with ours
+  lock: Option<Lock>, // on the document, while we're editing it
+  locked: Option<Holder>, // the instance editing the document, while we decide what to do about it
+  read_only: bool, // whether the document is edited in another instance, which saves it
+  config: Config,
+  overrides: Overrides, // settings from the command line and environment, which take precedence
+  auto_theme: &'static str, // the theme that suits the terminal, used unless one is configured
+  config_watch: config::Watch,
+  rates: rates::Feed, // fetched from the configured providers
+  quotes: quotes::Feed, // likewise, for the symbols the document needs
+  results: Option<String>, // the delimiter after which results are written into the document, if they are
+  control: Option<control::Listener>, // requests from other processes, if we're the editor receiving them
+  watched: Watched, // values of the lines which ask to be watched
+  history: History, // earlier results of each line
+  share: Option<share::Session>, // watched by others, if it's shared
+  discard: Option<path::PathBuf>, // the document last opened over unsaved changes, which opening again discards them for
 }
 
 impl Editor {
   pub fn new_with_size(size: (usize, usize), opts: options::Options) -> Self {
+    let backups = Backups::in_state_dir(opts.backups).ok(); // backups are best effort
+    let results = opts.write_results.then(|| opts.result_delimiter.clone());
     Editor{
       reader: Reader,
       writer: Writer::new_with_size(size, opts),
       text: Text::new((size.0 / 3) * 2),
       mode: Mode::Normal,
       pos: text::ZERO_POS,
+      doc: None,
+      protection: Protection::None,
+      recent: Recent::load(),
+      picker: None,
+      settings: None,
+      palette: None,
+      outline: None,
+      imports: Imports::new(path::Path::new("")),
+      workspace: None,
+      notice: None,
+      backups,
+      disk: None,
+      conflict: None,
+      lock: None,
+      locked: None,
+      read_only: false,
+      config: Config::default(),
+      overrides: Overrides::default(),
+      auto_theme: theme::DARK,
+      config_watch: config::Watch::new(),
+      rates: rates::Feed::new(&[]),
+      quotes: quotes::Feed::new(&[]),
+      results,
+      control: None,
+      watched: Watched::default(),
+      history: History::default(),
+      share: None,
+      discard: None,
+    }
+  }
+  
+  /// Choose the theme to use when none is configured to suit the terminal.
+  /// This must be done before reading input; see `theme::detect`.
+  pub fn detect_theme(&mut self) {
+    if let Some(name) = theme::detect() {
+      self.auto_theme = name;
+    }
+  }
+  
+  pub fn set_overrides(&mut self, overrides: Overrides) {
+    self.overrides = overrides;
+  }
+  
+  pub fn set_config(&mut self, mut conf: Config) {
+    self.overrides.configure(&mut conf);
+    if let Err(err) = self.set_theme(conf.theme.as_deref().unwrap_or(self.auto_theme)) {
+      self.notice(tr(Message::Configuration), vec![err]);
     }
+    self.set_placement(conf.placement);
+    self.rates = rates::Feed::new(&conf.providers);
+    self.rates.refresh();
+    self.quotes = quotes::Feed::new(&conf.quotes);
+    self.config = conf;
+  }
+  
+  /// Change where results are drawn, which changes the width of the text.
+  fn set_placement(&mut self, placement: Placement) {
+    self.writer.set_placement(placement);
+    self.pos = self.text.set_width(self.writer.text_width());
+  }
+  
+  /// Switch to the named theme.
+  fn set_theme(&mut self, name: &str) -> Result<(), String> {
+    if self.writer.theme().name != name {
+      self.writer.set_theme(theme::load(name)?);
+    }
+    Ok(())
   }
   
   pub fn set_text(&mut self, text: String) {
-    self.text.set_text(text)
+    self.text.set_text(text);
+    self.pos = text::ZERO_POS;
+    self.watched = Watched::default(); // its lines are new
+    self.history = History::default();
+    self.history.touch();
+  }
+  
+  /// Open a document, replacing the current text, and record it in the
+  /// recently opened document list.
+  ///
+  /// If the document is encrypted, its passphrase is prompted for and kept
+  /// in memory so the document can be encrypted again when it is saved.
+  pub fn open(&mut self, doc: &path::Path) -> Result<(), error::Error> {
+    let prot = if document::is_encrypted(doc)? {
+      match prompt::secret(&fill(Message::PassphraseFor, &[&doc.display().to_string()]))? {
+        Some(pass) => Protection::Passphrase(pass),
+        None => return Ok(()), // cancelled; leave the current document open
+      }
+    }else{
+      Protection::None
+    };
+    let text = document::load_with_protection(doc, &prot)?;
+    log::info!("opened {}", doc.display());
+    self.disk = Some(Snapshot::new(doc, &text));
+    self.conflict = None;
+    self.doc = Some(doc.to_owned());
+    self.protection = prot;
+    self.imports = Imports::new_for_document(doc); // before results are stripped, which evaluates the document
+    let text = self.strip_results(&text);
+    self.set_text(text);
+    self.lock(doc);
+    self.recent.add(doc);
+    let _ = self.recent.save(); // failing to record history is not worth interrupting the user
+    self.discard = None;
+    Ok(())
+  }
+  
+  /// Open a document the user chose, unless that would discard unsaved
+  /// changes to the current one, in which case they're warned, and it's
+  /// only opened if they choose it again. Failing to open it is shown
+  /// rather than ending the session, which would lose the current one.
+  fn open_chosen(&mut self, doc: &path::Path) {
+    if self.discard.as_deref() != Some(doc) && self.is_dirty() {
+      self.discard = Some(doc.to_owned());
+      self.notice(&fill(Message::UnsavedChanges, &[&self.doc_name()]), vec![tr(Message::OpenAgainToDiscard).to_string()]);
+    }else if let Err(err) = self.open(doc) {
+      log::warn!("could not open {}: {}", doc.display(), err);
+      self.notice(&fill(Message::NotOpened, &[&doc.display().to_string()]), vec![err.to_string()]);
+    }
+  }
+  
+  /// Whether the document has changed since it was opened or last saved.
+  /// One which has never been saved has if there's anything in it.
+  fn is_dirty(&mut self) -> bool {
+    match self.disk.as_ref().map(|d| d.text.clone()) {
+      Some(saved) => self.stored_text() != saved,
+      None => !self.text.text().is_empty(),
+    }
+  }
+  
+  /// Lock a document which has been opened, so other instances don't edit
+  /// it too. If another instance holds the lock, the document is read-only
+  /// until it's decided what to do about that. Failing to lock it, as in a
+  /// directory we can't write, is not worth interrupting the user.
+  fn lock(&mut self, doc: &path::Path) {
+    self.lock = None;
+    self.locked = None;
+    self.read_only = false;
+    match lock::acquire(doc) {
+      Ok(Ok(lock)) => self.lock = Some(lock),
+      Ok(Err(holder)) => {
+        log::info!("{} is locked by process {} on {}", doc.display(), holder.pid, holder.host);
+        self.locked = Some(holder);
+        self.read_only = true;
+      },
+      Err(err) => log::warn!("could not lock {}: {}", doc.display(), err),
+    }
+  }
+  
+  /// Open a directory as a workspace, in which documents can reference the
+  /// variables of other documents, and offer its documents in the picker.
+  pub fn open_workspace(&mut self, dir: &path::Path) -> Result<(), error::Error> {
+    let ws = Workspace::open(dir)?;
+    self.picker = Some(Picker::new(&ws.docs()));
+    self.imports = Imports::new(dir);
+    self.workspace = Some(ws);
+    Ok(())
+  }
+  
+  /// Produce a context in which to evaluate the current document,
+  /// configured by its settings.
+  fn context(&mut self) -> exec::Context {
+    let mut cxt = match &mut self.workspace {
+      Some(ws) => {
+        let name = self.doc.as_deref().and_then(|d| ws.name(d)).map(|n| n.to_string());
+        ws.context(name.as_deref(), self.text.text())
+      },
+      None => exec::Context::new_with_stdlib(),
+    };
+    cxt.set_locale(self.config.locale.clone());
+    self.doc_settings().configure(&mut cxt);
+    for (symbol, price) in self.quotes.quotes() {
+      cxt.set_quote(symbol, *price);
+    }
+    cxt
+  }
+  
+  /// The settings of the current document, with those it doesn't set
+  /// taken from the configuration, and any overrides applied.
+  fn doc_settings(&self) -> Settings {
+    let mut defaults = self.config.defaults();
+    defaults.rates.extend_from_slice(self.rates.rates()); // after those configured, which take precedence
+    self.overrides.apply(Settings::parse(self.text.text()).or(&defaults))
+  }
+  
+  /// Produce the text of the status bar, if it is shown.
+  fn status(&self) -> Option<String> {
+    if !self.config.status.is_visible() {
+      return None;
+    }
+    let settings = self.doc_settings();
+    let mut doc = match &self.doc {
+      Some(path) => path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+      None => tr(Message::Untitled).to_string(),
+    };
+    if self.read_only {
+      doc = format!("{} ({})", doc, tr(Message::ReadOnly));
+    }
+    if let Some(session) = &self.share {
+      doc = format!("{} ({})", doc, fill(Message::Shared, &[&session.viewers().to_string()]));
+    }
+    let precision = match (settings.precision, settings.figures) {
+      (Some(n), _) => format!("{} dp", n),
+      (None, Some(n)) => format!("{} sf", n),
+      (None, None) => "auto".to_string(),
+    };
+    let rates_age = match self.rates.current() {
+      Some(current) => fill(if self.rates.is_stale() { Message::RatesStale }else{ Message::RatesFetched }, &[&current.provider, &rates::age(current.fetched)]),
+      None if self.rates.is_pending() => tr(Message::RatesFetching).to_string(),
+      None => tr(if settings.rates.is_empty() { Message::NoRates }else{ Message::Pinned }).to_string(),
+    };
+    let quotes_age = match self.quotes.current() {
+      Some(current) => fill(if self.quotes.is_stale() { Message::RatesStale }else{ Message::RatesFetched }, &[&current.provider, &rates::age(current.fetched)]),
+      None if self.quotes.is_pending() => tr(Message::QuotesFetching).to_string(),
+      None => tr(Message::NoQuotes).to_string(),
+    };
+    let mode = match self.mode {
+      Mode::Normal => tr(Message::ModeNormal),
+      Mode::Delete => tr(Message::ModeDelete),
+    };
+    Some(self.config.status.render(&status::Info{
+      mode,
+      cursor: (self.pos.y, self.pos.x),
+      doc: &doc,
+      rates_age: &rates_age,
+      quotes_age: &quotes_age,
+      precision: &precision,
+    }))
+  }
+  
+  /// Produce the text of the document as it is edited from its content on
+  /// disk, which may have results written into it.
+  fn strip_results(&mut self, text: &str) -> String {
+    match self.results.clone() {
+      Some(delim) => {
+        let cxt = self.context();
+        results::strip(cxt, text, &delim, &mut self.imports)
+      },
+      None => text.to_string(),
+    }
+  }
+  
+  /// Produce the content of the document to store on disk, with results
+  /// written into it if we're doing that.
+  fn stored_text(&mut self) -> String {
+    match self.results.clone() {
+      Some(delim) => {
+        let cxt = self.context();
+        results::annotate(cxt, self.text.text(), &delim, &mut self.imports)
+      },
+      None => self.text.text().to_string(),
+    }
+  }
+  
+  /// Display a notice over the document until the next key is pressed.
+  pub fn notice(&mut self, title: &str, mut lines: Vec<String>) {
+    lines.push(String::new());
+    lines.push(tr(Message::PressAnyKey).to_string());
+    self.notice = Some((title.to_string(), lines));
+  }
+  
+  /// Save the current document, if it has a path. If the document has been
+  /// changed by another program since we loaded it, it is not saved and the
+  /// conflict is raised instead.
+  pub fn save(&mut self) -> Result<(), error::Error> {
+    if self.lock.as_ref().is_some_and(|l| !l.is_held()) {
+      self.lock = None;
+      self.read_only = true;
+      self.notice(&fill(Message::OpenElsewhere, &[&self.doc_name()]), vec![tr(Message::TakenOver).to_string()]);
+      return Ok(());
+    }
+    if self.read_only {
+      self.notice(&fill(Message::OpenElsewhere, &[&self.doc_name()]), vec![tr(Message::ReadOnlyHelp).to_string()]);
+      return Ok(());
+    }
+    if let (Some(doc), Some(disk)) = (&self.doc, &self.disk) {
+      if let Some(theirs) = disk.differs(doc, &self.protection)? {
+        self.conflict = Some(theirs);
+        return Ok(());
+      }
+    }
+    self.write()
+  }
+  
+  /// Show that the document couldn't be saved, as when its directory can't
+  /// be written or the disk is full, rather than ending the session, which
+  /// would lose what was to be saved.
+  fn not_saved(&mut self, err: error::Error) {
+    log::warn!("could not save {}: {}", self.doc_name(), err);
+    self.notice(&fill(Message::NotSaved, &[&self.doc_name()]), vec![err.to_string()]);
+  }
+  
+  /// Save the current document unconditionally.
+  fn write(&mut self) -> Result<(), error::Error> {
+    if let Some(doc) = self.doc.clone() {
+      let text = self.stored_text();
+      document::save(&doc, &text, &self.protection, self.backups.as_ref())?;
+      log::info!("saved {}", doc.display());
+      self.disk = Some(Snapshot::new(&doc, &text));
+    }
+    self.imports.reload(); // pick up changes to imported files
+    if let Some(ws) = &mut self.workspace {
+      ws.reload()?; // and to other documents in the workspace
+    }
+    Ok(())
+  }
+  
+  /// Prompt for a new passphrase with which the document will be encrypted
+  /// the next time it is saved. One which is empty, or isn't confirmed, is
+  /// prompted for again, saying why, until it's given or the prompt is
+  /// cancelled.
+  pub fn encrypt(&mut self) -> Result<(), error::Error> {
+    let mut ask = tr(Message::NewPassphrase).to_string();
+    loop {
+      let pass = match prompt::secret(&ask)? {
+        Some(pass) => pass,
+        None => return Ok(()),
+      };
+      if pass.is_empty() {
+        ask = format!("{}. {}", tr(Message::EmptyPassphrase), tr(Message::NewPassphrase));
+        continue;
+      }
+      match prompt::secret(tr(Message::ConfirmPassphrase))? {
+        Some(confirm) if confirm == pass => {
+          self.protection = Protection::Passphrase(pass);
+          return Ok(());
+        },
+        Some(_) => ask = format!("{}. {}", tr(Message::PassphraseMismatch), tr(Message::NewPassphrase)),
+        None => return Ok(()),
+      };
+    }
+  }
+  
+  /// Evaluate the document, producing each paragraph along with its results.
+  fn results(&mut self) -> Vec<(String, String)> {
+    let mut cxt = self.context();
+    let imports = &mut self.imports;
+    self.text.paragraphs().map(|(l, _)| {
+      let failed = rdl::import(imports, &mut cxt, l);
+      let (_, res) = rdl::render_with_options(&mut cxt, l, 0, 0, None, None);
+      (l.to_string(), failed.map_or(res.text().to_string(), |err| err.to_string()))
+    }).collect()
   }
   
-  pub fn key(&mut self) -> crossterm::Result<bool> {
-    let evt = self.reader.read_key()?;
-    let op = match self.mode {
+  /// Determine the paragraph that contains the provided visual line.
+  fn paragraph_for_line(&self, y: usize) -> usize {
+    let mut n = 0;
+    for (i, (_, lines)) in self.text.paragraphs().enumerate() {
+      n += lines;
+      if y < n {
+        return i;
+      }
+    }
+    usize::MAX // past the end of the text
+  }
+  
+  /// Confirm that paragraphs were copied by flashing them, or show why they
+  /// couldn't be, as when there's no clipboard to reach and copying through
+  /// the terminal is off.
+  fn copied(&mut self, res: io::Result<()>, paragraphs: ops::RangeInclusive<usize>) {
+    match res {
+      Ok(()) => {
+        let last = self.text.paragraphs().count().saturating_sub(1); // a selection may run past the end
+        self.watched.flash((*paragraphs.start()..=last.min(*paragraphs.end())).collect());
+      },
+      Err(err) => {
+        log::warn!("could not copy: {}", err);
+        let mut lines = vec![err.to_string()];
+        if !self.config.osc52 {
+          lines.push(tr(Message::NoClipboard).to_string());
+        }
+        self.notice(tr(Message::NotCopied), lines);
+      },
+    }
+  }
+  
+  fn copy_result(&mut self) {
+    let res = self.results();
+    let p = self.paragraph_for_line(self.pos.y);
+    if let Some((_, result)) = res.get(p) {
+      self.copied(clipboard::copy(result), p..=p);
+    }
+  }
+  
+  fn copy_line_with_result(&mut self) {
+    let res = self.results();
+    let p = self.paragraph_for_line(self.pos.y);
+    if let Some((line, result)) = res.get(p) {
+      self.copied(clipboard::copy(&copy::line_with_result(line, result)), p..=p);
+    }
+  }
+  
+  /// Determine the paragraphs that are selected or, if there is no
+  /// selection, the paragraph under the cursor.
+  fn selected_paragraphs(&self) -> (usize, usize) {
+    let (start, end) = match self.text.selection() {
+      Some(sel) => (self.text.index(sel.start).y, self.text.index(sel.end).y),
+      None => (self.pos.y, self.pos.y),
+    };
+    (self.paragraph_for_line(start), self.paragraph_for_line(end))
+  }
+  
+  fn copy_selection_as_markdown(&mut self) {
+    let (start, end) = self.selected_paragraphs();
+    let rows: Vec<(String, String)> = self.results().into_iter()
+      .enumerate()
+      .filter(|(i, _)| *i >= start && *i <= end)
+      .map(|(_, r)| r)
+      .collect();
+    if !rows.is_empty() {
+      self.copied(clipboard::copy(&copy::markdown(&rows)), start..=end);
+    }
+  }
+  
+  fn copy_selection_as_snippet(&mut self) {
+    let (start, end) = self.selected_paragraphs();
+    let cxt = self.context();
+    let text: Vec<&str> = self.text.paragraphs().map(|(l, _)| l).collect();
+    let snip = Snippet::new(cxt, &text.join("\n"), start..end.saturating_add(1), &mut self.imports);
+    self.copied(clipboard::copy(&snip.ansi()), start..=end);
+  }
+  
+  fn copy_selection_formatted(&mut self) {
+    let (start, end) = self.selected_paragraphs();
+    let cxt = self.context();
+    let text: Vec<&str> = self.text.paragraphs().map(|(l, _)| l).collect();
+    let snip = Snippet::new(cxt, &text.join("\n"), start..end.saturating_add(1), &mut self.imports);
+    self.copied(clipboard::copy_rtf(&snip.rtf(), &snip.plain()), start..=end);
+  }
+  
+  /// Determine whether the document has been changed by another program,
+  /// raising a conflict if it has. Problems reading the document are left
+  /// for the next save to report.
+  fn detect_changes(&mut self) -> bool {
+    let (doc, disk) = match (&self.doc, &mut self.disk) {
+      (Some(doc), Some(disk)) => (doc, disk),
+      _ => return false,
+    };
+    if self.conflict.is_some() || !disk.touched(doc) {
+      return false;
+    }
+    match disk.differs(doc, &self.protection) {
+      Ok(Some(theirs)) => {
+        log::info!("{} changed on disk", doc.display());
+        self.conflict = Some(theirs);
+      },
+      _ => disk.touch(doc),
+    }
+    self.conflict.is_some()
+  }
+  
+  /// Take exchange rates once they are fetched, and fetch them again when
+  /// they are no longer current. Failures are shown. Produces whether
+  /// anything arrived.
+  fn receive_rates(&mut self) -> bool {
+    let pending = self.rates.is_pending();
+    if let Some(errs) = self.rates.receive() {
+      self.notice(tr(Message::ExchangeRates), errs);
+    }
+    let arrived = pending && !self.rates.is_pending();
+    self.rates.refresh();
+    arrived
+  }
+  
+  /// Take quotes once they are fetched, and fetch them again when they are
+  /// no longer current or the document needs others. Failures are shown.
+  /// Produces whether anything arrived.
+  fn receive_quotes(&mut self) -> bool {
+    if !self.quotes.is_configured() {
+      return false;
+    }
+    let pending = self.quotes.is_pending();
+    if let Some(errs) = self.quotes.receive() {
+      self.notice(tr(Message::Quotes), errs);
+    }
+    let arrived = pending && !self.quotes.is_pending();
+    self.quotes.want(rdl::quote_symbols(self.text.text()));
+    self.quotes.refresh();
+    arrived
+  }
+  
+  /// See whether the values of watched lines have changed, and flash
+  /// those which have. Lines which ask to be notified are, unless the
+  /// change was made by typing, when it's already being looked at.
+  fn check_watched(&mut self, typed: bool) {
+    if !self.text.paragraphs().any(|(l, _)| rdl::line_watch(l).is_some()) {
+      self.watched.forget(); // nothing to evaluate, which is most of the time
+      self.writer.set_flash(self.watched.flashing());
+      return;
+    }
+    let cxt = self.context();
+    let changes = self.watched.update(cxt, &mut self.imports, self.text.paragraphs().map(|(l, _)| l));
+    for change in changes.iter().filter(|c| c.watch == Watch::Notify && !typed) {
+      notify::send(&fill(Message::ValueChanged, &[&change.expr]), &format!("{} → {}", change.before, change.after));
+    }
+    self.writer.set_flash(self.watched.flashing());
+  }
+  
+  /// Record what each line evaluates to now, as one of its earlier results
+  /// once it changes.
+  fn record_history(&mut self) {
+    let rows = self.results();
+    self.history.record(&rows, time::SystemTime::now());
+  }
+  
+  /// Show what the line the cursor is on evaluated to before, most recent
+  /// first, along with how it was written if it was written differently.
+  fn show_earlier_results(&mut self) {
+    self.record_history(); // so that the current result isn't listed as an earlier one
+    let n = self.paragraph_for_line(self.pos.y);
+    let current = self.text.paragraphs().nth(n).map(|(l, _)| l.to_string()).unwrap_or_default();
+    let mut lines: Vec<String> = self.history.earlier(n).map(|e| {
+      let ago = fill(Message::Ago, &[&rates::age(e.at)]);
+      if e.text == current {
+        format!("{}  {}", e.result, ago)
+      }else{
+        format!("{}  {}  ({})", e.result, ago, e.text.trim())
+      }
+    }).collect();
+    if lines.is_empty() {
+      lines.push(tr(Message::NoEarlierResults).to_string());
+    }
+    self.notice(tr(Message::EarlierResults), lines);
+  }
+  
+  /// Receive requests from other processes, such as `resolver --send`,
+  /// from now on. Only one editor receives them; any started after it
+  /// doesn't.
+  pub fn listen(&mut self) -> io::Result<()> {
+    self.control = control::listen()?;
+    Ok(())
+  }
+  
+  /// Share the session read-only with anyone on the local network, on a
+  /// port. Produces the address it can be watched at.
+  pub fn share(&mut self, port: u16) -> io::Result<String> {
+    let session = share::Session::start(port)?;
+    let url = session.url();
+    log::info!("sharing the session at {}", url);
+    self.share = Some(session);
+    self.publish();
+    Ok(url)
+  }
+  
+  /// Send the document as it is now rendered to those watching it, if
+  /// it's shared.
+  fn publish(&mut self) {
+    if self.share.is_none() {
+      return;
+    }
+    let mut cxt = self.context();
+    let rows = Snippet::evaluate(&mut cxt, self.text.text(), 0..usize::MAX, &mut self.imports).html_rows();
+    let mut y = 0;
+    let cursor = self.text.paragraphs().position(|(_, n)| {
+      y += n;
+      y > self.pos.y
+    }).unwrap_or(rows.len().saturating_sub(1));
+    if let Some(session) = &self.share {
+      session.publish(rows, cursor);
+    }
+  }
+  
+  /// Answer requests from other processes. Expressions are evaluated
+  /// after the document, so they see what it defines. Produces whether a
+  /// document was opened.
+  fn receive_requests(&mut self) -> bool {
+    let mut opened = false;
+    while let Some(received) = self.control.as_ref().and_then(|c| c.receive()) {
+      let reply = match &received.request {
+        Request::Open(_) if self.is_dirty() => Err(format!("{} has unsaved changes; save it first", self.doc_name())),
+        Request::Open(doc) => match self.open(doc) {
+          Ok(()) => {
+            opened = true;
+            Ok(format!("opened {}", doc.display()))
+          },
+          Err(err) => Err(format!("{}: {}", doc.display(), err)),
+        },
+        Request::Eval(expr) => {
+          let mut cxt = self.context();
+          for (line, _) in self.text.paragraphs() {
+            rdl::import(&mut self.imports, &mut cxt, line); // a file which can't be imported is shown where the line is drawn
+            let _ = rdl::eval(&mut cxt, line);
+          }
+          oneshot::eval(&mut cxt, expr).map_err(|err| err.to_string())
+        },
+      };
+      received.reply(reply);
+    }
+    opened
+  }
+  
+  /// Apply changes to the configuration file. Problems with it are shown,
+  /// as are changes which can't be applied until the next startup.
+  fn reload_config(&mut self) -> bool {
+    let (mut conf, probs) = match self.config_watch.changed() {
+      Some(changed) => changed,
+      None => return false,
+    };
+    self.overrides.configure(&mut conf);
+    log::info!("reloaded the configuration");
+    for prob in &probs {
+      log::warn!("{}", prob);
+    }
+    let mut lines: Vec<String> = probs.iter().map(|p| p.to_string()).collect();
+    if !self.config.is_live(&conf) {
+      lines.push(tr(Message::RestartNeeded).to_string());
+    }
+    if self.config.theme != conf.theme {
+      if let Err(err) = self.set_theme(conf.theme.as_deref().unwrap_or(self.auto_theme)) {
+        lines.push(err);
+      }
+    }
+    if self.config.ambiguous_width != conf.ambiguous_width {
+      width::set_ambiguous(conf.ambiguous_width.unwrap_or_else(width::detect));
+      self.pos = self.text.set_width(self.writer.text_width()); // rewrap
+    }
+    if self.config.language != conf.language {
+      i18n::set_language(conf.language.unwrap_or_else(i18n::detect));
+    }
+    if self.config.osc52 != conf.osc52 {
+      clipboard::set_osc52(conf.osc52);
+    }
+    if self.config.placement != conf.placement {
+      self.set_placement(conf.placement);
+    }
+    self.config = conf;
+    if !lines.is_empty() {
+      self.notice(tr(Message::Configuration), lines);
+    }
+    true
+  }
+  
+  fn key_conflict(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let theirs = match self.conflict.take() {
+      Some(theirs) => theirs,
+      None => return Ok(true),
+    };
+    let doc = match &self.doc {
+      Some(doc) => doc.clone(),
+      None => return Ok(true),
+    };
+    match evt.code {
+      event::KeyCode::Char('r') => {
+        let text = self.strip_results(&theirs);
+        self.set_text(text);
+        self.disk = Some(Snapshot::new(&doc, &theirs));
+      },
+      event::KeyCode::Char('m') => {
+        let saved = self.disk.as_ref().map(|d| d.text.clone()).unwrap_or_default();
+        let (base, stripped) = (self.strip_results(&saved), self.strip_results(&theirs));
+        let (merged, _) = diff::merge(&base, self.text.text(), &stripped);
+        self.set_text(merged);
+        self.disk = Some(Snapshot::new(&doc, &theirs));
+      },
+      event::KeyCode::Char('o') => if let Err(err) = self.write() {
+        self.not_saved(err);
+      },
+      event::KeyCode::Esc => {
+        if let Some(disk) = &mut self.disk {
+          disk.touch(&doc); // don't raise this again until it changes again or we save
+        }
+      },
+      _ => self.conflict = Some(theirs), // keep waiting for a decision
+    };
+    Ok(true)
+  }
+  
+  /// The name of the document, as notices about it refer to it.
+  fn doc_name(&self) -> String {
+    match self.doc.as_ref().and_then(|d| d.file_name()) {
+      Some(name) => name.to_string_lossy().to_string(),
+      None => tr(Message::TheDocument).to_string(),
+    }
+  }
+  
+  fn key_locked(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let holder = match self.locked.take() {
+      Some(holder) => holder,
+      None => return Ok(true),
+    };
+    match (evt.code, &self.doc) {
+      (event::KeyCode::Char('r') | event::KeyCode::Esc, _) => {}, // read-only already
+      (event::KeyCode::Char('t'), Some(doc)) => {
+        log::info!("took over {} from process {} on {}", doc.display(), holder.pid, holder.host);
+        self.lock = Some(lock::take_over(doc)?);
+        self.read_only = false;
+      },
+      _ => self.locked = Some(holder), // keep waiting for a decision
+    };
+    Ok(true)
+  }
+  
+  fn draw_locked(&mut self) -> Result<(), error::Error> {
+    let holder = match &self.locked {
+      Some(holder) => holder,
+      None => return Ok(()),
+    };
+    let name = self.doc_name();
+    let lines = vec![
+      fill(Message::EditedElsewhere, &[&holder.pid.to_string(), &holder.host]),
+      String::new(),
+      format!("r    {}", tr(Message::LockReadOnly)),
+      format!("t    {}", tr(Message::LockTakeOver)),
+    ];
+    self.writer.draw_notice(&fill(Message::OpenElsewhere, &[&name]), &lines)
+  }
+  
+  fn draw_conflict(&mut self) -> Result<(), error::Error> {
+    let name = self.doc_name();
+    let lines = vec![
+      tr(Message::ModifiedElsewhere).to_string(),
+      String::new(),
+      format!("r    {}", tr(Message::ConflictReload)),
+      format!("m    {}", tr(Message::ConflictMerge)),
+      format!("o    {}", tr(Message::ConflictOverwrite)),
+      format!("Esc  {}", tr(Message::ConflictKeep)),
+    ];
+    self.writer.draw_notice(&fill(Message::ChangedOnDisk, &[&name]), &lines)
+  }
+  
+  /// The operation performed by movements in the current mode.
+  fn operation(&self) -> Operation {
+    match self.mode {
       Mode::Normal => Operation::Move,
       Mode::Delete => Operation::Delete,
+    }
+  }
+  
+  /// Run a command. Produces false if the editor should exit.
+  fn run(&mut self, cmd: Command) -> Result<bool, error::Error> {
+    crash::record(cmd.name());
+    match cmd {
+      Command::Open => {
+        self.picker = Some(match &self.workspace {
+          Some(ws) => Picker::new(&ws.docs()),
+          None => Picker::new(self.recent.docs()),
+        });
+      },
+      Command::Save => if let Err(err) = self.save() {
+        self.not_saved(err);
+      },
+      Command::Quit => return Ok(false),
+      Command::Settings => self.settings = Some(Panel::new(Settings::parse(self.text.text()))),
+      Command::CopyResult => self.copy_result(),
+      Command::CopyLine => self.copy_line_with_result(),
+      Command::CopyMarkdown => self.copy_selection_as_markdown(),
+      Command::CopySnippet => self.copy_selection_as_snippet(),
+      Command::CopyFormatted => self.copy_selection_formatted(),
+      Command::Palette => self.palette = Some(Palette::new(Command::all(&theme::names()))),
+      Command::RefreshRates => if self.rates.is_configured() || self.quotes.is_configured() {
+        if self.rates.is_configured() {
+          self.rates.refresh_now();
+        }
+        if self.quotes.is_configured() {
+          self.quotes.refresh_now();
+        }
+      }else{
+        self.notice(tr(Message::ExchangeRates), vec![tr(Message::NoProviders).to_string()]);
+      },
+      Command::ToggleStats => self.writer.show_stats(!self.writer.shows_stats()),
+      Command::ToggleOutline => self.toggle_outline(),
+      Command::EarlierResults => self.show_earlier_results(),
+      Command::ShowLog => {
+        let lines = match logging::tail(LOG_LINES) {
+          Ok(lines) if lines.is_empty() => vec![tr(Message::NothingLogged).to_string()],
+          Ok(lines) => lines,
+          Err(err) => vec![err.to_string()],
+        };
+        self.notice(tr(Message::Log), lines);
+      },
+      Command::Keybindings => {
+        let lines = self.config.keymap.describe();
+        self.notice(tr(Message::Keybindings), lines);
+      },
+      Command::DeleteMode => self.mode = Mode::Delete,
+      Command::StartOfWord => self.pos = self.text.edit_rel(Action::new(Movement::StartOfWord, self.operation())),
+      Command::EndOfWord => self.pos = self.text.edit_rel(Action::new(Movement::EndOfWord, self.operation())),
+      Command::NextWord => self.pos = self.text.edit_rel(Action::new(Movement::Word, self.operation())),
+      Command::Theme(name) => if let Err(err) = self.set_theme(&name) {
+        self.notice(tr(Message::Theme), vec![err]);
+      },
+    };
+    Ok(true)
+  }
+  
+  /// Show the outline with focus, give it focus if it's shown without,
+  /// or hide it if it has focus already.
+  fn toggle_outline(&mut self) {
+    match &mut self.outline {
+      Some(outline) if !outline.is_focused() => outline.focus(true),
+      Some(_) => self.outline = None,
+      None => {
+        self.outline = Some(Outline::new());
+        self.update_outline(); // selecting the entry the cursor is under
+        if let Some(outline) = &mut self.outline {
+          outline.focus(true);
+        }
+      },
+    };
+    self.writer.show_outline(self.outline.is_some());
+    self.pos = self.text.set_width(self.writer.text_width());
+  }
+  
+  /// List the document as it is now in the outline, if it's shown.
+  fn update_outline(&mut self) {
+    if self.outline.is_none() {
+      return;
+    }
+    let cxt = self.context();
+    let entries = outline::build(cxt, &mut self.imports, self.text.paragraphs().map(|(l, _)| l));
+    let cursor = self.paragraph_for_line(self.pos.y);
+    if let Some(outline) = &mut self.outline {
+      outline.update(entries, cursor);
+    }
+  }
+  
+  fn key_outline(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let outline = match &mut self.outline {
+      Some(outline) => outline,
+      None => return Ok(true),
+    };
+    match evt.code {
+      event::KeyCode::Esc => outline.focus(false),
+      event::KeyCode::Up => outline.up(),
+      event::KeyCode::Down => outline.down(),
+      event::KeyCode::Enter => {
+        let line = outline.selected().map(|e| e.line);
+        outline.focus(false);
+        if let Some(line) = line {
+          self.text.select(None, false);
+          self.pos = self.text.paragraph_rel(line);
+        }
+      },
+      _ => if let Some(cmd) = self.config.keymap.lookup(&evt).cloned() {
+        return self.run(cmd); // as toggling the outline, to hide it
+      },
+    };
+    Ok(true)
+  }
+  
+  fn key_palette(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let palette = match &mut self.palette {
+      Some(palette) => palette,
+      None => return Ok(true),
     };
     match evt {
       event::KeyEvent{
-        code: event::KeyCode::Char('q'),
-        modifiers: event::KeyModifiers::CONTROL,
+        code: event::KeyCode::Esc,
         ..
-      } => return Ok(false),
-      
+      } => self.palette = None,
       event::KeyEvent{
-        code: event::KeyCode::Char('d'),
+        code: event::KeyCode::Char('p'),
         modifiers: event::KeyModifiers::CONTROL,
         ..
+      } => self.palette = None,
+      event::KeyEvent{
+        code: event::KeyCode::Up,
+        ..
+      } => palette.up(),
+      event::KeyEvent{
+        code: event::KeyCode::Down,
+        ..
+      } => palette.down(),
+      event::KeyEvent{
+        code: event::KeyCode::Backspace,
+        ..
+      } => palette.pop(),
+      event::KeyEvent{
+        code: event::KeyCode::Char(v),
+        modifiers: event::KeyModifiers::NONE | event::KeyModifiers::SHIFT,
+        ..
+      } => palette.push(v),
+      event::KeyEvent{
+        code: event::KeyCode::Enter,
+        ..
       } => {
-        self.mode = Mode::Delete;
+        let cmd = palette.selected().cloned();
+        self.palette = None;
+        if let Some(cmd) = cmd {
+          return self.run(cmd);
+        }
+      },
+      _ => {},
+    };
+    Ok(true)
+  }
+  
+  fn key_settings(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let panel = match &mut self.settings {
+      Some(panel) => panel,
+      None => return Ok(true),
+    };
+    match evt.code {
+      event::KeyCode::Esc | event::KeyCode::Enter => {
+        self.settings = None;
         return Ok(true);
       },
-      
+      event::KeyCode::Up => panel.up(),
+      event::KeyCode::Down => panel.down(),
+      event::KeyCode::Left => panel.prev(),
+      event::KeyCode::Right | event::KeyCode::Char(' ') => panel.next(),
+      _ => return Ok(true),
+    };
+    let text = panel.settings().apply(self.text.text());
+    if text != self.text.text() {
+      self.set_text(text); // results are evaluated again when we next draw
+    }
+    Ok(true)
+  }
+  
+  fn key_picker(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    let picker = match &mut self.picker {
+      Some(picker) => picker,
+      None => return Ok(true),
+    };
+    match evt {
+      event::KeyEvent{
+        code: event::KeyCode::Esc,
+        ..
+      } => self.picker = None,
+      event::KeyEvent{
+        code: event::KeyCode::Char('o'),
+        modifiers: event::KeyModifiers::CONTROL,
+        ..
+      } => self.picker = None,
+      event::KeyEvent{
+        code: event::KeyCode::Up,
+        ..
+      } => picker.up(),
+      event::KeyEvent{
+        code: event::KeyCode::Down,
+        ..
+      } => picker.down(),
+      event::KeyEvent{
+        code: event::KeyCode::Backspace,
+        ..
+      } => picker.pop(),
+      event::KeyEvent{
+        code: event::KeyCode::Char(v),
+        modifiers: event::KeyModifiers::NONE | event::KeyModifiers::SHIFT,
+        ..
+      } => picker.push(v),
+      event::KeyEvent{
+        code: event::KeyCode::Enter,
+        ..
+      } => {
+        let doc = picker.selected().map(|e| e.path.clone());
+        self.picker = None;
+        if let Some(doc) = doc {
+          self.open_chosen(&doc);
+        }
+      },
+      _ => {},
+    };
+    Ok(true)
+  }
+  
+  pub fn key(&mut self, evt: event::KeyEvent) -> Result<bool, error::Error> {
+    if self.notice.is_some() {
+      self.notice = None; // any key dismisses the notice
+      return Ok(true);
+    }
+    if self.locked.is_some() {
+      return self.key_locked(evt);
+    }
+    if self.conflict.is_some() {
+      return self.key_conflict(evt);
+    }
+    if self.picker.is_some() {
+      return self.key_picker(evt);
+    }
+    if self.settings.is_some() {
+      return self.key_settings(evt);
+    }
+    if self.palette.is_some() {
+      return self.key_palette(evt);
+    }
+    if self.outline.as_ref().is_some_and(|o| o.is_focused()) {
+      return self.key_outline(evt);
+    }
+    let op = self.operation();
+    let select = evt.modifiers == event::KeyModifiers::SHIFT && matches!(evt.code,
+      event::KeyCode::Left | event::KeyCode::Right | event::KeyCode::Up | event::KeyCode::Down |
+      event::KeyCode::Home | event::KeyCode::End
+    );
+    if select {
+      let mvmt = match evt.code {
+        event::KeyCode::Left  => Movement::Left,
+        event::KeyCode::Right => Movement::Right,
+        event::KeyCode::Up    => Movement::Up,
+        event::KeyCode::Down  => Movement::Down,
+        event::KeyCode::Home  => Movement::StartOfLine,
+        _                     => Movement::EndOfLine,
+      };
+      self.pos = self.text.edit_rel(Action::new(mvmt, Operation::Select));
+      return Ok(true);
+    }
+    if let Some(cmd) = self.config.keymap.lookup(&evt).cloned() {
+      if !cmd.uses_selection() {
+        self.text.select(None, false);
+      }
+      if cmd != Command::DeleteMode {
+        self.mode = Mode::Normal; // mode resets after any other command
+      }
+      return self.run(cmd);
+    }
+    self.text.select(None, false); // any other key clears the selection
+    match evt {
       event::KeyEvent{
         code: event::KeyCode::Left,
         modifiers: event::KeyModifiers::NONE,
@@ -92,21 +1160,6 @@ impl Editor {
         ..
       } => self.pos = self.text.edit_rel(Action::new(Movement::EndOfLine, op)),
 
-      event::KeyEvent{
-        code: event::KeyCode::Char('b'),
-        modifiers: event::KeyModifiers::CONTROL,
-        ..
-      } => self.pos = self.text.edit_rel(Action::new(Movement::StartOfWord, op)),
-      event::KeyEvent{
-        code: event::KeyCode::Char('e'),
-        modifiers: event::KeyModifiers::CONTROL,
-        ..
-      } => self.pos = self.text.edit_rel(Action::new(Movement::EndOfWord, op)),
-      event::KeyEvent{
-        code: event::KeyCode::Char('w'),
-        modifiers: event::KeyModifiers::CONTROL,
-        ..
-      } => self.pos = self.text.edit_rel(Action::new(Movement::Word, op)),
       
       event::KeyEvent{
         code: event::KeyCode::Backspace,
@@ -155,13 +1208,80 @@ impl Editor {
   }
   
   pub fn draw(&mut self) -> Result<bool, error::Error> {
-    self.writer.refresh(&self.pos, &self.text)?;
+    let cxt = self.context();
+    let status = self.status();
+    self.writer.set_cursor(match self.mode {
+      Mode::Normal => self.config.cursor.normal,
+      Mode::Delete => self.config.cursor.delete,
+    });
+    self.update_outline();
+    self.writer.refresh(&self.pos, &self.text, &mut self.imports, cxt, status.as_deref(), self.outline.as_ref())?;
+    if let Some(picker) = &self.picker {
+      self.writer.draw_picker(picker)?;
+    }
+    if let Some(palette) = &self.palette {
+      self.writer.draw_palette(palette, &self.config.keymap)?;
+    }
+    if let Some(panel) = &self.settings {
+      self.writer.draw_notice(tr(Message::Settings), &panel.lines())?;
+    }
+    if let Some((title, lines)) = &self.notice {
+      self.writer.draw_notice(title, lines)?;
+    }
+    if self.conflict.is_some() {
+      self.draw_conflict()?;
+    }
+    if self.locked.is_some() {
+      self.draw_locked()?;
+    }
+    self.publish();
     Ok(true)
   }
   
+  /// Insert pasted text at the cursor, cleaned of anything which could
+  /// control the terminal or disguise what the text computes. Pastes are
+  /// ignored while a panel or prompt is open.
+  pub fn paste(&mut self, text: &str) {
+    let busy = self.notice.is_some() || self.locked.is_some() || self.conflict.is_some() ||
+      self.picker.is_some() || self.settings.is_some() || self.palette.is_some() || self.outline.as_ref().is_some_and(|o| o.is_focused());
+    if busy {
+      return;
+    }
+    self.text.select(None, false);
+    self.pos = self.text.insert_str_rel(&sanitize::clean(text));
+    self.mode = Mode::Normal;
+  }
+  
+  /// Handle a key or paste if one comes soon; see `handle`.
   pub fn step(&mut self) -> Result<bool, error::Error> {
-    let res = self.key()?;
-    self.draw()?;
+    let evt = self.reader.poll(TICK)?;
+    self.handle(evt)
+  }
+  
+  /// Handle a key or paste, if there was one, and anything that happened
+  /// in the meantime, such as rates arriving from the background; the
+  /// editor is drawn again only if something changed. Produces false if
+  /// the editor should exit.
+  pub fn handle(&mut self, evt: Option<event::Event>) -> Result<bool, error::Error> {
+    let (res, pressed) = match evt {
+      Some(event::Event::Key(evt)) => (self.key(evt)?, true),
+      Some(event::Event::Paste(text)) => {
+        self.paste(&text);
+        (true, true)
+      },
+      _ => (true, false),
+    };
+    if pressed {
+      crash::track(self.doc.as_deref(), self.text.text(), &self.protection);
+      self.history.touch();
+    }else if self.history.is_edited() {
+      self.record_history(); // once typing pauses
+    }
+    let changed = self.detect_changes() | self.reload_config() | self.receive_rates() | self.receive_quotes() | self.receive_requests() | self.watched.expire();
+    if pressed || changed {
+      self.check_watched(pressed);
+      self.draw()?;
+    }
     Ok(res)
   }
 }
@@ -0,0 +1,183 @@
+use crate::rdl::{self, exec};
+use crate::rdl::import::Imports;
+use crate::document::{sections, settings};
+use crate::text::width;
+use crate::i18n::{tr, Message};
+
+/// What a line of the document is listed in the outline as.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+  Heading,  // a comment, as in `# Income`
+  Variable, // an assignment, as in `rent = 1200 EUR`
+  Total,    // a result worked out from variables, as in `rent + food`
+}
+
+/// A line of the document listed in the outline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+  pub kind: Kind,
+  pub name: String,
+  pub value: Option<String>,
+  pub line: usize, // the paragraph it is
+}
+
+/// List the headings, variables, and totals of a document, in order.
+/// Comments which are settings or only tag a section's style aren't
+/// headings, and results which don't depend on any variable aren't totals.
+pub fn build<'a>(mut cxt: exec::Context, imports: &mut Imports, lines: impl Iterator<Item = &'a str>) -> Vec<Entry> {
+  let mut entries = Vec::new();
+  for (i, line) in lines.enumerate() {
+    rdl::import(imports, &mut cxt, line); // a file which can't be imported is shown where the line is drawn
+    if rdl::is_comment(line) {
+      if let Some(name) = heading(line) {
+        entries.push(Entry{kind: Kind::Heading, name, value: None, line: i});
+      }
+      continue;
+    }
+    for work in rdl::render_work(&mut cxt, line) {
+      let (kind, name) = match work.output.expr.assigns() {
+        Some(name) => (Kind::Variable, name.to_string()),
+        None if work.steps.iter().any(|s| matches!(s, exec::Step::Variable(..) | exec::Step::Reference(..) | exec::Step::Quote(..))) => (Kind::Total, line[work.output.range.clone()].trim().to_string()),
+        None => continue,
+      };
+      entries.push(Entry{kind, name, value: Some(work.output.text), line: i});
+    }
+  }
+  entries
+}
+
+/// The text of a heading, without the comment marker or a style tag.
+fn heading(line: &str) -> Option<String> {
+  if settings::is_setting(line) {
+    return None;
+  }
+  let text = line.trim_start().trim_start_matches(rdl::COMMENT);
+  let text = match sections::tag(line) {
+    Some(_) => text.split('[').next().unwrap_or(""),
+    None => text,
+  };
+  Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// The outline panel, which lists the headings, variables, and totals of
+/// the document beside it. While it has focus, the keys move its
+/// selection and enter moves the cursor to the line selected; otherwise
+/// the entry the cursor is under is selected as it moves.
+pub struct Outline {
+  entries: Vec<Entry>,
+  sel: usize,
+  focused: bool,
+}
+
+impl Outline {
+  pub fn new() -> Outline {
+    Outline{
+      entries: Vec::new(),
+      sel: 0,
+      focused: false,
+    }
+  }
+
+  pub fn selected(&self) -> Option<&Entry> {
+    self.entries.get(self.sel)
+  }
+
+  pub fn is_focused(&self) -> bool {
+    self.focused
+  }
+
+  pub fn focus(&mut self, focused: bool) {
+    self.focused = focused;
+  }
+
+  /// Replace the entries listed, as the document has them now, and the
+  /// paragraph the cursor is on, which selects the entry it is under
+  /// unless the panel has focus.
+  pub fn update(&mut self, entries: Vec<Entry>, cursor: usize) {
+    self.entries = entries;
+    if !self.focused {
+      self.sel = self.entries.iter().rposition(|e| e.line <= cursor).unwrap_or(0);
+    }
+    self.sel = self.sel.min(self.entries.len().saturating_sub(1));
+  }
+
+  pub fn up(&mut self) {
+    self.sel = self.sel.saturating_sub(1);
+  }
+
+  pub fn down(&mut self) {
+    if self.sel + 1 < self.entries.len() {
+      self.sel += 1;
+    }
+  }
+
+  /// Describe as many entries as fit in some rows, each fit to a width,
+  /// scrolled so the selected entry is among them. Produces the row it is
+  /// on as well.
+  pub fn lines(&self, width: usize, rows: usize) -> (Vec<(String, Kind)>, usize) {
+    if self.entries.is_empty() {
+      return (vec![(tr(Message::NoOutline).to_string(), Kind::Heading)], usize::MAX);
+    }
+    let first = if self.sel >= rows { self.sel + 1 - rows } else { 0 };
+    let lines = self.entries.iter().skip(first).take(rows).map(|e| {
+      let text = match (&e.kind, &e.value) {
+        (Kind::Heading, _) | (_, None) => e.name.clone(),
+        (_, Some(value)) => {
+          let name = format!("  {}", e.name);
+          let gap = width.saturating_sub(width::str_width(&name) + width::str_width(value)).max(1);
+          format!("{}{}{}", name, " ".repeat(gap), value)
+        },
+      };
+      (text, e.kind)
+    }).collect();
+    (lines, self.sel - first)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path;
+  use crate::editor::driver::Driver;
+
+  #[test]
+  fn outline_document() {
+    let text = "# precision: 2\n# Income [style: positive]\nsalary = 3000 EUR\nbonus = 500 EUR\n# Costs\nrent = 1200 EUR\n2 + 2\nsalary + bonus - rent\n";
+    let entries = build(exec::Context::new_with_stdlib(), &mut Imports::new(path::Path::new("")), text.lines());
+    let names: Vec<(Kind, &str, Option<&str>)> = entries.iter().map(|e| (e.kind, e.name.as_str(), e.value.as_deref())).collect();
+    assert_eq!(vec![
+      (Kind::Heading, "Income", None),
+      (Kind::Variable, "salary", Some("3000 EUR")),
+      (Kind::Variable, "bonus", Some("500 EUR")),
+      (Kind::Heading, "Costs", None),
+      (Kind::Variable, "rent", Some("1200 EUR")),
+      (Kind::Total, "salary + bonus - rent", Some("2300 EUR")),
+    ], names);
+
+    let mut outline = Outline::new();
+    outline.update(entries.clone(), 6);
+    assert_eq!(Some(5), outline.selected().map(|e| e.line)); // the variable the cursor is below
+    let (lines, sel) = outline.lines(24, 3);
+    assert_eq!(("  rent          1200 EUR".to_string(), Kind::Variable), lines[sel]);
+    outline.focus(true);
+    outline.up();
+    outline.update(entries, 7);
+    assert_eq!(Some("Costs"), outline.selected().map(|e| e.name.as_str())); // left alone while it has focus
+
+    let mut driver = Driver::new((100, 12));
+    driver.set_text("# Income\nsalary = 3000\n# Costs\nrent = 1200\nsalary - rent");
+    driver.press("f3");
+    assert!(driver.shows("Costs") && driver.row(3).contains("rent") && driver.row(3).ends_with("1200"));
+    driver.press("down");
+    driver.press("down");
+    driver.press("enter");
+    assert_eq!(2, driver.cursor().y);
+    driver.type_text("x");
+    assert!(driver.text().contains("\nx# Costs"));
+    assert!(driver.row(1).contains("┊   salary"));
+    driver.press("f3");
+    driver.press("f3");
+    assert!(!driver.row(1).contains("┊   salary"));
+  }
+
+}
@@ -0,0 +1,95 @@
+use std::cmp;
+
+use crate::editor::command::Command;
+use crate::editor::picker::fuzzy_score;
+
+/// The command palette. Commands are matched fuzzily by name.
+pub struct Palette {
+  cmds: Vec<Command>,
+  query: String,
+  matches: Vec<Command>,
+  sel: usize,
+}
+
+impl Palette {
+  pub fn new(cmds: Vec<Command>) -> Palette {
+    let mut p = Palette{
+      cmds,
+      query: String::new(),
+      matches: Vec::new(),
+      sel: 0,
+    };
+    p.update();
+    p
+  }
+
+  pub fn query(&self) -> &str {
+    &self.query
+  }
+
+  pub fn matches(&self) -> &Vec<Command> {
+    &self.matches
+  }
+
+  pub fn selection(&self) -> usize {
+    self.sel
+  }
+
+  pub fn selected(&self) -> Option<&Command> {
+    self.matches.get(self.sel)
+  }
+
+  pub fn push(&mut self, c: char) {
+    self.query.push(c);
+    self.update();
+  }
+
+  pub fn pop(&mut self) {
+    self.query.pop();
+    self.update();
+  }
+
+  pub fn up(&mut self) {
+    if self.sel > 0 {
+      self.sel -= 1;
+    }
+  }
+
+  pub fn down(&mut self) {
+    if self.sel + 1 < self.matches.len() {
+      self.sel += 1;
+    }
+  }
+
+  fn update(&mut self) {
+    let mut matches: Vec<(i64, usize, &Command)> = self.cmds.iter().enumerate()
+      .filter_map(|(i, c)| fuzzy_score(&self.query, &c.to_string()).map(|s| (s, i, c)))
+      .collect();
+    matches.sort_by_key(|(s, i, _)| (cmp::Reverse(*s), *i)); // best first, then in the order given
+    self.matches = matches.into_iter().map(|(_, _, c)| c.clone()).collect();
+    self.sel = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn match_commands() {
+    let mut p = Palette::new(Command::all(&["dark".to_string(), "light".to_string()]));
+    assert_eq!(22, p.matches().len());
+    for c in "thlight".chars() {
+      p.push(c);
+    }
+    assert_eq!(Some(&Command::Theme("light".to_string())), p.selected());
+    p.down();
+    assert_eq!(0, p.selection());
+    for _ in 0..7 {
+      p.pop();
+    }
+    p.down();
+    assert_eq!(Some(&Command::Save), p.selected());
+  }
+
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::cmp;
+use std::path;
+
+const MAX_CONTENT: u64 = 1 << 20; // don't search files larger than this
+
+/// A candidate document matching the current query.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Entry {
+  pub path: path::PathBuf,
+  pub detail: Option<String>, // the matching line, when matched by content
+  score: i64,
+}
+
+impl Entry {
+  pub fn name(&self) -> String {
+    match self.path.file_name() {
+      Some(name) => name.to_string_lossy().to_string(),
+      None => self.path.to_string_lossy().to_string(),
+    }
+  }
+  
+  pub fn describe(&self) -> String {
+    match &self.detail {
+      Some(detail) => format!("{}: {}", self.name(), detail.trim()),
+      None => match self.path.parent() {
+        Some(dir) => format!("{} — {}", self.name(), dir.to_string_lossy()),
+        None => self.name(),
+      },
+    }
+  }
+}
+
+struct Doc {
+  path: path::PathBuf,
+  text: Option<String>,
+}
+
+/// The quick-open picker. Documents are matched fuzzily by file name and,
+/// failing that, by a literal match against their content.
+pub struct Picker {
+  docs: Vec<Doc>,
+  query: String,
+  matches: Vec<Entry>,
+  sel: usize,
+}
+
+impl Picker {
+  pub fn new(docs: &[path::PathBuf]) -> Picker {
+    let docs = docs.iter().map(|p| Doc{
+      path: p.clone(),
+      text: read_small(p),
+    }).collect();
+    let mut p = Picker{
+      docs,
+      query: String::new(),
+      matches: Vec::new(),
+      sel: 0,
+    };
+    p.update();
+    p
+  }
+  
+  pub fn query(&self) -> &str {
+    &self.query
+  }
+  
+  pub fn matches(&self) -> &Vec<Entry> {
+    &self.matches
+  }
+  
+  pub fn selection(&self) -> usize {
+    self.sel
+  }
+  
+  pub fn selected(&self) -> Option<&Entry> {
+    self.matches.get(self.sel)
+  }
+  
+  pub fn push(&mut self, c: char) {
+    self.query.push(c);
+    self.update();
+  }
+  
+  pub fn pop(&mut self) {
+    self.query.pop();
+    self.update();
+  }
+  
+  pub fn up(&mut self) {
+    if self.sel > 0 {
+      self.sel -= 1;
+    }
+  }
+  
+  pub fn down(&mut self) {
+    if self.sel + 1 < self.matches.len() {
+      self.sel += 1;
+    }
+  }
+  
+  fn update(&mut self) {
+    let mut matches: Vec<Entry> = Vec::new();
+    for (i, doc) in self.docs.iter().enumerate() {
+      let recency = -(i as i64); // prefer more recent documents when scores are equal
+      let name = match doc.path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => continue,
+      };
+      if let Some(score) = fuzzy_score(&self.query, &name) {
+        matches.push(Entry{path: doc.path.clone(), detail: None, score: score * 1000 + recency});
+      }else if let Some(text) = &doc.text {
+        if let Some(line) = content_match(&self.query, text) {
+          matches.push(Entry{path: doc.path.clone(), detail: Some(line.to_string()), score: recency});
+        }
+      }
+    }
+    matches.sort_by_key(|e| cmp::Reverse(e.score));
+    self.matches = matches;
+    self.sel = 0;
+  }
+}
+
+fn read_small(p: &path::Path) -> Option<String> {
+  match fs::metadata(p) {
+    Ok(meta) if meta.len() <= MAX_CONTENT => fs::read_to_string(p).ok(),
+    _ => None,
+  }
+}
+
+/// Score a fuzzy match of the query against the candidate, or None if the
+/// query is not a case-insensitive subsequence of the candidate. Consecutive
+/// matches and matches at the start of a word score higher.
+pub fn fuzzy_score(query: &str, cand: &str) -> Option<i64> {
+  let mut score: i64 = 0;
+  let mut chars = cand.chars().enumerate();
+  let mut last: Option<usize> = None;
+  let cv: Vec<char> = cand.chars().collect();
+  for q in query.chars().flat_map(|c| c.to_lowercase()) {
+    let (i, _) = chars.find(|(_, c)| c.to_lowercase().eq(Some(q)))?;
+    score += 1;
+    if let Some(last) = last {
+      if last + 1 == i {
+        score += 5;
+      }
+    }
+    if i == 0 || !cv[i - 1].is_alphanumeric() {
+      score += 3;
+    }
+    last = Some(i);
+  }
+  Some(score)
+}
+
+fn content_match<'a>(query: &str, text: &'a str) -> Option<&'a str> {
+  if query.is_empty() {
+    return None;
+  }
+  let query = query.to_lowercase();
+  text.lines().find(|l| l.to_lowercase().contains(&query))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn fuzzy_match() {
+    assert_eq!(Some(0), fuzzy_score("", "budget.res"));
+    assert_eq!(None, fuzzy_score("xyz", "budget.res"));
+    assert_eq!(None, fuzzy_score("tb", "budget.res"));
+    assert!(fuzzy_score("bud", "budget.res") > fuzzy_score("bgt", "budget.res"));
+    assert_eq!(fuzzy_score("BUD", "budget.res"), fuzzy_score("bud", "budget.res"));
+  }
+  
+  #[test]
+  fn match_content() {
+    assert_eq!(Some("tax = 0.0875"), content_match("TAX", "Hello\ntax = 0.0875\n"));
+    assert_eq!(None, content_match("total", "Hello\ntax = 0.0875\n"));
+    assert_eq!(None, content_match("", "Hello"));
+  }
+  
+}
@@ -0,0 +1,48 @@
+use std::io::{stdout, Write};
+
+use crossterm::event;
+use crossterm::queue;
+use crossterm::cursor;
+use crossterm::terminal;
+
+use crate::Reader;
+
+/// Prompt for a secret, such as a passphrase, on a cleared screen. Input is
+/// not echoed. Produces None if the prompt is cancelled with Escape.
+pub fn secret(msg: &str) -> crossterm::Result<Option<String>> {
+  let mut out = stdout();
+  queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+  write!(out, "{}: ", msg)?;
+  out.flush()?;
+  
+  let mut val = String::new();
+  loop {
+    match Reader.read_key()? {
+      event::KeyEvent{
+        code: event::KeyCode::Enter,
+        ..
+      } => return Ok(Some(val)),
+      event::KeyEvent{
+        code: event::KeyCode::Esc,
+        ..
+      } => return Ok(None),
+      event::KeyEvent{
+        code: event::KeyCode::Char('c'),
+        modifiers: event::KeyModifiers::CONTROL,
+        ..
+      } => return Ok(None),
+      event::KeyEvent{
+        code: event::KeyCode::Backspace,
+        ..
+      } => {
+        val.pop();
+      },
+      event::KeyEvent{
+        code: event::KeyCode::Char(v),
+        modifiers: event::KeyModifiers::NONE | event::KeyModifiers::SHIFT,
+        ..
+      } => val.push(v),
+      _ => {},
+    };
+  }
+}
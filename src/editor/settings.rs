@@ -0,0 +1,87 @@
+use crate::document::settings::{Settings, LOCALES, CURRENCIES, MAX_PRECISION, MAX_FIGURES};
+use crate::rdl::unit::ROUNDINGS;
+use crate::text::width;
+use crate::i18n::{fill, tr, Message};
+
+const FIELDS: usize = 6;
+
+/// The settings panel, which edits the settings of the current document.
+pub struct Panel {
+  settings: Settings,
+  sel: usize,
+}
+
+impl Panel {
+  pub fn new(settings: Settings) -> Panel {
+    Panel{
+      settings,
+      sel: 0,
+    }
+  }
+
+  pub fn settings(&self) -> &Settings {
+    &self.settings
+  }
+
+  pub fn up(&mut self) {
+    self.sel = (self.sel + FIELDS - 1) % FIELDS;
+  }
+
+  pub fn down(&mut self) {
+    self.sel = (self.sel + 1) % FIELDS;
+  }
+
+  /// Change the selected setting to its next value.
+  pub fn next(&mut self) {
+    self.cycle(true);
+  }
+
+  /// Change the selected setting to its previous value.
+  pub fn prev(&mut self) {
+    self.cycle(false);
+  }
+
+  fn cycle(&mut self, fwd: bool) {
+    let s = &mut self.settings;
+    match self.sel {
+      0 => s.precision = step(&(0..=MAX_PRECISION).collect::<Vec<_>>(), s.precision, fwd),
+      1 => s.figures = step(&(1..=MAX_FIGURES).collect::<Vec<_>>(), s.figures, fwd),
+      2 => s.rounding = step(&ROUNDINGS, s.rounding, fwd),
+      3 => s.locale = step(&LOCALES, s.locale.as_deref(), fwd).map(|l| l.to_string()),
+      4 => s.currency = step(&CURRENCIES, s.currency.as_deref(), fwd).map(|c| c.to_string()),
+      _ => s.strict = !s.strict,
+    };
+  }
+
+  /// Describe each setting, marking the selected one.
+  pub fn lines(&self) -> Vec<String> {
+    let s = &self.settings;
+    let fields = [
+      (tr(Message::Precision), s.precision.map(|p| fill(Message::Places, &[&p.to_string()])).unwrap_or_else(|| tr(Message::Automatic).to_string())),
+      (tr(Message::Figures), s.figures.map(|f| fill(Message::Significant, &[&f.to_string()])).unwrap_or_else(|| tr(Message::Automatic).to_string())),
+      (tr(Message::Rounding), s.rounding.map(|r| r.to_string()).unwrap_or_else(|| tr(Message::Default).to_string())),
+      (tr(Message::Locale), s.locale.clone().unwrap_or_else(|| tr(Message::Nothing).to_string())),
+      (tr(Message::Currency), s.currency.clone().unwrap_or_else(|| tr(Message::Nothing).to_string())),
+      (tr(Message::StrictUnits), tr(if s.strict { Message::On } else { Message::Off }).to_string()),
+    ];
+    let pad = fields.iter().map(|(name, _)| width::str_width(name)).max().unwrap_or(0) + 2; // names differ in length between languages
+    let mut lines: Vec<String> = fields.iter().enumerate().map(|(i, (name, val))| {
+      format!("{} {}{}‹ {} ›", if i == self.sel { "▸" } else { " " }, name, " ".repeat(pad - width::str_width(name)), val)
+    }).collect();
+    lines.push(String::new());
+    lines.push(tr(Message::SettingsHelp).to_string());
+    lines
+  }
+}
+
+/// Step through a list of values, where none precedes the first value and
+/// follows the last.
+fn step<T: Copy + PartialEq>(vals: &[T], cur: Option<T>, fwd: bool) -> Option<T> {
+  let idx = cur.and_then(|c| vals.iter().position(|v| *v == c));
+  match (idx, fwd) {
+    (None, true)     => vals.first().copied(),
+    (None, false)    => vals.last().copied(),
+    (Some(i), true)  => vals.get(i + 1).copied(),
+    (Some(i), false) => i.checked_sub(1).and_then(|i| vals.get(i)).copied(),
+  }
+}
@@ -0,0 +1,76 @@
+use std::time;
+
+use crate::i18n::{tr, Message};
+use crate::rdl::Timings;
+
+/// Measurements of the last frame drawn, shown over the document so that
+/// performance problems can be reported with numbers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+  pub frame: time::Duration, // to lay out and draw everything, not counting writing it to the terminal
+  pub lines: usize, // every line is evaluated again each frame
+  pub timings: Timings,
+  pub imports: (usize, usize), // files found in the cache of imports and read, ever
+}
+
+impl Stats {
+  /// Describe the measurements, a line each, along with how much memory
+  /// is resident, if that's known.
+  pub fn describe(&self, resident: Option<u64>) -> Vec<String> {
+    let (hits, misses) = self.imports;
+    let imports = match hits + misses {
+      0 => tr(Message::Nothing).to_string(),
+      n => format!("{:.0}% of {}", hits as f64 * 100.0 / n as f64, n),
+    };
+    let memory = resident.map_or(tr(Message::Unknown).to_string(), |n| format!("{:.1} MiB", n as f64 / (1 << 20) as f64));
+    [
+      (Message::StatFrame, format!("{:.2?}", self.frame)),
+      (Message::StatLines, self.lines.to_string()),
+      (Message::StatParse, format!("{:.2?}", self.timings.parse)),
+      (Message::StatEval, format!("{:.2?}", self.timings.eval)),
+      (Message::StatImports, imports),
+      (Message::StatMemory, memory),
+    ].into_iter().map(|(label, value)| format!("{:<20}{:>12}", tr(label), value)).collect()
+  }
+}
+
+/// How much memory the editor has resident, in bytes, where that's known.
+#[cfg(target_os = "linux")]
+pub fn resident() -> Option<u64> {
+  let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+  let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+  let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+  (size > 0).then(|| pages * size as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn resident() -> Option<u64> {
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn describe_stats() {
+    let stats = Stats{
+      frame: time::Duration::from_micros(1_250),
+      lines: 42,
+      timings: Timings{parse: time::Duration::from_micros(300), eval: time::Duration::from_micros(500)},
+      imports: (3, 1),
+    };
+    assert_eq!(vec![
+      "frame                     1.25ms",
+      "lines evaluated               42",
+      "parsing                 300.00µs",
+      "evaluating              500.00µs",
+      "import cache hits       75% of 4",
+      "memory                   1.5 MiB",
+    ], stats.describe(Some(3 << 19)));
+    assert_eq!("import cache hits           none", Stats::default().describe(None)[4]);
+    assert_eq!("memory                   unknown", Stats::default().describe(None)[5]);
+    assert!(!cfg!(target_os = "linux") || resident().is_some_and(|n| n > 0));
+  }
+
+}
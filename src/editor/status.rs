@@ -0,0 +1,130 @@
+/// The template of the status bar unless one is configured.
+pub const DEFAULT: &str = "{doc}  {mode}  {cursor}  {precision}";
+
+/// Names of the segments a status bar template may refer to.
+pub const SEGMENTS: [&str; 6] = ["mode", "cursor", "doc", "rates_age", "quotes_age", "precision"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+  Text(String),
+  Field(&'static str), // one of SEGMENTS
+}
+
+/// What the status bar shows, from a template of text and segments in
+/// braces, such as `{doc} line {cursor}`. Braces are written doubled to
+/// appear as themselves. An empty template hides the status bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+  segments: Vec<Segment>,
+}
+
+impl Default for Status {
+  fn default() -> Status {
+    Status::parse(DEFAULT).expect("The default status bar template is invalid")
+  }
+}
+
+/// The values of the segments of the status bar.
+pub struct Info<'a> {
+  pub mode: &'a str,
+  pub cursor: (usize, usize), // line and column, from zero
+  pub doc: &'a str,
+  pub rates_age: &'a str,
+  pub quotes_age: &'a str,
+  pub precision: &'a str,
+}
+
+impl Status {
+  /// Parse a template, or describe what is wrong with it.
+  pub fn parse(template: &str) -> Result<Status, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          text.push('{');
+        },
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          text.push('}');
+        },
+        '{' => {
+          let mut name = String::new();
+          loop {
+            match chars.next() {
+              Some('}') => break,
+              Some(c) => name.push(c),
+              None => return Err(format!("unclosed segment {{{}", name)),
+            }
+          }
+          let field = match SEGMENTS.iter().find(|s| **s == name.trim()) {
+            Some(field) => field,
+            None => return Err(format!("unknown segment {{{}}}; expected one of {}", name, SEGMENTS.join(", "))),
+          };
+          if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+          }
+          segments.push(Segment::Field(field));
+        },
+        '}' => return Err("unmatched }; write }} for a brace".to_string()),
+        c => text.push(c),
+      }
+    }
+    if !text.is_empty() {
+      segments.push(Segment::Text(text));
+    }
+    Ok(Status{
+      segments,
+    })
+  }
+
+  /// Whether the status bar is shown at all.
+  pub fn is_visible(&self) -> bool {
+    !self.segments.is_empty()
+  }
+
+  /// Produce the text of the status bar.
+  pub fn render(&self, info: &Info) -> String {
+    let mut out = String::new();
+    for seg in &self.segments {
+      match seg {
+        Segment::Text(text)         => out.push_str(text),
+        Segment::Field("mode")      => out.push_str(info.mode),
+        Segment::Field("cursor")    => out.push_str(&format!("{}:{}", info.cursor.0 + 1, info.cursor.1 + 1)),
+        Segment::Field("doc")       => out.push_str(info.doc),
+        Segment::Field("rates_age") => out.push_str(info.rates_age),
+        Segment::Field("quotes_age") => out.push_str(info.quotes_age),
+        Segment::Field("precision") => out.push_str(info.precision),
+        Segment::Field(_)           => {},
+      }
+    }
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_status() {
+    let info = Info{
+      mode: "normal",
+      cursor: (2, 0),
+      doc: "budget.rdl",
+      rates_age: "pinned",
+      quotes_age: "stooq 5m ago",
+      precision: "2 dp",
+    };
+    assert_eq!("budget.rdl  normal  3:1  2 dp", Status::default().render(&info));
+    assert_eq!("{budget.rdl} rates pinned", Status::parse("{{{doc}}} rates { rates_age }").unwrap().render(&info));
+    assert_eq!("quotes stooq 5m ago", Status::parse("quotes {quotes_age}").unwrap().render(&info));
+    assert!(!Status::parse("").unwrap().is_visible());
+    assert_eq!(Err("unknown segment {line}; expected one of mode, cursor, doc, rates_age, quotes_age, precision".to_string()), Status::parse("{line}"));
+    assert_eq!(Err("unclosed segment {doc".to_string()), Status::parse("{doc"));
+    assert_eq!(Err("unmatched }; write }} for a brace".to_string()), Status::parse("doc}"));
+  }
+
+}
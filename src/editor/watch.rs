@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::time;
+
+use crate::rdl::{self, exec, Watch};
+use crate::rdl::import::Imports;
+
+/// How long a line whose value changed stays highlighted.
+const FLASH: time::Duration = time::Duration::from_millis(1500);
+
+/// A change to the value of a watched line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+  pub line: usize, // the paragraph the line is
+  pub expr: String,
+  pub before: String,
+  pub after: String,
+  pub watch: Watch,
+}
+
+/// The values of the lines of a document which ask to be watched, written
+/// as `total * rate # watch`, so that changes to them can be pointed out.
+#[derive(Debug, Default)]
+pub struct Watched {
+  values: HashMap<(String, usize), String>, // by the text of the line, and which of the lines with that text it is
+  flash: Vec<usize>, // the paragraphs highlighted, until the flash is over
+  until: Option<time::Instant>,
+}
+
+impl Watched {
+  /// Evaluate the lines of a document and compare the values of those
+  /// which are watched to what they were when last evaluated, producing
+  /// those which changed. A line which has been edited is a different
+  /// line, and not a change. The lines which changed are flashed.
+  pub fn update<'a>(&mut self, mut cxt: exec::Context, imports: &mut Imports, lines: impl Iterator<Item = &'a str>) -> Vec<Change> {
+    let mut values = HashMap::new();
+    let mut changes = Vec::new();
+    for (i, line) in lines.enumerate() {
+      rdl::import(imports, &mut cxt, line); // a file which can't be imported is shown where the line is drawn
+      let res = rdl::render(&mut cxt, line);
+      let watch = match rdl::line_watch(line) {
+        Some(watch) => watch,
+        None => continue,
+      };
+      let n = values.keys().filter(|(l, _)| l == line).count();
+      let key = (line.to_string(), n);
+      let after = res.iter().map(|r| r.text.as_str()).collect::<Vec<&str>>().join(", ");
+      if let Some(before) = self.values.get(&key).filter(|b| **b != after) {
+        changes.push(Change{
+          line: i,
+          expr: rdl::split_line(&cxt, line).0.trim().to_string(),
+          before: before.clone(),
+          after: after.clone(),
+          watch,
+        });
+      }
+      values.insert(key, after);
+    }
+    self.values = values;
+    if !changes.is_empty() {
+      self.flash = changes.iter().map(|c| c.line).collect();
+      self.until = Some(time::Instant::now() + FLASH);
+    }
+    changes
+  }
+
+  /// Forget the values of lines, as when none are watched any more. Lines
+  /// which are flashing go on until they're done.
+  pub fn forget(&mut self) {
+    self.values.clear();
+  }
+
+  /// Flash paragraphs for a moment, as when they've been copied.
+  pub fn flash(&mut self, paragraphs: Vec<usize>) {
+    self.flash = paragraphs;
+    self.until = Some(time::Instant::now() + FLASH);
+  }
+
+  /// The paragraphs which are flashed because their values changed, or
+  /// because they were copied.
+  pub fn flashing(&self) -> &[usize] {
+    &self.flash
+  }
+
+  /// Stop flashing lines once they have been for long enough. Produces
+  /// whether they stopped, so that they can be drawn again.
+  pub fn expire(&mut self) -> bool {
+    match self.until {
+      Some(until) if time::Instant::now() >= until => {
+        self.flash.clear();
+        self.until = None;
+        true
+      },
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn watch_values() {
+    assert_eq!(Some(Watch::Flash), rdl::line_watch("total * 2 # watch"));
+    assert_eq!(Some(Watch::Notify), rdl::line_watch("total * 2 # precision: 2, watch: notify"));
+    assert_eq!(None, rdl::line_watch("total * 2 # watch: loudly"));
+    assert_eq!(None, rdl::line_watch("total * 2 # watch this"));
+    assert_eq!(None, rdl::line_watch("# watch"));
+
+    let mut watched = Watched::default();
+    let mut imports = Imports::new(std::path::Path::new(""));
+    let mut update = |w: &mut Watched, doc: &str| w.update(exec::Context::new(), &mut imports, doc.lines());
+    assert!(update(&mut watched, "a = 2\na * 3 # watch\na * 3 # watch").is_empty()); // seen for the first time
+    assert!(watched.flashing().is_empty());
+    assert!(update(&mut watched, "a = 2\na * 3 # watch\na * 3 # watch").is_empty());
+    let changes = update(&mut watched, "a = 4\na * 3 # watch\na * 3 # watch");
+    assert_eq!(vec![(1, "a * 3", "6", "12"), (2, "a * 3", "6", "12")], changes.iter().map(|c| (c.line, c.expr.as_str(), c.before.as_str(), c.after.as_str())).collect::<Vec<_>>());
+    assert_eq!(&[1, 2], watched.flashing());
+    assert!(!watched.expire());
+    assert!(update(&mut watched, "a = 4\na * 4 # watch: notify").is_empty()); // edited, so a different line
+    assert_eq!(Watch::Notify, update(&mut watched, "a = 5\na * 4 # watch: notify")[0].watch);
+    watched.until = Some(time::Instant::now());
+    assert!(watched.expire());
+    assert!(watched.flashing().is_empty());
+
+    watched.flash(vec![0, 1]); // as copied
+    watched.forget();
+    assert_eq!(&[0, 1], watched.flashing());
+    assert!(update(&mut watched, "a = 5\na * 4 # watch: notify").is_empty()); // forgotten, so seen for the first time
+    assert_eq!(&[0, 1], watched.flashing());
+  }
+
+}
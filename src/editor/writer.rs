@@ -1,30 +1,99 @@
+use std::fmt;
+use std::path;
 use std::io::stdout;
 use std::io::Write;
+use std::ops;
+use std::cmp::min;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time;
 
+use clap::Parser;
 use crossterm;
 use crossterm::queue;
 use crossterm::cursor;
 use crossterm::execute;
 use crossterm::terminal;
-use crossterm::style::{Color, Stylize};
+use crossterm::style::Color;
 
 use crate::options;
 use crate::error;
 use crate::buffer::Buffer;
 use crate::text::{Text, Content, Storage, Renderable, Pos};
 use crate::text::attrs;
+use crate::text::color;
+use crate::text::layout;
+use crate::text::width;
 use crate::frame::Frame;
+use crate::document::sections;
+use crate::document::settings::Settings;
+use crate::i18n::{tr, Message};
+use crate::theme::{Theme, Style};
+use crate::editor::picker::Picker;
+use crate::editor::palette::Palette;
+use crate::editor::outline::{self, Outline};
+use crate::editor::keymap::{self, Keymap};
+use crate::editor::cursor as cursor_style;
+use crate::editor::stats::{self, Stats};
 
 use crate::rdl;
 use crate::rdl::exec;
+use crate::rdl::import::Imports;
 
 const _VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const PICKER_WIDTH: usize = 72;
+const PICKER_ROWS: usize = 10;
+const STATS_WIDTH: usize = 34;
+const OUTLINE_WIDTH: usize = 32;
+
+const GUTTER_WIDTH: usize = 5;
+const INLINE_PREFIX: &str = " = ";
+const BELOW_PREFIX: &str = "  = ";
+const SELECTION_PRIORITY: i32 = 1; // the selection is painted over highlighting
+
+/// Where results are drawn relative to the expressions they belong to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Placement {
+  Column, // right-aligned in a column beside the text
+  Inline, // after the end of the line
+  Below,  // dimmed, on a line of their own below
+}
+
+/// Every placement, in the order they are listed.
+pub const PLACEMENTS: [Placement; 3] = [Placement::Column, Placement::Inline, Placement::Below];
+
+impl Placement {
+  pub fn from_name(name: &str) -> Option<Placement> {
+    PLACEMENTS.iter().find(|p| p.to_string() == name.trim().to_lowercase()).copied()
+  }
+}
+
+impl fmt::Display for Placement {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Placement::Column => write!(f, "column"),
+      Placement::Inline => write!(f, "inline"),
+      Placement::Below  => write!(f, "below"),
+    }
+  }
+}
+
+/// Whether the style of the cursor has been changed, and must be restored.
+static CURSOR_STYLED: AtomicBool = AtomicBool::new(false);
+
 pub struct Writer {
   opts: options::Options,
   term_size: (usize, usize),
   frame: Frame,
   buf: Buffer,
+  theme: Theme,
+  placement: Placement,
+  cursor: Option<cursor_style::Style>,       // the style the cursor should have
+  drawn_cursor: Option<cursor_style::Style>, // the style it was last given
+  stats: Option<Stats>, // of the last frame, if they're shown
+  flash: Vec<usize>, // paragraphs highlighted because their values changed
+  outline: bool, // whether the outline is drawn beside the document
+  below: Style, // of results placed below their lines, resolved in the theme as each frame is drawn
 }
 
 impl Writer {
@@ -34,29 +103,119 @@ impl Writer {
       term_size: size,
       frame: Frame::new(size.0, opts),
       buf: Buffer::new(),
+      theme: Theme::dark(),
+      placement: Placement::Column,
+      cursor: None,
+      drawn_cursor: None,
+      stats: None,
+      flash: Vec::new(),
+      outline: false,
+      below: Style::Named("muted".to_string()),
+    }
+  }
+  
+  /// A writer which keeps what it draws instead of writing it to the
+  /// terminal; see `take_output`.
+  #[cfg(test)]
+  pub fn capturing(size: (usize, usize), opts: options::Options) -> Self {
+    Self{
+      buf: Buffer::capturing(),
+      ..Self::new_with_size(size, opts)
     }
   }
   
+  /// Take what has been drawn since this was last called, by a writer which
+  /// is capturing it.
+  #[cfg(test)]
+  pub fn take_output(&mut self) -> String {
+    self.buf.take_captured()
+  }
+  
+  pub fn set_placement(&mut self, placement: Placement) {
+    self.placement = placement;
+  }
+  
+  /// The width to which the text being edited is wrapped, which is narrower
+  /// when results are drawn beside it.
+  pub fn text_width(&self) -> usize {
+    let width = match self.placement {
+      Placement::Column => (self.term_size.0 / 3) * 2,
+      _ => self.term_size.0.saturating_sub(GUTTER_WIDTH + 2),
+    };
+    width.saturating_sub(if self.outline { self.outline_width() }else{ 0 })
+  }
+  
+  /// Make room for the outline beside the document, or stop, from the
+  /// next refresh. The text must be wrapped again to its new width.
+  pub fn show_outline(&mut self, show: bool) {
+    self.outline = show;
+  }
+  
+  fn outline_width(&self) -> usize {
+    min(OUTLINE_WIDTH, self.term_size.0 / 4)
+  }
+  
+  /// Show or hide measurements of each frame over the document, from the
+  /// next refresh.
+  pub fn show_stats(&mut self, show: bool) {
+    self.stats = show.then(Stats::default);
+  }
+  
+  pub fn shows_stats(&self) -> bool {
+    self.stats.is_some()
+  }
+  
+  /// Highlight paragraphs of the text, by their index, from the next
+  /// refresh.
+  pub fn set_flash(&mut self, paragraphs: &[usize]) {
+    self.flash = paragraphs.to_vec();
+  }
+  
+  pub fn theme(&self) -> &Theme {
+    &self.theme
+  }
+  
+  pub fn set_theme(&mut self, theme: Theme) {
+    self.theme = theme;
+  }
+  
+  /// Set the style of the cursor from the next refresh, or none to leave
+  /// it as the terminal draws it.
+  pub fn set_cursor(&mut self, style: Option<cursor_style::Style>) {
+    self.cursor = style;
+  }
+  
+  /// Return the cursor to the terminal's own style, if we changed it.
+  pub fn restore_cursor() -> crossterm::Result<()> {
+    if CURSOR_STYLED.load(Ordering::Relaxed) {
+      let mut out = stdout();
+      out.write_all(cursor_style::RESET.as_bytes())?;
+      out.flush()?;
+    }
+    Ok(())
+  }
+  
   pub fn clear() -> crossterm::Result<()> {
     execute!(stdout(), terminal::Clear(terminal::ClearType::All))?;
     execute!(stdout(), cursor::MoveTo(0, 0))?;
     Ok(())
   }
   
-  fn draw_formula(&self, width: usize, _height: usize, text: &Text) -> (Content, Content) {
+  /// Draw the text being edited and the results of its expressions. When
+  /// results are placed in a column they are drawn separately; otherwise
+  /// they are drawn among the text. Also produces the row on which each
+  /// line of the text is drawn, since results below a line push the rest
+  /// of the text down.
+  fn draw_formula(&self, width: usize, _height: usize, text: &Text, imports: &mut Imports, mut cxt: exec::Context, mut timings: Option<&mut rdl::Timings>) -> (Content, Option<Content>, Vec<usize>) {
     let mut edit_text = String::new();
     let mut edit_spns: Vec<attrs::Span> = Vec::new();
     let mut fmla_text = String::new();
     let mut fmla_spns: Vec<attrs::Span> = Vec::new();
-    let mut cxt = exec::Context::new_with_stdlib();
+    let mut rows: Vec<usize> = Vec::new();
+    let mut layers: Vec<(ops::Range<usize>, ops::Range<usize>, &attrs::Attributes)> = Vec::new(); // ranges of text and results in tagged sections
+    let mut section: Option<&attrs::Attributes> = None;
     
-    let style = vec![
-      attrs::Attributes{bold: true, invert: false, color: Some(Color::Yellow), background: None},
-      attrs::Attributes{bold: true, invert: false, color: Some(Color::Magenta), background: None},
-      attrs::Attributes{bold: true, invert: false, color: Some(Color::Cyan), background: None},
-      attrs::Attributes{bold: true, invert: false, color: Some(Color::Green), background: None},
-      attrs::Attributes{bold: true, invert: false, color: Some(Color::Blue), background: None},
-    ];
+    let style = self.theme.results.clone();
     
     let opts = rdl::Options{
       verbose: self.opts.debug,
@@ -64,66 +223,434 @@ impl Writer {
     };
     
     let mut boff0 = 0;
-    for (l, n) in text.paragraphs() {
-      let (mut txt, mut exp) = rdl::render_with_options(&mut cxt, l, boff0, fmla_text.len(), Some(&style), Some(&opts));
+    let mut row = 0;
+    for (p, (l, n)) in text.paragraphs().enumerate() {
+      let failed = rdl::import(imports, &mut cxt, l);
+      if let Some(name) = sections::tag(l) {
+        section = if name == sections::NONE { None }else{ self.theme.section(&name) };
+      }
+      let foff0 = fmla_text.len();
+      let prefix = match self.placement {
+        Placement::Column => "",
+        Placement::Inline => INLINE_PREFIX,
+        Placement::Below  => BELOW_PREFIX,
+      };
+      let boff1 = match self.placement {
+        Placement::Column => fmla_text.len(),
+        Placement::Inline => boff0 + l.len() + prefix.len(),
+        Placement::Below  => boff0 + l.len() + 1 /* newline */ + prefix.len(),
+      };
+      let (mut txt, mut exp) = rdl::render_timed_with_options(&mut cxt, l, boff0, boff1, Some(&style), Some(&opts), timings.as_deref_mut());
+      if let Some(err) = failed {
+        exp = rdl::render_failure(&err, boff1, Some(&style));
+      }
       
+      rows.extend(row..row + n);
+      row += n;
       edit_text.push_str(txt.text());
-      edit_text.push_str("\n");
       edit_spns.append(txt.spans_mut());
       
-      fmla_text.push_str(exp.text());
-      fmla_text.push_str("\n");
-      fmla_spns.append(exp.spans_mut());
-      
-      if n > 1 {
-        fmla_text.push_str(&"\n".repeat(n - 1));
+      match self.placement {
+        Placement::Column => {
+          fmla_text.push_str(exp.text());
+          fmla_text.push('\n');
+          fmla_spns.append(exp.spans_mut());
+          if n > 1 {
+            fmla_text.push_str(&"\n".repeat(n - 1));
+          }
+        },
+        Placement::Inline => {
+          // the result must fit on the last line of the paragraph, or the
+          // text would wrap differently than it does while editing
+          let last = layout::layout(txt.text(), text.width()).last().map_or(0, |l| l.width());
+          let (res, spns) = clip_result(&exp, boff1, text.width().saturating_sub(last + prefix.len() + 1));
+          if !res.is_empty() {
+            edit_text.push_str(prefix);
+            edit_text.push_str(&res);
+            edit_spns.extend(spns);
+          }
+        },
+        Placement::Below => {
+          let (res, spns) = clip_result(&exp, boff1, text.width().saturating_sub(prefix.len() + 1));
+          if !res.is_empty() {
+            edit_text.push('\n');
+            edit_text.push_str(prefix);
+            edit_text.push_str(&res);
+            let dim = vec![attrs::Span::new(boff1 - prefix.len()..boff1 + res.len(), self.theme.resolve(&self.below))];
+            edit_spns.extend(attrs::merge_with_policy(spns, dim, attrs::Policy::Last)); // results keep their colors, since they start after the prefix
+            row += 1;
+          }
+        },
+      };
+      if let Some(layer) = section {
+        layers.push((boff0..edit_text.len(), foff0..fmla_text.len(), layer));
       }
-      
-      boff0 += txt.len() + 1 /* newline */;
+      if self.flash.contains(&p) {
+        layers.push((boff0..edit_text.len(), foff0..fmla_text.len(), &self.theme.flash));
+      }
+      edit_text.push('\n');
+      boff0 = edit_text.len();
+    }
+    
+    for (edit, fmla, layer) in layers {
+      edit_spns = attrs::overlay(edit_spns, edit, layer);
+      fmla_spns = attrs::overlay(fmla_spns, fmla, layer);
+    }
+    
+    if let Some(sel) = text.selected_offsets() {
+      edit_spns = attrs::merge_with_policy(edit_spns, vec![attrs::Span::new_with_priority(sel, self.theme.selection.clone(), SELECTION_PRIORITY)], attrs::Policy::Priority);
     }
     
-    (
-      Content::new_with_attributed(edit_text, edit_spns, text.width()),
-      Content::new_with_attributed(fmla_text, fmla_spns, width)
-    )
+    let fmla = match self.placement {
+      Placement::Column => Some(Content::new_with_attributed(fmla_text, fmla_spns, width)),
+      _ => None,
+    };
+    (Content::new_with_attributed(edit_text, edit_spns, text.width()), fmla, rows)
   }
   
   fn draw_gutter(&self, width: usize, height: usize, nlines: usize) -> Content {
-    let style = attrs::Attributes{bold: true, invert: false, color: None, background: None};
+    let style = &self.theme.gutter;
     
-    let mut text = String::new();
-    let mut spns: Vec<attrs::Span> = Vec::new();
+    let mut text = attrs::AttributedBuilder::new();
     for i in 0..height {
       let line = format!(" {:>3}", i+1);
-      let start = text.len();
-      text.push_str(&line);
       if i < nlines {
-        spns.push(attrs::Span::new(start..text.len(), style.clone()));
+        text.push_styled(&line, style);
+      }else{
+        text.push_plain(&line);
       }
-      text.push('\n');
+      text.push_plain("\n");
     }
     
+    let (text, spns) = text.finish().into_parts();
     Content::new_with_attributed(text, spns, width)
   }
   
-  pub fn refresh(&mut self, pos: &Pos, text: &Text) -> Result<(), error::Error> {
+  /// Draw the outline as a column, one entry to a line, with the entry
+  /// selected highlighted.
+  fn draw_outline(&self, outline: &Outline, height: usize) -> Content {
+    let width = self.outline_width();
+    let (lines, sel) = outline.lines(width.saturating_sub(2), height);
+    let mut text = attrs::AttributedBuilder::new();
+    for (i, (line, kind)) in lines.iter().enumerate() {
+      let style = match kind {
+        _ if i == sel => if outline.is_focused() { &self.theme.highlight }else{ &self.theme.current },
+        outline::Kind::Heading => &self.theme.title,
+        _ => &self.theme.gutter,
+      };
+      text.push_styled(&format!(" {}", clip(line, width.saturating_sub(2))), style);
+      text.push_plain("\n");
+    }
+    let (text, spns) = text.finish().into_parts();
+    Content::new_with_attributed(text, spns, width)
+  }
+  
+  /// Draw the document and its results, with a status bar along the
+  /// bottom of the terminal when one is provided, and the outline beside
+  /// them when it is shown.
+  pub fn refresh(&mut self, pos: &Pos, text: &Text, imports: &mut Imports, cxt: exec::Context, status: Option<&str>, outline: Option<&Outline>) -> Result<(), error::Error> {
     let tw = (self.term_size.0 / 3) - 6;
-    let gw = if self.opts.debug_editor { 0 }else{ 5 };
+    let gw = if self.opts.debug_editor { 0 }else{ GUTTER_WIDTH };
     let ox = if self.opts.debug_editor { 0 }else{ gw + 1 };
+    let height = if status.is_some() { self.term_size.1.saturating_sub(1) }else{ self.term_size.1 };
+    let start = time::Instant::now();
+    let mut timings = rdl::Timings::default();
     
-    let (edit, fmla) = self.draw_formula(tw, self.term_size.1 as usize, text);
-    let gutter = self.draw_gutter(gw, self.term_size.1 as usize, edit.num_lines());
-    let cols: Vec<&dyn Renderable> = if self.opts.debug_editor {
+    let (edit, fmla, rows) = self.draw_formula(tw, height, text, imports, cxt, self.stats.is_some().then_some(&mut timings));
+    let gutter = self.draw_gutter(gw, height, edit.num_lines());
+    let mut cols: Vec<&dyn Renderable> = if self.opts.debug_editor {
       vec![&edit]
     }else{
-      vec![&gutter, &edit, &fmla]
+      vec![&gutter, &edit]
     };
+    if let Some(fmla) = &fmla {
+      if !self.opts.debug_editor {
+        cols.push(fmla);
+      }
+    }
+    let outline = outline.filter(|_| self.outline).map(|o| self.draw_outline(o, height));
+    if let Some(outline) = &outline {
+      cols.push(outline);
+    }
+    
+    let mut vpos = *pos; // where the cursor is drawn, past any results above it
+    if let Some(row) = rows.get(pos.y) {
+      vpos.y = *row;
+    }
+    if let Some(line) = text.line_text(pos.y) { // in columns, which wide characters take two of
+      vpos.x = width::str_width(&line.chars().take(pos.x).collect::<String>());
+    }
     
     queue!(self.buf, cursor::Hide)?;
-    self.frame.write_cols(cols, self.term_size.1 as usize, &mut self.buf, pos)?;
-    queue!(self.buf, cursor::MoveTo((pos.x + ox) as u16, pos.y as u16), cursor::Show)?;
+    self.frame.write_cols(cols, height, &mut self.buf, &vpos, &self.theme.current)?;
+    if let Some(status) = status {
+      queue!(self.buf, cursor::MoveTo(0, height as u16))?;
+      let status = fit(&format!(" {}", status), self.term_size.0);
+      let spans = self.status_spans(&status);
+      self.buf.push_str(&attrs::Attributed::new_with_string(status, spans).render());
+    }
+    if self.stats.is_some() {
+      let stats = Stats{
+        frame: start.elapsed(),
+        lines: text.paragraphs().count(),
+        timings,
+        imports: imports.lookups(),
+      };
+      self.draw_stats(&stats)?;
+      self.stats = Some(stats);
+    }
+    queue!(self.buf, cursor::MoveTo((vpos.x + ox) as u16, vpos.y as u16), cursor::Show)?;
+    if self.cursor != self.drawn_cursor { // only when it changes, which would restart blinking
+      match self.cursor {
+        Some(style) => self.buf.push_str(&style.sequence()),
+        None => self.buf.push_str(cursor_style::RESET),
+      };
+      self.drawn_cursor = self.cursor;
+      CURSOR_STYLED.store(true, Ordering::Relaxed);
+    }
+    self.buf.flush()?;
+    
+    Ok(())
+  }
+  
+  /// Draw the document and its results as `refresh` does, without the
+  /// gutter, cursor, or status bar, with attributes written as markup
+  /// rather than sent to the terminal. Each column is padded to its width
+  /// and separated from the next as it is on screen, and trailing space is
+  /// trimmed from each row.
+  pub fn markup(&self, text: &Text, imports: &mut Imports, cxt: exec::Context) -> String {
+    let tw = (self.term_size.0 / 3) - 6;
+    let (edit, fmla, _) = self.draw_formula(tw, self.term_size.1, text, imports, cxt, None);
+    let mut cols: Vec<&Content> = vec![&edit];
+    if let Some(fmla) = &fmla {
+      cols.push(fmla);
+    }
+    let rows = cols.iter().map(|c| c.num_lines()).max().unwrap_or(0);
+    let mut out = String::new();
+    for i in 0..rows {
+      let mut row = String::new();
+      for (x, c) in cols.iter().enumerate() {
+        let adj = if x > 0 {
+          row.push(self.frame.separator());
+          width::char_width(self.frame.separator())
+        }else{
+          0
+        };
+        let (line, n) = c.markup_line(i).unwrap_or_default();
+        row.push_str(&line);
+        row.push_str(&" ".repeat(c.width().saturating_sub(adj + n)));
+      }
+      out.push_str(row.trim_end());
+      out.push('\n');
+    }
+    out
+  }
+  
+  /// Draw measurements of a frame in the top right corner, over the
+  /// document.
+  fn draw_stats(&mut self, stats: &Stats) -> Result<(), error::Error> {
+    let lines = stats.describe(stats::resident());
+    let width = min(STATS_WIDTH, self.term_size.0);
+    let x = self.term_size.0 - width;
+    queue!(self.buf, cursor::MoveTo(x as u16, 0))?;
+    self.buf.push_str(&self.theme.alert.render(&fit(&format!(" {}", tr(Message::Performance)), width)));
+    for (i, line) in lines.iter().enumerate().take(self.term_size.1.saturating_sub(1)) {
+      queue!(self.buf, cursor::MoveTo(x as u16, (i + 1) as u16))?;
+      self.buf.push_str(&self.theme.panel.render(&fit(&format!(" {}", line), width)));
+    }
+    Ok(())
+  }
+  
+  pub fn draw_picker(&mut self, picker: &Picker) -> Result<(), error::Error> {
+    let entries: Vec<String> = picker.matches().iter().map(|e| e.describe()).collect();
+    let query = picker.query().to_lowercase(); // matched regardless of case, as the picker does
+    self.draw_list(&format!(" {}: {}", tr(Message::OpenPrompt), picker.query()), &entries, &query, picker.selection(), tr(Message::NoDocuments))
+  }
+  
+  pub fn draw_palette(&mut self, palette: &Palette, keys: &Keymap) -> Result<(), error::Error> {
+    let entries: Vec<String> = palette.matches().iter().map(|c| {
+      let name = c.to_string();
+      match keys.key(c) {
+        Some(key) => format!("{:<40}{}", name, keymap::format_key(&key)),
+        None => name,
+      }
+    }).collect();
+    self.draw_list(&format!(" > {}", palette.query()), &entries, "", palette.selection(), tr(Message::NoCommands))
+  }
+  
+  /// Style the status bar as a panel, with whatever is still being fetched
+  /// fading into its background, where the colors of the panel are known.
+  /// Where the panel leaves its text to the terminal, it's taken to be grey
+  /// over a dark background, and black over a light one.
+  fn status_spans(&self, status: &str) -> Vec<attrs::Span> {
+    let panel = &self.theme.panel;
+    let mut spans = vec![attrs::Span::new(0..status.len(), panel.clone())];
+    if let Some(bg) = panel.background.and_then(color::rgb) {
+      let dark = (bg.0 as u16 + bg.1 as u16 + bg.2 as u16) < 384;
+      let fg = panel.color.unwrap_or(if dark { Color::Grey }else{ Color::Black });
+      if let Some(fg) = color::rgb(fg) {
+        let faded = color::mix(fg, bg, 0.6);
+        for msg in [Message::RatesFetching, Message::QuotesFetching] {
+          if let Some(at) = status.find(tr(msg)) {
+            spans = attrs::merge_with_policy(spans, attrs::gradient(status, at..at + tr(msg).len(), fg, faded, panel), attrs::Policy::Last);
+          }
+        }
+      }
+    }
+    spans
+  }
+  
+  /// Draw a prompt over a list of entries, one of which is selected, with
+  /// whatever in them matches a query underlined.
+  fn draw_list(&mut self, prompt: &str, entries: &[String], query: &str, sel: usize, empty: &str) -> Result<(), error::Error> {
+    let title = &self.theme.title;
+    let normal = &self.theme.panel;
+    let select = &self.theme.highlight;
+    
+    let width = min(PICKER_WIDTH, self.term_size.0.saturating_sub(4));
+    let rows = min(PICKER_ROWS, self.term_size.1.saturating_sub(4));
+    let x = (self.term_size.0 - width) / 2;
+    let y = 1;
+    
+    queue!(self.buf, cursor::Hide, cursor::MoveTo(x as u16, y as u16))?;
+    self.buf.push_str(&title.render(&fit(prompt, width)));
+    
+    let first = if sel >= rows { sel + 1 - rows } else { 0 };
+    for i in 0..rows {
+      queue!(self.buf, cursor::MoveTo(x as u16, (y + 1 + i) as u16))?;
+      let line = match entries.get(first + i) {
+        Some(entry) => fit(&format!("   {}", entry), width),
+        None if i == 0 && entries.is_empty() => fit(&format!("   {}", empty), width),
+        None => fit("", width),
+      };
+      let style = if first + i == sel && sel < entries.len() { select }else{ normal };
+      let mut line = attrs::Attributed::new_with_str(&line, vec![attrs::Span::new(0..line.len(), style.clone())]);
+      if first + i < entries.len() {
+        line.highlight(query, &attrs::Attributes{underline: true, ..style.clone()});
+      }
+      self.buf.push_str(&line.render());
+    }
+    
+    let cx = min(x + width::str_width(prompt), x + width.saturating_sub(1));
+    queue!(self.buf, cursor::MoveTo(cx as u16, y as u16), cursor::Show)?;
     self.buf.flush()?;
+    Ok(())
+  }
+  
+  pub fn draw_notice(&mut self, title: &str, lines: &[String]) -> Result<(), error::Error> {
+    let head = &self.theme.alert;
+    let normal = &self.theme.panel;
+    
+    let width = min(PICKER_WIDTH, self.term_size.0.saturating_sub(4));
+    let lines: Vec<String> = lines.iter().flat_map(|l| { // wrapped to fit beside the margin, rather than cut short
+      let wrapped = attrs::Attributed::new_with_str(l, Vec::new()).wrap(width.saturating_sub(1));
+      if wrapped.is_empty() { vec![String::new()] }else{ wrapped.iter().map(|w| w.text().to_string()).collect() }
+    }).collect();
+    let rows = min(lines.len(), self.term_size.1.saturating_sub(4));
+    let x = (self.term_size.0 - width) / 2;
+    let y = 1;
+    
+    queue!(self.buf, cursor::Hide, cursor::MoveTo(x as u16, y as u16))?;
+    self.buf.push_str(&head.render(&fit(&format!(" {}", title), width)));
+    for (i, line) in lines.iter().take(rows).enumerate() {
+      queue!(self.buf, cursor::MoveTo(x as u16, (y + 1 + i) as u16))?;
+      self.buf.push_str(&normal.render(&fit(&format!(" {}", line), width)));
+    }
     
+    self.buf.flush()?;
     Ok(())
   }
 }
+
+/// Render a document as markup, as the editor draws it at a width with
+/// results placed as provided, in the dark theme and with the standard
+/// library only, so that the same document always renders the same way;
+/// see `Writer::markup`. This is meant for comparing how documents are
+/// drawn against fixtures which are known to be right.
+pub fn render_markup(doc: &str, width: usize, placement: Placement) -> String {
+  let mut writer = Writer::new_with_size((width, 0), options::Options::parse_from(["resolver"]));
+  writer.set_placement(placement);
+  let text = Text::new_with_str(writer.text_width(), doc);
+  let mut cxt = exec::Context::new_with_stdlib();
+  Settings::parse(doc).configure(&mut cxt);
+  writer.markup(&text, &mut Imports::new(path::Path::new("")), cxt)
+}
+
+/// Pad or truncate text to exactly the provided width, in columns.
+fn fit(text: &str, width: usize) -> String {
+  let mut out = String::new();
+  let mut n = 0;
+  for c in text.chars() {
+    let w = width::char_width(c);
+    if n + w > width {
+      break;
+    }
+    out.push(c);
+    n += w;
+  }
+  out.push_str(&" ".repeat(width - n));
+  out
+}
+
+/// Truncate text to at most the provided width, in columns.
+fn clip(text: &str, width: usize) -> &str {
+  let mut n = 0;
+  for (i, c) in text.char_indices() {
+    n += width::char_width(c);
+    if n > width {
+      return &text[..i];
+    }
+  }
+  text
+}
+
+/// Truncate a result to at most the provided width, in columns, marking
+/// where it was cut. Its spans start at an offset, which the spans returned
+/// do too.
+fn clip_result(exp: &attrs::Attributed, off: usize, width: usize) -> (String, Vec<attrs::Span>) {
+  let spns = exp.spans().iter().map(|s| attrs::Span::new(s.range().start.saturating_sub(off)..s.range().end.saturating_sub(off), s.attrs().clone())).collect();
+  let (text, spns) = attrs::Attributed::new_with_str(exp.text(), spns).truncate_to_width(width).into_parts();
+  (text, spns.iter().map(|s| attrs::Span::new(s.range().start + off..s.range().end + off, s.attrs().clone())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::env;
+
+  /// Each document in `test/golden` is rendered with each placement and
+  /// compared against the markup beside it, named for the placement, as in
+  /// `budget.column.markup`. Set `UPDATE_GOLDEN` to write the markup as it
+  /// renders now instead, after checking the differences are intended.
+  #[test]
+  fn golden_markup() {
+    let dir = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("test/golden");
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut docs: Vec<path::PathBuf> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).filter(|p| p.extension().is_some_and(|e| e == "res")).collect();
+    docs.sort();
+    assert!(!docs.is_empty());
+    for doc in docs {
+      let text = fs::read_to_string(&doc).unwrap();
+      for placement in PLACEMENTS {
+        let golden = doc.with_extension(format!("{}.markup", placement));
+        let markup = render_markup(&text, 90, placement);
+        assert_eq!(markup, render_markup(&text, 90, placement)); // rendering is deterministic
+        if update {
+          fs::write(&golden, &markup).unwrap();
+        }else{
+          let expect = fs::read_to_string(&golden).unwrap_or_else(|_| panic!("No golden markup at {}; set UPDATE_GOLDEN to write it", golden.display()));
+          assert!(expect == markup, "{} rendered differently than {}:\n{}", doc.display(), golden.display(), markup);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn draw_picker_without_room() {
+    let opts = options::Options::parse_from(["resolver"]);
+    let mut writer = Writer::capturing((1, 12), opts); // too narrow for the picker to have any width, as while resizing
+    writer.draw_picker(&Picker::new(&[path::PathBuf::from("budget.rdl")])).unwrap();
+  }
+
+}
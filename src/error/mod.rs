@@ -6,6 +6,7 @@ use std::string;
 pub enum Error {
   IOError(io::Error),
   UTF8Error(string::FromUtf8Error),
+  NotifyError(Box<notify::Error>), // boxed, since it is much larger than the others
 }
 
 impl From<io::Error> for Error {
@@ -20,11 +21,18 @@ impl From<string::FromUtf8Error> for Error {
   }
 }
 
+impl From<notify::Error> for Error {
+  fn from(error: notify::Error) -> Self {
+    Self::NotifyError(Box::new(error))
+  }
+}
+
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Self::IOError(err) => err.fmt(f),
       Self::UTF8Error(err) => err.fmt(f),
+      Self::NotifyError(err) => err.fmt(f),
     }
   }
 }
@@ -1,11 +1,11 @@
 use crossterm::queue;
-use crossterm::style;
 use crossterm::cursor;
 use crossterm::terminal;
 
 use crate::error;
 use crate::text::{Renderable, Pos};
 use crate::text::attrs;
+use crate::text::width;
 use crate::buffer::Buffer;
 use crate::options;
 
@@ -26,10 +26,12 @@ impl Frame {
     }
   }
   
-  pub fn write_cols(&self, cols: Vec<&dyn Renderable>, height: usize, buf: &mut Buffer, vpos: &Pos) -> Result<usize, error::Error> {
-    let highlight: attrs::Attributes = attrs::Attributes{
-      bold: false, invert: false, color: None, background: Some(style::Color::Rgb{r: 10, g: 10, b: 10}),
-    };
+  /// The character drawn between columns.
+  pub fn separator(&self) -> char {
+    self.sep
+  }
+  
+  pub fn write_cols(&self, cols: Vec<&dyn Renderable>, height: usize, buf: &mut Buffer, vpos: &Pos, highlight: &attrs::Attributes) -> Result<usize, error::Error> {
     let lines: Vec<usize> = cols.iter().map(|t| { t.num_lines() }).collect();
     let lmax: usize = match lines.iter().reduce(|a, b| {
       if a > b { a } else { b }
@@ -44,14 +46,14 @@ impl Frame {
       for (x, c) in cols.iter().enumerate() {
         let adj = if x > 0 {
           buf.push(self.sep);
-          1
+          width::char_width(self.sep)
         }else{
           0
         };
         let (n, b) = if i == vpos.y {
           let hspan = vec![attrs::Span::new(0..c.width(), highlight.clone())];
           let attrs = match c.attributes() {
-            Some(v) => attrs::merge(v.clone(), hspan),
+            Some(v) => attrs::merge_with_policy(v.clone(), hspan, attrs::Policy::Blend), // so sections show through
             None => hspan,
           };
           c.write_line_with_attrs(i, buf, Some(&attrs))
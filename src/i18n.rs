@@ -0,0 +1,327 @@
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The language the interface is currently presented in, as an index into
+/// `LANGUAGES`.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// A language the interface is translated into. This is the language of
+/// menus, notices, and prompts; documents are read and written the same
+/// way whichever is chosen.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Language {
+  English,
+  German,
+  French,
+}
+
+/// Every language, in the order of the translations in the catalog.
+pub const LANGUAGES: [Language; 3] = [
+  Language::English,
+  Language::German,
+  Language::French,
+];
+
+impl Language {
+  pub fn from_name(name: &str) -> Option<Language> {
+    LANGUAGES.iter().find(|l| l.to_string() == name.trim().to_lowercase()).copied()
+  }
+
+  fn index(&self) -> usize {
+    LANGUAGES.iter().position(|l| l == self).unwrap_or(0)
+  }
+}
+
+impl fmt::Display for Language {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Language::English => write!(f, "en"),
+      Language::German  => write!(f, "de"),
+      Language::French  => write!(f, "fr"),
+    }
+  }
+}
+
+/// Choose a language from the locale the environment is set up for, or
+/// English if it isn't one we have.
+pub fn detect() -> Language {
+  let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+    .filter_map(|v| env::var(v).ok())
+    .find(|v| !v.is_empty())
+    .unwrap_or_default();
+  let lang = locale.split(['_', '.', '@']).next().unwrap_or("");
+  Language::from_name(lang).unwrap_or(Language::English)
+}
+
+/// Present the interface in a language from now on.
+pub fn set_language(lang: Language) {
+  CURRENT.store(lang.index(), Ordering::Relaxed);
+}
+
+/// The language the interface is presented in.
+pub fn language() -> Language {
+  LANGUAGES.get(CURRENT.load(Ordering::Relaxed)).copied().unwrap_or(Language::English)
+}
+
+/// A string presented in the interface. Strings which include values mark
+/// where they go with `{}`; see `fill`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Message {
+  // Commands
+  Open,
+  Save,
+  Quit,
+  Settings,
+  Palette,
+  Keybindings,
+  CopyResult,
+  CopyLine,
+  CopyMarkdown,
+  CopySnippet,
+  CopyFormatted,
+  DeleteMode,
+  StartOfWord,
+  EndOfWord,
+  NextWord,
+  RefreshRates,
+  ShowLog,
+  ToggleStats,
+  ToggleOutline,
+  EarlierResults,
+  ThemeNamed,
+  Unbound,
+  // Notices
+  Configuration,
+  Theme,
+  StartupProblems,
+  PressAnyKey,
+  RestartNeeded,
+  ExchangeRates,
+  Quotes,
+  NoProviders,
+  Log,
+  NothingLogged,
+  Performance,
+  StatFrame,
+  StatLines,
+  StatParse,
+  StatEval,
+  StatImports,
+  StatMemory,
+  Unknown,
+  ValueChanged,
+  Sharing,
+  SharingAt,
+  NotShared,
+  Shared,
+  // Prompts
+  PassphraseFor,
+  NewPassphrase,
+  ConfirmPassphrase,
+  EmptyPassphrase,
+  PassphraseMismatch,
+  // Conflicts
+  TheDocument,
+  ChangedOnDisk,
+  ModifiedElsewhere,
+  ConflictReload,
+  ConflictMerge,
+  ConflictOverwrite,
+  ConflictKeep,
+  OpenElsewhere,
+  EditedElsewhere,
+  LockReadOnly,
+  LockTakeOver,
+  ReadOnly,
+  ReadOnlyHelp,
+  TakenOver,
+  NotOpened,
+  NotSaved,
+  UnsavedChanges,
+  OpenAgainToDiscard,
+  NotCopied,
+  NoClipboard,
+  // Lists
+  OpenPrompt,
+  NoDocuments,
+  NoCommands,
+  NoOutline,
+  NoEarlierResults,
+  // Document settings
+  Precision,
+  Figures,
+  Rounding,
+  Locale,
+  Currency,
+  StrictUnits,
+  Places,
+  Significant,
+  Automatic,
+  Default,
+  Nothing,
+  On,
+  Off,
+  SettingsHelp,
+  // Status bar
+  ModeNormal,
+  ModeDelete,
+  Untitled,
+  NoRates,
+  Pinned,
+  RatesFetched,
+  RatesStale,
+  Ago,
+  RatesFetching,
+  NoQuotes,
+  QuotesFetching,
+}
+
+impl Message {
+  /// The translations of a string, in the order of `LANGUAGES`.
+  fn catalog(&self) -> [&'static str; 3] {
+    match self {
+      Message::Open               => ["Open document", "Dokument öffnen", "Ouvrir un document"],
+      Message::Save               => ["Save document", "Dokument speichern", "Enregistrer le document"],
+      Message::Quit               => ["Quit", "Beenden", "Quitter"],
+      Message::Settings           => ["Document settings", "Dokumenteinstellungen", "Réglages du document"],
+      Message::Palette            => ["Command palette", "Befehlspalette", "Palette de commandes"],
+      Message::Keybindings        => ["Show keybindings", "Tastenbelegung anzeigen", "Afficher les raccourcis"],
+      Message::CopyResult         => ["Copy result", "Ergebnis kopieren", "Copier le résultat"],
+      Message::CopyLine           => ["Copy line with result", "Zeile mit Ergebnis kopieren", "Copier la ligne et son résultat"],
+      Message::CopyMarkdown       => ["Copy selection as Markdown", "Auswahl als Markdown kopieren", "Copier la sélection en Markdown"],
+      Message::CopySnippet        => ["Copy selection as snippet", "Auswahl als Ausschnitt kopieren", "Copier la sélection comme extrait"],
+      Message::CopyFormatted      => ["Copy selection with formatting", "Auswahl mit Formatierung kopieren", "Copier la sélection avec sa mise en forme"],
+      Message::DeleteMode         => ["Delete with the next movement", "Mit der nächsten Bewegung löschen", "Supprimer au prochain déplacement"],
+      Message::StartOfWord        => ["Move to start of word", "Zum Wortanfang", "Aller au début du mot"],
+      Message::EndOfWord          => ["Move to end of word", "Zum Wortende", "Aller à la fin du mot"],
+      Message::NextWord           => ["Move to next word", "Zum nächsten Wort", "Aller au mot suivant"],
+      Message::RefreshRates       => ["Refresh exchange rates and quotes", "Wechselkurse und Aktienkurse aktualisieren", "Actualiser les taux de change et les cours"],
+      Message::ShowLog            => ["Show log", "Protokoll anzeigen", "Afficher le journal"],
+      Message::ToggleStats        => ["Show or hide performance stats", "Leistungswerte ein- oder ausblenden", "Afficher ou masquer les performances"],
+      Message::ToggleOutline      => ["Show or hide the outline", "Gliederung ein- oder ausblenden", "Afficher ou masquer le plan"],
+      Message::EarlierResults     => ["Show earlier results of this line", "Frühere Ergebnisse dieser Zeile anzeigen", "Afficher les résultats précédents de cette ligne"],
+      Message::ThemeNamed         => ["Theme: {}", "Farbschema: {}", "Thème : {}"],
+      Message::Unbound            => ["unbound", "nicht belegt", "non attribué"],
+      Message::Configuration      => ["Configuration", "Konfiguration", "Configuration"],
+      Message::Theme              => ["Theme", "Farbschema", "Thème"],
+      Message::StartupProblems    => ["Startup problems", "Probleme beim Start", "Problèmes au démarrage"],
+      Message::PressAnyKey        => ["Press any key to continue", "Weiter mit beliebiger Taste", "Appuyez sur une touche pour continuer"],
+      Message::RestartNeeded      => ["Changes to providers, units, aliases, and plugins take effect when Resolver is restarted", "Änderungen an Anbietern, Einheiten, Aliasen und Plugins werden nach einem Neustart von Resolver wirksam", "Les modifications des fournisseurs, unités, alias et plugins prennent effet au redémarrage de Resolver"],
+      Message::ExchangeRates      => ["Exchange rates", "Wechselkurse", "Taux de change"],
+      Message::Quotes             => ["Quotes", "Aktienkurse", "Cours"],
+      Message::NoProviders        => ["No rate or quote providers are configured", "Keine Kursanbieter konfiguriert", "Aucun fournisseur de taux ou de cours n'est configuré"],
+      Message::Log                => ["Log", "Protokoll", "Journal"],
+      Message::NothingLogged      => ["Nothing has been logged", "Nichts protokolliert", "Rien n'a été journalisé"],
+      Message::Performance        => ["Performance", "Leistung", "Performances"],
+      Message::StatFrame          => ["frame", "Bild", "image"],
+      Message::StatLines          => ["lines evaluated", "ausgewertete Zeilen", "lignes évaluées"],
+      Message::StatParse          => ["parsing", "Parsen", "analyse"],
+      Message::StatEval           => ["evaluating", "Auswerten", "évaluation"],
+      Message::StatImports        => ["import cache hits", "Import-Cache-Treffer", "succès du cache"],
+      Message::StatMemory         => ["memory", "Speicher", "mémoire"],
+      Message::Unknown            => ["unknown", "unbekannt", "inconnue"],
+      Message::Sharing            => ["Sharing", "Freigabe", "Partage"],
+      Message::SharingAt          => ["Anyone on your network can watch this session, read-only, at {}", "Alle in Ihrem Netzwerk können diese Sitzung schreibgeschützt unter {} verfolgen", "Toute personne sur votre réseau peut suivre cette session, en lecture seule, à {}"],
+      Message::NotShared          => ["The session could not be shared: {}", "Die Sitzung konnte nicht freigegeben werden: {}", "La session n'a pas pu être partagée : {}"],
+      Message::Shared             => ["shared, {} watching", "freigegeben, {} Zuschauer", "partagé, {} spectateurs"],
+      Message::ValueChanged       => ["{} changed", "{} hat sich geändert", "{} a changé"],
+      Message::PassphraseFor      => ["Passphrase for {}", "Passphrase für {}", "Phrase secrète pour {}"],
+      Message::NewPassphrase      => ["New passphrase", "Neue Passphrase", "Nouvelle phrase secrète"],
+      Message::ConfirmPassphrase  => ["Confirm passphrase", "Passphrase bestätigen", "Confirmez la phrase secrète"],
+      Message::EmptyPassphrase    => ["Passphrase cannot be empty", "Die Passphrase darf nicht leer sein", "La phrase secrète ne peut pas être vide"],
+      Message::PassphraseMismatch => ["Passphrases do not match", "Die Passphrasen stimmen nicht überein", "Les phrases secrètes ne correspondent pas"],
+      Message::TheDocument        => ["The document", "Das Dokument", "Le document"],
+      Message::ChangedOnDisk      => ["{} changed on disk", "{} wurde auf der Festplatte geändert", "{} a été modifié sur le disque"],
+      Message::ModifiedElsewhere  => ["It was modified by another program after you opened it.", "Es wurde nach dem Öffnen von einem anderen Programm geändert.", "Un autre programme l'a modifié après son ouverture."],
+      Message::ConflictReload     => ["Reload it, discarding your changes", "Neu laden und eigene Änderungen verwerfen", "Le recharger en abandonnant vos modifications"],
+      Message::ConflictMerge      => ["Merge its changes with yours", "Seine Änderungen mit den eigenen zusammenführen", "Fusionner ses modifications avec les vôtres"],
+      Message::ConflictOverwrite  => ["Overwrite it with your version", "Mit der eigenen Fassung überschreiben", "L'écraser avec votre version"],
+      Message::ConflictKeep       => ["Keep editing", "Weiter bearbeiten", "Continuer l'édition"],
+      Message::OpenElsewhere      => ["{} is open elsewhere", "{} ist anderswo geöffnet", "{} est ouvert ailleurs"],
+      Message::EditedElsewhere    => ["Another instance of Resolver, process {} on {}, is editing it.", "Eine andere Instanz von Resolver, Prozess {} auf {}, bearbeitet es.", "Une autre instance de Resolver, le processus {} sur {}, le modifie."],
+      Message::LockReadOnly       => ["Open it read-only", "Schreibgeschützt öffnen", "L'ouvrir en lecture seule"],
+      Message::LockTakeOver       => ["Take it over; the other instance can no longer save it", "Übernehmen; die andere Instanz kann es nicht mehr speichern", "Le reprendre ; l'autre instance ne pourra plus l'enregistrer"],
+      Message::ReadOnly           => ["read-only", "schreibgeschützt", "lecture seule"],
+      Message::ReadOnlyHelp       => ["It is being edited in another instance of Resolver. Open it again to take it over.", "Es wird in einer anderen Instanz von Resolver bearbeitet. Erneut öffnen, um es zu übernehmen.", "Il est modifié dans une autre instance de Resolver. Rouvrez-le pour le reprendre."],
+      Message::TakenOver          => ["Another instance of Resolver took it over, so it is now read-only. Open it again to take it back.", "Eine andere Instanz von Resolver hat es übernommen, daher ist es jetzt schreibgeschützt. Erneut öffnen, um es zurückzuholen.", "Une autre instance de Resolver l'a repris, il est donc en lecture seule. Rouvrez-le pour le récupérer."],
+      Message::NotOpened          => ["{} could not be opened", "{} konnte nicht geöffnet werden", "{} n'a pas pu être ouvert"],
+      Message::NotSaved           => ["{} could not be saved", "{} konnte nicht gespeichert werden", "{} n'a pas pu être enregistré"],
+      Message::UnsavedChanges     => ["{} has unsaved changes", "{} hat ungespeicherte Änderungen", "{} a des modifications non enregistrées"],
+      Message::OpenAgainToDiscard => ["Save it first, or open the other document again to discard them.", "Zuerst speichern, oder das andere Dokument erneut öffnen, um sie zu verwerfen.", "Enregistrez-le d'abord, ou rouvrez l'autre document pour les abandonner."],
+      Message::NotCopied          => ["Nothing could be copied", "Es konnte nichts kopiert werden", "Rien n'a pu être copié"],
+      Message::NoClipboard        => ["Install pbcopy, wl-copy, xclip, or xsel, or set osc52 = true in the configuration to copy through the terminal.", "pbcopy, wl-copy, xclip oder xsel installieren, oder osc52 = true in der Konfiguration setzen, um über das Terminal zu kopieren.", "Installez pbcopy, wl-copy, xclip ou xsel, ou définissez osc52 = true dans la configuration pour copier via le terminal."],
+      Message::OpenPrompt         => ["Open", "Öffnen", "Ouvrir"],
+      Message::NoDocuments        => ["No matching documents", "Keine passenden Dokumente", "Aucun document correspondant"],
+      Message::NoCommands         => ["No matching commands", "Keine passenden Befehle", "Aucune commande correspondante"],
+      Message::NoOutline          => ["No headings or variables", "Keine Überschriften oder Variablen", "Aucun titre ni variable"],
+      Message::NoEarlierResults   => ["This line hasn't evaluated to anything else", "Diese Zeile hatte kein anderes Ergebnis", "Cette ligne n'a pas eu d'autre résultat"],
+      Message::Precision          => ["Precision", "Nachkommastellen", "Précision"],
+      Message::Figures            => ["Figures", "Stellen", "Chiffres"],
+      Message::Rounding           => ["Rounding", "Rundung", "Arrondi"],
+      Message::Locale             => ["Locale", "Region", "Région"],
+      Message::Currency           => ["Currency", "Währung", "Devise"],
+      Message::StrictUnits        => ["Strict units", "Strenge Einheiten", "Unités strictes"],
+      Message::Places             => ["{} places", "{} Stellen", "{} décimales"],
+      Message::Significant        => ["{} significant", "{} signifikant", "{} significatifs"],
+      Message::Automatic          => ["automatic", "automatisch", "automatique"],
+      Message::Default            => ["default", "Standard", "par défaut"],
+      Message::Nothing            => ["none", "keine", "aucune"],
+      Message::On                 => ["on", "an", "oui"],
+      Message::Off                => ["off", "aus", "non"],
+      Message::SettingsHelp       => ["↑↓ Choose   ←→ Change   Esc Done", "↑↓ Wählen   ←→ Ändern   Esc Fertig", "↑↓ Choisir   ←→ Changer   Esc Terminé"],
+      Message::ModeNormal         => ["normal", "normal", "normal"],
+      Message::ModeDelete         => ["delete", "löschen", "supprimer"],
+      Message::Untitled           => ["untitled", "unbenannt", "sans titre"],
+      Message::NoRates            => ["no rates", "keine Kurse", "aucun taux"],
+      Message::Pinned             => ["pinned", "festgelegt", "fixés"],
+      Message::RatesFetched       => ["{} {} ago", "{} vor {}", "{} il y a {}"],
+      Message::RatesStale         => ["{} {} ago, stale", "{} vor {}, veraltet", "{} il y a {}, périmés"],
+      Message::Ago                => ["{} ago", "vor {}", "il y a {}"],
+      Message::RatesFetching      => ["fetching rates", "Kurse werden abgerufen", "récupération des taux"],
+      Message::NoQuotes           => ["no quotes", "keine Aktienkurse", "aucun cours"],
+      Message::QuotesFetching     => ["fetching quotes", "Aktienkurse werden abgerufen", "récupération des cours"],
+    }
+  }
+
+  /// This string in a language.
+  pub fn text(&self, lang: Language) -> &'static str {
+    self.catalog()[lang.index()]
+  }
+}
+
+/// A string in the current language.
+pub fn tr(msg: Message) -> &'static str {
+  msg.text(language())
+}
+
+/// A string in the current language, with values in the places marked
+/// for them, in order.
+pub fn fill(msg: Message, vals: &[&str]) -> String {
+  let mut out = String::new();
+  let mut vals = vals.iter();
+  let mut parts = tr(msg).split("{}").peekable();
+  while let Some(part) = parts.next() {
+    out.push_str(part);
+    if parts.peek().is_some() {
+      out.push_str(vals.next().unwrap_or(&""));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn translate_messages() {
+    assert_eq!(Some(Language::German), Language::from_name("DE"));
+    assert_eq!(None, Language::from_name("tlh"));
+    assert_eq!("Dokument öffnen", Message::Open.text(Language::German));
+    assert_eq!("Thème : {}", Message::ThemeNamed.text(Language::French));
+    assert_eq!("Theme: light", fill(Message::ThemeNamed, &["light"]));
+    assert_eq!("3 places", fill(Message::Places, &["3"]));
+  }
+
+}
@@ -0,0 +1,170 @@
+// Logging to a file in the state directory, for diagnosing the editor while
+// it holds the terminal. It implements the `log` facade, which the project
+// already builds with offline, rather than depending on `tracing`; messages
+// are lines of text, not structured events with spans.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path;
+use std::sync::Mutex;
+use std::time;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::state;
+
+/// The log, in the state directory.
+const FILE: &str = "resolver.log";
+/// The log is set aside for a new one once it grows past this size, in
+/// bytes; only the one before it is kept.
+const MAX_SIZE: u64 = 1 << 20;
+
+/// What is logged unless something else is chosen.
+pub const DEFAULT: &str = "warn";
+
+/// Which messages are logged: those at a level or more severe, which may be
+/// chosen differently for particular modules, as in `warn,rates=debug`.
+/// Modules are named as in the source, without the crate, and the most
+/// specific one that applies to a message decides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+  level: LevelFilter,
+  modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+  pub fn parse(spec: &str) -> Result<Filter, String> {
+    let mut filter = Filter{
+      level: LevelFilter::Warn,
+      modules: Vec::new(),
+    };
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+      match part.split_once('=') {
+        Some((module, level)) => filter.modules.push((module.trim().to_string(), parse_level(level)?)),
+        None => filter.level = parse_level(part)?,
+      }
+    }
+    Ok(filter)
+  }
+
+  /// The most detailed level anything is logged at.
+  fn max(&self) -> LevelFilter {
+    self.modules.iter().map(|(_, l)| *l).fold(self.level, Ord::max)
+  }
+
+  /// Whether a message at a level, from the module a target names, is logged.
+  fn allows(&self, target: &str, level: Level) -> bool {
+    let module = target.split_once("::").map_or("", |(_, m)| m); // without the crate
+    let within = |m: &str| module == m || module.strip_prefix(m).is_some_and(|rest| rest.starts_with("::"));
+    let chosen = self.modules.iter()
+      .filter(|(m, _)| within(m))
+      .max_by_key(|(m, _)| m.len())
+      .map_or(self.level, |(_, l)| *l);
+    level <= chosen
+  }
+}
+
+fn parse_level(name: &str) -> Result<LevelFilter, String> {
+  name.trim().parse().map_err(|_| format!("expected one of off, error, warn, info, debug, or trace, got {}", name.trim()))
+}
+
+struct Logger {
+  filter: Filter,
+  file: Mutex<fs::File>,
+}
+
+impl Log for Logger {
+  fn enabled(&self, meta: &Metadata) -> bool {
+    self.filter.allows(meta.target(), meta.level())
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+    let line = format!("{} {:<5} {}: {}\n", timestamp(time::SystemTime::now()), record.level(), record.target(), record.args());
+    if let Ok(mut file) = self.file.lock() {
+      let _ = file.write_all(line.as_bytes()); // there's nowhere to report it if we can't
+    }
+  }
+
+  fn flush(&self) {
+    if let Ok(mut file) = self.file.lock() {
+      let _ = file.flush();
+    }
+  }
+}
+
+/// The path to the log, if there is a state directory.
+pub fn path() -> Option<path::PathBuf> {
+  state::dir().map(|d| d.join(FILE))
+}
+
+/// Log messages the filter allows to the log in the state directory from
+/// now on. A log which has grown too large is set aside first.
+pub fn init(filter: Filter) -> io::Result<()> {
+  let path = state::file(FILE)?;
+  if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_SIZE) {
+    fs::rename(&path, path.with_extension("log.old"))?;
+  }
+  let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+  log::set_max_level(filter.max());
+  log::set_boxed_logger(Box::new(Logger{
+    filter,
+    file: Mutex::new(file),
+  })).map_err(io::Error::other)
+}
+
+/// The last lines of the log, oldest first.
+pub fn tail(n: usize) -> io::Result<Vec<String>> {
+  let path = match path() {
+    Some(path) => path,
+    None => return Ok(Vec::new()),
+  };
+  let text = match fs::read_to_string(path) {
+    Ok(text) => text,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(err) => return Err(err),
+  };
+  let lines: Vec<&str> = text.lines().collect();
+  Ok(lines[lines.len().saturating_sub(n)..].iter().map(|l| l.to_string()).collect())
+}
+
+/// Format a time as a date and time of day in UTC, as in
+/// `2024-03-01 14:05:09`.
+pub fn timestamp(t: time::SystemTime) -> String {
+  let secs = t.duration_since(time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+  let (days, secs) = (secs / 86_400, secs % 86_400);
+  // from days since the epoch to a civil date; see Howard Hinnant's date algorithms
+  let z = days as i64 + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 }else{ mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 }else{ 0 };
+  format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, secs / 3_600, secs % 3_600 / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn filter_messages() {
+    let filter = Filter::parse("info, rates=debug, rates::ecb=off").unwrap();
+    assert!(filter.allows("resolver::editor", Level::Info));
+    assert!(!filter.allows("resolver::editor", Level::Debug));
+    assert!(filter.allows("resolver::rates::file", Level::Debug));
+    assert!(!filter.allows("resolver::rates::ecb", Level::Error));
+    assert!(!filter.allows("resolver::ratesx", Level::Debug));
+    assert_eq!(LevelFilter::Debug, filter.max());
+    assert_eq!(Err("expected one of off, error, warn, info, debug, or trace, got loud".to_string()), Filter::parse("rates=loud"));
+    assert_eq!(Ok(Filter{level: LevelFilter::Warn, modules: Vec::new()}), Filter::parse(DEFAULT));
+    assert_eq!("1970-01-01 00:00:00", timestamp(time::UNIX_EPOCH));
+    assert_eq!("2024-02-29 13:05:09", timestamp(time::UNIX_EPOCH + time::Duration::from_secs(1_709_211_909)));
+  }
+
+}
@@ -1,15 +1,34 @@
+#[cfg(test)]
+mod bench;
 mod buffer;
+mod cli;
+mod clipboard;
+mod config;
+mod control;
+mod crash;
+mod document;
 mod editor;
 mod frame;
+mod i18n;
+mod logging;
+mod notify;
 mod options;
+mod rates;
 mod error;
 mod rdl;
+mod share;
+mod snippet;
+mod state;
 mod text;
+mod theme;
 mod util;
+mod workspace;
 
 use std::time;
-use std::io::stdout;
-use std::fs;
+use std::io::{self, stdout, IsTerminal};
+use std::path;
+use std::process;
+use std::thread;
 
 use crossterm;
 use crossterm::event;
@@ -19,6 +38,7 @@ use crossterm::terminal;
 use clap::Parser;
 
 use editor::Editor;
+use i18n::Message;
 use editor::writer::Writer;
 
 struct Finalize {
@@ -27,6 +47,10 @@ struct Finalize {
 
 impl Drop for Finalize {
   fn drop(&mut self) {
+    if thread::panicking() {
+      return; // the terminal was restored when the panic was reported
+    }
+    let _ = execute!(stdout(), event::DisableBracketedPaste); // not every terminal supports it
     terminal::disable_raw_mode().expect("Could not finalize terminal (good luck)");
     if !self.opts.debug_alternate {
       execute!(stdout(), terminal::LeaveAlternateScreen).expect("Could not exit alternate screen");
@@ -34,6 +58,7 @@ impl Drop for Finalize {
     if !self.opts.debug {
       Writer::clear().expect("Could not clear screen");
     }
+    Writer::restore_cursor().expect("Could not restore cursor");
   }
 }
 
@@ -42,30 +67,175 @@ struct Reader;
 impl Reader {
   fn read_key(&self) -> crossterm::Result<event::KeyEvent> {
     loop {
-      if event::poll(time::Duration::from_millis(500))? {
-        if let event::Event::Key(event) = event::read()? {
-          return Ok(event);
-        }
+      if let Some(event) = self.poll_key(time::Duration::from_millis(500))? {
+        return Ok(event);
       }
     }
   }
+
+  /// Wait a while for a key to be pressed, producing none if one isn't.
+  fn poll_key(&self, timeout: time::Duration) -> crossterm::Result<Option<event::KeyEvent>> {
+    match self.poll(timeout)? {
+      Some(event::Event::Key(event)) => Ok(Some(event)),
+      _ => Ok(None),
+    }
+  }
+
+  /// Wait a while for a key to be pressed or text to be pasted, producing
+  /// none if neither is.
+  fn poll(&self, timeout: time::Duration) -> crossterm::Result<Option<event::Event>> {
+    if event::poll(timeout)? {
+      if let event @ (event::Event::Key(_) | event::Event::Paste(_)) = event::read()? {
+        return Ok(Some(event));
+      }
+    }
+    Ok(None)
+  }
 }
 
 fn main() -> Result<(), error::Error> {
   let opts = options::Options::parse();
+  let _ = logging::init(opts.log_level.clone()); // logging is best effort
+  let init = config::init::load();
+  rdl::exec::set_globals(init.vars);
+  let (conf, probs) = config::file::load();
+  let (overrides, over_probs) = config::overrides::Overrides::load(&opts.set);
+  let plugins = conf.plugins.discover();
+  let plugin_probs: Vec<String> = plugins.iter().filter_map(|(name, res)| match res {
+    Ok(plugin) => plugin.module.as_ref().map(|m| format!("plugin {}: {} can't be run; this build has no WebAssembly runtime", name, m.display())),
+    Err(err) => Some(format!("plugin {}: {}", name, err)),
+  }).collect();
+  rdl::unit::set_custom(conf.units.clone());
+  rdl::scan::set_aliases(conf.aliases.clone());
+  text::width::set_ambiguous(conf.ambiguous_width.unwrap_or_else(text::width::detect));
+  i18n::set_language(conf.language.unwrap_or_else(i18n::detect));
+  clipboard::set_osc52(conf.osc52);
+  let interactive = !opts.quick && !opts.serve && opts.kernel.is_none() && !opts.install_kernel && opts.send.is_none() && opts.completions.is_none() && !opts.show_config && !opts.list_plugins && opts.expr.is_none() && opts.print.is_none() && opts.markup.is_none() && opts.view_markup.is_none() && opts.export.is_none() && opts.show_work.is_none() && opts.snippet.is_none() && opts.import.is_none() && opts.watch.is_none() && opts.diff_backup.is_none() && opts.diff_rev.is_none() && opts.restore_backup.is_none() && io::stdin().is_terminal();
+  if !interactive && opts.kernel.is_none() { // notebooks show the escape sequences the kernel sends them
+    text::color::set_styling(text::color::probe());
+  }
+  if !interactive {
+    for diag in &init.diagnostics {
+      eprintln!("resolver: {}", diag);
+    }
+    for prob in &probs {
+      eprintln!("resolver: {}", prob);
+    }
+    for prob in over_probs.iter().chain(plugin_probs.iter()) {
+      eprintln!("resolver: {}", prob);
+    }
+  }
+  if let Some(shell) = opts.completions {
+    return cli::completions::run(shell);
+  }
+  if let Some(req) = &opts.send {
+    if !cli::send::run(req)? {
+      process::exit(1);
+    }
+    return Ok(());
+  }
+  if opts.list_plugins {
+    return cli::config::plugins(&plugins);
+  }
+  if opts.show_config {
+    return cli::config::show(&conf, &overrides, opts.doc.as_deref());
+  }
+  if opts.serve {
+    return cli::serve::run(&conf, opts.port);
+  }
+  if let Some(file) = &opts.kernel {
+    return cli::kernel::run(&conf, file);
+  }
+  if opts.install_kernel {
+    let dir = cli::kernel::install()?;
+    println!("Installed the Jupyter kernel to {}", dir.display());
+    return Ok(());
+  }
+  if let Some(expr) = &opts.expr {
+    if !cli::oneshot::run(expr, opts.format)? {
+      process::exit(1);
+    }
+    return Ok(());
+  }
+  if opts.quick {
+    return cli::quick::run();
+  }
+  if let Some(doc) = &opts.print {
+    return cli::print::run(doc, opts.page_width, opts.page_height);
+  }
+  if let Some(doc) = &opts.markup {
+    return cli::markup::run(doc, opts.markup_width);
+  }
+  if let Some(file) = &opts.view_markup {
+    return cli::markup::view(file, opts.tab_width);
+  }
+  if let Some(file) = &opts.import {
+    return cli::migrate::run(file, opts.import_from);
+  }
+  if let Some(doc) = &opts.snippet {
+    return cli::snippet::run(doc, opts.lines.as_deref(), opts.svg, opts.markdown);
+  }
+  if let Some(doc) = &opts.export {
+    return cli::export::run(doc);
+  }
+  if let Some(doc) = &opts.show_work {
+    return cli::provenance::run(doc, &conf, opts.format);
+  }
+  if let (Some(n), Some(doc)) = (opts.diff_backup, &opts.doc) {
+    return cli::compare::run_backup(doc, n, opts.backups, &conf);
+  }
+  if let (Some(rev), Some(doc)) = (&opts.diff_rev, &opts.doc) {
+    return cli::compare::run_revision(doc, rev, &conf);
+  }
+  if let (Some(n), Some(doc)) = (opts.restore_backup, &opts.doc) {
+    return cli::backup::restore(doc, n, opts.backups);
+  }
+  if let Some(doc) = &opts.watch {
+    return cli::watch::run(doc, &opts);
+  }
+  if !io::stdin().is_terminal() {
+    return cli::pipe::run(&opts);
+  }
+  
   let _cleanup = Finalize{opts: opts.clone()};
+  crash::install();
   execute!(stdout(), terminal::EnterAlternateScreen)?;
   terminal::enable_raw_mode()?;
+  let _ = execute!(stdout(), event::EnableBracketedPaste); // so a paste arrives whole, not as keys
   
   let size = terminal::size().unwrap();
   let mut editor = Editor::new_with_size((size.0 as usize, size.1 as usize), opts.clone());
-  if let Some(doc) = opts.doc {
-    match fs::read_to_string(doc) {
-      Ok(text) => editor.set_text(text),
-      Err(err) => return Err(err.into()),
-    };
+  editor.detect_theme(); // before anything reads input
+  if let Some(doc) = &opts.doc {
+    let doc = path::Path::new(doc);
+    if doc.is_dir() {
+      editor.open_workspace(doc)?;
+    }else{
+      editor.open(doc)?;
+    }
+  }
+  if opts.encrypt {
+    editor.encrypt()?;
+  }
+  editor.set_overrides(overrides);
+  editor.set_config(conf);
+  let problems: Vec<String> = init.diagnostics.iter().map(|d| d.to_string()).chain(probs.iter().map(|p| p.to_string())).chain(over_probs).chain(plugin_probs).collect();
+  for prob in &problems {
+    log::warn!("{}", prob);
+  }
+  if !problems.is_empty() {
+    editor.notice(i18n::tr(Message::StartupProblems), problems);
   }
   
+  if let Err(err) = editor.listen() {
+    log::warn!("could not listen for requests: {}", err);
+  }
+  if opts.share {
+    match editor.share(opts.port) {
+      Ok(url) => editor.notice(i18n::tr(Message::Sharing), vec![i18n::fill(Message::SharingAt, &[&url])]),
+      Err(err) => editor.notice(i18n::tr(Message::Sharing), vec![i18n::fill(Message::NotShared, &[&err.to_string()])]),
+    }
+  }
   editor.draw()?;
   loop {
     if !editor.step()? {
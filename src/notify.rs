@@ -0,0 +1,62 @@
+use std::env;
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+
+/// Show a notification on the desktop. It is sent by `notify-send` or, on
+/// macOS, AppleScript; where neither can reach the desktop, as in a session
+/// over SSH, the terminal is asked to show it instead, with an OSC 9 escape
+/// sequence, which terminals that don't understand it ignore. Notifications
+/// are sent in the background, and whether they were shown isn't known.
+pub fn send(title: &str, body: &str) {
+  let remote = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+  if remote {
+    let _ = send_osc9(title, body);
+    return;
+  }
+  let cmd = command(title, body);
+  thread::spawn(move || {
+    let res = process::Command::new(&cmd[0])
+      .args(&cmd[1..])
+      .stdin(process::Stdio::null())
+      .stdout(process::Stdio::null())
+      .stderr(process::Stdio::null())
+      .status();
+    if let Err(err) = res {
+      log::debug!("{}: {}", cmd[0], err);
+    }
+  });
+}
+
+/// The command which shows a notification on this platform.
+fn command(title: &str, body: &str) -> Vec<String> {
+  if cfg!(target_os = "macos") {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    vec!["osascript".to_string(), "-e".to_string(), format!("display notification {} with title {}", quote(body), quote(title))]
+  }else{
+    vec!["notify-send".to_string(), "--app-name=resolver".to_string(), title.to_string(), body.to_string()]
+  }
+}
+
+fn send_osc9(title: &str, body: &str) -> io::Result<()> {
+  let text: String = format!("{}: {}", title, body).chars().filter(|c| !c.is_control()).collect();
+  let mut out = io::stdout();
+  write!(out, "\x1b]9;{}\x07", text)?;
+  out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn notification_command() {
+    let cmd = command("Total changed", "total = \"1,200 EUR\"");
+    if cfg!(target_os = "macos") {
+      assert_eq!("display notification \"total = \\\"1,200 EUR\\\"\" with title \"Total changed\"", cmd[2]);
+    }else{
+      assert_eq!(vec!["notify-send", "--app-name=resolver", "Total changed", "total = \"1,200 EUR\""], cmd);
+    }
+  }
+
+}
@@ -1,4 +1,12 @@
-use clap::Parser;
+use clap::{Parser, ArgEnum};
+
+use crate::document::migrate::Dialect;
+
+#[derive(ArgEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+  Text,
+  Json,
+}
 
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
@@ -11,6 +19,82 @@ pub struct Options {
   pub debug_editor: bool,
   #[clap(long)]
   pub verbose: bool,
-  #[clap(help="Document to open")]
+  #[clap(long, default_value=crate::logging::DEFAULT, value_name="FILTER", parse(try_from_str=crate::logging::Filter::parse), help="What to log, e.g., 'info' or 'warn,rates=debug'; the log is kept in the state directory")]
+  pub log_level: crate::logging::Filter,
+  #[clap(long, help="Encrypt the document with a passphrase when it is saved")]
+  pub encrypt: bool,
+  #[clap(long="eval", short='e', value_name="EXPR", help="Evaluate an expression, print the result, and exit")]
+  pub expr: Option<String>,
+  #[clap(long, short='q', help="Open a one-line calculator prompt, e.g., as a popup bound to a hotkey")]
+  pub quick: bool,
+  #[clap(long, value_name="DOC", help="Evaluate a document, print the results, and re-evaluate it whenever it changes")]
+  pub watch: Option<String>,
+  #[clap(long, value_name="DOC", help="Evaluate a document and print its variables and results as JSON")]
+  pub export: Option<String>,
+  #[clap(long, value_name="DOC", help="Evaluate a document and show the work behind each result: its expression with variables substituted, and the conversions and exchange rates applied")]
+  pub show_work: Option<String>,
+  #[clap(long, value_name="DOC", help="Print lines of a document and their results, highlighted, for sharing")]
+  pub snippet: Option<String>,
+  #[clap(long, value_name="RANGE", help="Lines to include in a snippet, e.g., '3-7'; the whole document by default")]
+  pub lines: Option<String>,
+  #[clap(long, help="Produce a snippet as an SVG image instead of ANSI-styled text")]
+  pub svg: bool,
+  #[clap(long, conflicts_with="svg", help="Produce a snippet as Markdown instead of ANSI-styled text")]
+  pub markdown: bool,
+  #[clap(long, value_name="FILE", help="Convert a Soulver or Numi document to Resolver syntax and print it")]
+  pub import: Option<String>,
+  #[clap(long, arg_enum, value_name="APP", help="The app an imported document is from; inferred from its extension by default")]
+  pub import_from: Option<Dialect>,
+  #[clap(long, value_name="DOC", help="Print a document paginated as plain text, suitable for a printer")]
+  pub print: Option<String>,
+  #[clap(long, default_value="80", help="Page width, in columns, when printing")]
+  pub page_width: usize,
+  #[clap(long, default_value="66", help="Page height, in lines, when printing")]
+  pub page_height: usize,
+  #[clap(long, value_name="DOC", help="Print a document as the editor draws it, with styles written as markup such as '<b>', in a fixed theme and width, for comparing against golden files")]
+  pub markup: Option<String>,
+  #[clap(long, default_value="100", help="Width, in columns, a document is drawn at as markup")]
+  pub markup_width: usize,
+  #[clap(long, value_name="FILE", help="Print a file of markup, such as a golden fixture, styled as it would be in the terminal")]
+  pub view_markup: Option<String>,
+  #[clap(long, default_value="8", help="Columns between tab stops when viewing markup")]
+  pub tab_width: usize,
+  #[clap(long, arg_enum, default_value="text", help="Output format when evaluating from the command line or a pipe")]
+  pub format: Format,
+  #[clap(long, short='r', help="When reading from a pipe, print only results instead of 'expression = result'")]
+  pub result_only: bool,
+  #[clap(long, default_value="5", value_name="N", help="Number of backups to keep for each document; zero disables backups")]
+  pub backups: usize,
+  #[clap(long, value_name="N", requires="doc", help="Show the lines of the document which differ from its Nth most recent backup, and how their results changed")]
+  pub diff_backup: Option<usize>,
+  #[clap(long, value_name="REV", requires="doc", help="Show the lines of the document which differ from a git revision of it, e.g., 'HEAD', and how their results changed")]
+  pub diff_rev: Option<String>,
+  #[clap(long, value_name="N", requires="doc", help="Restore the document from its Nth most recent backup")]
+  pub restore_backup: Option<usize>,
+  #[clap(long, help="Write results into the document when it is saved, after a delimiter, for viewing in other editors")]
+  pub write_results: bool,
+  #[clap(long, default_value=crate::document::results::DELIMITER, value_name="DELIM", help="Delimiter that precedes results written into the document")]
+  pub result_delimiter: String,
+  #[clap(long="set", value_name="KEY=VALUE", multiple_occurrences=true, help="Override a setting of documents and the configuration, e.g., 'precision=2'; also set as RESOLVER_PRECISION=2")]
+  pub set: Vec<String>,
+  #[clap(long, help="Print the effective settings, for the document if one is provided, and where each comes from")]
+  pub show_config: bool,
+  #[clap(long, help="List the enabled plugins and whether each could be loaded")]
+  pub list_plugins: bool,
+  #[clap(long, help="Serve a JSON API for evaluating expressions and documents on localhost")]
+  pub serve: bool,
+  #[clap(long, help="Share the editing session read-only with anyone on the local network, who can watch it from a browser")]
+  pub share: bool,
+  #[clap(long, default_value="7373", help="Port to serve the API or the shared session on")]
+  pub port: u16,
+  #[clap(long, value_name="CONNECTION_FILE", help="Run as a Jupyter kernel, connecting as the file Jupyter provides says")]
+  pub kernel: Option<String>,
+  #[clap(long, help="Install the Jupyter kernel, so notebooks can be created with it")]
+  pub install_kernel: bool,
+  #[clap(long, value_name="REQUEST", help="Ask the running editor to open a document or evaluate an expression, e.g., 'open budget.rdl' or 'eval 3 EUR in USD'")]
+  pub send: Option<String>,
+  #[clap(long, arg_enum, value_name="SHELL", help="Print a script which completes options, units, and currencies in a shell")]
+  pub completions: Option<crate::cli::completions::Shell>,
+  #[clap(help="Document to open, or a directory to open as a workspace")]
   pub doc: Option<String>,
 }
@@ -0,0 +1,71 @@
+use std::time;
+
+use crate::rates::{self, Fetched, RateProvider};
+use crate::rdl::unit;
+
+pub const NAME: &str = "ecb";
+
+/// The reference rates the European Central Bank publishes every working
+/// day, against the euro.
+const URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+/// Rates from the European Central Bank.
+pub struct Ecb;
+
+impl RateProvider for Ecb {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn ttl(&self) -> time::Duration {
+    time::Duration::from_secs(12 * 60 * 60) // published once a day
+  }
+
+  fn fetch(&self) -> Result<Fetched, String> {
+    parse(&rates::get(URL)?)
+  }
+}
+
+/// Find the value of an attribute in an element, written in either quotes.
+fn attr<'a>(elem: &'a str, name: &str) -> Option<&'a str> {
+  let at = elem.find(&format!("{}=", name))? + name.len() + 1;
+  let quote = elem[at..].chars().next()?;
+  let rest = &elem[at + 1..];
+  Some(&rest[..rest.find(quote)?])
+}
+
+/// Parse the rates in the bank's XML, which are elements such as
+/// `<Cube currency='USD' rate='1.0876'/>` inside one with the date.
+fn parse(xml: &str) -> Result<Fetched, String> {
+  let mut fetched = Fetched{rates: Vec::new(), published: None};
+  for elem in xml.split("<Cube").skip(1) {
+    let elem = &elem[..elem.find('>').unwrap_or(elem.len())];
+    if let Some(date) = attr(elem, "time") {
+      fetched.published = Some(date.to_string());
+    }
+    let rate = attr(elem, "currency").zip(attr(elem, "rate").and_then(|r| r.parse().ok()));
+    if let Some(rate) = rate.and_then(|(code, rate)| unit::Rate::new(&format!("EUR/{}", code), rate)) {
+      fetched.rates.push(rate);
+    }
+  }
+  if fetched.rates.is_empty() {
+    Err("no rates for the currencies Resolver knows".to_string())
+  }else{
+    Ok(fetched)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_rates() {
+    let xml = "<gesmes:Envelope><Cube><Cube time='2026-10-14'>\n<Cube currency='USD' rate='1.0876'/>\n<Cube currency=\"GBP\" rate=\"0.8512\"/>\n<Cube currency='SEK' rate='11.2'/>\n</Cube></Cube></gesmes:Envelope>";
+    let fetched = parse(xml).unwrap();
+    assert_eq!(Some("2026-10-14".to_string()), fetched.published);
+    assert_eq!(vec![unit::Rate::parse("EUR/USD = 1.0876").unwrap(), unit::Rate::parse("EUR/GBP = 0.8512").unwrap()], fetched.rates);
+    assert!(parse("<html>Maintenance</html>").is_err());
+  }
+
+}
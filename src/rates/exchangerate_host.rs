@@ -0,0 +1,70 @@
+use std::time;
+
+use crate::rates::{self, Fetched, RateProvider};
+use crate::rdl::unit;
+
+pub const NAME: &str = "exchangerate.host";
+
+const URL: &str = "https://api.exchangerate.host/live";
+
+/// Rates from exchangerate.host, which needs an access key.
+pub struct ExchangerateHost {
+  key: String,
+}
+
+impl ExchangerateHost {
+  pub fn new(key: &str) -> ExchangerateHost {
+    ExchangerateHost{
+      key: key.to_string(),
+    }
+  }
+}
+
+impl RateProvider for ExchangerateHost {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn ttl(&self) -> time::Duration {
+    time::Duration::from_secs(60 * 60)
+  }
+
+  fn fetch(&self) -> Result<Fetched, String> {
+    let codes: Vec<&str> = unit::CURRENCIES.iter().map(|c| c.code()).filter(|c| *c != "USD").collect();
+    parse(&rates::get(&format!("{}?access_key={}&source=USD&currencies={}", URL, self.key, codes.join(",")))?)
+  }
+}
+
+/// Parse the service's JSON, whose quotes are named for the pair they are
+/// the rate of, as in `{"quotes": {"USDEUR": 0.92}}`.
+fn parse(data: &str) -> Result<Fetched, String> {
+  let obj: serde_json::Value = serde_json::from_str(data).map_err(|err| err.to_string())?;
+  if let Some(err) = obj.get("error") {
+    return Err(err.get("info").and_then(|i| i.as_str()).map_or_else(|| err.to_string(), |i| i.to_string()));
+  }
+  let rates: Vec<unit::Rate> = obj.get("quotes").and_then(|q| q.as_object()).into_iter().flatten()
+    .filter(|(pair, _)| pair.len() == 6 && pair.is_ascii())
+    .filter_map(|(pair, rate)| unit::Rate::new(&format!("{}/{}", &pair[..3], &pair[3..]), rate.as_f64()?))
+    .collect();
+  if rates.is_empty() {
+    return Err("no rates for the currencies Resolver knows".to_string());
+  }
+  Ok(Fetched{
+    rates,
+    published: obj.get("timestamp").and_then(|t| t.as_i64()).map(|t| t.to_string()),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_quotes() {
+    let fetched = parse(r#"{"success": true, "timestamp": 1791964800, "source": "USD", "quotes": {"USDEUR": 0.92, "USDXAU": 0.0004}}"#).unwrap();
+    assert_eq!(vec![unit::Rate::parse("USD/EUR = 0.92").unwrap()], fetched.rates);
+    assert_eq!(Some("1791964800".to_string()), fetched.published);
+    assert_eq!(Err("Invalid access key".to_string()), parse(r#"{"success": false, "error": {"code": 101, "info": "Invalid access key"}}"#));
+  }
+
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::path;
+use std::time;
+
+use crate::config::plugins;
+use crate::rates::{Fetched, RateProvider};
+use crate::rdl::unit;
+
+pub const NAME: &str = "file";
+
+/// Rates kept in a TOML file, written as they are in the configuration,
+/// such as `"EUR/USD" = 1.09`. A `date` says when they were published.
+pub struct File {
+  path: path::PathBuf,
+}
+
+impl File {
+  /// Read rates from a file; `~` is the home directory, and relative paths
+  /// are relative to the configuration directory.
+  pub fn new(path: &str) -> File {
+    File{
+      path: plugins::expand(path),
+    }
+  }
+}
+
+impl RateProvider for File {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn ttl(&self) -> time::Duration {
+    time::Duration::from_secs(60)
+  }
+
+  fn fetch(&self) -> Result<Fetched, String> {
+    let text = fs::read_to_string(&self.path).map_err(|err| format!("{}: {}", self.path.display(), err))?;
+    let table = match text.parse::<toml::Value>() {
+      Ok(toml::Value::Table(table)) => table,
+      Ok(_) => toml::value::Table::new(),
+      Err(err) => return Err(format!("{}: {}", self.path.display(), err)),
+    };
+    let mut fetched = Fetched{rates: Vec::new(), published: None};
+    for (key, val) in &table {
+      if key == "date" {
+        fetched.published = val.as_str().map(|d| d.to_string());
+        continue;
+      }
+      match val.as_float().or_else(|| val.as_integer().map(|n| n as f64)).and_then(|r| unit::Rate::new(key, r)) {
+        Some(rate) => fetched.rates.push(rate),
+        None => return Err(format!("{}: {}: expected a pair of different currencies, such as \"EUR/USD\", and a positive rate, got {}", self.path.display(), key, val)),
+      };
+    }
+    Ok(fetched)
+  }
+}
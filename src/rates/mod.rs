@@ -0,0 +1,232 @@
+pub mod cache;
+pub mod ecb;
+pub mod exchangerate_host;
+pub mod file;
+pub mod quotes;
+
+use std::time;
+use std::thread;
+use std::process;
+use std::sync::mpsc;
+
+use crate::rdl::unit;
+
+/// The names of the providers exchange rates can be fetched from. Some take
+/// an argument, written after a colon, as in `file:~/rates.toml`.
+pub const NAMES: [&str; 3] = [ecb::NAME, exchangerate_host::NAME, file::NAME];
+
+/// How long to wait before fetching again after every provider failed.
+const RETRY: time::Duration = time::Duration::from_secs(10 * 60);
+
+/// Exchange rates as they were fetched from a provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+  pub rates: Vec<unit::Rate>,
+  pub published: Option<String>, // the date the provider published them, as it writes it
+}
+
+/// A service or file exchange rates are fetched from.
+pub trait RateProvider: Send {
+  /// The name of the provider, as it is configured.
+  fn name(&self) -> &str;
+  /// How long rates from this provider are current after they are fetched.
+  fn ttl(&self) -> time::Duration;
+  /// Fetch the current exchange rates, or describe why they couldn't be.
+  fn fetch(&self) -> Result<Fetched, String>;
+}
+
+/// Create a provider from its configuration, `name` or `name:argument`.
+pub fn provider(spec: &str) -> Result<Box<dyn RateProvider>, String> {
+  let (name, arg) = match spec.split_once(':') {
+    Some((name, arg)) => (name, Some(arg)),
+    None => (spec, None),
+  };
+  match (name, arg) {
+    (ecb::NAME, None) => Ok(Box::new(ecb::Ecb)),
+    (exchangerate_host::NAME, Some(key)) => Ok(Box::new(exchangerate_host::ExchangerateHost::new(key))),
+    (exchangerate_host::NAME, None) => Err(format!("{} needs an access key, as in \"{}:KEY\"", name, name)),
+    (file::NAME, Some(path)) => Ok(Box::new(file::File::new(path))),
+    (file::NAME, None) => Err(format!("{} needs a path, as in \"{}:~/rates.toml\"", name, name)),
+    (ecb::NAME, Some(_)) => Err(format!("{} takes no argument", name)),
+    _ => Err(format!("expected one of {}, got {}", NAMES.join(", "), spec)),
+  }
+}
+
+/// Fetch rates from each provider in turn until one produces them, and
+/// produce that provider with them, or why each provider failed.
+pub fn fetch(providers: &[Box<dyn RateProvider>]) -> Result<(&dyn RateProvider, Fetched), Vec<String>> {
+  let mut errs = Vec::new();
+  for provider in providers {
+    match provider.fetch() {
+      Ok(fetched) => return Ok((provider.as_ref(), fetched)),
+      Err(err) => {
+        log::debug!("{}: {}", provider.name(), err);
+        errs.push(format!("{}: {}", provider.name(), err));
+      },
+    };
+  }
+  Err(errs)
+}
+
+type Outcome = Result<(String, time::Duration, Fetched), Vec<String>>;
+
+/// Rates fetched from a provider, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Current {
+  pub provider: String,
+  pub fetched: time::SystemTime,
+  pub rates: Fetched,
+}
+
+/// Rates fetched in the background from the configured providers, which
+/// are fetched again once they are no longer current. The rates last
+/// fetched are cached, and used until they can be fetched again, however
+/// old they are.
+pub struct Feed {
+  specs: Vec<String>, // of the providers, in order
+  pending: Option<mpsc::Receiver<Outcome>>,
+  current: Option<Current>,
+  expires: Option<time::SystemTime>, // none to fetch as soon as possible
+  report: bool, // whether to report failures even with rates to fall back on
+}
+
+impl Feed {
+  pub fn new(specs: &[String]) -> Feed {
+    let names: Vec<&str> = specs.iter().map(|s| s.split(':').next().unwrap_or(s)).collect();
+    let cached = cache::load().filter(|(current, _)| names.contains(&current.provider.as_str())); // unless the providers have changed
+    Feed{
+      specs: specs.to_vec(),
+      pending: None,
+      current: cached.as_ref().map(|(current, _)| current.clone()),
+      expires: cached.map(|(_, expires)| expires),
+      report: false,
+    }
+  }
+
+  /// The rates last fetched, if any.
+  pub fn current(&self) -> Option<&Current> {
+    self.current.as_ref()
+  }
+
+  pub fn rates(&self) -> &[unit::Rate] {
+    self.current.as_ref().map_or(&[], |c| &c.rates.rates)
+  }
+
+  /// Whether the rates last fetched are no longer current.
+  pub fn is_stale(&self) -> bool {
+    self.expires.is_none_or(|e| e <= time::SystemTime::now())
+  }
+
+  /// Whether rates are being fetched in the background.
+  pub fn is_pending(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  /// Whether any providers are configured.
+  pub fn is_configured(&self) -> bool {
+    !self.specs.is_empty()
+  }
+
+  /// Start fetching rates in the background, unless there are no providers,
+  /// rates are being fetched already, or those fetched are still current.
+  pub fn refresh(&mut self) {
+    if self.specs.is_empty() || self.pending.is_some() || !self.is_stale() {
+      return;
+    }
+    let (tx, rx) = mpsc::channel();
+    let specs = self.specs.clone();
+    thread::spawn(move || {
+      let providers: Vec<Box<dyn RateProvider>> = specs.iter().filter_map(|s| provider(s).ok()).collect(); // invalid ones are reported with the configuration
+      let _ = tx.send(fetch(&providers).map(|(provider, fetched)| (provider.name().to_string(), provider.ttl(), fetched)));
+    });
+    self.pending = Some(rx);
+  }
+
+  /// Fetch rates again now, however current they are, and report it if
+  /// they can't be.
+  pub fn refresh_now(&mut self) {
+    self.expires = None;
+    self.report = true;
+    self.refresh();
+  }
+
+  /// Take rates fetched in the background once they arrive, or produce why
+  /// they could not be fetched. Failures aren't reported while there are
+  /// cached rates to use instead, unless rates were refreshed explicitly.
+  pub fn receive(&mut self) -> Option<Vec<String>> {
+    let res = self.pending.as_ref()?.try_recv().ok()?;
+    self.pending = None;
+    let report = std::mem::take(&mut self.report);
+    let now = time::SystemTime::now();
+    match res {
+      Ok((provider, ttl, rates)) => {
+        log::info!("fetched {} rates from {}", rates.rates.len(), provider);
+        let current = Current{provider, fetched: now, rates};
+        self.expires = Some(now + ttl);
+        let _ = cache::save(&current, now + ttl); // the cache is best effort
+        self.current = Some(current);
+        None
+      },
+      Err(errs) => {
+        log::warn!("could not fetch rates: {}", errs.join("; "));
+        self.expires = Some(now + RETRY); // rates which have expired are used meanwhile
+        (report || self.current.is_none()).then_some(errs)
+      },
+    }
+  }
+}
+
+/// Describe how long ago something happened, briefly, as in `3h`.
+pub fn age(since: time::SystemTime) -> String {
+  let secs = time::SystemTime::now().duration_since(since).map_or(0, |d| d.as_secs());
+  match secs {
+    s if s < 60 * 60      => format!("{}m", s / 60),
+    s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+    s                     => format!("{}d", s / (24 * 60 * 60)),
+  }
+}
+
+/// Fetch a document over HTTP. This runs curl, as copying runs the system's
+/// clipboard command, instead of linking an HTTP client.
+fn get(url: &str) -> Result<String, String> {
+  let out = process::Command::new("curl")
+    .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "10", url])
+    .output()
+    .map_err(|err| format!("could not run curl: {}", err))?;
+  if out.status.success() {
+    String::from_utf8(out.stdout).map_err(|err| err.to_string())
+  }else{
+    Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fail_over() {
+    let dir = std::env::temp_dir().join(format!("resolver-test-rates-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("rates.toml"), "\"EUR/USD\" = 1.25\n").unwrap();
+    let providers = vec![
+      provider(&format!("file:{}", dir.join("missing.toml").display())).unwrap(),
+      provider(&format!("file:{}", dir.join("rates.toml").display())).unwrap(),
+    ];
+    let res = fetch(&providers);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let (source, fetched) = res.unwrap();
+    assert_eq!("file", source.name());
+    assert_eq!(unit::Rate::parse("EUR/USD = 1.25").into_iter().collect::<Vec<_>>(), fetched.rates);
+
+    assert!(provider("ecb").is_ok());
+    assert_eq!(Some("exchangerate.host needs an access key, as in \"exchangerate.host:KEY\"".to_string()), provider("exchangerate.host").err());
+    assert_eq!(Some("expected one of ecb, exchangerate.host, file, got oanda".to_string()), provider("oanda").err());
+
+    let now = time::SystemTime::now();
+    assert_eq!("5m", age(now - time::Duration::from_secs(5 * 60 + 10)));
+    assert_eq!("23h", age(now - time::Duration::from_secs(23 * 60 * 60 + 10)));
+    assert_eq!("3d", age(now - time::Duration::from_secs(80 * 60 * 60)));
+  }
+
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::io;
+use std::time;
+
+use crate::state;
+use crate::rates::quotes::{self, Current, Fetched};
+
+const FILE: &str = "quotes.toml";
+
+/// Load the quotes last fetched, and when they expire, from the state
+/// directory. A missing or unreadable cache is treated as empty.
+pub fn load() -> Option<(Current, time::SystemTime)> {
+  decode(&state::file(FILE).and_then(fs::read_to_string).ok()?)
+}
+
+/// Save the quotes last fetched, and when they expire.
+pub fn save(current: &Current, expires: time::SystemTime) -> io::Result<()> {
+  fs::write(state::file(FILE)?, encode(current, expires))
+}
+
+fn secs(t: time::SystemTime) -> i64 {
+  t.duration_since(time::UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+fn time(secs: i64) -> Option<time::SystemTime> {
+  time::UNIX_EPOCH.checked_add(time::Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+fn encode(current: &Current, expires: time::SystemTime) -> String {
+  let mut table = toml::value::Table::new();
+  table.insert("provider".to_string(), toml::Value::String(current.provider.clone()));
+  table.insert("fetched".to_string(), toml::Value::Integer(secs(current.fetched)));
+  table.insert("expires".to_string(), toml::Value::Integer(secs(expires)));
+  if let Some(date) = &current.quotes.published {
+    table.insert("published".to_string(), toml::Value::String(date.clone()));
+  }
+  let quotes = current.quotes.quotes.iter().map(|(symbol, price)| (symbol.clone(), toml::Value::String(price.to_string()))).collect();
+  table.insert("quotes".to_string(), toml::Value::Table(quotes));
+  toml::Value::Table(table).to_string()
+}
+
+fn decode(text: &str) -> Option<(Current, time::SystemTime)> {
+  let table = match text.parse::<toml::Value>().ok()? {
+    toml::Value::Table(table) => table,
+    _ => return None,
+  };
+  let quotes = table.get("quotes")?.as_table()?.iter()
+    .map(|(symbol, price)| Some((symbol.clone(), quotes::parse_price(price.as_str()?)?)))
+    .collect::<Option<Vec<_>>>()?;
+  let current = Current{
+    provider: table.get("provider")?.as_str()?.to_string(),
+    fetched: time(table.get("fetched")?.as_integer()?)?,
+    quotes: Fetched{
+      quotes,
+      published: table.get("published").and_then(|p| p.as_str()).map(|p| p.to_string()),
+    },
+  };
+  Some((current, time(table.get("expires")?.as_integer()?)?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rdl::unit;
+
+  #[test]
+  fn round_trip() {
+    let current = Current{
+      provider: "stooq".to_string(),
+      fetched: time(1791964800).unwrap(),
+      quotes: Fetched{
+        quotes: vec![("AAPL".to_string(), unit::Value::new(227.55, unit::Unit::Money(unit::Currency::Usd))), ("^SPX".to_string(), unit::Value::raw(5801.25))],
+        published: Some("2026-10-14".to_string()),
+      },
+    };
+    let expires = time(1791966600).unwrap();
+    assert_eq!(Some((current.clone(), expires)), decode(&encode(&current, expires)));
+    assert_eq!(None, decode("provider = \"stooq\"\n"));
+  }
+
+}
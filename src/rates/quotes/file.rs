@@ -0,0 +1,80 @@
+use std::fs;
+use std::path;
+use std::time;
+
+use crate::config::plugins;
+use crate::rates::quotes::{self, Fetched, QuoteProvider};
+
+pub const NAME: &str = "file";
+
+/// Quotes kept in a TOML file, by symbol, such as `AAPL = "227.5 USD"`, or
+/// a number for an index. A `date` says when they were published.
+pub struct File {
+  path: path::PathBuf,
+}
+
+impl File {
+  /// Read quotes from a file; `~` is the home directory, and relative
+  /// paths are relative to the configuration directory.
+  pub fn new(path: &str) -> File {
+    File{
+      path: plugins::expand(path),
+    }
+  }
+}
+
+impl QuoteProvider for File {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn ttl(&self) -> time::Duration {
+    time::Duration::from_secs(60)
+  }
+
+  fn fetch(&self, symbols: &[String]) -> Result<Fetched, String> {
+    let text = fs::read_to_string(&self.path).map_err(|err| format!("{}: {}", self.path.display(), err))?;
+    parse(&text, symbols).map_err(|err| format!("{}: {}", self.path.display(), err))
+  }
+}
+
+fn parse(text: &str, symbols: &[String]) -> Result<Fetched, String> {
+  let table = match text.parse::<toml::Value>() {
+    Ok(toml::Value::Table(table)) => table,
+    Ok(_) => toml::value::Table::new(),
+    Err(err) => return Err(err.to_string()),
+  };
+  let mut fetched = Fetched{quotes: Vec::new(), published: None};
+  for (key, val) in &table {
+    if key == "date" {
+      fetched.published = val.as_str().map(|d| d.to_string());
+      continue;
+    }
+    let price = match val {
+      toml::Value::String(text) => quotes::parse_price(text),
+      _ => val.as_float().or_else(|| val.as_integer().map(|n| n as f64)).and_then(|n| quotes::parse_price(&n.to_string())),
+    };
+    match price {
+      Some(price) if symbols.iter().any(|s| s.eq_ignore_ascii_case(key)) => fetched.quotes.push((key.to_uppercase(), price)),
+      Some(_) => {},
+      None => return Err(format!("{}: expected a price, such as \"227.5 USD\", or the level of an index, got {}", key, val)),
+    };
+  }
+  Ok(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rdl::unit;
+
+  #[test]
+  fn parse_file() {
+    let symbols = vec!["AAPL".to_string(), "^SPX".to_string()];
+    let fetched = parse("date = \"2026-10-14\"\naapl = \"227.5 USD\"\n\"^SPX\" = 5801\nMSFT = \"415 USD\"\n", &symbols).unwrap();
+    assert_eq!(vec![("^SPX".to_string(), unit::Value::raw(5801.0)), ("AAPL".to_string(), unit::Value::new(227.5, unit::Unit::Money(unit::Currency::Usd)))], fetched.quotes);
+    assert_eq!(Some("2026-10-14".to_string()), fetched.published);
+    assert!(parse("AAPL = \"lots\"\n", &symbols).is_err());
+  }
+
+}
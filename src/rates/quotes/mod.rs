@@ -0,0 +1,238 @@
+pub mod cache;
+pub mod file;
+pub mod stooq;
+
+use std::time;
+use std::thread;
+use std::sync::mpsc;
+
+use crate::rdl::unit;
+
+/// The names of the providers quotes can be fetched from. Some take an
+/// argument, written after a colon, as in `file:~/quotes.toml`.
+pub const NAMES: [&str; 2] = [stooq::NAME, file::NAME];
+
+/// How long to wait before fetching again after every provider failed.
+const RETRY: time::Duration = time::Duration::from_secs(10 * 60);
+
+/// Quotes as they were fetched from a provider: the price of a share of
+/// each stock, or the level of each index, by its symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+  pub quotes: Vec<(String, unit::Value)>,
+  pub published: Option<String>, // the date the provider published them, as it writes it
+}
+
+/// A service or file quotes for stocks and indices are fetched from, as
+/// exchange rates are from a `RateProvider`.
+pub trait QuoteProvider: Send {
+  /// The name of the provider, as it is configured.
+  fn name(&self) -> &str;
+  /// How long quotes from this provider are current after they are fetched.
+  fn ttl(&self) -> time::Duration;
+  /// Fetch the current quotes for some symbols, or describe why they
+  /// couldn't be. Symbols the provider doesn't know are left out.
+  fn fetch(&self, symbols: &[String]) -> Result<Fetched, String>;
+}
+
+/// Create a provider from its configuration, `name` or `name:argument`.
+pub fn provider(spec: &str) -> Result<Box<dyn QuoteProvider>, String> {
+  let (name, arg) = match spec.split_once(':') {
+    Some((name, arg)) => (name, Some(arg)),
+    None => (spec, None),
+  };
+  match (name, arg) {
+    (stooq::NAME, None) => Ok(Box::new(stooq::Stooq)),
+    (file::NAME, Some(path)) => Ok(Box::new(file::File::new(path))),
+    (file::NAME, None) => Err(format!("{} needs a path, as in \"{}:~/quotes.toml\"", name, name)),
+    (stooq::NAME, Some(_)) => Err(format!("{} takes no argument", name)),
+    _ => Err(format!("expected one of {}, got {}", NAMES.join(", "), spec)),
+  }
+}
+
+/// Fetch quotes from each provider in turn until one produces them, and
+/// produce that provider with them, or why each provider failed.
+pub fn fetch<'a>(providers: &'a [Box<dyn QuoteProvider>], symbols: &[String]) -> Result<(&'a dyn QuoteProvider, Fetched), Vec<String>> {
+  let mut errs = Vec::new();
+  for provider in providers {
+    match provider.fetch(symbols) {
+      Ok(fetched) => return Ok((provider.as_ref(), fetched)),
+      Err(err) => {
+        log::debug!("{}: {}", provider.name(), err);
+        errs.push(format!("{}: {}", provider.name(), err));
+      },
+    };
+  }
+  Err(errs)
+}
+
+/// Parse a price as providers and the cache write it: an amount, and the
+/// code of its currency unless it is the level of an index, as in
+/// `227.5 USD`.
+pub fn parse_price(text: &str) -> Option<unit::Value> {
+  let mut parts = text.split_whitespace();
+  let value = parts.next()?.parse::<f64>().ok().filter(|v| v.is_finite())?;
+  match (parts.next(), parts.next()) {
+    (None, _) => Some(unit::Value::raw(value)),
+    (Some(code), None) => Some(unit::Value::new(value, unit::Unit::Money(unit::Currency::from_code(code)?))),
+    _ => None,
+  }
+}
+
+type Outcome = Result<(String, time::Duration, Fetched), Vec<String>>;
+
+/// Quotes fetched from a provider, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Current {
+  pub provider: String,
+  pub fetched: time::SystemTime,
+  pub quotes: Fetched,
+}
+
+/// Quotes for the symbols a document needs, fetched in the background
+/// from the configured providers as exchange rates are (see `rates::Feed`).
+/// Quotes are fetched again once they are no longer current, or when a
+/// symbol is needed which hasn't been fetched. The quotes last fetched are
+/// cached, and used until they can be fetched again, however old they are.
+pub struct Feed {
+  specs: Vec<String>, // of the providers, in order
+  symbols: Vec<String>, // needed
+  pending: Option<mpsc::Receiver<Outcome>>,
+  current: Option<Current>,
+  expires: Option<time::SystemTime>, // none to fetch as soon as possible
+  report: bool, // whether to report failures even with quotes to fall back on
+}
+
+impl Feed {
+  pub fn new(specs: &[String]) -> Feed {
+    let names: Vec<&str> = specs.iter().map(|s| s.split(':').next().unwrap_or(s)).collect();
+    let cached = cache::load().filter(|(current, _)| names.contains(&current.provider.as_str())); // unless the providers have changed
+    Feed{
+      specs: specs.to_vec(),
+      symbols: Vec::new(),
+      pending: None,
+      current: cached.as_ref().map(|(current, _)| current.clone()),
+      expires: cached.map(|(_, expires)| expires),
+      report: false,
+    }
+  }
+
+  /// The quotes last fetched, if any.
+  pub fn current(&self) -> Option<&Current> {
+    self.current.as_ref()
+  }
+
+  pub fn quotes(&self) -> &[(String, unit::Value)] {
+    self.current.as_ref().map_or(&[], |c| &c.quotes.quotes)
+  }
+
+  /// Whether the quotes last fetched are no longer current.
+  pub fn is_stale(&self) -> bool {
+    self.expires.is_none_or(|e| e <= time::SystemTime::now())
+  }
+
+  /// Whether quotes are being fetched in the background.
+  pub fn is_pending(&self) -> bool {
+    self.pending.is_some()
+  }
+
+  /// Whether any providers are configured.
+  pub fn is_configured(&self) -> bool {
+    !self.specs.is_empty()
+  }
+
+  /// Need quotes for some symbols, which are fetched as soon as possible
+  /// if any of them haven't been.
+  pub fn want(&mut self, symbols: Vec<String>) {
+    if symbols == self.symbols {
+      return;
+    }
+    if symbols.iter().any(|s| !self.quotes().iter().any(|(q, _)| q == s)) {
+      self.expires = None;
+    }
+    self.symbols = symbols;
+  }
+
+  /// Start fetching quotes in the background, unless there are no
+  /// providers or no symbols to fetch, quotes are being fetched already,
+  /// or those fetched are still current.
+  pub fn refresh(&mut self) {
+    if self.specs.is_empty() || self.symbols.is_empty() || self.pending.is_some() || !self.is_stale() {
+      return;
+    }
+    let (tx, rx) = mpsc::channel();
+    let specs = self.specs.clone();
+    let symbols = self.symbols.clone();
+    thread::spawn(move || {
+      let providers: Vec<Box<dyn QuoteProvider>> = specs.iter().filter_map(|s| provider(s).ok()).collect(); // invalid ones are reported with the configuration
+      let _ = tx.send(fetch(&providers, &symbols).map(|(provider, fetched)| (provider.name().to_string(), provider.ttl(), fetched)));
+    });
+    self.pending = Some(rx);
+  }
+
+  /// Fetch quotes again now, however current they are, and report it if
+  /// they can't be.
+  pub fn refresh_now(&mut self) {
+    self.expires = None;
+    self.report = true;
+    self.refresh();
+  }
+
+  /// Take quotes fetched in the background once they arrive, or produce
+  /// why they could not be fetched. Failures aren't reported while there
+  /// are cached quotes to use instead, unless quotes were refreshed
+  /// explicitly.
+  pub fn receive(&mut self) -> Option<Vec<String>> {
+    let res = self.pending.as_ref()?.try_recv().ok()?;
+    self.pending = None;
+    let report = std::mem::take(&mut self.report);
+    let now = time::SystemTime::now();
+    match res {
+      Ok((provider, ttl, quotes)) => {
+        log::info!("fetched {} quotes from {}", quotes.quotes.len(), provider);
+        let current = Current{provider, fetched: now, quotes};
+        self.expires = Some(now + ttl);
+        let _ = cache::save(&current, now + ttl); // the cache is best effort
+        self.current = Some(current);
+        None
+      },
+      Err(errs) => {
+        log::warn!("could not fetch quotes: {}", errs.join("; "));
+        self.expires = Some(now + RETRY); // quotes which have expired are used meanwhile
+        (report || self.current.is_none()).then_some(errs)
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quote_feed() {
+    let usd = unit::Unit::Money(unit::Currency::Usd);
+    assert_eq!(Some(unit::Value::new(227.5, usd)), parse_price("227.5 USD"));
+    assert_eq!(Some(unit::Value::raw(5801.25)), parse_price(" 5801.25 "));
+    assert_eq!(None, parse_price("227.5 SEK"));
+    assert_eq!(None, parse_price("N/D"));
+    assert!(provider("stooq").is_ok());
+    assert!(provider("file").is_err());
+    assert_eq!(Some("expected one of stooq, file, got yahoo".to_string()), provider("yahoo").err());
+
+    let mut feed = Feed::new(&[]);
+    feed.current = Some(Current{
+      provider: "stooq".to_string(),
+      fetched: time::SystemTime::now(),
+      quotes: Fetched{quotes: vec![("AAPL".to_string(), unit::Value::new(227.5, usd))], published: None},
+    });
+    feed.expires = Some(time::SystemTime::now() + time::Duration::from_secs(60));
+    feed.want(vec!["AAPL".to_string()]);
+    assert!(!feed.is_stale()); // already fetched
+    feed.want(vec!["AAPL".to_string(), "MSFT".to_string()]);
+    assert!(feed.is_stale());
+    feed.refresh();
+    assert!(!feed.is_pending()); // nothing is configured to fetch them from
+  }
+
+}
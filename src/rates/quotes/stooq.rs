@@ -0,0 +1,107 @@
+use std::time;
+
+use crate::rates::{self, quotes::{Fetched, QuoteProvider}};
+use crate::rdl::unit;
+
+pub const NAME: &str = "stooq";
+
+/// Quotes as CSV, with the symbol, the date, and the close, or the last
+/// price while the market is open.
+const URL: &str = "https://stooq.com/q/l/?f=sd2c&h&e=csv&s=";
+
+/// Quotes from Stooq, which needs no access key. A symbol without a market,
+/// such as `AAPL`, is listed in the US; others name theirs, as in `SAP.DE`,
+/// and indices start with a caret, as in `^SPX`.
+pub struct Stooq;
+
+impl QuoteProvider for Stooq {
+  fn name(&self) -> &str {
+    NAME
+  }
+
+  fn ttl(&self) -> time::Duration {
+    time::Duration::from_secs(15 * 60) // quotes are delayed about as long
+  }
+
+  fn fetch(&self, symbols: &[String]) -> Result<Fetched, String> {
+    let query: Vec<String> = symbols.iter().map(|s| stooq_symbol(s).to_lowercase()).collect();
+    parse(&rates::get(&format!("{}{}", URL, query.join("+")))?, symbols)
+  }
+}
+
+/// The symbol Stooq knows a stock by, with its market.
+fn stooq_symbol(symbol: &str) -> String {
+  if symbol.starts_with('^') || symbol.contains('.') {
+    symbol.to_string()
+  }else{
+    format!("{}.US", symbol)
+  }
+}
+
+/// The currency prices on a market are in, and what they must be divided
+/// by to be in it; London quotes pence. Indices have no currency.
+fn currency(symbol: &str) -> Option<(Option<unit::Currency>, f64)> {
+  if symbol.starts_with('^') {
+    return Some((None, 1.0));
+  }
+  let (code, div) = match symbol.rsplit_once('.').map(|(_, market)| market.to_uppercase()).as_deref() {
+    Some("US") => ("USD", 1.0),
+    Some("DE") | Some("F") => ("EUR", 1.0),
+    Some("UK") => ("GBP", 100.0),
+    Some("JP") => ("JPY", 1.0),
+    _ => return None,
+  };
+  Some((unit::Currency::from_code(code), div))
+}
+
+/// Parse the CSV, whose rows are `Symbol,Date,Close`. Symbols are matched
+/// to those asked for; those without a price, written `N/D`, or in a
+/// currency Resolver doesn't know are left out.
+fn parse(csv: &str, symbols: &[String]) -> Result<Fetched, String> {
+  let mut fetched = Fetched{quotes: Vec::new(), published: None};
+  for row in csv.lines().skip(1) {
+    let cols: Vec<&str> = row.split(',').map(str::trim).collect();
+    let (sym, date, close) = match cols[..] {
+      [sym, date, close] => (sym, date, close),
+      _ => continue,
+    };
+    let symbol = match symbols.iter().find(|s| stooq_symbol(s).eq_ignore_ascii_case(sym)) {
+      Some(symbol) => symbol,
+      None => continue,
+    };
+    let price = match (close.parse::<f64>(), currency(sym)) {
+      (Ok(price), Some((Some(currency), div))) => unit::Value::new(price / div, unit::Unit::Money(currency)),
+      (Ok(price), Some((None, _))) => unit::Value::raw(price),
+      _ => continue,
+    };
+    if fetched.published.as_deref().is_none_or(|d| d < date) {
+      fetched.published = Some(date.to_string());
+    }
+    fetched.quotes.push((symbol.clone(), price));
+  }
+  if fetched.quotes.is_empty() {
+    Err(format!("no quotes for {}", symbols.join(", ")))
+  }else{
+    Ok(fetched)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_quotes() {
+    let symbols: Vec<String> = ["AAPL", "VOD.UK", "^SPX", "NOPE", "SAP.HK"].iter().map(|s| s.to_string()).collect();
+    let csv = "Symbol,Date,Close\r\nAAPL.US,2026-10-14,227.55\r\nVOD.UK,2026-10-13,71.5\r\n^SPX,2026-10-14,5801.25\r\nNOPE.US,N/D,N/D\r\nSAP.HK,2026-10-14,12\r\n";
+    let fetched = parse(csv, &symbols).unwrap();
+    assert_eq!(vec![
+      ("AAPL".to_string(), unit::Value::new(227.55, unit::Unit::Money(unit::Currency::Usd))),
+      ("VOD.UK".to_string(), unit::Value::new(0.715, unit::Unit::Money(unit::Currency::Gbp))),
+      ("^SPX".to_string(), unit::Value::raw(5801.25)),
+    ], fetched.quotes);
+    assert_eq!(Some("2026-10-14".to_string()), fetched.published);
+    assert_eq!(Err("no quotes for NOPE".to_string()), parse("Symbol,Date,Close\nNOPE.US,N/D,N/D\n", &symbols[3..4]));
+  }
+
+}
@@ -1,12 +1,4 @@
-pub mod error;
-pub mod scan;
-pub mod parse;
-pub mod exec;
-pub mod unit;
-
-use scan::Scanner;
-use parse::Parser;
-use exec::Context;
+pub use resolver_core::*;
 
 use crate::text::attrs;
 
@@ -15,46 +7,57 @@ pub struct Options {
   pub debug: bool,   // enable debugging
 }
 
-pub fn render_with_options(cxt: &mut Context, text: &str, boff0: usize, boff1: usize, attrs: Option<&Vec<attrs::Attributes>>, opts: Option<&Options>) -> (attrs::Attributed, attrs::Attributed) {
+/// Import the file a line names, if it's a `use` directive, producing why
+/// it couldn't be imported if it couldn't. Where results are drawn, that's
+/// shown as the line's result; where they're printed, it's reported on
+/// standard error.
+pub fn import(imports: &mut import::Imports, cxt: &mut exec::Context, text: &str) -> Option<error::Error> {
+  imports.apply(cxt, text).and_then(Result::err)
+}
+
+/// The result drawn for a line whose file couldn't be imported: why it
+/// couldn't, starting at an offset, as `render_with_options` places them.
+pub fn render_failure(err: &error::Error, boff1: usize, attrs: Option<&Vec<attrs::Attributes>>) -> attrs::Attributed {
+  let text = err.to_string();
+  let spans = match attrs.and_then(|a| a.first()) {
+    Some(a) => vec![attrs::Span::new(boff1..boff1 + text.len(), a.clone())],
+    None => Vec::new(),
+  };
+  attrs::Attributed::new_with_string(text, spans)
+}
+
+pub fn render_with_options(cxt: &mut exec::Context, text: &str, boff0: usize, boff1: usize, attrs: Option<&Vec<attrs::Attributes>>, opts: Option<&Options>) -> (attrs::Attributed, attrs::Attributed) {
+  render_timed_with_options(cxt, text, boff0, boff1, attrs, opts, None)
+}
+
+/// Like `render_with_options`, adding how long was spent parsing and
+/// evaluating to the timings, if there are any.
+pub fn render_timed_with_options(cxt: &mut exec::Context, text: &str, boff0: usize, boff1: usize, attrs: Option<&Vec<attrs::Attributes>>, opts: Option<&Options>, timings: Option<&mut Timings>) -> (attrs::Attributed, attrs::Attributed) {
   let mut g = String::new();
   let mut s0: Vec<attrs::Span> = Vec::new();
   let mut s1: Vec<attrs::Span> = Vec::new();
-  let mut p = Parser::new(Scanner::new(text));
-  let mut i = 0;
-  loop {
-    let exp = match p.parse() {
-      Ok(exp) => exp,
-      Err(_)  => break,
-    };
-    
-    let res = match exp.ast.exec(cxt) {
-      Ok(res) => res.to_string(),
-      Err(_)  => continue,
-    };
-    
+  for (i, out) in render_timed(cxt, text, timings).into_iter().enumerate() {
     if i > 0 {
       g.push_str("; ");
     }
     
     if let Some(opts) = opts {
       if opts.debug {
-        g.push_str(&format!("[{:?}] ", boff0+exp.range.start..boff0+exp.range.end));
+        g.push_str(&format!("[{:?}] ", boff0+out.range.start..boff0+out.range.end));
       }
       if opts.debug || opts.verbose {
-        g.push_str(&format!("{} → ", exp.ast));
+        g.push_str(&format!("{} → ", out.expr));
       }
     }
     
     if let Some(attrs) = &attrs {
       let l = boff1 + g.len();
       let a = &attrs[i % attrs.len()];
-      s0.push(attrs::Span::new(boff0+exp.range.start..boff0+exp.range.end, a.clone()));
-      s1.push(attrs::Span::new(l..l+res.len(), a.clone()));
+      s0.push(attrs::Span::new(boff0+out.range.start..boff0+out.range.end, a.clone()));
+      s1.push(attrs::Span::new(l..l+out.text.len(), a.clone()));
     }
     
-    g.push_str(&res);
-    
-    i += 1;
+    g.push_str(&out.text);
   }
   (
     attrs::Attributed::new_with_str(text, s0),
@@ -0,0 +1,333 @@
+pub mod websocket;
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time;
+
+use serde::Serialize;
+
+/// How long sending an update to a viewer may take before it is given up
+/// on. Updates are sent from a thread of the viewer's own, so a slow one
+/// holds up neither the editor nor the others.
+const SEND_TIMEOUT: time::Duration = time::Duration::from_secs(1);
+
+/// The largest request line and headers accepted, together, in bytes.
+const MAX_HEAD: usize = 8 << 10;
+
+/// How long a browser may take to send any part of its request before it's
+/// given up on. Viewers may be quiet for as long as they like once they've
+/// been accepted.
+const READ_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// The page a viewer opens, which draws the document and keeps it up to date
+/// with the updates sent over its WebSocket.
+const PAGE: &str = include_str!("page.html");
+
+/// A line of the document as it is shown: its text and its results, each
+/// rendered as HTML.
+pub type Row = (String, String);
+
+/// What changed since a viewer was last sent an update: how many lines the
+/// document has, the lines which are different, and the line the cursor is
+/// on.
+#[derive(Debug, Serialize, PartialEq)]
+struct Update<'a> {
+  len: usize,
+  changed: Vec<(usize, &'a str, &'a str)>,
+  cursor: usize,
+}
+
+#[derive(Default)]
+struct Shared {
+  rows: Vec<Row>,
+  cursor: usize,
+  viewers: HashMap<usize, mpsc::Sender<String>>, // the updates each viewer's thread is to send it
+  next: usize,
+}
+
+impl Shared {
+  /// The update which brings a viewer with some rows up to date.
+  fn update<'a>(&'a self, before: &[Row]) -> Update<'a> {
+    Update{
+      len: self.rows.len(),
+      changed: self.rows.iter().enumerate()
+        .filter(|(i, row)| before.get(*i) != Some(row))
+        .map(|(i, (text, res))| (i, text.as_str(), res.as_str()))
+        .collect(),
+      cursor: self.cursor,
+    }
+  }
+}
+
+/// A session shared read-only with anyone on the local network who has
+/// its address, who can watch it from a browser. The address has a secret
+/// token in its path, without which nothing is served, since the document
+/// may be one which was decrypted to edit. The document is sent as it is
+/// rendered, and after that only the lines which change.
+pub struct Session {
+  addr: SocketAddr,
+  token: String,
+  shared: Arc<Mutex<Shared>>,
+}
+
+impl Session {
+  /// Start serving the session on a port of every interface.
+  pub fn start(port: u16) -> io::Result<Session> {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    let addr = listener.local_addr()?;
+    let token = token();
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let (state, secret) = (shared.clone(), token.clone());
+    thread::spawn(move || for stream in listener.incoming().flatten() {
+      let (state, secret) = (state.clone(), secret.clone());
+      thread::spawn(move || if let Err(err) = serve(stream, &secret, state) {
+        log::debug!("could not serve a viewer: {}", err);
+      });
+    });
+    Ok(Session{
+      addr,
+      token,
+      shared,
+    })
+  }
+
+  /// The address viewers open, on this machine's address on the local
+  /// network if that can be found.
+  pub fn url(&self) -> String {
+    let ip = local_ip().unwrap_or(Ipv4Addr::LOCALHOST.into());
+    format!("http://{}/{}/", SocketAddr::new(ip, self.addr.port()), self.token)
+  }
+
+  /// Send viewers the lines of the document which have changed since they
+  /// were last sent, and the line the cursor is on. They're handed to the
+  /// thread sending to each viewer, so this never waits on the network.
+  /// Viewers whose threads have given up are dropped.
+  pub fn publish(&self, rows: Vec<Row>, cursor: usize) {
+    let mut shared = self.shared.lock().unwrap();
+    let before = std::mem::replace(&mut shared.rows, rows);
+    let moved = std::mem::replace(&mut shared.cursor, cursor) != cursor;
+    let update = shared.update(&before);
+    if update.changed.is_empty() && update.len == before.len() && !moved {
+      return;
+    }
+    let msg = serde_json::to_string(&update).expect("Could not encode update");
+    shared.viewers.retain(|_, updates| updates.send(msg.clone()).is_ok());
+  }
+
+  /// How many viewers are watching.
+  pub fn viewers(&self) -> usize {
+    self.shared.lock().unwrap().viewers.len()
+  }
+}
+
+/// What a browser asks for: the path, and the headers which matter to
+/// how it's answered.
+#[derive(Debug, Default, PartialEq)]
+struct Request {
+  path: String,
+  key: Option<String>,
+  host: Option<String>,
+  origin: Option<String>,
+}
+
+/// Answer a request from a browser: the page for the session's path, and
+/// for `ws` under it the WebSocket the session is sent over. Anything
+/// else, and a WebSocket asked for by a page from somewhere else, which a
+/// browser says in its origin, is refused.
+fn serve(mut stream: TcpStream, token: &str, shared: Arc<Mutex<Shared>>) -> io::Result<()> {
+  stream.set_read_timeout(Some(READ_TIMEOUT))?;
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let req = match read_request(&mut reader)? {
+    Ok(req) => req,
+    Err(status) => return refuse(&mut stream, status),
+  };
+  match (req.path.strip_prefix('/').and_then(|p| p.strip_prefix(token)), req.key) {
+    (Some("/ws"), Some(_)) if !same_origin(req.origin.as_deref(), req.host.as_deref()) => refuse(&mut stream, 403),
+    (Some("/ws"), Some(key)) => {
+      stream.set_read_timeout(None)?;
+      let mut sender = websocket::accept(stream.try_clone()?, &key)?;
+      sender.set_write_timeout(Some(SEND_TIMEOUT))?;
+      let (updates, pending) = mpsc::channel::<String>();
+      let id = {
+        let mut shared = shared.lock().unwrap();
+        updates.send(serde_json::to_string(&shared.update(&[])).expect("Could not encode update")).expect("Could not queue update");
+        let id = shared.next;
+        shared.next += 1;
+        shared.viewers.insert(id, updates);
+        id
+      };
+      thread::spawn(move || {
+        for msg in pending {
+          if websocket::send(&mut sender, &msg).is_err() {
+            let _ = sender.shutdown(Shutdown::Both); // so the viewer is dropped, as if it had left
+            break;
+          }
+        }
+      });
+      log::info!("a viewer joined the shared session");
+      let res = websocket::drain(&mut reader);
+      shared.lock().unwrap().viewers.remove(&id);
+      log::info!("a viewer left the shared session");
+      res
+    },
+    (Some("" | "/"), _) => write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", PAGE.len(), PAGE),
+    _ => refuse(&mut stream, 404),
+  }
+}
+
+/// Read the path and headers of a request, or produce the status to refuse
+/// it with if it can't be read, as when it's longer than is allowed.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, u16>> {
+  let mut reader = reader.take(MAX_HEAD as u64);
+  let mut line = String::new();
+  reader.read_line(&mut line)?;
+  let mut req = match line.split_whitespace().nth(1) {
+    Some(path) => Request{path: path.to_string(), ..Request::default()},
+    None if reader.limit() == 0 => return Ok(Err(431)),
+    None => return Ok(Err(400)),
+  };
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+      if reader.limit() == 0 {
+        return Ok(Err(431));
+      }
+      break;
+    }
+    if let Some((name, val)) = header.split_once(':') {
+      let val = Some(val.trim().to_string());
+      match name.trim().to_ascii_lowercase().as_str() {
+        "sec-websocket-key" => req.key = val,
+        "host" => req.host = val,
+        "origin" => req.origin = val,
+        _ => {},
+      }
+    }
+  }
+  Ok(Ok(req))
+}
+
+/// Answer a request with nothing but a status.
+fn refuse(stream: &mut impl Write, status: u16) -> io::Result<()> {
+  let reason = match status {
+    400 => "Bad Request",
+    403 => "Forbidden",
+    404 => "Not Found",
+    431 => "Request Header Fields Too Large",
+    _   => "Internal Server Error",
+  };
+  write!(stream, "HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status, reason)
+}
+
+/// Whether a request comes from the session's own page, as a browser
+/// says in its origin, or from something other than a browser, which
+/// says nothing.
+fn same_origin(origin: Option<&str>, host: Option<&str>) -> bool {
+  match origin {
+    Some(origin) => host.is_some_and(|host| origin.strip_prefix("http://") == Some(host)),
+    None => true,
+  }
+}
+
+/// A secret for the session's address, as hex, from the system's random
+/// numbers. Where they can't be read from a device, as on Windows, the
+/// standard library's randomly keyed hashing stands in for them.
+fn token() -> String {
+  let mut bytes = [0u8; 16];
+  if fs::File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes)).is_err() {
+    let state = RandomState::new();
+    bytes[..8].copy_from_slice(&state.hash_one(0u8).to_le_bytes());
+    bytes[8..].copy_from_slice(&state.hash_one(1u8).to_le_bytes());
+  }
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// This machine's address on the local network: the one it would send
+/// from to reach elsewhere. Nothing is sent to find it.
+fn local_ip() -> Option<std::net::IpAddr> {
+  let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+  socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?; // a documentation address
+  socket.local_addr().ok().map(|a| a.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn share_session() {
+    let row = |t: &str, r: &str| (t.to_string(), r.to_string());
+    let shared = Shared{rows: vec![row("a = 2", "2"), row("a * 3", "6")], cursor: 1, ..Shared::default()};
+    assert_eq!(Update{len: 2, changed: vec![(1, "a * 3", "6")], cursor: 1}, shared.update(&[row("a = 2", "2"), row("a * 3", "9"), row("x", "")]));
+
+    let session = Session::start(0).unwrap();
+    session.publish(vec![row("a = 2", "2")], 0);
+    assert_eq!(32, session.token.len());
+    assert!(session.url().ends_with(&format!(":{}/{}/", session.addr.port(), session.token)));
+    let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), session.addr.port());
+    let get = |req: String| {
+      let mut res = String::new();
+      let mut stream = TcpStream::connect(addr).unwrap();
+      stream.write_all(req.as_bytes()).unwrap();
+      stream.read_to_string(&mut res).unwrap();
+      res
+    };
+    let page = get(format!("GET /{}/ HTTP/1.1\r\nHost: localhost\r\n\r\n", session.token));
+    assert!(page.starts_with("HTTP/1.1 200 OK\r\n") && page.contains("new WebSocket"));
+    assert!(get("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_string()).starts_with("HTTP/1.1 404 Not Found\r\n")); // without the token
+    let upgrade = "Upgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13";
+    assert!(get(format!("GET /ws HTTP/1.1\r\nHost: localhost\r\n{}\r\n\r\n", upgrade)).starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(get(format!("GET /{}/ws HTTP/1.1\r\nHost: localhost\r\nOrigin: http://evil.example\r\n{}\r\n\r\n", session.token, upgrade)).starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+    let mut viewer = TcpStream::connect(addr).unwrap();
+    write!(viewer, "GET /{}/ws HTTP/1.1\r\nHost: localhost\r\nOrigin: http://localhost\r\n{}\r\n\r\n", session.token, upgrade).unwrap();
+    let mut reader = BufReader::new(viewer.try_clone().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!("HTTP/1.1 101 Switching Protocols\r\n", line);
+    while line.trim() != "" {
+      line.clear();
+      reader.read_line(&mut line).unwrap();
+    }
+    let mut read_message = || {
+      let mut head = [0; 2];
+      reader.read_exact(&mut head).unwrap();
+      let mut body = vec![0; head[1] as usize];
+      reader.read_exact(&mut body).unwrap();
+      String::from_utf8(body).unwrap()
+    };
+    assert_eq!(r#"{"len":1,"changed":[[0,"a = 2","2"]],"cursor":0}"#, read_message());
+    while session.viewers() == 0 {
+      thread::sleep(time::Duration::from_millis(10));
+    }
+    session.publish(vec![row("a = 2", "2"), row("a * 3", "6")], 1);
+    assert_eq!(r#"{"len":2,"changed":[[1,"a * 3","6"]],"cursor":1}"#, read_message());
+    viewer.write_all(&[0x88, 0x80, 0, 0, 0, 0]).unwrap(); // close
+    while session.viewers() > 0 {
+      thread::sleep(time::Duration::from_millis(10));
+    }
+  }
+
+  #[test]
+  fn read_requests() {
+    let req = "GET /abc/ws HTTP/1.1\r\nHost: 192.168.1.2:7071\r\nOrigin: http://192.168.1.2:7071\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+    assert_eq!(Ok(Request{
+      path: "/abc/ws".to_string(),
+      key: Some("dGhlIHNhbXBsZSBub25jZQ==".to_string()),
+      host: Some("192.168.1.2:7071".to_string()),
+      origin: Some("http://192.168.1.2:7071".to_string()),
+    }), read_request(&mut req.as_bytes()).unwrap());
+    let status = |req: &str| read_request(&mut req.as_bytes()).unwrap().err();
+    assert_eq!(None, status("GET / HTTP/1.1\r\n\r\n"));
+    assert_eq!(Some(400), status("\r\n"));
+    assert_eq!(Some(431), status(&format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "x".repeat(MAX_HEAD))));
+    assert_eq!(Some(431), status(&format!("GET /{} HTTP/1.1\r\n\r\n", "x".repeat(MAX_HEAD))));
+  }
+
+}
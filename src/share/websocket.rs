@@ -0,0 +1,153 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::clipboard::base64;
+
+/// Appended to the key a client sends when it opens a WebSocket, to prove
+/// the server understood the request; see RFC 6455.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest frame accepted from a client, in bytes. Clients only ever
+/// send short control frames to a shared session.
+const MAX_FRAME: u64 = 1 << 16;
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+
+/// The header which accepts a WebSocket opened with a key.
+pub fn accept_key(key: &str) -> String {
+  base64(&sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/// Complete the opening handshake of a WebSocket, whose request has been
+/// read, producing a connection which messages can be sent on.
+pub fn accept(mut stream: TcpStream, key: &str) -> io::Result<TcpStream> {
+  write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept_key(key))?;
+  stream.flush()?;
+  Ok(stream)
+}
+
+/// Send a text message, as a single unmasked frame, as servers do.
+pub fn send(stream: &mut impl Write, text: &str) -> io::Result<()> {
+  stream.write_all(&frame(TEXT, text.as_bytes()))?;
+  stream.flush()
+}
+
+/// Encode a frame with its payload.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+  let mut out = vec![0x80 | opcode]; // final
+  match payload.len() {
+    n if n < 126 => out.push(n as u8),
+    n if n <= u16::MAX as usize => {
+      out.push(126);
+      out.extend_from_slice(&(n as u16).to_be_bytes());
+    },
+    n => {
+      out.push(127);
+      out.extend_from_slice(&(n as u64).to_be_bytes());
+    },
+  }
+  out.extend_from_slice(payload);
+  out
+}
+
+/// Read frames from a client until it closes the connection, discarding
+/// them, since nothing a client sends changes the session.
+pub fn drain(stream: &mut impl Read) -> io::Result<()> {
+  loop {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head)?;
+    let len = match head[1] & 0x7f {
+      126 => {
+        let mut buf = [0; 2];
+        stream.read_exact(&mut buf)?;
+        u16::from_be_bytes(buf) as u64
+      },
+      127 => {
+        let mut buf = [0; 8];
+        stream.read_exact(&mut buf)?;
+        u64::from_be_bytes(buf)
+      },
+      n => n as u64,
+    };
+    if len > MAX_FRAME {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is too large", len)));
+    }
+    let masked = head[1] & 0x80 != 0;
+    io::copy(&mut stream.take(len + if masked { 4 }else{ 0 }), &mut io::sink())?;
+    if head[0] & 0x0f == CLOSE {
+      return Ok(());
+    }
+  }
+}
+
+/// The SHA-1 digest of some bytes. It is only used to answer the opening
+/// handshake, which calls for it, and not for anything that needs to be
+/// secure.
+fn sha1(data: &[u8]) -> [u8; 20] {
+  let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+  let mut msg = data.to_vec();
+  msg.push(0x80);
+  while msg.len() % 64 != 56 {
+    msg.push(0);
+  }
+  msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+  for block in msg.chunks(64) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks(4).enumerate() {
+      w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+      w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = h;
+    for (i, w) in w.iter().enumerate() {
+      let (f, k) = match i {
+        0..=19  => ((b & c) | (!b & d), 0x5a827999),
+        20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+        40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+        _       => (b ^ c ^ d, 0xca62c1d6),
+      };
+      let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*w);
+      e = d;
+      d = c;
+      c = b.rotate_left(30);
+      b = a;
+      a = t;
+    }
+    for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+      *h = h.wrapping_add(v);
+    }
+  }
+  let mut out = [0; 20];
+  for (i, v) in h.iter().enumerate() {
+    out[i * 4..i * 4 + 4].copy_from_slice(&v.to_be_bytes());
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn frame_messages() {
+    let hex = |b: &[u8]| b.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", hex(&sha1(b"abc")));
+    assert_eq!("da39a3ee5e6b4b0d3255bfef95601890afd80709", hex(&sha1(b"")));
+    assert_eq!("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", accept_key("dGhlIHNhbXBsZSBub25jZQ=="));
+
+    assert_eq!(b"\x81\x02hi".to_vec(), frame(TEXT, b"hi"));
+    let long = frame(TEXT, &[b'x'; 300]);
+    assert_eq!(&[0x81, 126, 1, 44], &long[..4]);
+    assert_eq!(304, long.len());
+
+    let ping = [0x89, 0x82, 1, 2, 3, 4, b'o' ^ 1, b'k' ^ 2]; // masked, as clients send
+    let close = [0x88, 0x80, 0, 0, 0, 0];
+    let mut input = io::Cursor::new([&ping[..], &close[..], b"unread"].concat());
+    drain(&mut input).unwrap();
+    assert_eq!(ping.len() + close.len(), input.position() as usize);
+    assert!(drain(&mut io::Cursor::new(vec![0x81, 0xff])).is_err());
+  }
+
+}
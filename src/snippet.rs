@@ -0,0 +1,248 @@
+use std::ops;
+
+use crate::rdl;
+use crate::rdl::exec;
+use crate::rdl::import::Imports;
+use crate::text::attrs;
+use crate::text::color;
+use crate::theme::Theme;
+
+const GUTTER: usize = 3; // columns between the text and its results
+
+const FONT_SIZE: usize = 14;
+const CHAR_WIDTH: f64 = 8.4;  // advance of a monospace char at the font size
+const LINE_HEIGHT: usize = 20;
+const PADDING: usize = 16;
+const FOREGROUND: &str = "#d4d4d4";
+const BACKGROUND: &str = "#1e1e1e";
+
+/// A region of a document and its results, highlighted as they are in the
+/// editor, for sharing a calculation elsewhere.
+pub struct Snippet {
+  rows: Vec<(attrs::Attributed, attrs::Attributed)>,
+}
+
+impl Snippet {
+  /// Evaluate a document and take the lines in the provided range, which is
+  /// zero-based. The whole document is evaluated, so lines in the snippet see
+  /// everything defined before them.
+  pub fn new(mut cxt: exec::Context, text: &str, lines: ops::Range<usize>, imports: &mut Imports) -> Snippet {
+    Snippet::evaluate(&mut cxt, text, lines, imports)
+  }
+
+  /// Like `new`, but evaluating in a context which is kept afterwards, so
+  /// what the document defines is seen by whatever is evaluated in it next.
+  pub fn evaluate(cxt: &mut exec::Context, text: &str, lines: ops::Range<usize>, imports: &mut Imports) -> Snippet {
+    let style = Theme::dark().results;
+    let mut rows = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+      if i >= lines.end {
+        break;
+      }
+      let failed = rdl::import(imports, cxt, line);
+      let mut row = rdl::render_with_options(cxt, line, 0, 0, Some(&style), None);
+      if let Some(err) = failed {
+        row.1 = rdl::render_failure(&err, 0, Some(&style));
+      }
+      if i >= lines.start {
+        rows.push(row);
+      }
+    }
+    Snippet{
+      rows,
+    }
+  }
+
+  fn width(&self) -> usize {
+    self.rows.iter().map(|(t, _)| t.text().chars().count()).max().unwrap_or(0)
+  }
+
+  /// Render the snippet as text styled with ANSI escape sequences, with the
+  /// results aligned in a column beside the text.
+  pub fn ansi(&self) -> String {
+    self.text(attrs::Mode::Terminal)
+  }
+
+  /// Render the snippet as text without any styling, laid out as `ansi`
+  /// lays it out.
+  pub fn plain(&self) -> String {
+    self.text(attrs::Mode::Plain)
+  }
+
+  /// Render the snippet as Markdown, keeping what styling Markdown can,
+  /// with each line of it ending in a line break, for pasting into a README
+  /// or a chat.
+  pub fn markdown(&self) -> String {
+    self.text(attrs::Mode::Markdown).lines().map(|l| format!("{}  \n", l)).collect()
+  }
+
+  fn text(&self, mode: attrs::Mode) -> String {
+    let width = self.width();
+    let mut out = String::new();
+    for row in &self.rows {
+      out.push_str(&self.row(row, width).render_with_mode(mode));
+      out.push('\n');
+    }
+    out
+  }
+
+  /// A line of the snippet with its results, if it has any, aligned in a
+  /// column the provided width from its start.
+  fn row(&self, (text, res): &(attrs::Attributed, attrs::Attributed), width: usize) -> attrs::Attributed {
+    if res.text().is_empty() {
+      text.clone()
+    }else{
+      let mut row = text.clone();
+      row.push_str(&" ".repeat(width - text.text().chars().count() + GUTTER));
+      row + res.clone()
+    }
+  }
+
+  /// Render the snippet as an SVG image.
+  pub fn svg(&self) -> String {
+    let width = self.width();
+    let rw = self.rows.iter().map(|(_, r)| r.text().chars().count()).max().unwrap_or(0);
+    let cols = width + GUTTER + rw;
+    let w = (cols as f64 * CHAR_WIDTH).ceil() as usize + PADDING * 2;
+    let h = self.rows.len() * LINE_HEIGHT + PADDING * 2;
+
+    let mut out = String::new();
+    out.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n", w, h, w, h));
+    out.push_str(&format!("<rect width=\"100%\" height=\"100%\" rx=\"6\" fill=\"{}\"/>\n", BACKGROUND));
+    out.push_str(&format!("<g font-family=\"monospace\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">\n", FONT_SIZE, FOREGROUND));
+    for (i, (text, res)) in self.rows.iter().enumerate() {
+      let y = PADDING + (i + 1) * LINE_HEIGHT - (LINE_HEIGHT - FONT_SIZE);
+      out.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", PADDING, y, svg_spans(text)));
+      if !res.text().is_empty() {
+        let x = PADDING as f64 + ((width + GUTTER) as f64 * CHAR_WIDTH);
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{}\">{}</text>\n", x, y, svg_spans(res)));
+      }
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+  }
+
+  /// Render the snippet as an HTML `pre` element, with the results aligned
+  /// in a column beside the text.
+  pub fn html(&self) -> String {
+    let width = self.width();
+    let mut out = format!("<pre style=\"color: {}; background: {}; padding: {}px; border-radius: 6px\">", FOREGROUND, BACKGROUND, PADDING / 2);
+    for row in &self.rows {
+      out.push_str(&self.row(row, width).render_with_mode(attrs::Mode::Html));
+      out.push('\n');
+    }
+    out.push_str("</pre>");
+    out
+  }
+
+  /// Render the snippet as an RTF document, in a fixed-width font, with the
+  /// results aligned in a column beside the text, for pasting into word
+  /// processors and mail.
+  pub fn rtf(&self) -> String {
+    let width = self.width();
+    let mut out = format!("{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fmodern Courier New;}}}}{}\\f0 ", color::rtf_table());
+    for row in &self.rows {
+      out.push_str(&self.row(row, width).render_with_mode(attrs::Mode::Rtf));
+      out.push_str("\\par\n");
+    }
+    out.push('}');
+    out
+  }
+
+  /// Render each line of the snippet as HTML, separately from its
+  /// results, so they can be laid out and updated a line at a time.
+  pub fn html_rows(&self) -> Vec<(String, String)> {
+    self.rows.iter().map(|(text, res)| (text.render_with_mode(attrs::Mode::Html), res.render_with_mode(attrs::Mode::Html))).collect()
+  }
+}
+
+/// Parse a range of lines such as `3-7` or `5`, which are one-based and
+/// inclusive, into a zero-based range.
+pub fn parse_lines(spec: &str) -> Option<ops::Range<usize>> {
+  let (start, end) = match spec.split_once('-') {
+    Some((start, end)) => (start.trim().parse::<usize>().ok()?, end.trim().parse::<usize>().ok()?),
+    None => {
+      let n = spec.trim().parse::<usize>().ok()?;
+      (n, n)
+    },
+  };
+  if start == 0 || end < start {
+    None
+  }else{
+    Some(start - 1..end)
+  }
+}
+
+/// Escape text for SVG, wrapping each styled part of it as `wrap` does.
+fn styled(text: &attrs::Attributed, wrap: fn(&attrs::Attributes, &str) -> String) -> String {
+  text.segments().map(|(seg, attrs)| match attrs {
+    Some(attrs) => wrap(attrs, &attrs::escape_html(seg)),
+    None => attrs::escape_html(seg),
+  }).collect()
+}
+
+fn svg_spans(text: &attrs::Attributed) -> String {
+  styled(text, |a, text| {
+    let mut tspan = String::from("<tspan");
+    if let Some(color) = a.color.and_then(attrs::hex) {
+      tspan.push_str(&format!(" fill=\"{}\"", color));
+    }
+    if a.bold {
+      tspan.push_str(" font-weight=\"bold\"");
+    }
+    if let Some(decoration) = a.decoration() {
+      tspan.push_str(&format!(" text-decoration=\"{}\"", decoration));
+    }
+    if a.italic {
+      tspan.push_str(" font-style=\"italic\"");
+    }
+    if a.dim {
+      tspan.push_str(" fill-opacity=\"0.6\"");
+    }
+    format!("{}>{}</tspan>", tspan, text)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_snippet() {
+    let text = "Budget\nrent = 1200\nx = rent * 2\nnot this\n";
+    let snip = Snippet::new(exec::Context::new(), text, 1..3, &mut Imports::new(std::path::Path::new("")));
+    assert_eq!(2, snip.rows.len());
+
+    assert_eq!("rent = 1200    1200\nx = rent * 2   2400\n", snip.plain());
+    let stripped: String = snip.ansi().split('\x1b').map(|s| s.split_once('m').map_or(s, |(_, s)| s)).collect();
+    assert_eq!(snip.plain(), stripped);
+
+    assert_eq!("**rent = 1200**    **1200**  \n**x = rent \\* 2**   **2400**  \n", snip.markdown());
+
+    let svg = snip.svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("<tspan fill=\"#f5f543\" font-weight=\"bold\">rent = 1200</tspan>"));
+    assert!(svg.contains(">2400</tspan></text>"));
+
+    let html = snip.html();
+    assert!(html.starts_with("<pre style=\"color: #d4d4d4;"));
+    assert!(html.contains("<span style=\"color: #f5f543; font-weight: bold\">rent = 1200</span>"));
+    assert!(html.ends_with("2400</span>\n</pre>"));
+    assert_eq!("<span style=\"color: #f5f543; font-weight: bold\">1200</span>", snip.html_rows()[0].1);
+
+    let rtf = snip.rtf();
+    assert!(rtf.starts_with("{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Courier New;}}{\\colortbl;"));
+    assert!(rtf.contains("{\\b\\cf12 rent = 1200}    {\\b\\cf12 1200}\\par\n"));
+    assert!(rtf.ends_with("2400}\\par\n}"));
+  }
+
+  #[test]
+  fn parse_line_ranges() {
+    assert_eq!(Some(2..7), parse_lines("3-7"));
+    assert_eq!(Some(4..5), parse_lines("5"));
+    assert_eq!(None, parse_lines("0-2"));
+    assert_eq!(None, parse_lines("7-3"));
+    assert_eq!(None, parse_lines("x"));
+  }
+
+}
@@ -0,0 +1,31 @@
+pub mod recent;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path;
+
+const APP: &str = "resolver";
+
+/// The directory in which persistent application state is kept. This follows
+/// the XDG convention: `$XDG_STATE_HOME/resolver`, falling back to
+/// `~/.local/state/resolver` when the variable is not set.
+pub fn dir() -> Option<path::PathBuf> {
+  if let Some(base) = env::var_os("XDG_STATE_HOME") {
+    if !base.is_empty() {
+      return Some(path::PathBuf::from(base).join(APP));
+    }
+  }
+  env::var_os("HOME").map(|home| path::PathBuf::from(home).join(".local").join("state").join(APP))
+}
+
+/// Produce the path to a file in the state directory, creating the directory
+/// if it does not already exist.
+pub fn file(name: &str) -> io::Result<path::PathBuf> {
+  let dir = match dir() {
+    Some(dir) => dir,
+    None => return Err(io::Error::new(io::ErrorKind::NotFound, "No state directory is available")),
+  };
+  fs::create_dir_all(&dir)?;
+  Ok(dir.join(name))
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::state;
+
+const FILE: &str = "recent";
+const MAX_DOCS: usize = 50;
+
+/// Recently opened documents, most recent first, persisted in the state
+/// directory as one absolute path per line.
+pub struct Recent {
+  docs: Vec<path::PathBuf>,
+}
+
+impl Recent {
+  pub fn new() -> Recent {
+    Recent{
+      docs: Vec::new(),
+    }
+  }
+  
+  /// Load the recent document list. A missing or unreadable list is treated
+  /// as empty; this is a convenience and must never prevent startup.
+  pub fn load() -> Recent {
+    let text = match state::file(FILE).and_then(fs::read_to_string) {
+      Ok(text) => text,
+      Err(_) => return Recent::new(),
+    };
+    Recent{
+      docs: text.lines().filter(|l| !l.is_empty()).map(path::PathBuf::from).take(MAX_DOCS).collect(),
+    }
+  }
+  
+  pub fn save(&self) -> io::Result<()> {
+    let mut text = String::new();
+    for doc in &self.docs {
+      text.push_str(&doc.to_string_lossy());
+      text.push('\n');
+    }
+    fs::write(state::file(FILE)?, text)
+  }
+  
+  pub fn docs(&self) -> &Vec<path::PathBuf> {
+    &self.docs
+  }
+  
+  /// Move a document to the front of the list, adding it if necessary.
+  pub fn add(&mut self, doc: &path::Path) {
+    let doc = match fs::canonicalize(doc) {
+      Ok(doc) => doc,
+      Err(_) => doc.to_owned(),
+    };
+    self.docs.retain(|e| *e != doc);
+    self.docs.insert(0, doc);
+    self.docs.truncate(MAX_DOCS);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn add_recent() {
+    let mut r = Recent::new();
+    r.add(path::Path::new("/a/one.res"));
+    r.add(path::Path::new("/a/two.res"));
+    r.add(path::Path::new("/a/one.res"));
+    assert_eq!(&vec![path::PathBuf::from("/a/one.res"), path::PathBuf::from("/a/two.res")], r.docs());
+    for i in 0..MAX_DOCS {
+      r.add(&path::PathBuf::from(format!("/b/{}.res", i)));
+    }
+    assert_eq!(MAX_DOCS, r.docs().len());
+    assert_eq!(path::PathBuf::from(format!("/b/{}.res", MAX_DOCS - 1)), r.docs()[0]);
+  }
+  
+}
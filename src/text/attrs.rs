@@ -1,34 +1,73 @@
+use std::io;
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::ops;
+use std::collections::BTreeSet;
 use std::cmp::{min, max, Ordering};
 
-use crossterm::style::{Stylize, Color};
+use crossterm::style::{Color, Colored};
+#[cfg(feature = "serialize")]
+use serde::{Serialize, Deserialize};
 
 use crate::util;
+use crate::text::layout;
+use crate::text::sanitize;
+use crate::text::color::{self, Styling};
+use crate::text::width;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Mode {
-  Terminal,
-  Markup,
+  Terminal, // styled with escape sequences
+  Markup,   // styled with tags, as in `<b>total</b>`
+  Html,     // styled with HTML, as in `<span style="font-weight: bold">total</span>`
+  Plain,    // not styled at all
+  Markdown, // styled as far as Markdown can, as in `**total**`
+  Rtf,      // styled with RTF, as in `{\b total}`
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Attributes {
   pub bold: bool,
+  pub underline: bool,
+  pub italic: bool,
+  pub strikethrough: bool,
+  pub dim: bool,
   pub invert: bool,
+  #[cfg_attr(feature = "serialize", serde(default, with = "color::serde_option"))]
   pub color: Option<Color>,
+  #[cfg_attr(feature = "serialize", serde(default, with = "color::serde_option"))]
   pub background: Option<Color>,
+  #[cfg_attr(feature = "serialize", serde(default))]
+  pub link: Option<String>, // the URL the text links to
 }
 
 impl Attributes {
   pub fn merged(&self, with: &Attributes) -> Attributes {
     Attributes{
       bold: self.bold || with.bold,
+      underline: self.underline || with.underline,
+      italic: self.italic || with.italic,
+      strikethrough: self.strikethrough || with.strikethrough,
+      dim: self.dim || with.dim,
       invert: self.invert || with.invert,
       color: util::coalesce(self.color, with.color),
       background: util::coalesce(self.background, with.background),
+      link: self.link.clone().or_else(|| with.link.clone()),
     }
   }
   
+  /// Merge attributes as `merged` does, but with the background a blend of
+  /// both of them, if they both have one, so that a highlight laid over
+  /// another shows through it rather than hiding it.
+  pub fn blended(&self, with: &Attributes) -> Attributes {
+    let background = match (self.background, with.background) {
+      (Some(a), Some(b)) => Some(color::blend(a, b).unwrap_or(a)),
+      (a, b) => a.or(b),
+    };
+    Attributes{background, ..self.merged(with)}
+  }
+  
   pub fn render(&self, text: &str) -> String {
     self.render_with_mode(text, Mode::Terminal)
   }
@@ -37,31 +76,43 @@ impl Attributes {
     match mode {
       Mode::Terminal => self.render_term(text),
       Mode::Markup   => self.render_html(text),
+      Mode::Html     => self.render_css(text),
+      Mode::Plain    => text.to_string(),
+      Mode::Markdown => self.render_markdown(text),
+      Mode::Rtf      => self.render_rtf(text),
     }
   }
   
   fn render_term(&self, text: &str) -> String {
-    let mut styled = text.stylize();
-    if self.bold {
-      styled = styled.bold();
-    }
-    if self.invert {
-      styled = styled.reverse();
-    }
-    if let Some(color) = self.color {
-      styled = styled.with(color);
-    }
-    if let Some(background) = self.background {
-      styled = styled.on(background);
-    }
-    styled.to_string()
+    format!("{}{}{}", transition(&PLAIN, self), text, transition(self, &PLAIN))
+  }
+  
+  /// Whether text with these attributes is styled any differently in a
+  /// terminal than plain text is, apart from linking somewhere.
+  fn is_styled(&self) -> bool {
+    self.bold || self.underline || self.italic || self.strikethrough || self.dim || self.invert || self.color.is_some() || self.background.is_some()
   }
   
   fn render_html(&self, text: &str) -> String {
     let mut attrd = String::new();
+    if let Some(link) = &self.link {
+      attrd.push_str(&format!("<link:{}>", link));
+    }
     if self.bold {
       attrd.push_str("<b>");
     }
+    if self.underline {
+      attrd.push_str("<u>");
+    }
+    if self.italic {
+      attrd.push_str("<i>");
+    }
+    if self.strikethrough {
+      attrd.push_str("<s>");
+    }
+    if self.dim {
+      attrd.push_str("<dim>");
+    }
     if self.invert {
       attrd.push_str("<invert>");
     }
@@ -81,17 +132,187 @@ impl Attributes {
     if self.invert {
       attrd.push_str("</invert>");
     }
+    if self.dim {
+      attrd.push_str("</dim>");
+    }
+    if self.strikethrough {
+      attrd.push_str("</s>");
+    }
+    if self.italic {
+      attrd.push_str("</i>");
+    }
+    if self.underline {
+      attrd.push_str("</u>");
+    }
     if self.bold {
       attrd.push_str("</b>");
     }
+    if let Some(link) = &self.link {
+      attrd.push_str(&format!("</link:{}>", link));
+    }
     attrd
   }
+  
+  /// Render text as Markdown, which has bold, italic, and struck through
+  /// text, and code, which inverted text is written as, but neither color
+  /// nor underlining. Text which is code isn't escaped; see `shown`.
+  fn render_markdown(&self, text: &str) -> String {
+    let body = text.trim_matches(' '); // emphasis can't start or end with a space
+    if body.is_empty() {
+      return text.to_string();
+    }
+    let mut attrd = body.to_string();
+    if self.invert {
+      attrd = match body.contains('`') {
+        true  => format!("`` {} ``", attrd),
+        false => format!("`{}`", attrd),
+      };
+    }
+    if self.strikethrough {
+      attrd = format!("~~{}~~", attrd);
+    }
+    if self.italic {
+      attrd = format!("*{}*", attrd);
+    }
+    if self.bold {
+      attrd = format!("**{}**", attrd);
+    }
+    if let Some(link) = &self.link {
+      attrd = format!("[{}]({})", attrd, link.replace(' ', "%20").replace('(', "%28").replace(')', "%29"));
+    }
+    let lead = &text[..text.len() - text.trim_start_matches(' ').len()];
+    let trail = &text[text.trim_end_matches(' ').len()..];
+    format!("{}{}{}", lead, attrd, trail)
+  }
+  
+  /// Render text as RTF, which has bold, italic, underlined, and struck
+  /// through text, and colors, as the nearest of the 16 named colors, from
+  /// the color table `color::rtf_table` writes. Links are fields, which
+  /// word processors show as links.
+  fn render_rtf(&self, text: &str) -> String {
+    let mut words = String::new();
+    for (on, word) in [(self.bold, "\\b"), (self.italic, "\\i"), (self.underline, "\\ul"), (self.strikethrough, "\\strike")] {
+      if on {
+        words.push_str(word);
+      }
+    }
+    if let Some(n) = self.color.and_then(color::rtf_index) {
+      words.push_str(&format!("\\cf{}", n));
+    }
+    if let Some(n) = self.background.and_then(color::rtf_index) {
+      words.push_str(&format!("\\highlight{}", n));
+    }
+    let styled = if words.is_empty() {
+      text.to_string()
+    }else{
+      format!("{{{} {}}}", words, text)
+    };
+    match &self.link {
+      Some(link) => format!("{{\\field{{\\*\\fldinst HYPERLINK \"{}\"}}{{\\fldrslt {}}}}}", escape_rtf(&link.replace('"', "%22")), styled),
+      None => styled,
+    }
+  }
+  
+  fn render_css(&self, text: &str) -> String {
+    let style = self.css();
+    let styled = if style.is_empty() {
+      text.to_string()
+    }else{
+      format!("<span style=\"{}\">{}</span>", style.join("; "), text)
+    };
+    match &self.link {
+      Some(link) => format!("<a href=\"{}\">{}</a>", escape_html(link), styled),
+      None => styled,
+    }
+  }
+  
+  /// The CSS declarations which style text as these attributes do. Text
+  /// which is inverted is drawn in the background color on the foreground
+  /// color, or in the reverse of the page's colors if they aren't set.
+  pub fn css(&self) -> Vec<String> {
+    let (color, background) = match self.invert {
+      true => (self.background.and_then(hex).or(Some("Canvas".to_string())), self.color.and_then(hex).or(Some("CanvasText".to_string()))),
+      false => (self.color.and_then(hex), self.background.and_then(hex)),
+    };
+    let mut style = Vec::new();
+    if let Some(color) = color {
+      style.push(format!("color: {}", color));
+    }
+    if let Some(background) = background {
+      style.push(format!("background-color: {}", background));
+    }
+    if self.bold {
+      style.push("font-weight: bold".to_string());
+    }
+    if self.italic {
+      style.push("font-style: italic".to_string());
+    }
+    if let Some(decoration) = self.decoration() {
+      style.push(format!("text-decoration: {}", decoration));
+    }
+    if self.dim {
+      style.push("opacity: 0.6".to_string());
+    }
+    style
+  }
+  
+  /// The lines text is decorated with, if any, as CSS and SVG write them.
+  pub fn decoration(&self) -> Option<&'static str> {
+    match (self.underline, self.strikethrough) {
+      (true, true)   => Some("underline line-through"),
+      (true, false)  => Some("underline"),
+      (false, true)  => Some("line-through"),
+      (false, false) => None,
+    }
+  }
+
+}
+
+/// A color as HTML writes it, as in `#f5f543`. The named colors are those
+/// of a typical dark terminal theme.
+pub fn hex(color: Color) -> Option<String> {
+  let named = match color {
+    Color::Black       => "#000000",
+    Color::DarkGrey    => "#808080",
+    Color::Red         => "#f14c4c",
+    Color::DarkRed     => "#cd3131",
+    Color::Green       => "#23d18b",
+    Color::DarkGreen   => "#0dbc79",
+    Color::Yellow      => "#f5f543",
+    Color::DarkYellow  => "#e5e510",
+    Color::Blue        => "#3b8eea",
+    Color::DarkBlue    => "#2472c8",
+    Color::Magenta     => "#d670d6",
+    Color::DarkMagenta => "#bc3fbc",
+    Color::Cyan        => "#29b8db",
+    Color::DarkCyan    => "#11a8cd",
+    Color::White       => "#ffffff",
+    Color::Grey        => "#e5e5e5",
+    Color::Rgb{r, g, b} => return Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+    Color::AnsiValue(n) if n < 16 => return hex(color::named(n)),
+    Color::AnsiValue(n) => {
+      let (r, g, b) = color::rgb_256(n);
+      return hex(Color::Rgb{r, g, b});
+    },
+    _ => return None,
+  };
+  Some(named.to_string())
 }
 
+/// Escape text to be included in HTML.
+pub fn escape_html(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A range of text with some attributes. The range is in bytes, and when
+/// it doesn't fall between characters it is widened to when rendered.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Span {
   range: ops::Range<usize>,
   attrs: Attributes,
+  #[cfg_attr(feature = "serialize", serde(default))]
+  priority: i32, // spans with higher priorities are painted over those with lower ones, when merged by priority
 }
 
 impl PartialOrd for Span {
@@ -111,17 +332,43 @@ impl Ord for Span {
 
 impl Span {
   pub fn new(range: ops::Range<usize>, attrs: Attributes) -> Span {
+    Self::new_with_priority(range, attrs, 0)
+  }
+  
+  pub fn new_with_priority(range: ops::Range<usize>, attrs: Attributes, priority: i32) -> Span {
     Span{
       range: range,
       attrs: attrs,
+      priority,
     }
   }
+  
+  pub fn range(&self) -> &ops::Range<usize> {
+    &self.range
+  }
+  
+  pub fn attrs(&self) -> &Attributes {
+    &self.attrs
+  }
+}
+
+/// Whose attributes prevail where spans which overlap conflict, as over
+/// which color the text they overlap is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Policy {
+  First,    // the span sorted first, which starts first
+  Last,     // the span sorted last
+  Priority, // the span with the highest priority, or of those with the same, the span sorted first
+  Blend,    // the span sorted first, with the backgrounds of all of them blended
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Attributed {
   text: String,
   spans: Vec<Span>,
+  #[cfg_attr(feature = "serialize", serde(skip))]
+  rendered: RefCell<Vec<((Mode, Styling), String)>>, // what's been rendered, by mode, until the text or its spans change
 }
 
 impl Attributed {
@@ -129,6 +376,7 @@ impl Attributed {
     Attributed{
       text: String::new(),
       spans: Vec::new(),
+      rendered: RefCell::new(Vec::new()),
     }
   }
   
@@ -136,6 +384,7 @@ impl Attributed {
     Attributed{
       text: text.to_string(),
       spans: spans,
+      rendered: RefCell::new(Vec::new()),
     }
   }
   
@@ -143,6 +392,7 @@ impl Attributed {
     Attributed{
       text: text,
       spans: spans,
+      rendered: RefCell::new(Vec::new()),
     }
   }
   
@@ -159,6 +409,7 @@ impl Attributed {
   }
   
   pub fn spans_mut<'a>(&'a mut self) -> &'a mut Vec<Span> {
+    self.invalidate();
     &mut self.spans
   }
   
@@ -166,189 +417,1342 @@ impl Attributed {
     self.render_with_mode(Mode::Terminal)
   }
   
-  fn render_with_mode(&self, mode: Mode) -> String {
-    render_with_mode(&self.text, &self.spans, mode)
+  /// Render the text in a mode. What's rendered is kept, for each mode and
+  /// the styling terminals are rendered with, so that text rendered over
+  /// and over again is only rendered once, until it is changed.
+  pub fn render_with_mode(&self, mode: Mode) -> String {
+    let key = (mode, color::styling());
+    if let Some((_, rendered)) = self.rendered.borrow().iter().find(|(k, _)| *k == key) {
+      return rendered.clone();
+    }
+    let rendered = render_with_mode(&self.text, &self.spans, mode);
+    self.rendered.borrow_mut().push((key, rendered.clone()));
+    rendered
+  }
+  
+  /// Write the text as `render_with_mode` renders it, as it is rendered,
+  /// without collecting it first, unless it has been already.
+  pub fn render_to<W: io::Write>(&self, w: &mut W, mode: Mode) -> io::Result<()> {
+    let key = (mode, color::styling());
+    match self.rendered.borrow().iter().find(|(k, _)| *k == key) {
+      Some((_, rendered)) => w.write_all(rendered.as_bytes()),
+      None => render_into(w, &self.text, 0, &self.spans, mode),
+    }
+  }
+  
+  /// Forget what's been rendered, once the text or its spans change.
+  fn invalidate(&mut self) {
+    self.rendered.get_mut().clear();
+  }
+  
+  /// The text in a range of bytes, with the parts of the spans over it,
+  /// offset to where they are in it. The range is widened, if it must be,
+  /// so it starts and ends between characters.
+  pub fn slice(&self, range: ops::Range<usize>) -> Attributed {
+    let range = widen_to_chars(&self.text, range);
+    let spans = self.spans.iter().filter(|s| s.range.start < range.end && s.range.end > range.start).map(|s| {
+      Span::new_with_priority(max(s.range.start, range.start) - range.start..min(s.range.end, range.end) - range.start, s.attrs.clone(), s.priority)
+    }).collect();
+    Attributed::new_with_str(&self.text[range], spans)
+  }
+  
+  /// Break the text into lines which fit in a width, in columns, between
+  /// words where it can, as the editor wraps text, and at newlines. Each
+  /// line has the parts of the spans over it, offset to where they are in
+  /// it, and leaves out the whitespace or newline it is broken at.
+  pub fn wrap(&self, width: usize) -> Vec<Attributed> {
+    layout::layout(&self.text, width).iter().map(|l| self.slice(l.boff..l.boff + l.bytes)).collect()
+  }
+  
+  /// The text cut short to fit in a width, in columns, with an ellipsis
+  /// where it was cut. The ellipsis takes the spans over the first
+  /// character cut away, and any spans beyond it are dropped. Text which
+  /// already fits is left as it is.
+  pub fn truncate_to_width(&self, width: usize) -> Attributed {
+    if width::str_width(&self.text) <= width {
+      return self.clone();
+    }
+    if width == 0 {
+      return Attributed::new();
+    }
+    let mut cut = 0;
+    let mut n = 0;
+    for (i, c) in self.text.char_indices() {
+      let w = width::char_width(c);
+      cut = i;
+      if n + w > width - 1 {
+        break;
+      }
+      n += w;
+    }
+    let end = cut + ELLIPSIS.len_utf8();
+    let spans = self.spans.iter().filter(|s| s.range.start < cut || (s.range.start == cut && s.range.end > cut)).map(|s| {
+      Span::new_with_priority(s.range.start..if s.range.end > cut { end }else{ s.range.end }, s.attrs.clone(), s.priority)
+    }).collect();
+    let mut text = self.text[..cut].to_string();
+    text.push(ELLIPSIS);
+    Attributed::new_with_string(text, spans)
+  }
+  
+  /// Style every match of a pattern in the text, merging the attributes
+  /// over any spans already there, which they prevail over wherever those
+  /// start, since they're painted at a higher priority. Matches don't
+  /// overlap. A pattern with no uppercase letters matches regardless of
+  /// case, as searching usually does.
+  pub fn highlight(&mut self, pattern: &str, attrs: &Attributes) {
+    let fold = !pattern.chars().any(|c| c.is_uppercase());
+    let over = self.spans.iter().map(|s| s.priority).max().unwrap_or(0).saturating_add(1); // over every span, wherever it starts
+    let mut found = Vec::new();
+    let mut from = 0;
+    while from < self.text.len() {
+      match match_at(&self.text[from..], pattern, fold) {
+        Some(n) if n > 0 => {
+          found.push(Span::new_with_priority(from..from + n, attrs.clone(), over));
+          from += n;
+        },
+        _ => from += self.text[from..].chars().next().map_or(1, |c| c.len_utf8()),
+      }
+    }
+    if !found.is_empty() {
+      self.invalidate();
+      self.spans = normalize(merge_with_policy(std::mem::take(&mut self.spans), found, Policy::Priority)).0; // joined again where a span started inside a match
+    }
+  }
+  
+  /// The runs of text which are styled the same way, with their
+  /// attributes, as they're rendered.
+  pub fn segments(&self) -> Segments<'_> {
+    Segments::new(&self.text, 0, &self.spans)
+  }
+  
+  /// Take the text and its spans apart.
+  pub fn into_parts(self) -> (String, Vec<Span>) {
+    (self.text, self.spans)
+  }
+  
+  /// Replace a range of bytes in the text, widened as `slice` widens it,
+  /// keeping the spans over the rest of it where they were; see
+  /// `splice_spans`.
+  pub fn replace_range(&mut self, range: ops::Range<usize>, text: &str) {
+    self.invalidate();
+    let range = widen_to_chars(&self.text, range);
+    self.spans = splice_spans(&self.spans, range.clone(), text.len());
+    self.text.replace_range(range, text);
+  }
+  
+  /// Insert text at a byte offset, widened as `slice` widens it, shifting
+  /// the spans which start at or after it along. What's inserted within a
+  /// span is styled by it; see `splice_spans`.
+  pub fn insert_str(&mut self, idx: usize, text: &str) {
+    self.replace_range(idx..idx, text);
+  }
+  
+  /// Append text which isn't styled.
+  pub fn push_str(&mut self, text: &str) {
+    self.insert_str(self.text.len(), text);
+  }
+  
+  /// Replace each tab with spaces up to the next tab stop, every so many
+  /// columns along its line, as a terminal would show it, so the spans
+  /// stay over the text they were over once it's drawn. A span over a tab
+  /// covers the spaces it becomes; see `splice_spans`. Stops are at least a
+  /// column apart.
+  pub fn expand_tabs(&mut self, width: usize) {
+    let width = max(width, 1);
+    let mut tabs = Vec::new();
+    let mut col = 0;
+    for (i, c) in self.text.char_indices() {
+      match c {
+        '\n' => col = 0,
+        '\t' => {
+          tabs.push((i, width - col % width));
+          col += width - col % width;
+        },
+        c => col += width::char_width(c),
+      }
+    }
+    for (i, n) in tabs.into_iter().rev() { // from the end, so the offsets of those before are unchanged
+      self.replace_range(i..i + 1, &" ".repeat(n));
+    }
+  }
+  
+  /// Append other attributed text, with its spans offset to where it ends
+  /// up.
+  pub fn append(&mut self, other: Attributed) {
+    self.invalidate();
+    let off = self.text.len();
+    self.text.push_str(&other.text);
+    self.spans.extend(other.spans.into_iter().map(|s| Span::new_with_priority(s.range.start + off..s.range.end + off, s.attrs, s.priority)));
   }
 }
 
-pub fn merge(a: Vec<Span>, b: Vec<Span>) -> Vec<Span> {
-  let mut res: Vec<Span> = Vec::new();
-  let mut dup: Vec<Span> = Vec::with_capacity(a.len() + b.len());
+impl ops::Add for Attributed {
+  type Output = Attributed;
   
-  dup.extend(a);
-  dup.extend(b);
-  dup.sort();
+  fn add(mut self, other: Attributed) -> Attributed {
+    self.append(other);
+    self
+  }
+}
+
+impl Extend<Attributed> for Attributed {
+  fn extend<I: IntoIterator<Item = Attributed>>(&mut self, iter: I) {
+    for other in iter {
+      self.append(other);
+    }
+  }
+}
+
+/// Attributed text kept a line at a time, each with spans of its own, so
+/// that a line can be changed, or lines added or removed, without the
+/// spans of every line after it having to be offset again.
+#[derive(Debug, Clone, Default)]
+pub struct AttributedLines {
+  lines: Vec<Attributed>,
+}
+
+impl AttributedLines {
+  pub fn new() -> AttributedLines {
+    AttributedLines{
+      lines: Vec::new(),
+    }
+  }
   
-  loop {
-    match dup.len() {
-      0 => break,
-      1 => {
-        res.push(dup[0].clone());
-        break;
-      },
-      _ => {
-        if dup[0].range.end > dup[1].range.start {
-            if dup[0].range.start < dup[1].range.start {
-              res.push(Span{
-                range: dup[0].range.start..dup[1].range.start,
-                attrs: dup[0].attrs.clone(),
-              });
-              dup[0] = Span{
-                range: dup[1].range.start..dup[0].range.end,
-                attrs: dup[0].attrs.clone(),
-              };
-            }
-            let (end, nxt, attrs) = if dup[0].range.end < dup[1].range.end {
-              (dup[0].range.end, dup[1].range.end, dup[1].attrs.clone())
-            }else{
-              (dup[1].range.end, dup[0].range.end, dup[0].attrs.clone())
-            };
-            dup[0] = Span{
-              range: dup[1].range.start..end,
-              attrs: dup[0].attrs.merged(&dup[1].attrs),
-            };
-            if nxt == end {
-              dup.remove(1);
-            }else{
-              dup[1] = Span{
-                range: end..nxt,
-                attrs: attrs,
-              };
-            }
-        }
-        
-        while dup.len() > 1 {
-          if dup[0].range.end <= dup[1].range.start {
-            res.push(dup[0].clone());
-            dup.remove(0);
-          }else{
-            break;
-          }
-        }
-      },
+  pub fn num_lines(&self) -> usize {
+    self.lines.len()
+  }
+  
+  pub fn line(&self, i: usize) -> Option<&Attributed> {
+    self.lines.get(i)
+  }
+  
+  pub fn iter(&self) -> std::slice::Iter<'_, Attributed> {
+    self.lines.iter()
+  }
+  
+  /// Insert a line before the line at an index, which mustn't be past the
+  /// last line.
+  pub fn insert(&mut self, i: usize, line: Attributed) {
+    self.lines.insert(i, line);
+  }
+  
+  pub fn push(&mut self, line: Attributed) {
+    self.insert(self.lines.len(), line);
+  }
+  
+  /// Remove the line at an index, which must be one of them.
+  pub fn remove(&mut self, i: usize) -> Attributed {
+    self.lines.remove(i)
+  }
+  
+  /// Write a range of the lines, as many of them as there are, each as
+  /// `Attributed::render_to` writes it and followed by a newline.
+  pub fn render_to<W: io::Write>(&self, w: &mut W, rows: ops::Range<usize>, mode: Mode) -> io::Result<()> {
+    for line in self.lines.iter().skip(rows.start).take(rows.len()) {
+      line.render_to(w, mode)?;
+      w.write_all(b"\n")?;
+    }
+    Ok(())
+  }
+}
+
+impl From<Attributed> for AttributedLines {
+  /// Break attributed text into lines at its newlines, as `str::split`
+  /// does, so text which ends with one has an empty line after it.
+  fn from(text: Attributed) -> AttributedLines {
+    let mut lines = AttributedLines::new();
+    let mut start = 0;
+    for line in text.text().split('\n') {
+      lines.push(text.slice(start..start + line.len()));
+      start += line.len() + 1;
+    }
+    lines
+  }
+}
+
+impl FromIterator<Attributed> for AttributedLines {
+  fn from_iter<I: IntoIterator<Item = Attributed>>(iter: I) -> AttributedLines {
+    AttributedLines{
+      lines: iter.into_iter().collect(),
+    }
+  }
+}
+
+/// Iterates over the runs of text which are styled the same way, with the
+/// attributes of the span over each, if there is one, as they're rendered.
+/// Where spans overlap, the one which starts first prevails and the rest
+/// are cut short; merge them first to combine their attributes instead.
+pub struct Segments<'a> {
+  text: &'a str,
+  boff: usize, // where the text starts, which spans are offset from
+  spans: Vec<&'a Span>,
+  next: usize, // the next span to copy
+  x: usize, // how much of the text has been copied
+}
+
+impl<'a> Segments<'a> {
+  fn new(text: &'a str, boff: usize, spans: &'a [Span]) -> Segments<'a> {
+    let mut spans: Vec<&Span> = spans.iter().collect();
+    spans.sort();
+    Segments{
+      text,
+      boff,
+      spans,
+      next: 0,
+      x: 0,
+    }
+  }
+}
+
+impl<'a> Iterator for Segments<'a> {
+  type Item = (&'a str, Option<&'a Attributes>);
+  
+  fn next(&mut self) -> Option<Self::Item> {
+    let len = self.text.len();
+    while let Some(span) = self.spans.get(self.next) {
+      if span.range.end < self.boff { // skip spans that end before the current offset
+        self.next += 1;
+        continue;
+      }
+      let start = min(max(self.boff, span.range.start) - self.boff, len);
+      let end = min(span.range.end - self.boff, len);
+      let ops::Range{start, end} = widen_to_chars(self.text, start..end); // spans are in bytes, which might not fall between characters
+      let (start, end) = (max(start, self.x), max(end, self.x)); // nor overlap what's been copied, once widened
+      if start > self.x { // copy before span starts
+        let seg = &self.text[self.x..start];
+        self.x = start;
+        return Some((seg, None));
+      }
+      self.next += 1;
+      self.x = end;
+      if end > start { // copy attributed range
+        return Some((&self.text[start..end], Some(&span.attrs)));
+      }
+    }
+    if self.x < len {
+      let seg = &self.text[self.x..];
+      self.x = len;
+      return Some((seg, None));
+    }
+    None
+  }
+}
+
+/// Builds attributed text a piece at a time, working out the range of each
+/// span from where the text styled by it was appended.
+#[derive(Debug, Clone, Default)]
+pub struct AttributedBuilder {
+  text: String,
+  spans: Vec<Span>,
+}
+
+impl AttributedBuilder {
+  pub fn new() -> AttributedBuilder {
+    AttributedBuilder::default()
+  }
+  
+  /// Append text without attributes.
+  pub fn push_plain(&mut self, text: &str) -> &mut Self {
+    self.text.push_str(text);
+    self
+  }
+  
+  /// Append text with some attributes. Text appended right after text with
+  /// the same attributes extends its span rather than adding another.
+  pub fn push_styled(&mut self, text: &str, attrs: &Attributes) -> &mut Self {
+    if text.is_empty() || *attrs == PLAIN {
+      return self.push_plain(text);
+    }
+    let start = self.text.len();
+    self.text.push_str(text);
+    match self.spans.last_mut() {
+      Some(last) if last.range.end == start && last.attrs == *attrs => last.range.end = self.text.len(),
+      _ => self.spans.push(Span::new(start..self.text.len(), attrs.clone())),
+    };
+    self
+  }
+  
+  pub fn finish(self) -> Attributed {
+    Attributed::new_with_string(self.text, self.spans)
+  }
+}
+
+/// Spans sorted, without any which are empty or run backwards, and with
+/// those next to one another which are styled the same way joined into
+/// one, along with the ranges in which spans overlap. Rendering resolves
+/// overlaps by letting the span which starts first prevail, which is
+/// rarely what was meant; merge them instead to combine their attributes.
+pub fn normalize(spans: Vec<Span>) -> (Vec<Span>, Vec<ops::Range<usize>>) {
+  let mut spans: Vec<Span> = spans.into_iter().filter(|s| s.range.start < s.range.end).collect();
+  spans.sort();
+  let mut norm: Vec<Span> = Vec::with_capacity(spans.len());
+  let mut overlaps: Vec<ops::Range<usize>> = Vec::new();
+  let mut reach = 0; // the furthest any span so far ends
+  for s in spans {
+    if s.range.start < reach {
+      overlaps.push(s.range.start..min(reach, s.range.end));
+    }
+    reach = max(reach, s.range.end);
+    match norm.last_mut() {
+      Some(last) if last.range.end == s.range.start && last.attrs == s.attrs && last.priority == s.priority => last.range.end = s.range.end,
+      _ => norm.push(s),
     };
   }
+  (norm, overlaps)
+}
+
+/// Merge two sets of spans into one in which none overlap. Where spans
+/// overlap, the text between each of their boundaries is given the
+/// attributes of all of them, merged in the order the spans are sorted in,
+/// so the color of the one which starts first prevails.
+pub fn merge(a: Vec<Span>, b: Vec<Span>) -> Vec<Span> {
+  merge_with_policy(a, b, Policy::First)
+}
+
+/// Spans as they are after a range of bytes in the text under them is
+/// replaced by the provided number of bytes. Spans after the range are
+/// shifted by however much longer or shorter it becomes, those which only
+/// partly overlap it are cut back to where it starts or ends, and those
+/// within it are dropped. A span over the whole range, or which text is
+/// inserted inside of, covers what replaces it, so typing within a styled
+/// word keeps its style.
+pub fn splice_spans(spans: &[Span], range: ops::Range<usize>, len: usize) -> Vec<Span> {
+  let ops::Range{start: a, end: b} = range;
+  let shift = |x: usize| x - (b - a) + len;
+  spans.iter().filter_map(|s| {
+    let r = if s.range.end <= a {
+      s.range.clone()
+    }else if s.range.start >= b {
+      shift(s.range.start)..shift(s.range.end)
+    }else if s.range.start <= a && s.range.end >= b {
+      s.range.start..shift(s.range.end)
+    }else{
+      (if s.range.start < a { s.range.start }else{ a + len })..(if s.range.end > b { shift(s.range.end) }else{ a })
+    };
+    if r.start < r.end {
+      Some(Span::new_with_priority(r, s.attrs.clone(), s.priority))
+    }else{
+      None
+    }
+  }).collect()
+}
+
+/// Merge two sets of spans into one in which none overlap, as `merge`
+/// does, with the attributes of the span the policy prefers prevailing
+/// where they conflict. A span produced where spans overlap has the highest
+/// of their priorities. This is a sweep over the boundaries of the spans,
+/// tracking which cover the text between each, so it scales to the
+/// thousands of spans a frame can have.
+pub fn merge_with_policy(a: Vec<Span>, b: Vec<Span>, policy: Policy) -> Vec<Span> {
+  let mut spans: Vec<Span> = a.into_iter().chain(b).filter(|s| s.range.start < s.range.end).collect();
+  spans.sort(); // stable, so spans over the same range stay in the order provided
+  
+  let mut bounds: Vec<(usize, bool, usize)> = Vec::with_capacity(spans.len() * 2); // offset, whether a span starts there, and which
+  for (i, span) in spans.iter().enumerate() {
+    bounds.push((span.range.start, true, i));
+    bounds.push((span.range.end, false, i));
+  }
+  bounds.sort_unstable(); // spans ending at an offset before those starting at it
+  
+  let mut res: Vec<Span> = Vec::new();
+  let mut active: BTreeSet<usize> = BTreeSet::new(); // the spans covering the text since the last boundary, in order
+  let mut at = 0;
+  for (offset, starts, i) in bounds {
+    if offset > at && !active.is_empty() {
+      let covering = active.iter().map(|j| &spans[*j]);
+      let attrs = match policy { // merged in order of precedence, since the colors of the first prevail
+        Policy::First    => merge_attrs(covering, Attributes::merged),
+        Policy::Last     => merge_attrs(covering.rev(), Attributes::merged),
+        Policy::Blend    => merge_attrs(covering, Attributes::blended),
+        Policy::Priority => {
+          let mut covering: Vec<&Span> = covering.collect();
+          covering.sort_by_key(|s| -s.priority); // stable, so the first of those with the same priority prevails
+          merge_attrs(covering.into_iter(), Attributes::merged)
+        },
+      };
+      let priority = active.iter().map(|j| spans[*j].priority).max().unwrap_or(0);
+      res.push(Span{range: at..offset, attrs, priority});
+    }
+    at = max(at, offset);
+    if starts {
+      active.insert(i);
+    }else{
+      active.remove(&i);
+    }
+  }
   
   res
 }
 
+/// Merge the attributes of some spans, which mustn't be none, in order,
+/// one after another.
+fn merge_attrs<'a>(mut spans: impl Iterator<Item = &'a Span>, merge: fn(&Attributes, &Attributes) -> Attributes) -> Attributes {
+  let first = spans.next().map_or(PLAIN, |s| s.attrs.clone());
+  spans.fold(first, |acc, s| merge(&acc, &s.attrs))
+}
+
+/// Lay attributes over a range, taking precedence over those of the spans
+/// within it. Text in the range without attributes of its own takes them
+/// too.
+pub fn overlay(spans: Vec<Span>, range: ops::Range<usize>, layer: &Attributes) -> Vec<Span> {
+  let plain = Attributes{bold: false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+  merge(spans, vec![Span::new(range.clone(), plain)]).into_iter().map(|s| {
+    if s.range.start >= range.start && s.range.end <= range.end {
+      Span::new(s.range, layer.merged(&s.attrs))
+    }else{
+      s
+    }
+  }).collect()
+}
+
 pub fn render(text: &str, spans: &Vec<Span>) -> String {
   render_with_mode(text, spans, Mode::Terminal)
 }
 
-pub fn render_with_offset(text: &str, boff: usize, spans: &Vec<Span>) -> String {
-  render_with_options(text, boff, spans, Mode::Terminal)
+/// Write text from an offset, with the parts of spans over it, as it is
+/// rendered for a terminal, straight into whatever the terminal is written
+/// from.
+pub fn render_with_offset_to<W: io::Write>(w: &mut W, text: &str, boff: usize, spans: &[Span]) -> io::Result<()> {
+  render_into(w, text, boff, spans, Mode::Terminal)
+}
+
+/// Text with its attributes written as tags, as in `<b>total</b>`, rather
+/// than the sequences a terminal understands, so that what is drawn can be
+/// compared as text.
+pub fn render_markup_with_offset(text: &str, boff: usize, spans: &Vec<Span>) -> String {
+  render_with_options(text, boff, spans, Mode::Markup)
 }
 
 fn render_with_mode(text: &str, spans: &Vec<Span>, mode: Mode) -> String {
   render_with_options(text, 0, spans, mode)
 }
 
+/// Text as it is written to the terminal, which mustn't be sent control
+/// characters from a document.
+fn shown(text: &str, mode: Mode) -> Cow<'_, str> {
+  match mode {
+    Mode::Terminal => sanitize::shown(text),
+    Mode::Html     => Cow::Owned(escape_html(text)),
+    Mode::Markdown => Cow::Owned(escape_markdown(text)),
+    Mode::Rtf      => Cow::Owned(escape_rtf(text)),
+    Mode::Markup | Mode::Plain => Cow::Borrowed(text),
+  }
+}
+
+/// Escape the characters Markdown would otherwise take to be formatting.
+fn escape_markdown(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '<' | '#' | '|') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// Escape the characters RTF would otherwise take to be control words or
+/// groups, and write those beyond ASCII as the UTF-16 code units RTF
+/// writes them as, and newlines as line breaks.
+fn escape_rtf(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '\\' | '{' | '}' => {
+        escaped.push('\\');
+        escaped.push(c);
+      },
+      '\n' => escaped.push_str("\\line "),
+      ' '..='~' => escaped.push(c),
+      c => for u in c.encode_utf16(&mut [0; 2]) {
+        escaped.push_str(&format!("\\u{}?", *u as i16)); // signed, with a stand-in for readers which don't know it
+      },
+    }
+  }
+  escaped
+}
+
+/// Spans over a range of bytes of some text, widened as `Attributed::slice`
+/// widens it, one to a character, each with the attributes provided but
+/// colored along a gradient, from one color at the first character to
+/// another at the last.
+pub fn gradient(text: &str, range: ops::Range<usize>, from: (u8, u8, u8), to: (u8, u8, u8), attrs: &Attributes) -> Vec<Span> {
+  let range = widen_to_chars(text, range);
+  let steps = max(text[range.clone()].chars().count(), 2) - 1;
+  text[range.clone()].char_indices().enumerate().map(|(i, (off, c))| {
+    let (r, g, b) = color::mix(from, to, i as f64 / steps as f64);
+    let start = range.start + off;
+    Span::new(start..start + c.len_utf8(), Attributes{color: Some(Color::Rgb{r, g, b}), ..attrs.clone()})
+  }).collect()
+}
+
+/// Widen a range of bytes of some text, if it must be, so that it starts
+/// and ends between characters. A character which is only partly in the
+/// range is taken to be in it.
+pub fn widen_to_chars(text: &str, range: ops::Range<usize>) -> ops::Range<usize> {
+  let mut start = min(range.start, text.len());
+  while !text.is_char_boundary(start) {
+    start -= 1;
+  }
+  let mut end = max(min(range.end, text.len()), start);
+  while !text.is_char_boundary(end) {
+    end += 1;
+  }
+  start..end
+}
+
 fn render_with_options(text: &str, boff: usize, spans: &Vec<Span>, mode: Mode) -> String {
-  let mut dup = spans.clone();
-  dup.sort();
-  
-  let len = text.len();
-  let mut x = 0;
-  let mut attrd = String::new();
-  for span in dup {
-    if span.range.end < boff { // skip spans that end before the current offset
+  let mut attrd = Vec::new();
+  let _ = render_into(&mut attrd, text, boff, spans, mode); // which can't fail, into memory
+  String::from_utf8(attrd).unwrap_or_default() // and is only ever text
+}
+
+/// Write text with its attributes as it is rendered, rather than
+/// collecting it first.
+fn render_into<W: io::Write>(w: &mut W, text: &str, boff: usize, spans: &[Span], mode: Mode) -> io::Result<()> {
+  let mut current = PLAIN; // the style a terminal is left in
+  for (text, attrs) in Segments::new(text, boff, spans) {
+    write_segment(w, &mut current, attrs, text, mode)?;
+  }
+  if let Mode::Terminal = mode {
+    w.write_all(transition(&current, &PLAIN).as_bytes())?;
+  }
+  Ok(())
+}
+
+/// Write a segment of text, with its attributes if it has any. For a
+/// terminal, only what changes from the style the segment before left it
+/// in is written, rather than styling each segment from scratch and
+/// resetting after it, since the whole screen is written every frame.
+fn write_segment<W: io::Write>(w: &mut W, current: &mut Attributes, attrs: Option<&Attributes>, text: &str, mode: Mode) -> io::Result<()> {
+  match (mode, attrs) {
+    (Mode::Terminal, attrs) => {
+      let attrs = attrs.unwrap_or(&PLAIN);
+      if attrs != current {
+        w.write_all(transition(current, attrs).as_bytes())?;
+        *current = attrs.clone();
+      }
+      w.write_all(shown(text, mode).as_bytes())
+    },
+    (Mode::Markdown, Some(attrs)) if attrs.invert => w.write_all(attrs.render_with_mode(text, mode).as_bytes()), // code, which isn't escaped
+    (mode, Some(attrs)) => w.write_all(attrs.render_with_mode(&shown(text, mode), mode).as_bytes()),
+    (mode, None) => w.write_all(shown(text, mode).as_bytes()),
+  }
+}
+
+/// The escape sequences which change the style of a terminal from one set
+/// of attributes to another, as far as what's written to is styled; see
+/// `color::styling`. Without color, only the rest of the attributes change,
+/// and text which isn't styled at all has no escape sequences in it.
+fn transition(from: &Attributes, to: &Attributes) -> String {
+  match color::styling() {
+    Styling::Full => sequence(from, to),
+    Styling::Monochrome => sequence(&uncolored(from), &uncolored(to)),
+    Styling::Plain => String::new(),
+  }
+}
+
+/// Attributes without their colors.
+fn uncolored(attrs: &Attributes) -> Attributes {
+  Attributes{color: None, background: None, ..attrs.clone()}
+}
+
+/// The escape sequences which change the style of a terminal from one set
+/// of attributes to another: the changes in graphic rendition, or a reset
+/// if none remain, and the end or start of a hyperlink, in OSC 8, which
+/// terminals that don't support it ignore.
+fn sequence(from: &Attributes, to: &Attributes) -> String {
+  let mut seq = String::new();
+  if from.link.is_some() && from.link != to.link {
+    seq.push_str("\x1b]8;;\x1b\\");
+  }
+  let mut params: Vec<String> = Vec::new();
+  if !to.is_styled() {
+    if from.is_styled() {
+      params.push("0".to_string());
+    }
+  }else{
+    let (mut bold, mut dim) = (from.bold, from.dim);
+    if (bold && !to.bold) || (dim && !to.dim) { // which are turned off together
+      params.push("22".to_string());
+      (bold, dim) = (false, false);
+    }
+    let flags = [(bold, to.bold, "1", ""), (dim, to.dim, "2", ""), (from.italic, to.italic, "3", "23"), (from.underline, to.underline, "4", "24"), (from.invert, to.invert, "7", "27"), (from.strikethrough, to.strikethrough, "9", "29")];
+    for (was, is, on, off) in flags {
+      match (was, is) {
+        (false, true) => params.push(on.to_string()),
+        (true, false) => params.push(off.to_string()),
+        _ => {},
+      };
+    }
+    if from.color != to.color {
+      params.push(Colored::ForegroundColor(to.color.map_or(Color::Reset, color::fit)).to_string());
+    }
+    if from.background != to.background {
+      params.push(Colored::BackgroundColor(to.background.map_or(Color::Reset, color::fit)).to_string());
+    }
+  }
+  if !params.is_empty() {
+    seq.push_str(&format!("\x1b[{}m", params.join(";")));
+  }
+  if let Some(link) = to.link.as_ref().filter(|l| from.link.as_ref() != Some(*l)) {
+    seq.push_str(&format!("\x1b]8;;{}\x1b\\", link.replace(|c: char| c.is_control(), "")));
+  }
+  seq
+}
+
+/// Attributes which leave text as it is.
+const PLAIN: Attributes = Attributes{bold: false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+
+/// The length, in bytes, of the text a pattern matches at the start of it,
+/// if it does, comparing characters regardless of case when folding.
+fn match_at(text: &str, pattern: &str, fold: bool) -> Option<usize> {
+  let mut chars = text.char_indices();
+  for p in pattern.chars() {
+    let (_, c) = chars.next()?;
+    if c != p && !(fold && c.to_lowercase().eq(p.to_lowercase())) {
+      return None;
+    }
+  }
+  Some(chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+/// Marks where text was cut short.
+const ELLIPSIS: char = '…';
+
+/// Parse text styled with markup, as `Mode::Markup` renders it or as it's
+/// written by hand, into the text and its spans. Tags may be nested, as in
+/// `<b>x<i>y</i>z</b>`, and a span is produced for each run of text between
+/// them, styled by every tag open over it. Anything between angle brackets
+/// that isn't a tag the markup has is part of the text, since text isn't
+/// escaped in it, and tags which are never closed are closed at the end.
+pub fn parse_markup(markup: &str) -> Attributed {
+  let mut text = String::new();
+  let mut spans = Vec::new();
+  let mut open: Vec<&str> = Vec::new(); // the tags over the text, in the order they were opened
+  let mut attrs = PLAIN; // as they set them
+  let mut start = 0; // where the run of text styled by them starts
+  let mut rest = markup;
+  while let Some(i) = rest.find('<') {
+    text.push_str(&rest[..i]);
+    rest = &rest[i..];
+    let tag = match rest.find('>') {
+      Some(end) => &rest[1..end],
+      None => break,
+    };
+    let (close, name) = match tag.strip_prefix('/') {
+      Some(name) => (true, name),
+      None => (false, tag),
+    };
+    if !apply_tag(&mut attrs.clone(), name, true) {
+      text.push('<');
+      rest = &rest[1..];
       continue;
     }
-    let start = min(max(boff, span.range.start) - boff, len);
-    if start > x { // copy before span starts
-      attrd.push_str(&text[x..start]);
+    if text.len() > start && attrs != PLAIN {
+      spans.push(Span::new(start..text.len(), attrs.clone()));
     }
-    let end = min(span.range.end - boff, len);
-    if end > start { // copy attributed range
-      attrd.push_str(&span.attrs.render_with_mode(&text[start..end], mode));
+    start = text.len();
+    if !close {
+      open.push(name);
+    }else if let Some(j) = open.iter().rposition(|t| *t == name) { // the innermost of those it could close
+      open.remove(j);
     }
-    x = end;
+    attrs = PLAIN;
+    for tag in &open {
+      apply_tag(&mut attrs, tag, true);
+    }
+    rest = &rest[tag.len() + 2..];
   }
-  if x < len {
-    attrd.push_str(&text[x..]);
+  text.push_str(rest);
+  if text.len() > start && attrs != PLAIN {
+    spans.push(Span::new(start..text.len(), attrs));
   }
-  
-  attrd
+  Attributed::new_with_string(text, spans)
+}
+
+/// Set or clear the attribute a tag of markup is named for. Produces
+/// whether it is a tag.
+fn apply_tag(attrs: &mut Attributes, name: &str, set: bool) -> bool {
+  let flag = match name {
+    "b"      => &mut attrs.bold,
+    "u"      => &mut attrs.underline,
+    "i"      => &mut attrs.italic,
+    "s"      => &mut attrs.strikethrough,
+    "dim"    => &mut attrs.dim,
+    "invert" => &mut attrs.invert,
+    _ => {
+      let (slot, color) = match name.split_once(':') {
+        Some(("link", link)) => {
+          attrs.link = Some(link.to_string()).filter(|_| set);
+          return true;
+        },
+        Some(("fg", color)) => (&mut attrs.color, color),
+        Some(("bg", color)) => (&mut attrs.background, color),
+        _ => return false,
+      };
+      return match parse_color(color) {
+        Some(color) => {
+          *slot = Some(color).filter(|_| set);
+          true
+        },
+        None => false,
+      };
+    },
+  };
+  *flag = set;
+  true
+}
+
+/// Parse text styled with ANSI escape sequences, as other programs write
+/// it to a terminal, into the text and its spans. Only the sequences which
+/// select graphic rendition style the text, and those which link it;
+/// any other escape sequences are dropped, along with styles the
+/// attributes can't express, such as blinking.
+pub fn parse_ansi(styled: &str) -> Attributed {
+  let mut text = AttributedBuilder::new();
+  let mut attrs = PLAIN;
+  let mut rest = styled;
+  while let Some(i) = rest.find('\x1b') {
+    text.push_styled(&rest[..i], &attrs);
+    rest = &rest[i + 1..];
+    match rest.chars().next() {
+      Some('[') => { // a control sequence, of parameters then a final byte
+        let end = rest[1..].find(|c: char| ('\x40'..='\x7e').contains(&c)).map_or(rest.len(), |e| e + 1);
+        if rest[end..].starts_with('m') {
+          apply_sgr(&mut attrs, &rest[1..end]);
+        }
+        rest = rest.get(end + 1..).unwrap_or("");
+      },
+      Some(']') => { // an operating system command, up to a bell or string terminator
+        let end = rest.find(['\x07', '\x1b']).unwrap_or(rest.len());
+        if let Some(link) = rest[1..end].strip_prefix("8;") { // a hyperlink, as in `8;params;url`, which ends it when the url is empty
+          attrs.link = link.split_once(';').map(|(_, url)| url.to_string()).filter(|url| !url.is_empty());
+        }
+        rest = rest.get(end..).unwrap_or("");
+        rest = rest.strip_prefix('\x07').or_else(|| rest.strip_prefix("\x1b\\")).unwrap_or(rest);
+      },
+      Some(c) => rest = &rest[c.len_utf8()..],
+      None => {},
+    };
+  }
+  text.push_styled(rest, &attrs);
+  text.finish()
+}
+
+/// Apply the parameters of a sequence which selects graphic rendition,
+/// such as `1;38;5;208`.
+fn apply_sgr(attrs: &mut Attributes, params: &str) {
+  let mut codes = params.split([';', ':']).map(|p| p.parse::<u8>().unwrap_or(0));
+  while let Some(code) = codes.next() {
+    match code {
+      0 => *attrs = Attributes{link: attrs.link.take(), ..PLAIN}, // links are ended by a sequence of their own
+      1 => attrs.bold = true,
+      2 => attrs.dim = true,
+      3 => attrs.italic = true,
+      4 => attrs.underline = true,
+      7 => attrs.invert = true,
+      9 => attrs.strikethrough = true,
+      22 => {
+        attrs.bold = false;
+        attrs.dim = false;
+      },
+      23 => attrs.italic = false,
+      24 => attrs.underline = false,
+      27 => attrs.invert = false,
+      29 => attrs.strikethrough = false,
+      30..=37 => attrs.color = Some(color::named(code - 30)),
+      90..=97 => attrs.color = Some(color::named(code - 90 + 8)),
+      40..=47 => attrs.background = Some(color::named(code - 40)),
+      100..=107 => attrs.background = Some(color::named(code - 100 + 8)),
+      39 => attrs.color = None,
+      49 => attrs.background = None,
+      38 | 48 => {
+        let color = match codes.next() {
+          Some(5) => codes.next().map(|n| if n < 16 { color::named(n) }else{ Color::AnsiValue(n) }),
+          Some(2) => match (codes.next(), codes.next(), codes.next()) {
+            (Some(r), Some(g), Some(b)) => Some(Color::Rgb{r, g, b}),
+            _ => None,
+          },
+          _ => None,
+        };
+        if code == 38 {
+          attrs.color = color;
+        }else{
+          attrs.background = color;
+        }
+      },
+      _ => {},
+    };
+  }
+}
+
+/// Parse a color as markup writes it, as in `Blue`, `AnsiValue(244)`, or
+/// `Rgb { r: 255, g: 136, b: 0 }`.
+fn parse_color(text: &str) -> Option<Color> {
+  if let Some(n) = text.strip_prefix("AnsiValue(").and_then(|t| t.strip_suffix(')')) {
+    return n.parse().ok().map(Color::AnsiValue);
+  }
+  if let Some(parts) = text.strip_prefix("Rgb {").and_then(|t| t.strip_suffix('}')) {
+    let mut vals = parts.split(',').map(|p| p.split_once(':').and_then(|(_, v)| v.trim().parse::<u8>().ok()));
+    return match (vals.next()?, vals.next()?, vals.next()?) {
+      (Some(r), Some(g), Some(b)) => Some(Color::Rgb{r, g, b}),
+      _ => None,
+    };
+  }
+  (0..16).map(color::named).chain([Color::Reset]).find(|c| format!("{:?}", c) == text)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::buffer::Buffer;
   
   #[test]
   fn merge_attributes() {
-    let a = Attributes{bold:true,  invert: false, color: None, background: None};
-    let b = Attributes{bold:false, invert: true,  color: None, background: None};
-    let c = Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None};
+    let a = Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+    let b = Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: None, background: None, link: None};
+    let c = Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None};
     
-    assert_eq!(Attributes{bold:true,  invert: true, color: None, background: None}, a.merged(&b));
-    assert_eq!(Attributes{bold:false, invert: true, color: Some(Color::Blue), background: None}, c.merged(&b));
+    assert_eq!(Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true, color: None, background: None, link: None}, a.merged(&b));
+    assert_eq!(Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: true, color: Some(Color::Blue), background: None, link: None}, c.merged(&b));
+    
+    let d = Attributes{bold:false, underline: true,  italic: true,  strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+    let e = Attributes{bold:false, underline: false, italic: false, strikethrough: true,  dim: true,  invert: false, color: None, background: None, link: None};
+    assert_eq!(Attributes{bold:false, underline: true, italic: true, strikethrough: true, dim: true, invert: false, color: None, background: None, link: None}, d.merged(&e));
   }
   
   #[test]
   fn merge_spans() {
     let a = vec![
-      Span::new(0..5, Attributes{bold:true,  invert: false, color: None, background: None}),
+      Span::new(0..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
     ];
     let b = vec![
-      Span::new(0..5, Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None}),
+      Span::new(0..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
     ];
     assert_eq!(vec![
-      Span::new(0..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None}),
+      Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
     ], merge(a, b));
     
     let a = vec![
-      Span::new(0..5, Attributes{bold:true,  invert: false, color: None, background: None}),
+      Span::new(0..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
     ];
     let b = vec![
-      Span::new(3..5, Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None}),
+      Span::new(3..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
     ];
     assert_eq!(vec![
-      Span::new(0..3, Attributes{bold:true, invert: false, color: None, background: None}),
-      Span::new(3..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None}),
+      Span::new(0..3, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
+      Span::new(3..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
     ], merge(a, b));
     
     let a = vec![
-      Span::new(3..5, Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None}),
-      Span::new(3..5, Attributes{bold:true,  invert: true,  color: None, background: None}),
-      Span::new(0..5, Attributes{bold:false, invert: false, color: None, background: None}),
+      Span::new(3..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
+      Span::new(3..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: None, background: None, link: None}),
+      Span::new(0..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
     ];
     let b = vec![
-      Span::new(3..5, Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None}),
-      Span::new(3..5, Attributes{bold:true,  invert: true,  color: None, background: None}),
+      Span::new(3..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
+      Span::new(3..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: None, background: None, link: None}),
     ];
     assert_eq!(vec![
-      Span::new(0..3, Attributes{bold:false, invert: false, color: None, background: None}),
-      Span::new(3..5, Attributes{bold:true,  invert: true,  color: Some(Color::Blue), background: None}),
+      Span::new(0..3, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
+      Span::new(3..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: Some(Color::Blue), background: None, link: None}),
     ], merge(a, b));
     
     let a = vec![
-      Span::new(3..5, Attributes{bold:false, invert: false, color: Some(Color::Red), background: None}), // first non-null color prevails
-      Span::new(0..5, Attributes{bold:false, invert: false, color: None, background: None}),
+      Span::new(3..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Red), background: None, link: None}), // first non-null color prevails
+      Span::new(0..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
     ];
     let b = vec![
-      Span::new(3..5, Attributes{bold:true,  invert: true,  color: None, background: None}),
-      Span::new(3..5, Attributes{bold:false, invert: false, color: Some(Color::Blue), background: None}),
+      Span::new(3..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: None, background: None, link: None}),
+      Span::new(3..5, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
     ];
     assert_eq!(vec![
-      Span::new(0..3, Attributes{bold:false, invert: false, color: None, background: None}),
-      Span::new(3..5, Attributes{bold:true,  invert: true,  color: Some(Color::Red), background: None}),
+      Span::new(0..3, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
+      Span::new(3..5, Attributes{bold:true,  underline: false, italic: false, strikethrough: false, dim: false, invert: true,  color: Some(Color::Red), background: None, link: None}),
     ], merge(a, b));
+    
+    let colors = [Color::Red, Color::Green, Color::Blue];
+    let a: Vec<Span> = (0..2000).map(|i| Span::new(i..i + 1 + i % 7, Attributes{bold: i % 3 == 0, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(colors[i % 3]), background: None, link: None})).collect();
+    let b: Vec<Span> = (0..500).map(|i| Span::new(i * 4..i * 4 + 2, Attributes{bold: false, underline: true, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None})).collect();
+    let merged = merge(a.clone(), b.clone());
+    assert!(merged.windows(2).all(|w| w[0].range.end <= w[1].range.start)); // none overlap
+    for offset in [0, 1, 6, 7, 100, 1003, 1999, 2003] { // the color of the span starting first, which a span at 4n..4n+2 underlines
+      let attrs = merged.iter().find(|s| s.range.contains(&offset)).map(|s| s.attrs.clone());
+      let first = a.iter().find(|s| s.range.contains(&offset)).map(|s| s.attrs.color);
+      assert_eq!(first, attrs.as_ref().map(|a| a.color), "{}", offset);
+      assert_eq!(Some(offset % 4 < 2 && offset < 2000), attrs.map(|a| a.underline), "{}", offset);
+    }
+  }
+  
+  #[test]
+  fn merge_spans_with_policy() {
+    let syntax = |color| Attributes{bold: true, color: Some(color), ..PLAIN};
+    let selection = Attributes{color: Some(Color::Black), background: Some(Color::White), ..PLAIN};
+    let a = vec![Span::new(0..4, syntax(Color::Yellow)), Span::new(7..11, syntax(Color::Cyan))];
+    let b = vec![Span::new_with_priority(2..9, selection.clone(), 1)];
+    let colors = |spans: Vec<Span>| spans.iter().map(|s| (s.range.clone(), s.attrs.color, s.priority)).collect::<Vec<_>>();
+    assert_eq!(vec![(0..2, Some(Color::Yellow), 0), (2..4, Some(Color::Yellow), 1), (4..7, Some(Color::Black), 1), (7..9, Some(Color::Black), 1), (9..11, Some(Color::Cyan), 0)], colors(merge(a.clone(), b.clone())));
+    assert_eq!(vec![(0..2, Some(Color::Yellow), 0), (2..4, Some(Color::Black), 1), (4..7, Some(Color::Black), 1), (7..9, Some(Color::Cyan), 1), (9..11, Some(Color::Cyan), 0)], colors(merge_with_policy(a.clone(), b.clone(), Policy::Last)));
+    let merged = merge_with_policy(a.clone(), b.clone(), Policy::Priority);
+    assert_eq!(vec![(0..2, Some(Color::Yellow), 0), (2..4, Some(Color::Black), 1), (4..7, Some(Color::Black), 1), (7..9, Some(Color::Black), 1), (9..11, Some(Color::Cyan), 0)], colors(merged.clone()));
+    assert!(merged[1].attrs.bold && merged[1].attrs.background == Some(Color::White)); // what doesn't conflict is merged all the same
+    let c = vec![Span::new_with_priority(3..8, syntax(Color::Red), 2)];
+    assert_eq!(Some(Color::Red), merge_with_policy(merged, c, Policy::Priority).iter().find(|s| s.range.contains(&7)).and_then(|s| s.attrs.color)); // painted over the selection, whose priority was kept
+  }
+  
+  #[test]
+  fn build_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let mut b = AttributedBuilder::new();
+    b.push_plain("Total: ").push_styled("1,", &bold).push_styled("200", &bold).push_styled("", &red).push_plain(" ").push_styled("EUR", &red).push_styled("!", &PLAIN);
+    let a = b.finish();
+    assert_eq!("Total: 1,200 EUR!", a.text());
+    assert_eq!(&vec![Span::new(7..12, bold), Span::new(13..16, red)], a.spans());
+  }
+  
+  #[test]
+  fn slice_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200 €\n", vec![Span::new(0..4, bold.clone()), Span::new(7..15, red.clone())]);
+    let s = a.slice(2..9);
+    assert_eq!("nt = 12", s.text());
+    assert_eq!(&vec![Span::new(0..2, bold.clone()), Span::new(5..7, red.clone())], s.spans());
+    let s = a.slice(13..14); // within the euro sign, so widened to all of it
+    assert_eq!("€", s.text());
+    assert_eq!(&vec![Span::new(0..3, red.clone())], s.spans());
+    assert_eq!(0, a.slice(4..7).spans().len());
+    assert_eq!("", a.slice(20..30).text());
+  }
+  
+  #[test]
+  fn wrap_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200 EUR × 12 months\n\nfood", vec![Span::new(7..15, bold.clone()), Span::new(19..21, bold.clone())]);
+    let lines = a.wrap(12);
+    assert_eq!(vec!["rent = 1200", "EUR × 12", "months", "", "food"], lines.iter().map(|l| l.text()).collect::<Vec<_>>());
+    assert_eq!(&vec![Span::new(7..11, bold.clone())], lines[0].spans());
+    assert_eq!(&vec![Span::new(0..3, bold.clone()), Span::new(7..9, bold.clone())], lines[1].spans()); // split, and offset
+    assert!(lines[2].spans().is_empty());
+    assert_eq!(vec!["日本語の", "テキスト"], Attributed::new_with_str("日本語のテキスト", vec![]).wrap(8).iter().map(|l| l.text().to_string()).collect::<Vec<_>>()); // by columns, not chars
+  }
+  
+  #[test]
+  fn truncate_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200 EUR", vec![Span::new(0..4, red.clone()), Span::new(7..11, bold.clone()), Span::new(12..15, red.clone())]);
+    assert_eq!("rent = 1200 EUR", a.truncate_to_width(15).text()); // fits
+    let t = a.truncate_to_width(10);
+    assert_eq!("rent = 12…", t.text());
+    assert_eq!(&vec![Span::new(0..4, red.clone()), Span::new(7..12, bold.clone())], t.spans()); // clipped, with the ellipsis styled as what it replaces
+    let t = a.truncate_to_width(5);
+    assert_eq!("rent…", t.text());
+    assert_eq!(&vec![Span::new(0..4, red.clone())], t.spans()); // dropped
+    assert_eq!("日本…", Attributed::new_with_str("日本語のテキスト", vec![]).truncate_to_width(6).text()); // by columns, not bytes
+    assert_eq!("日…", Attributed::new_with_str("日本語のテキスト", vec![]).truncate_to_width(4).text()); // a wide character which won't fit is left out
+    assert_eq!("", a.truncate_to_width(0).text());
+  }
+  
+  #[test]
+  fn highlight_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let mark = Attributes{underline: true, color: Some(Color::Yellow), ..PLAIN};
+    let mut a = Attributed::new_with_str("Tax is tax, taxed at 8.75%", vec![Span::new(0..3, red.clone()), Span::new(7..10, bold.clone())]);
+    a.highlight("tax", &mark);
+    assert_eq!(&vec![
+      Span::new_with_priority(0..3, Attributes{underline: true, color: Some(Color::Yellow), ..PLAIN}, 1), // prevails over the color, and stays over it when merged again
+      Span::new_with_priority(7..10, Attributes{bold: true, underline: true, color: Some(Color::Yellow), ..PLAIN}, 1),
+      Span::new_with_priority(12..15, mark.clone(), 1),
+    ], a.spans());
+    let mut a = Attributed::new_with_str("Tax is tax", vec![]);
+    a.highlight("Tax", &mark);
+    assert_eq!(&vec![Span::new_with_priority(0..3, mark.clone(), 1)], a.spans()); // with uppercase, case matters
+    let mut a = Attributed::new_with_str("aaa × ×", vec![]);
+    a.highlight("aa", &mark);
+    a.highlight("×", &mark);
+    a.highlight("", &mark);
+    assert_eq!(vec![0..2, 4..6, 7..9], a.spans().iter().map(|s| s.range.clone()).collect::<Vec<_>>()); // matches don't overlap
+    let mut a = Attributed::new_with_str("abcdefghij", vec![Span::new(5..10, red.clone())]);
+    a.highlight("defg", &mark);
+    assert_eq!("abc<u><fg:Yellow>defg</fg:Yellow></u><fg:Red>hij</fg:Red>", a.render_with_mode(Mode::Markup)); // over a span starting inside the match
+  }
+  
+  #[test]
+  fn append_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent", vec![Span::new(0..4, bold.clone())]);
+    let b = Attributed::new_with_str(" × 12", vec![Span::new(3..6, red.clone())]);
+    let c = a.clone() + b.clone();
+    assert_eq!("rent × 12", c.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(7..10, red.clone())], c.spans()); // offset by the bytes before it
+    let mut d = Attributed::new();
+    d.extend(vec![a.clone(), b.clone(), a.clone()]);
+    assert_eq!("rent × 12rent", d.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(7..10, red.clone()), Span::new(10..14, bold.clone())], d.spans());
+  }
+  
+  #[test]
+  fn segment_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200 × 12", vec![Span::new(7..11, red.clone()), Span::new(0..4, bold.clone()), Span::new(2..6, red.clone()), Span::new(13..14, bold.clone())]);
+    assert_eq!(vec![
+      ("rent", Some(&bold)),
+      (" =", Some(&red)), // cut short by what it overlaps
+      (" ", None),
+      ("1200", Some(&red)),
+      (" ", None),
+      ("×", Some(&bold)), // widened to the character
+      (" 12", None),
+    ], a.segments().collect::<Vec<_>>());
+    assert_eq!(a.text(), a.segments().map(|(t, _)| t).collect::<String>());
+    assert_eq!(0, Attributed::new().segments().count());
+  }
+  
+  #[test]
+  fn replace_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200 EUR", vec![Span::new(0..4, bold.clone()), Span::new(7..11, red.clone()), Span::new(12..15, bold.clone())]);
+    let mut b = a.clone();
+    b.replace_range(7..11, "1,450.50");
+    assert_eq!("rent = 1,450.50 EUR", b.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(7..15, red.clone()), Span::new(16..19, bold.clone())], b.spans()); // covered, and shifted
+    let mut b = a.clone();
+    b.replace_range(9..9, "0");
+    assert_eq!("rent = 12000 EUR", b.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(7..12, red.clone()), Span::new(13..16, bold.clone())], b.spans()); // typed within
+    let mut b = a.clone();
+    b.replace_range(2..9, "");
+    assert_eq!("re00 EUR", b.text());
+    assert_eq!(&vec![Span::new(0..2, bold.clone()), Span::new(2..4, red.clone()), Span::new(5..8, bold.clone())], b.spans()); // cut back at both ends
+    let mut b = a.clone();
+    b.replace_range(6..12, "× ");
+    assert_eq!("rent =× EUR", b.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(9..12, bold.clone())], b.spans()); // dropped
+    let mut b = a.clone();
+    b.replace_range(4..4, "al");
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(9..13, red.clone()), Span::new(14..17, bold.clone())], b.spans()); // not extended past its end
+  }
+  
+  #[test]
+  fn normalize_spans() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let (spans, overlaps) = normalize(vec![
+      Span::new(4..8, bold.clone()),
+      Span::new(0..4, bold.clone()),
+      Span::new(8..8, red.clone()), // empty
+      #[allow(clippy::reversed_empty_ranges)]
+      Span::new(12..10, red.clone()), // backwards
+      Span::new(8..10, red.clone()),
+      Span::new(10..12, Attributes{color: Some(Color::Red), ..PLAIN}),
+      Span::new(12..14, bold.clone()),
+      Span::new_with_priority(14..16, bold.clone(), 1), // prevails differently
+    ]);
+    assert_eq!(vec![Span::new(0..8, bold.clone()), Span::new(8..12, red.clone()), Span::new(12..14, bold.clone()), Span::new_with_priority(14..16, bold.clone(), 1)], spans);
+    assert!(overlaps.is_empty());
+    let (spans, overlaps) = normalize(vec![Span::new(0..10, bold.clone()), Span::new(2..4, red.clone()), Span::new(6..12, red.clone())]);
+    assert_eq!(3, spans.len());
+    assert_eq!(vec![2..4, 6..10], overlaps);
+  }
+  
+  #[test]
+  fn expand_tabs() {
+    let mut a = Attributed::new_with_str("a\tb\n\tx€\ty", vec![
+      Span::new(2..3, Attributes{bold: true, ..PLAIN}),
+      Span::new(4..5, Attributes{underline: true, ..PLAIN}),
+      Span::new(10..11, Attributes{italic: true, ..PLAIN}),
+    ]);
+    let mut b = a.clone();
+    a.expand_tabs(4);
+    assert_eq!("a   b\n    x€  y", a.text());
+    assert_eq!("a   <b>b</b>\n<u>    </u>x€  <i>y</i>", a.render_with_mode(Mode::Markup)); // the second stop on the second line is two columns along
+    b.expand_tabs(0);
+    assert_eq!("a b\n x€ y", b.text());
+  }
+  
+  #[test]
+  fn insert_attributed() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let red = Attributes{color: Some(Color::Red), ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200", vec![Span::new(0..4, bold.clone()), Span::new(7..11, red.clone())]);
+    let mut b = a.clone();
+    b.insert_str(7, "12 × 100 = ");
+    assert_eq!("rent = 12 × 100 = 1200", b.text());
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(19..23, red.clone())], b.spans()); // shifted from where it's inserted
+    let mut b = a.clone();
+    b.insert_str(4, "al");
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(9..13, red.clone())], b.spans());
+    let mut b = a.clone();
+    b.insert_str(9, "0");
+    assert_eq!(&vec![Span::new(0..4, bold.clone()), Span::new(7..12, red.clone())], b.spans()); // within
+    let mut b = a.clone();
+    b.push_str(" EUR");
+    assert_eq!("rent = 1200 EUR", b.text());
+    assert_eq!(a.spans(), b.spans());
+  }
+  
+  #[test]
+  fn blend_backgrounds() {
+    let current = Attributes{bold: true, background: Some(Color::Rgb{r: 10, g: 10, b: 10}), ..PLAIN};
+    let section = Attributes{color: Some(Color::Red), background: Some(Color::Rgb{r: 60, g: 16, b: 16}), ..PLAIN};
+    assert_eq!(Attributes{bold: true, color: Some(Color::Red), background: Some(Color::Rgb{r: 35, g: 13, b: 13}), ..PLAIN}, current.blended(&section));
+    assert_eq!(Some(Color::Rgb{r: 128, g: 0, b: 128}), Attributes{background: Some(Color::Red), ..PLAIN}.blended(&Attributes{background: Some(Color::AnsiValue(21)), ..PLAIN}).background); // as red, green, and blue
+    assert_eq!(current, current.blended(&PLAIN));
+    assert_eq!(Some(Color::Reset), Attributes{background: Some(Color::Reset), ..PLAIN}.blended(&section).background); // which can't be blended
+    let spans = merge_with_policy(vec![Span::new(0..10, section.clone())], vec![Span::new(5..15, current.clone())], Policy::Blend);
+    assert_eq!(vec![Span::new(0..5, section.clone()), Span::new(5..10, section.blended(&current)), Span::new(10..15, current.clone())], spans);
+  }
+  
+  #[test]
+  fn attributed_lines() {
+    let bold = Attributes{bold: true, ..PLAIN};
+    let a = Attributed::new_with_str("rent = 1200\nfood = 400\n", vec![Span::new(7..11, bold.clone()), Span::new(19..22, bold.clone())]);
+    let mut lines = AttributedLines::from(a);
+    assert_eq!(vec!["rent = 1200", "food = 400", ""], lines.iter().map(|l| l.text()).collect::<Vec<_>>());
+    assert_eq!(&vec![Span::new(7..10, bold.clone())], lines.line(1).unwrap().spans()); // offset within the line
+    lines.remove(2);
+    lines.insert(1, Attributed::new_with_str("tax = 8%", vec![Span::new(6..8, bold.clone())]));
+    assert_eq!(3, lines.num_lines());
+    assert_eq!(&vec![Span::new(7..10, bold.clone())], lines.line(2).unwrap().spans()); // which a line before it doesn't change
+    let mut out = Vec::new();
+    lines.render_to(&mut out, 1..5, Mode::Markup).unwrap(); // as many as there are
+    assert_eq!("tax = <b>8%</b>\nfood = <b>400</b>\n", String::from_utf8(out).unwrap());
+    let lines: AttributedLines = lines.iter().map(|l| l.slice(0..4)).collect();
+    assert_eq!(vec!["rent", "tax ", "food"], lines.iter().map(|l| l.text()).collect::<Vec<_>>());
+  }
+  
+  #[cfg(feature = "serialize")]
+  #[test]
+  fn serialize_attributed() {
+    let a = Attributed::new_with_str("rent = 1200", vec![
+      Span::new(0..4, Attributes{bold: true, color: Some(Color::DarkRed), ..PLAIN}),
+      Span::new(7..11, Attributes{underline: true, color: Some(Color::AnsiValue(208)), background: Some(Color::Rgb{r: 255, g: 136, b: 0}), ..PLAIN}),
+    ]);
+    let json = serde_json::to_string(&a).unwrap();
+    assert!(json.contains(r#""color":"dark_red""#) && json.contains(r#""color":"208""#) && json.contains(r##""background":"#ff8800""##), "{}", json);
+    let b: Attributed = serde_json::from_str(&json).unwrap();
+    assert_eq!(a.text(), b.text());
+    assert_eq!(a.spans(), b.spans());
+    let attrs: Attributes = serde_json::from_str(r#"{"bold":true,"underline":false,"italic":false,"strikethrough":false,"dim":false,"invert":false,"color":"dark-blue"}"#).unwrap();
+    assert_eq!(Attributes{bold: true, color: Some(Color::DarkBlue), ..PLAIN}, attrs);
+    assert!(serde_json::from_str::<Attributes>(r#"{"bold":true,"underline":false,"italic":false,"strikethrough":false,"dim":false,"invert":false,"color":"mauve"}"#).is_err());
+  }
+  
+  #[test]
+  fn overlay_spans() {
+    let red = Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Red), background: None, link: None};
+    let blue = Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None};
+    let a = vec![
+      Span::new(0..4, blue.clone()),
+      Span::new(8..12, blue.clone()),
+    ];
+    assert_eq!(vec![
+      Span::new(0..2, blue.clone()),
+      Span::new(2..4, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Red), background: None, link: None}),
+      Span::new(4..6, red.clone()),
+      Span::new(8..12, blue.clone()),
+    ], overlay(a, 2..6, &red));
   }
   
   #[test]
   fn render_attributes() {
     let t = "Hello, there.";
     
-    let a = vec![Span::new(0..5, Attributes{bold:true, invert: false, color: None, background: None})];
+    let a = vec![Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None})];
     assert_eq!("<b>Hello</b>, there.", render_with_mode(t, &a, Mode::Markup));
     
-    let a = vec![Span::new(0..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None})];
+    let a = vec![Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None})];
     assert_eq!("<b><fg:Blue>Hello</fg:Blue></b>, there.", render_with_mode(t, &a, Mode::Markup));
     
-    let a = vec![Span::new(7..12, Attributes{bold:false, invert: false, color: Some(Color::Green), background: None}), Span::new(0..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None})];
+    let a = vec![Span::new(7..12, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Green), background: None, link: None}), Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None})];
     assert_eq!("<b><fg:Blue>Hello</fg:Blue></b>, <fg:Green>there</fg:Green>.", render_with_mode(t, &a, Mode::Markup));
+    
+    let a = vec![Span::new(0..5, Attributes{bold:false, underline: true, italic: true, strikethrough: true, dim: true, invert: false, color: None, background: None, link: None})];
+    assert_eq!("<u><i><s><dim>Hello</dim></s></i></u>, there.", render_with_mode(t, &a, Mode::Markup));
+    assert_eq!("\x1b[2;3;4;9mHello\x1b[0m, there.", render(t, &a));
+  }
+  
+  #[test]
+  fn render_changes_in_style() {
+    let t = "Hello, there.";
+    let a = vec![
+      Span::new(0..5, Attributes{bold: true, color: Some(Color::Blue), ..PLAIN}),
+      Span::new(5..7, Attributes{bold: true, color: Some(Color::Green), ..PLAIN}),
+      Span::new(7..12, Attributes{dim: true, underline: true, color: Some(Color::Green), ..PLAIN}),
+      Span::new(12..13, Attributes{dim: true, ..PLAIN}),
+    ];
+    assert_eq!("\x1b[1;38;5;12mHello\x1b[38;5;10m, \x1b[22;2;4mthere\x1b[24;39m.\x1b[0m", render(t, &a)); // only what changes
+    let a = vec![Span::new(0..5, Attributes{bold: true, ..PLAIN}), Span::new(7..12, Attributes{bold: true, ..PLAIN})];
+    assert_eq!("\x1b[1mHello\x1b[0m, \x1b[1mthere\x1b[0m.", render(t, &a));
+    let red = Attributes{bold: true, color: Some(Color::Red), ..PLAIN};
+    assert_eq!("\x1b[1m", sequence(&uncolored(&PLAIN), &uncolored(&red))); // without color
+    assert_eq!("", sequence(&uncolored(&PLAIN), &uncolored(&Attributes{color: Some(Color::Red), ..PLAIN})));
+  }
+  
+  #[test]
+  fn render_to_writer() {
+    let a = Attributed::new_with_str("Hello, there.", vec![Span::new(0..5, Attributes{bold: true, ..PLAIN}), Span::new(7..12, Attributes{underline: true, link: Some("https://example.com".to_string()), ..PLAIN})]);
+    for mode in [Mode::Terminal, Mode::Markup, Mode::Html, Mode::Markdown, Mode::Rtf, Mode::Plain] {
+      let mut out = Vec::new();
+      a.render_to(&mut out, mode).unwrap();
+      assert_eq!(a.render_with_mode(mode), String::from_utf8(out).unwrap());
+    }
+    let mut b = Buffer::new();
+    render_with_offset_to(&mut b, "there.", 7, a.spans()).unwrap(); // from an offset, as a line is
+    assert_eq!("\x1b[4m\x1b]8;;https://example.com\x1b\\there\x1b]8;;\x1b\\\x1b[0m.", b._text());
+  }
+  
+  #[test]
+  fn gradient_spans() {
+    let text = "Total: 1200 €";
+    let bold = Attributes{bold: true, ..PLAIN};
+    let spans = gradient(text, 7..14, (0, 0, 0), (250, 100, 50), &bold); // widened to the end of €
+    assert_eq!(6, spans.len());
+    assert_eq!(Span::new(7..8, Attributes{color: Some(Color::Rgb{r: 0, g: 0, b: 0}), ..bold.clone()}), spans[0]);
+    assert_eq!(Span::new(8..9, Attributes{color: Some(Color::Rgb{r: 50, g: 20, b: 10}), ..bold.clone()}), spans[1]);
+    assert_eq!(Span::new(12..15, Attributes{color: Some(Color::Rgb{r: 250, g: 100, b: 50}), ..bold.clone()}), spans[5]);
+    assert_eq!(vec![Span::new(0..1, Attributes{color: Some(Color::Rgb{r: 0, g: 0, b: 0}), ..PLAIN})], gradient(text, 0..1, (0, 0, 0), (250, 100, 50), &PLAIN));
+    assert_eq!(Vec::<Span>::new(), gradient(text, 3..3, (0, 0, 0), (250, 100, 50), &PLAIN));
+  }
+  
+  #[test]
+  fn cache_rendering() {
+    let mut a = Attributed::new_with_str("Hello, there.", vec![Span::new(0..5, Attributes{bold: true, ..PLAIN})]);
+    assert_eq!("<b>Hello</b>, there.", a.render_with_mode(Mode::Markup));
+    assert_eq!("<b>Hello</b>, there.", a.render_with_mode(Mode::Markup));
+    assert_eq!("Hello, there.", a.render_with_mode(Mode::Plain));
+    assert_eq!(2, a.rendered.borrow().len()); // once for each mode
+    let mut out = Vec::new();
+    a.render_to(&mut out, Mode::Markup).unwrap();
+    assert_eq!(b"<b>Hello</b>, there.".to_vec(), out);
+    
+    a.push_str("!");
+    assert_eq!("<b>Hello</b>, there.!", a.render_with_mode(Mode::Markup));
+    a.spans_mut()[0].range = 7..12;
+    assert_eq!("Hello, <b>there</b>.!", a.render_with_mode(Mode::Markup));
+    a.highlight("hello", &Attributes{italic: true, ..PLAIN});
+    assert_eq!("<i>Hello</i>, <b>there</b>.!", a.render_with_mode(Mode::Markup));
+    a.append(Attributed::new_with_str("?", Vec::new()));
+    assert_eq!("Hello, there.!?", a.render_with_mode(Mode::Plain));
+  }
+  
+  #[test]
+  fn render_attributes_between_chars() {
+    let t = "Ça coûte 12 €.";
+    let bold = Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None};
+    let a = vec![Span::new(1..2, bold.clone()), Span::new(2..4, bold.clone()), Span::new(14..15, bold.clone())]; // within Ç, then from its end, then within €
+    assert_eq!("<b>Ç</b><b>a </b>coûte 12 <b>€</b>.", render_with_mode(t, &a, Mode::Markup));
+    assert_eq!("co<b>û</b>te 12 €.", render_with_options(&t[4..], 4, &vec![Span::new(7..8, bold.clone())], Mode::Markup)); // within û, on a line from an offset
+    assert_eq!(0..2, widen_to_chars(t, 1..1));
+    assert_eq!(14..18, widen_to_chars(t, 16..99));
+    assert!(render(t, &vec![Span::new(0..t.len() + 1, bold)]).contains("12 €."));
   }
   
   #[test]
@@ -357,7 +1761,7 @@ mod tests {
     let x = 7;
     let p = &t[x..];
     
-    let a = vec![Span::new(7..12, Attributes{bold:false, invert: false, color: Some(Color::Green), background: None}), Span::new(12..13, Attributes{bold:true, invert: false, color: None, background: None})];
+    let a = vec![Span::new(7..12, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Green), background: None, link: None}), Span::new(12..13, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None})];
     assert_eq!("<fg:Green>there</fg:Green><b>.</b>", render_with_options(p, x, &a, Mode::Markup));
   }
   
@@ -365,20 +1769,116 @@ mod tests {
   fn render_attributed() {
     let t = "Hello, there.";
     
-    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, invert: false, color: None, background: None})]);
+    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None})]);
     assert_eq!("<b>Hello</b>, there.", a.render_with_mode(Mode::Markup));
     
-    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, invert: false, color: None, background: None})]);
+    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None})]);
     assert_eq!("<b>Hello</b>, there.", a.render_with_mode(Mode::Markup));
     
-    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None})]);
+    let a = Attributed::new_with_str(t, vec![Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None})]);
     assert_eq!("<b><fg:Blue>Hello</fg:Blue></b>, there.", a.render_with_mode(Mode::Markup));
     
     let a = Attributed::new_with_str(t, vec![
-      Span::new(7..12, Attributes{bold:false, invert: false, color: Some(Color::Green), background: None}), // deliberately out of order
-      Span::new(0..5, Attributes{bold:true, invert: false, color: Some(Color::Blue), background: None})
+      Span::new(7..12, Attributes{bold:false, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Green), background: None, link: None}), // deliberately out of order
+      Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None})
     ]);
     assert_eq!("<b><fg:Blue>Hello</fg:Blue></b>, <fg:Green>there</fg:Green>.", a.render_with_mode(Mode::Markup));
+    assert_eq!("Hello, there.", a.render_with_mode(Mode::Plain));
+    assert_eq!("<span style=\"color: #3b8eea; font-weight: bold\">Hello</span>, <span style=\"color: #23d18b\">there</span>.", a.render_with_mode(Mode::Html));
+    
+    let a = Attributed::new_with_str("<b> & \"c\"", vec![Span::new(0..3, Attributes{bold:false, underline: true, italic: false, strikethrough: true, dim: false, invert: true, color: Some(Color::Rgb{r: 255, g: 136, b: 0}), background: None, link: None})]);
+    assert_eq!("<span style=\"color: Canvas; background-color: #ff8800; text-decoration: underline line-through\">&lt;b&gt;</span> &amp; &quot;c&quot;", a.render_with_mode(Mode::Html));
+    assert_eq!(Some("#87ff00".to_string()), hex(Color::AnsiValue(118)));
+    assert_eq!(Some("#cd3131".to_string()), hex(Color::AnsiValue(1)));
+  }
+  
+  #[test]
+  fn parse_markup_spans() {
+    let t = "Hello, there <3.";
+    let a = vec![
+      Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
+      Span::new(5..7, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: None, background: None, link: None}),
+      Span::new(7..12, Attributes{bold:false, underline: true, italic: true, strikethrough: true, dim: true, invert: true, color: Some(Color::Rgb{r: 255, g: 136, b: 0}), background: Some(Color::AnsiValue(236)), link: None}),
+    ];
+    let markup = render_with_mode(t, &a, Mode::Markup);
+    assert_eq!("<b><fg:Blue>Hello</fg:Blue></b><b>, </b><u><i><s><dim><invert><bg:AnsiValue(236)><fg:Rgb { r: 255, g: 136, b: 0 }>there</fg:Rgb { r: 255, g: 136, b: 0 }></bg:AnsiValue(236)></invert></dim></s></i></u> <3.", &markup);
+    let parsed = parse_markup(&markup);
+    assert_eq!(t, parsed.text());
+    assert_eq!(&a, parsed.spans());
+    assert_eq!(markup, parsed.render_with_mode(Mode::Markup));
+    
+    let parsed = parse_markup("<b>a <x> <fg:Mauve>b</b><i>c");
+    assert_eq!("a <x> <fg:Mauve>bc", parsed.text());
+    assert_eq!(vec![(0..17, true, false), (17..18, false, true)], parsed.spans().iter().map(|s| (s.range().clone(), s.attrs().bold, s.attrs().italic)).collect::<Vec<_>>()); // an unclosed tag closes at the end
+    
+    let bold = Attributes{bold: true, ..PLAIN};
+    let parsed = parse_markup("<b>x<i>y</i>z</b> <u>a<fg:Red>b</u>c</fg:Red>"); // nested, as written by hand, and overlapping
+    assert_eq!("xyz abc", parsed.text());
+    assert_eq!(&vec![
+      Span::new(0..1, bold.clone()),
+      Span::new(1..2, Attributes{italic: true, ..bold.clone()}),
+      Span::new(2..3, bold.clone()),
+      Span::new(4..5, Attributes{underline: true, ..PLAIN}),
+      Span::new(5..6, Attributes{underline: true, color: Some(Color::Red), ..PLAIN}),
+      Span::new(6..7, Attributes{color: Some(Color::Red), ..PLAIN}),
+    ], parsed.spans());
+    assert_eq!(parsed.spans(), parse_markup(&parsed.render_with_mode(Mode::Markup)).spans());
+  }
+  
+  #[test]
+  fn parse_ansi_spans() {
+    let t = "Hello, there.";
+    let a = vec![
+      Span::new(0..5, Attributes{bold:true, underline: false, italic: false, strikethrough: false, dim: false, invert: false, color: Some(Color::Blue), background: None, link: None}),
+      Span::new(7..12, Attributes{bold:false, underline: true, italic: true, strikethrough: true, dim: true, invert: true, color: Some(Color::Rgb{r: 255, g: 136, b: 0}), background: Some(Color::AnsiValue(236)), link: None}),
+    ];
+    let parsed = parse_ansi("\x1b[38;5;12m\x1b[1mHello\x1b[0m, \x1b[4;3;9;2;7;48;5;236;38;2;255;136;0mthere\x1b[0m."); // as crossterm writes it, with true color
+    assert_eq!(t, parsed.text());
+    assert_eq!(&a, parsed.spans());
+    
+    let parsed = parse_ansi("\x1b]8;;http://x\x07link\x1b]8;;\x1b\\ \x1b[1;31mred\x1b[22m still\x1b[0m \x1b[2Kplain\x1b[");
+    assert_eq!("link red still plain", parsed.text());
+    assert_eq!("<link:http://x>link</link:http://x> <b><fg:DarkRed>red</fg:DarkRed></b><fg:DarkRed> still</fg:DarkRed> plain", parsed.render_with_mode(Mode::Markup));
+  }
+  
+  #[test]
+  fn render_rtf() {
+    let a = Attributed::new_with_str("Rent: {rent} = 1200 €", vec![
+      Span::new(6..12, Attributes{bold: true, italic: true, color: Some(Color::Red), ..PLAIN}),
+      Span::new(15..24, Attributes{underline: true, background: Some(Color::Rgb{r: 250, g: 250, b: 0}), dim: true, ..PLAIN}),
+    ]);
+    assert_eq!("Rent: {\\b\\i\\cf10 \\{rent\\}} = {\\ul\\highlight12 1200 \\u8364?}", a.render_with_mode(Mode::Rtf)); // the nearest named colors, and without dimming
+    let a = Attributed::new_with_str("ECB 😀\nrate", vec![Span::new(0..3, Attributes{link: Some("https://x/\"a\"".to_string()), ..PLAIN})]);
+    assert_eq!("{\\field{\\*\\fldinst HYPERLINK \"https://x/%22a%22\"}{\\fldrslt ECB}} \\u-10179?\\u-8704?\\line rate", a.render_with_mode(Mode::Rtf)); // in surrogates
+  }
+  
+  #[test]
+  fn render_markdown() {
+    let bold = Attributes{bold: true, color: Some(Color::Yellow), ..PLAIN};
+    let a = Attributed::new_with_str("# Rent: rent = 1_200 * 12 ", vec![
+      Span::new(8..13, Attributes{italic: true, strikethrough: true, ..PLAIN}),
+      Span::new(15..25, bold.clone()),
+    ]);
+    assert_eq!("\\# Rent: *~~rent~~* = **1\\_200 \\* 12** ", a.render_with_mode(Mode::Markdown));
+    let a = Attributed::new_with_str("use `x` * 2", vec![Span::new(3..8, Attributes{invert: true, ..PLAIN}), Span::new(8..11, Attributes{underline: true, ..PLAIN})]);
+    assert_eq!("use `` `x` `` \\* 2", a.render_with_mode(Mode::Markdown)); // code isn't escaped, and underlining is dropped
+    let a = Attributed::new_with_str("ECB", vec![Span::new(0..3, Attributes{bold: true, link: Some("https://x/a (b)".to_string()), ..PLAIN})]);
+    assert_eq!("[**ECB**](https://x/a%20%28b%29)", a.render_with_mode(Mode::Markdown));
+  }
+  
+  #[test]
+  fn render_links() {
+    let link = Attributes{underline: true, link: Some("https://www.ecb.europa.eu/?a=1&b=2".to_string()), ..PLAIN};
+    let a = Attributed::new_with_str("rate 1.08 ECB", vec![Span::new(10..13, link.clone())]);
+    assert_eq!("rate 1.08 \x1b[4m\x1b]8;;https://www.ecb.europa.eu/?a=1&b=2\x1b\\ECB\x1b]8;;\x1b\\\x1b[0m", a.render());
+    assert_eq!("rate 1.08 <a href=\"https://www.ecb.europa.eu/?a=1&amp;b=2\"><span style=\"text-decoration: underline\">ECB</span></a>", a.render_with_mode(Mode::Html));
+    let markup = a.render_with_mode(Mode::Markup);
+    assert_eq!("rate 1.08 <link:https://www.ecb.europa.eu/?a=1&b=2><u>ECB</u></link:https://www.ecb.europa.eu/?a=1&b=2>", markup);
+    assert_eq!(a.spans(), parse_markup(&markup).spans());
+    assert_eq!(a.spans(), parse_ansi(&a.render()).spans());
+    assert_eq!(Some("https://x"), Attributes{bold: true, ..PLAIN}.merged(&Attributes{link: Some("https://x".to_string()), ..PLAIN}).link.as_deref());
+    let evil = Attributes{link: Some("https://x\x1b[2J\x07".to_string()), ..PLAIN};
+    assert!(!evil.render("x").contains("\x1b[2J")); // control characters in a link can't escape it
   }
   
 }
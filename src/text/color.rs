@@ -0,0 +1,367 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crossterm::style::Color;
+
+/// The depth detected, once it has been; see `depth`.
+static DEPTH: AtomicU8 = AtomicU8::new(0);
+
+/// How text rendered for the terminal is styled; see `set_styling`.
+static STYLING: AtomicU8 = AtomicU8::new(Styling::Full as u8);
+
+/// How much styling what text is written to can show.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Styling {
+  Full = 0,       // escape sequences, in color as deep as the terminal shows
+  Monochrome = 1, // escape sequences without color, as `NO_COLOR` asks for
+  Plain = 2,      // no escape sequences at all
+}
+
+/// How many colors a terminal can show.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Depth {
+  Ansi16 = 1,  // the named colors only
+  Ansi256 = 2, // the named colors, a 6×6×6 cube, and a ramp of grays
+  TrueColor = 3,
+}
+
+/// The red, green, and blue of the 16 named colors, as xterm shows them by
+/// default, in the order of their ANSI numbers.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+  (Color::Black,       (0, 0, 0)),
+  (Color::DarkRed,     (205, 0, 0)),
+  (Color::DarkGreen,   (0, 205, 0)),
+  (Color::DarkYellow,  (205, 205, 0)),
+  (Color::DarkBlue,    (0, 0, 238)),
+  (Color::DarkMagenta, (205, 0, 205)),
+  (Color::DarkCyan,    (0, 205, 205)),
+  (Color::Grey,        (229, 229, 229)),
+  (Color::DarkGrey,    (127, 127, 127)),
+  (Color::Red,         (255, 0, 0)),
+  (Color::Green,       (0, 255, 0)),
+  (Color::Yellow,      (255, 255, 0)),
+  (Color::Blue,        (92, 92, 255)),
+  (Color::Magenta,     (255, 0, 255)),
+  (Color::Cyan,        (0, 255, 255)),
+  (Color::White,       (255, 255, 255)),
+];
+
+/// The names of the 16 named colors, as themes write them.
+const NAMES: [(Color, &str); 16] = [
+  (Color::Black,       "black"),
+  (Color::DarkGrey,    "dark_grey"),
+  (Color::Red,         "red"),
+  (Color::DarkRed,     "dark_red"),
+  (Color::Green,       "green"),
+  (Color::DarkGreen,   "dark_green"),
+  (Color::Yellow,      "yellow"),
+  (Color::DarkYellow,  "dark_yellow"),
+  (Color::Blue,        "blue"),
+  (Color::DarkBlue,    "dark_blue"),
+  (Color::Magenta,     "magenta"),
+  (Color::DarkMagenta, "dark_magenta"),
+  (Color::Cyan,        "cyan"),
+  (Color::DarkCyan,    "dark_cyan"),
+  (Color::White,       "white"),
+  (Color::Grey,        "grey"),
+];
+
+/// The levels of each of red, green, and blue in the 256-color cube.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors the terminal can show, from the environment it says
+/// so in. This is detected the first time it is needed.
+pub fn depth() -> Depth {
+  match DEPTH.load(Ordering::Relaxed) {
+    1 => Depth::Ansi16,
+    2 => Depth::Ansi256,
+    3 => Depth::TrueColor,
+    _ => {
+      let var = |name| env::var(name).unwrap_or_default();
+      let depth = detect(&var("COLORTERM"), &var("TERM"), env::var_os("WT_SESSION").is_some());
+      DEPTH.store(depth as u8, Ordering::Relaxed);
+      depth
+    },
+  }
+}
+
+/// Determine how many colors a terminal can show from `COLORTERM`, which
+/// terminals that show true color set, `TERM`, and whether it is Windows
+/// Terminal, which shows true color without saying so.
+fn detect(colorterm: &str, term: &str, windows_terminal: bool) -> Depth {
+  if matches!(colorterm, "truecolor" | "24bit") || term.ends_with("-direct") || windows_terminal {
+    Depth::TrueColor
+  }else if term.contains("256color") {
+    Depth::Ansi256
+  }else{
+    Depth::Ansi16
+  }
+}
+
+/// Set how text rendered for the terminal is styled from now on. It is
+/// fully styled until this is called.
+pub fn set_styling(styling: Styling) {
+  STYLING.store(styling as u8, Ordering::Relaxed);
+}
+
+/// How text rendered for the terminal is currently styled.
+pub fn styling() -> Styling {
+  match STYLING.load(Ordering::Relaxed) {
+    1 => Styling::Monochrome,
+    2 => Styling::Plain,
+    _ => Styling::Full,
+  }
+}
+
+/// Determine how much styling standard output can show: none if it isn't
+/// a terminal, as when it is redirected to a file, or is a dumb one, and
+/// no color if `NO_COLOR` is set.
+pub fn probe() -> Styling {
+  capability(io::stdout().is_terminal(), &env::var("NO_COLOR").unwrap_or_default(), &env::var("TERM").unwrap_or_default())
+}
+
+fn capability(terminal: bool, no_color: &str, term: &str) -> Styling {
+  if !terminal || term == "dumb" {
+    Styling::Plain
+  }else if !no_color.is_empty() { // set to anything but nothing, per no-color.org
+    Styling::Monochrome
+  }else{
+    Styling::Full
+  }
+}
+
+/// A color the terminal can show: the color itself if it can, and
+/// otherwise the nearest one it can.
+pub fn fit(color: Color) -> Color {
+  downgrade(color, depth())
+}
+
+/// The nearest color to another among those a terminal of some depth can
+/// show. The named colors can be shown by every terminal.
+pub fn downgrade(color: Color, depth: Depth) -> Color {
+  match (color, depth) {
+    (Color::Rgb{r, g, b}, Depth::Ansi256) => Color::AnsiValue(nearest_256((r, g, b))),
+    (Color::Rgb{r, g, b}, Depth::Ansi16) => nearest_16((r, g, b)),
+    (Color::AnsiValue(n), Depth::Ansi16) => nearest_16(rgb_256(n)),
+    (color, _) => color,
+  }
+}
+
+/// The square of the distance between two colors.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+  let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+  d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+  ANSI16.iter().min_by_key(|(_, c)| distance(*c, rgb)).map_or(Color::White, |(color, _)| *color)
+}
+
+/// The number of the nearest color in the 256-color cube or ramp of grays,
+/// whichever is nearer. The 16 named colors are left out, since terminals
+/// often show them differently than they are defined.
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+  let level = |v: u8| (0..CUBE.len()).min_by_key(|i| (CUBE[*i] as i32 - v as i32).abs()).unwrap_or(0);
+  let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+  let cube = (16 + 36 * r + 6 * g + b) as u8;
+  let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+  let gray = 232 + ((avg.saturating_sub(3) / 10).min(23)) as u8; // 8, 18, … 238
+  if distance(rgb_256(gray), rgb) < distance(rgb_256(cube), rgb) { gray }else{ cube }
+}
+
+/// The red, green, and blue of a color, if it has them, as xterm shows the
+/// named colors by default; the reset color has none.
+pub fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+  match color {
+    Color::Rgb{r, g, b} => Some((r, g, b)),
+    Color::AnsiValue(n) => Some(rgb_256(n)),
+    color => ANSI16.iter().find(|(c, _)| *c == color).map(|(_, rgb)| *rgb),
+  }
+}
+
+/// The color halfway between two others, if they both have a red, green,
+/// and blue.
+pub fn blend(a: Color, b: Color) -> Option<Color> {
+  let (r, g, b) = mix(rgb(a)?, rgb(b)?, 0.5);
+  Some(Color::Rgb{r, g, b})
+}
+
+/// The red, green, and blue a fraction of the way from one color to
+/// another, from none of the way to all of it.
+pub fn mix(a: (u8, u8, u8), b: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+  let t = t.clamp(0.0, 1.0);
+  let mix = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64) * t).round() as u8;
+  (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// The table of colors RTF text refers to, which has the 16 named colors,
+/// from one in the order of their ANSI numbers, since the first is the
+/// text's own color.
+pub fn rtf_table() -> String {
+  let colors: String = ANSI16.iter().map(|(_, (r, g, b))| format!("\\red{}\\green{}\\blue{};", r, g, b)).collect();
+  format!("{{\\colortbl;{}}}", colors)
+}
+
+/// Where the nearest named color to a color is in the table `rtf_table`
+/// writes, if it has one; the reset color has none.
+pub fn rtf_index(color: Color) -> Option<usize> {
+  let color = downgrade(color, Depth::Ansi16);
+  ANSI16.iter().position(|(c, _)| *c == color).map(|n| n + 1)
+}
+
+/// The named color with an ANSI number, which must be less than 16.
+pub fn named(n: u8) -> Color {
+  ANSI16[n as usize % 16].0
+}
+
+/// The red, green, and blue of one of the 256 colors.
+pub fn rgb_256(n: u8) -> (u8, u8, u8) {
+  match n {
+    0..=15 => ANSI16[n as usize].1,
+    16..=231 => {
+      let n = n - 16;
+      (CUBE[(n / 36) as usize], CUBE[(n / 6 % 6) as usize], CUBE[(n % 6) as usize])
+    },
+    _ => {
+      let v = 8 + (n - 232) * 10;
+      (v, v, v)
+    },
+  }
+}
+
+/// Parse a color as themes write it: a name, such as `red` or `dark_red`
+/// (or `dark-red`), a hex value, such as `#ff8800`, an ANSI color number,
+/// such as `208`, or its red, green, and blue, as in `rgb(255, 136, 0)`.
+/// The bright colors, as in `bright_blue`, are the colors without `dark`
+/// in their names, and the normal ones those with it.
+pub fn parse(text: &str) -> Result<Color, String> {
+  let name = text.trim().to_lowercase().replace('-', "_").replace("gray", "grey");
+  let name = match name.strip_prefix("bright_") {
+    Some("black") => "dark_grey".to_string(),
+    Some(bright) => bright.to_string(),
+    None => name,
+  };
+  if let Some((color, _)) = NAMES.iter().find(|(_, n)| *n == name) {
+    return Ok(*color);
+  }
+  if let Some(rgb) = name.strip_prefix("rgb(").and_then(|t| t.strip_suffix(')')) {
+    return match rgb.split(',').map(|v| v.trim().parse::<u8>()).collect::<Result<Vec<u8>, _>>().as_deref() {
+      Ok(&[r, g, b]) => Ok(Color::Rgb{r, g, b}),
+      _ => Err(format!("invalid color: {}", text)),
+    };
+  }
+  match name.strip_prefix('#') {
+    Some(hex) if hex.len() == 6 => match u32::from_str_radix(hex, 16) {
+      Ok(v) => Ok(Color::Rgb{r: (v >> 16) as u8, g: (v >> 8) as u8, b: v as u8}),
+      Err(_) => Err(format!("invalid color: {}", text)),
+    },
+    _ => match name.parse::<u8>() {
+      Ok(v) => Ok(Color::AnsiValue(v)),
+      Err(_) => Err(format!("invalid color: {}", text)),
+    },
+  }
+}
+
+/// The name of a color as themes write it, which `parse` reads back: its
+/// name, or its hex value or ANSI color number if it has none. Colors
+/// which can't be written as any of them, such as the reset color, have
+/// none.
+#[cfg(feature = "serialize")]
+pub fn name(color: Color) -> Option<String> {
+  match color {
+    Color::Rgb{r, g, b} => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+    Color::AnsiValue(n) => Some(n.to_string()),
+    color => NAMES.iter().find(|(c, _)| *c == color).map(|(_, n)| n.to_string()),
+  }
+}
+
+/// Colors written as themes write them, which `parse` reads back, so that
+/// what is written doesn't depend on how crossterm represents colors.
+#[cfg(feature = "serialize")]
+pub mod serde_option {
+  use serde::{Serializer, Deserializer, Deserialize};
+  use super::*;
+
+  pub fn serialize<S: Serializer>(color: &Option<Color>, s: S) -> Result<S::Ok, S::Error> {
+    match color.and_then(name) {
+      Some(name) => s.serialize_some(&name),
+      None => s.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Color>, D::Error> {
+    match Option::<String>::deserialize(d)? {
+      Some(text) => parse(&text).map(Some).map_err(serde::de::Error::custom),
+      None => Ok(None),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn downgrade_colors() {
+    assert_eq!(Depth::TrueColor, detect("truecolor", "xterm-256color", false));
+    assert_eq!(Depth::TrueColor, detect("", "xterm-direct", false));
+    assert_eq!(Depth::TrueColor, detect("", "", true));
+    assert_eq!(Depth::Ansi256, detect("", "screen-256color", false));
+    assert_eq!(Depth::Ansi16, detect("", "xterm", false));
+
+    let orange = Color::Rgb{r: 255, g: 136, b: 0};
+    assert_eq!(orange, downgrade(orange, Depth::TrueColor));
+    assert_eq!(Color::AnsiValue(208), downgrade(orange, Depth::Ansi256)); // 255, 135, 0
+    assert_eq!(Color::AnsiValue(234), downgrade(Color::Rgb{r: 30, g: 30, b: 30}, Depth::Ansi256)); // a gray, nearer than the cube's
+    assert_eq!(Color::AnsiValue(16), downgrade(Color::Rgb{r: 0, g: 0, b: 0}, Depth::Ansi256));
+    assert_eq!(Color::DarkYellow, downgrade(Color::Rgb{r: 200, g: 190, b: 20}, Depth::Ansi16));
+    assert_eq!(Color::Black, downgrade(Color::Rgb{r: 10, g: 10, b: 10}, Depth::Ansi16));
+    assert_eq!(Color::Red, downgrade(Color::AnsiValue(196), Depth::Ansi16));
+    assert_eq!(Color::DarkGrey, downgrade(Color::AnsiValue(244), Depth::Ansi16));
+    assert_eq!(Color::Blue, downgrade(Color::Blue, Depth::Ansi16));
+    for n in 16..=255 {
+      assert_eq!(rgb_256(n), rgb_256(nearest_256(rgb_256(n))), "{}", n); // each of the colors is its own nearest, or one the same
+    }
+  }
+
+  #[test]
+  fn parse_colors() {
+    assert_eq!(Ok(Color::DarkRed), parse("dark_red"));
+    assert_eq!(Ok(Color::DarkRed), parse(" Dark-Red "));
+    assert_eq!(Ok(Color::Blue), parse("bright-blue"));
+    assert_eq!(Ok(Color::DarkGrey), parse("bright_black"));
+    assert_eq!(Ok(Color::Grey), parse("gray"));
+    assert_eq!(Ok(Color::Rgb{r: 255, g: 136, b: 0}), parse("#FF8800"));
+    assert_eq!(Ok(Color::Rgb{r: 10, g: 20, b: 30}), parse("rgb(10, 20,30)"));
+    assert_eq!(Ok(Color::AnsiValue(208)), parse("208"));
+    for bad in ["mauve", "#ff88", "#gg8800", "rgb(10, 20)", "rgb(10, 20, 300)", "256", ""] {
+      assert!(parse(bad).is_err(), "{}", bad);
+    }
+    #[cfg(feature = "serialize")]
+    {
+      for color in [Color::DarkRed, Color::Blue, Color::Grey, Color::Rgb{r: 255, g: 136, b: 0}, Color::AnsiValue(208)] {
+        assert_eq!(Ok(color), parse(&name(color).unwrap())); // read back as written
+      }
+      assert_eq!(None, name(Color::Reset));
+    }
+  }
+
+  #[test]
+  fn rtf_colors() {
+    assert!(rtf_table().starts_with("{\\colortbl;\\red0\\green0\\blue0;\\red205\\green0\\blue0;"));
+    assert_eq!(Some(2), rtf_index(Color::DarkRed));
+    assert_eq!(Some(10), rtf_index(Color::Rgb{r: 250, g: 10, b: 10})); // the nearest, red
+    assert_eq!(None, rtf_index(Color::Reset));
+  }
+
+  #[test]
+  fn probe_styling() {
+    assert_eq!(Styling::Full, capability(true, "", "xterm-256color"));
+    assert_eq!(Styling::Monochrome, capability(true, "1", "xterm-256color"));
+    assert_eq!(Styling::Full, capability(true, "", "")); // set, but to nothing
+    assert_eq!(Styling::Plain, capability(false, "", "xterm-256color")); // redirected
+    assert_eq!(Styling::Plain, capability(true, "1", "dumb"));
+  }
+
+}
@@ -1,4 +1,5 @@
 use crate::text;
+use crate::text::width;
 
 fn is_break(c: char) -> bool {
   c == '\n'
@@ -11,10 +12,12 @@ pub fn layout(text: &str, width: usize) -> Vec<text::Line> {
   let mut ab: usize = 0; // absolute text offset, in bytes
   let mut lc: usize = 0; // line width, in chars
   let mut lb: usize = 0; // line width, in bytes
+  let mut lw: usize = 0; // line width, in columns
   let mut wc: usize = 0; // line width to beginning of last whitespace, in chars
   let mut wb: usize = 0; // line width to beginning of last whitespace, in bytes
   let mut rc: usize = 0; // line width to beginning of last non-whitespace, in chars
   let mut rb: usize = 0; // line width to beginning of last non-whitespace, in bytes
+  let mut rw: usize = 0; // line width to beginning of last non-whitespace, in columns
   let mut ly: usize = 0; // line number
   let mut p:  char = '\0'; // previous iteration character
   
@@ -32,6 +35,7 @@ pub fn layout(text: &str, width: usize) -> Vec<text::Line> {
       if !p.is_whitespace() {
         rc = lc;
         rb = lb;
+        rw = lw;
       }
       // set whitespace boundary to here
       wc = lc;
@@ -46,17 +50,21 @@ pub fn layout(text: &str, width: usize) -> Vec<text::Line> {
       if p.is_whitespace() {
         rc = lc;
         rb = lb;
+        rw = lw;
       }
     }
     
     lc += 1;
     lb += c.len_utf8();
+    lw += width::char_width(c);
     
-    if hard || lc >= width {
+    if hard || lw >= width {
       let bc = if  hard || wc > 0 { wc } else { lc }; // break
       let bb = if  hard || wb > 0 { wb } else { lb }; // break
-      let cc = if !hard && rc > 0 { rc } else { lc }; // consume width, in chars
-      let cb = if !hard && rb > 0 { rb } else { lb }; // consume width, in bytes
+      let tw = wc > 0 && wc > rc; // are we breaking in trailing whitespace? if so it's consumed, and the word before it isn't carried over
+      let cc = if !hard && rc > 0 && !tw { rc } else { lc }; // consume width, in chars
+      let cb = if !hard && rb > 0 && !tw { rb } else { lb }; // consume width, in bytes
+      let cw = if !hard && rb > 0 && !tw { rw } else { lw }; // consume width, in columns
       
       l.push(text::Line{
         num:   ly,
@@ -75,11 +83,13 @@ pub fn layout(text: &str, width: usize) -> Vec<text::Line> {
       
       lc = lc - cc; // remaining in the current line to carry over, in chars
       lb = lb - cb; // remaining in the current line to carry over, in bytes
+      lw -= cw;     // remaining in the current line to carry over, in columns
       
       wc = 0;   // reset whitespace boundary, in chars
       wb = 0;   // reset whitespace boundary, in bytes
       rc = 0;   // reset non-whitespace boundary, in chars
       rb = 0;   // reset non-whitespace boundary, in bytes
+      rw = 0;   // reset non-whitespace boundary, in columns
       
       p = '\0';
     }else{
@@ -102,3 +112,17 @@ pub fn layout(text: &str, width: usize) -> Vec<text::Line> {
   
   l
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn break_in_trailing_whitespace() {
+    let text = "subtotal = 1200 * 12";
+    let lines = layout(text, 18);
+    assert_eq!(vec!["subtotal = 1200 *", "12"], lines.iter().map(|l| l.text(text)).collect::<Vec<_>>());
+    assert_eq!(vec![0..18, 18..20], lines.iter().map(|l| l.coff..l.cext).collect::<Vec<_>>()); // not 16..20, which began "* 12"
+  }
+  
+}
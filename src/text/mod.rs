@@ -1,6 +1,9 @@
 pub mod attrs;
+pub mod color;
 pub mod layout;
 pub mod action;
+pub mod width;
+pub mod sanitize;
 
 use std::fmt;
 use std::ops;
@@ -149,7 +152,13 @@ impl Content {
     }
   }
   
+  /// Content with spans over it, which are normalized once here rather
+  /// than sorted again for every line drawn. Spans which overlap are a
+  /// mistake, in debug builds, since only one of them would be drawn;
+  /// merge them first.
   pub fn new_with_attributed(text: String, spans: Vec<attrs::Span>, width: usize) -> Content {
+    let (spans, overlaps) = attrs::normalize(spans);
+    debug_assert!(overlaps.is_empty(), "spans overlap at {:?}", overlaps);
     let lines = layout::layout(&text, width);
     Content{
       text: text,
@@ -158,6 +167,18 @@ impl Content {
       width: width,
     }
   }
+  
+  /// A line of the content with its attributes written as markup, and how
+  /// wide it is, in columns.
+  pub fn markup_line(&self, i: usize) -> Option<(String, usize)> {
+    let l = self.line_metrics(i)?;
+    let t = l.text(&self.text);
+    let markup = match &self.spans {
+      Some(spans) => attrs::render_markup_with_offset(t, l.boff, spans),
+      None => t.to_string(),
+    };
+    Some((markup, width::str_width(t)))
+  }
 }
 
 impl Storage for Content {
@@ -206,12 +227,13 @@ impl Renderable for Content {
       None => return (0, 0),
     };
     let t = l.text(&self.text);
-    let t = match &attrs {
-      Some(attrs) => attrs::render_with_offset(t, l.boff, attrs),
-      None => t.to_string(),
+    let w = width::str_width(t);
+    let start = b._text().len();
+    match &attrs {
+      Some(attrs) => { let _ = attrs::render_with_offset_to(b, t, l.boff, attrs); }, // which can't fail, into memory
+      None => b.push_str(&sanitize::shown(t)),
     };
-    b.push_str(&t);
-    (l.chars, t.len())
+    (w, b._text().len() - start)
   }
 }
 
@@ -249,6 +271,10 @@ impl Text {
     c
   }
   
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+  
   pub fn len(&self) -> usize {
     match self.lines.len() {
       0 => 0,
@@ -366,7 +392,7 @@ impl Text {
     self.sel.clone()
   }
   
-  pub fn selected_text<'a>(&'a self) -> Option<&'a str> {
+  pub fn selected_offsets(&self) -> Option<ops::Range<usize>> {
     let sel = match &self.sel {
       Some(sel) => sel,
       None => return None,
@@ -377,9 +403,16 @@ impl Text {
     };
     let end = match self.offset_for_index(sel.end) {
       Some(bix) => bix,
-      None => self.len(),
+      None => self.next_offset(),
     };
-    Some(&self.text[start..end])
+    Some(start..end)
+  }
+  
+  pub fn selected_text(&self) -> Option<&str> {
+    match self.selected_offsets() {
+      Some(rng) => Some(&self.text[rng]),
+      None => None,
+    }
   }
   
   fn reflow(&mut self) -> &mut Self {
@@ -442,6 +475,23 @@ impl Text {
     pos
   }
   
+  /// Move the cursor to the start of a paragraph, by its index, or to the
+  /// end of the text if there aren't that many.
+  pub fn paragraph_rel(&mut self, n: usize) -> Pos {
+    let mut para = 0;
+    let mut idx = self.len();
+    for line in &self.lines {
+      if para == n {
+        idx = line.coff;
+        break;
+      }
+      if line.hard {
+        para += 1;
+      }
+    }
+    self.to_abs(idx)
+  }
+  
   fn find_fwd(&self, idx: usize, check: impl Fn(char, char) -> bool) -> Option<Pos> {
     let bix = match self.offset_for_index(idx) {
       Some(bix) => bix,
@@ -641,17 +691,34 @@ impl Text {
     }
   }
   
+  /// Replace a range of bytes in the text, keeping any spans over the rest
+  /// of it where they were, so they needn't be worked out again.
+  fn splice(&mut self, range: ops::Range<usize>, text: &str) {
+    if let Some(spans) = &mut self.spans {
+      *spans = attrs::splice_spans(spans, range.clone(), text.len());
+    }
+    self.text.replace_range(range, text);
+  }
+  
   pub fn set_text(&mut self, text: String) {
     self.text = text;
     self.reflow();
   }
   
+  /// Change the width the text is wrapped to, producing the new position
+  /// of the cursor.
+  pub fn set_width(&mut self, width: usize) -> Pos {
+    self.width = width;
+    self.reflow();
+    self.index(self.loc)
+  }
+  
   pub fn insert(&mut self, idx: usize, c: char) -> Pos {
     let offset = match self.offset_for_index(idx) {
       Some(offset) => offset,
       None => self.next_offset(),
     };
-    self.text.insert(offset, c);
+    self.splice(offset..offset, c.encode_utf8(&mut [0; 4]));
     self.reflow();
     self.index(idx + 1)
   }
@@ -662,6 +729,20 @@ impl Text {
     pos
   }
   
+  /// Insert text at the cursor all at once, as when it is pasted,
+  /// producing the position after it.
+  pub fn insert_str_rel(&mut self, text: &str) -> Pos {
+    let offset = match self.offset_for_index(self.loc) {
+      Some(offset) => offset,
+      None => self.next_offset(),
+    };
+    self.splice(offset..offset, text);
+    self.reflow();
+    let pos = self.index(self.loc + text.chars().count());
+    self.loc = pos.index;
+    pos
+  }
+  
   pub fn delete(&mut self, rng: ops::Range<usize>) -> Option<Pos> {
     let start = match self.offset_for_index(rng.start) {
       Some(start) => start,
@@ -671,7 +752,7 @@ impl Text {
       Some(end) => end,
       None => self.next_offset(),
     };
-    self.text.replace_range(start..end, "");
+    self.splice(start..end, "");
     self.reflow();
     Some(self.index(start))
   }
@@ -730,7 +811,8 @@ impl Text {
       Some(offset) => offset,
       None => return ZERO_POS,
     };
-    self.text.remove(offset);
+    let end = offset + self.text[offset..].chars().next().map_or(0, |c| c.len_utf8());
+    self.splice(offset..end, "");
     self.reflow();
     self.index(eix)
   }
@@ -791,12 +873,13 @@ impl Renderable for Text {
       None => return (0, 0),
     };
     let t = l.text(&self.text);
-    let t = match &attrs {
-      Some(attrs) => attrs::render_with_offset(t, l.boff, attrs),
-      None => t.to_string(),
+    let w = width::str_width(t);
+    let start = b._text().len();
+    match &attrs {
+      Some(attrs) => { let _ = attrs::render_with_offset_to(b, t, l.boff, attrs); }, // which can't fail, into memory
+      None => b.push_str(&sanitize::shown(t)),
     };
-    b.push_str(&t);
-    (l.chars, t.len())
+    (w, b._text().len() - start)
   }
 }
 
@@ -920,6 +1003,18 @@ mod tests {
       ]
     );
     
+    test_reflow_case!(
+      18, "subtotal = 1200 * 12",
+      vec![
+        Line{num: 0, coff: 0, boff: 0, cext: 18, bext: 18, chars: 17, bytes: 17, hard: false},
+        Line{num: 1, coff: 18, boff: 18, cext: 20, bext: 20, chars: 2, bytes: 2, hard: false},
+      ],
+      vec![
+        "subtotal = 1200 *",
+        "12",
+      ]
+    );
+    
     test_reflow_case!(
       8, "Hello\nthere monchambo",
       vec![
@@ -1343,6 +1438,15 @@ mod tests {
     assert_eq!(None, it.next());
   }
   
+  #[test]
+  fn test_movement_paragraph() {
+    let mut x = Text::new_with_str(5, "Très bien,\nc'est époustouflant!\nD'acc");
+    assert_eq!(Pos{index: 11, x: 0, y: 3}, x.paragraph_rel(1));
+    assert_eq!(Pos{index: 32, x: 0, y: 8}, x.paragraph_rel(2));
+    assert_eq!(Pos{index: 0, x: 0, y: 0}, x.paragraph_rel(0));
+    assert_eq!(x.index(37), x.paragraph_rel(9));
+  }
+  
   #[test]
   fn test_select() {
     let t = "Très bien,\nc'est époustouflant!\nD'acc, à bientôt...";
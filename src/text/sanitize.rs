@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+/// Drawn in place of characters which have no picture of their own.
+const SUBSTITUTE: char = '\u{2426}'; // ␦
+
+/// Make text which comes from elsewhere, such as a paste or an imported
+/// document, safe to put in a document. Escape sequences, which would
+/// control the terminal when the text is drawn, are removed. Other control
+/// characters, and the codepoints which reorder bidirectional text and so
+/// could make a line appear to compute something it doesn't, are replaced
+/// with visible stand-ins. Line endings become newlines and tabs spaces,
+/// as they are when typed.
+pub fn clean(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '\x1b' => skip_escape(&mut chars),
+      '\u{9b}' => skip_control(&mut chars), // CSI, in one character
+      '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => skip_string(&mut chars),
+      '\r' => {
+        chars.next_if_eq(&'\n');
+        out.push('\n');
+      },
+      '\t' => out.push(' '),
+      c if is_suspicious(c) => out.push(visible(c)),
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+/// Skip an escape sequence, the escape itself already consumed.
+fn skip_escape<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) {
+  match chars.next() {
+    Some('[') => skip_control(chars),
+    Some(']' | 'P' | 'X' | '^' | '_') => skip_string(chars),
+    Some(c) if (' '..='/').contains(&c) => { // intermediates, then a final character
+      while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+      chars.next();
+    },
+    _ => {}, // a two character sequence
+  }
+}
+
+/// Skip the parameters and final character of a control sequence.
+fn skip_control<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) {
+  while chars.next_if(|c| (' '..='?').contains(c)).is_some() {}
+  chars.next_if(|c| ('@'..='~').contains(c));
+}
+
+/// Skip a control string, such as the title an OSC sequence sets, which
+/// ends with a bell or a string terminator.
+fn skip_string<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) {
+  while let Some(c) = chars.next() {
+    match c {
+      '\x07' | '\u{9c}' => break,
+      '\x1b' => {
+        chars.next_if_eq(&'\\');
+        break;
+      },
+      _ => {},
+    }
+  }
+}
+
+/// Whether a character is one which mustn't reach the terminal as it is:
+/// a control character other than a newline, or one which overrides the
+/// direction text is drawn in.
+pub fn is_suspicious(c: char) -> bool {
+  (c.is_control() && c != '\n') || matches!(c,
+    '\u{61c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+  )
+}
+
+/// The character drawn in place of a suspicious one: the picture of a
+/// control character where Unicode has one, and a substitute otherwise.
+/// Each takes a single column, as the character it stands for is measured.
+pub fn visible(c: char) -> char {
+  match c as u32 {
+    n @ 0..=0x1f => char::from_u32(0x2400 + n).unwrap_or(SUBSTITUTE),
+    0x7f => '\u{2421}', // ␡
+    _ => SUBSTITUTE,
+  }
+}
+
+/// Text as it is drawn, with any suspicious characters in it replaced.
+pub fn shown(text: &str) -> Cow<'_, str> {
+  if text.chars().any(is_suspicious) {
+    Cow::Owned(text.chars().map(|c| if is_suspicious(c) { visible(c) }else{ c }).collect())
+  }else{
+    Cow::Borrowed(text)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn clean_text() {
+    assert_eq!("rent = 1200\n", clean("rent = 1200\n"));
+    assert_eq!("a\nb\nc d", clean("a\r\nb\rc\td"));
+    assert_eq!("total 5", clean("\x1b[31mtotal\x1b[0m \x1b[2J5"));
+    assert_eq!("title", clean("\x1b]0;pwned\x07title\x1b]52;c;Zm9v\x1b\\"));
+    assert_eq!("x", clean("\x1b(Bx\x1bc"));
+    assert_eq!("a\u{2408}b\u{2421}", clean("a\x08b\x7f"));
+    assert_eq!("10 \u{2426}05 + 1\u{2426}", clean("10 \u{202e}05 + 1\u{202c}"));
+    assert_eq!("\u{2426}", clean("\u{85}"));
+    assert_eq!("ok\u{2426}", clean("ok\u{9b}1;2m\u{2067}"));
+
+    assert_eq!(Cow::Borrowed("plain"), shown("plain"));
+    assert_eq!("a\u{241b}[2Jb\u{2426}", shown("a\x1b[2Jb\u{200f}"));
+    assert!(!is_suspicious('\n') && !is_suspicious('€') && is_suspicious('\t'));
+  }
+
+}
@@ -0,0 +1,178 @@
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether characters of ambiguous width are currently treated as wide.
+static WIDE: AtomicBool = AtomicBool::new(false);
+
+/// How characters whose East Asian width is ambiguous, such as `€`, `°`,
+/// Greek letters, and box drawing, are measured. Terminals set up for CJK
+/// text usually draw them two columns wide; others draw them one wide.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Ambiguous {
+  Narrow,
+  Wide,
+}
+
+impl Ambiguous {
+  pub fn from_name(name: &str) -> Option<Ambiguous> {
+    match name.trim().to_lowercase().as_str() {
+      "narrow" => Some(Ambiguous::Narrow),
+      "wide"   => Some(Ambiguous::Wide),
+      _        => None,
+    }
+  }
+}
+
+impl fmt::Display for Ambiguous {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Ambiguous::Narrow => write!(f, "narrow"),
+      Ambiguous::Wide   => write!(f, "wide"),
+    }
+  }
+}
+
+/// Guess how the terminal draws ambiguous characters from the locale it is
+/// set up for: wide for Chinese, Japanese, and Korean, narrow otherwise.
+pub fn detect() -> Ambiguous {
+  let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+    .filter_map(|v| env::var(v).ok())
+    .find(|v| !v.is_empty())
+    .unwrap_or_default();
+  if is_cjk(&locale) {
+    Ambiguous::Wide
+  }else{
+    Ambiguous::Narrow
+  }
+}
+
+fn is_cjk(locale: &str) -> bool {
+  let lang = locale.split(['_', '.', '@']).next().unwrap_or("").to_lowercase();
+  matches!(lang.as_str(), "zh" | "ja" | "ko")
+}
+
+/// Set how ambiguous characters are measured from now on.
+pub fn set_ambiguous(policy: Ambiguous) {
+  WIDE.store(policy == Ambiguous::Wide, Ordering::Relaxed);
+}
+
+/// How ambiguous characters are currently measured.
+pub fn ambiguous() -> Ambiguous {
+  if WIDE.load(Ordering::Relaxed) {
+    Ambiguous::Wide
+  }else{
+    Ambiguous::Narrow
+  }
+}
+
+// Ranges of characters drawn two columns wide, from the East Asian Width
+// property; enough to cover the scripts and symbols likely to be typed.
+const WIDE_RANGES: &[(u32, u32)] = &[
+  (0x1100, 0x115f),   // Hangul Jamo
+  (0x2e80, 0x303e),   // CJK radicals and punctuation
+  (0x3041, 0x33ff),   // Kana, Bopomofo, CJK compatibility
+  (0x3400, 0x4dbf),   // CJK extension A
+  (0x4e00, 0x9fff),   // CJK unified ideographs
+  (0xa000, 0xa4cf),   // Yi
+  (0xac00, 0xd7a3),   // Hangul syllables
+  (0xf900, 0xfaff),   // CJK compatibility ideographs
+  (0xfe30, 0xfe4f),   // CJK compatibility forms
+  (0xff00, 0xff60),   // Fullwidth forms
+  (0xffe0, 0xffe6),   // Fullwidth signs
+  (0x1f300, 0x1f64f), // Pictographs and emoticons
+  (0x1f900, 0x1f9ff), // Supplemental pictographs
+  (0x20000, 0x3fffd), // CJK extensions B and beyond
+];
+
+// Ranges of characters whose width is ambiguous, from the same property.
+const AMBIGUOUS_RANGES: &[(u32, u32)] = &[
+  (0x00a1, 0x00a1), (0x00a4, 0x00a4), (0x00a7, 0x00a8), (0x00aa, 0x00aa),
+  (0x00ad, 0x00ae), (0x00b0, 0x00b4), (0x00b6, 0x00ba), (0x00bc, 0x00bf),
+  (0x00c6, 0x00c6), (0x00d0, 0x00d0), (0x00d7, 0x00d8), (0x00de, 0x00e1),
+  (0x00e6, 0x00e6), (0x00e8, 0x00ea), (0x00ec, 0x00ed), (0x00f0, 0x00f0),
+  (0x00f2, 0x00f3), (0x00f7, 0x00fa), (0x00fc, 0x00fc), (0x00fe, 0x00fe),
+  (0x0391, 0x03a9), (0x03b1, 0x03c9), // Greek
+  (0x0401, 0x0401), (0x0410, 0x044f), (0x0451, 0x0451), // Cyrillic
+  (0x2010, 0x2010), (0x2013, 0x2016), (0x2018, 0x2019), (0x201c, 0x201d),
+  (0x2020, 0x2022), (0x2024, 0x2027), (0x2030, 0x2030), (0x2032, 0x2033),
+  (0x2035, 0x2035), (0x203b, 0x203b), (0x203e, 0x203e), (0x20ac, 0x20ac),
+  (0x2103, 0x2103), (0x2109, 0x2109), (0x2113, 0x2113), (0x2116, 0x2116),
+  (0x2121, 0x2122), (0x2126, 0x2126), (0x212b, 0x212b), (0x2153, 0x2154),
+  (0x215b, 0x215e), (0x2160, 0x216b), (0x2170, 0x2179), (0x2190, 0x2199),
+  (0x21d2, 0x21d2), (0x21d4, 0x21d4), (0x2200, 0x2200), (0x2202, 0x2203),
+  (0x2207, 0x2208), (0x220b, 0x220b), (0x220f, 0x220f), (0x2211, 0x2211),
+  (0x2215, 0x2215), (0x221a, 0x221a), (0x221d, 0x2220), (0x2223, 0x2223),
+  (0x2225, 0x2225), (0x2227, 0x222c), (0x222e, 0x222e), (0x2234, 0x2237),
+  (0x223c, 0x223d), (0x2248, 0x2248), (0x224c, 0x224c), (0x2252, 0x2252),
+  (0x2260, 0x2261), (0x2264, 0x2267), (0x226a, 0x226b), (0x2282, 0x2283),
+  (0x2286, 0x2287), (0x2295, 0x2295), (0x2299, 0x2299), (0x22a5, 0x22a5),
+  (0x22bf, 0x22bf), (0x2312, 0x2312), (0x2460, 0x24e9), (0x24eb, 0x254b),
+  (0x2550, 0x2573), (0x2580, 0x258f), (0x2592, 0x2595), (0x25a0, 0x25a1),
+  (0x25a3, 0x25a9), (0x25b2, 0x25b3), (0x25b6, 0x25b7), (0x25bc, 0x25bd),
+  (0x25c0, 0x25c1), (0x25c6, 0x25c8), (0x25cb, 0x25cb), (0x25ce, 0x25d1),
+  (0x25e2, 0x25e5), (0x25ef, 0x25ef), (0x2605, 0x2606), (0x2609, 0x2609),
+  (0x260e, 0x260f), (0x2640, 0x2640), (0x2642, 0x2642), (0x2660, 0x2661),
+  (0x2663, 0x2665), (0x2667, 0x266a), (0x266c, 0x266d), (0x266f, 0x266f),
+  (0xe000, 0xf8ff), (0xfffd, 0xfffd),
+];
+
+// Ranges of characters which take no columns of their own.
+const ZERO_RANGES: &[(u32, u32)] = &[
+  (0x0300, 0x036f), // combining diacritical marks
+  (0x200b, 0x200d), // zero-width spaces and joiners
+  (0xfe00, 0xfe0f), // variation selectors
+];
+
+fn within(ranges: &[(u32, u32)], c: char) -> bool {
+  let c = c as u32;
+  ranges.binary_search_by(|(lo, hi)| {
+    if *hi < c {
+      std::cmp::Ordering::Less
+    }else if *lo > c {
+      std::cmp::Ordering::Greater
+    }else{
+      std::cmp::Ordering::Equal
+    }
+  }).is_ok()
+}
+
+/// The number of columns a character takes in the terminal.
+pub fn char_width(c: char) -> usize {
+  measure(c, ambiguous())
+}
+
+/// The number of columns text takes in the terminal.
+pub fn str_width(text: &str) -> usize {
+  text.chars().map(char_width).sum()
+}
+
+fn measure(c: char, policy: Ambiguous) -> usize {
+  if (c as u32) < 0xa1 {
+    1 // the common case
+  }else if within(ZERO_RANGES, c) {
+    0
+  }else if within(WIDE_RANGES, c) || (policy == Ambiguous::Wide && within(AMBIGUOUS_RANGES, c)) {
+    2
+  }else{
+    1
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn measure_width() {
+    assert_eq!(5, str_width("hello"));
+    assert_eq!(4, str_width("日本"));
+    assert_eq!(1, str_width("e\u{301}"));
+    let width = |text: &str, policy| text.chars().map(|c| measure(c, policy)).sum::<usize>();
+    assert_eq!(6, width("€ 5°", Ambiguous::Wide));
+    assert_eq!(4, width("€ 5°", Ambiguous::Narrow));
+    assert!(is_cjk("ja_JP.UTF-8"));
+    assert!(!is_cjk("en_US.UTF-8"));
+  }
+
+}
@@ -0,0 +1,460 @@
+use std::fs;
+use std::io;
+use std::env;
+use std::path;
+use std::time;
+
+use crossterm::style::Color;
+
+use crate::config;
+use crate::text::color;
+use crate::text::attrs::Attributes;
+
+pub const DARK: &str = "dark";
+pub const LIGHT: &str = "light";
+pub const MONO: &str = "mono";
+pub const AUTO: &str = "auto"; // choose a built-in theme to suit the terminal
+
+const DIR: &str = "themes";
+const EXTENSION: &str = "toml";
+
+const QUERY: &str = "\x1b]10;?\x07\x1b]11;?\x07"; // ask for the foreground and background colors
+const QUERY_TIMEOUT: time::Duration = time::Duration::from_millis(100);
+
+/// The styles in which the interface is drawn, by what they are used for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+  pub name: String,
+  pub results: Vec<Attributes>, // expressions and their results are highlighted in these styles, in turn
+  pub gutter: Attributes,       // line numbers
+  pub current: Attributes,      // the line the cursor is on
+  pub selection: Attributes,    // selected text
+  pub title: Attributes,        // the title of an overlay, such as the picker
+  pub alert: Attributes,        // the title of a notice
+  pub panel: Attributes,        // the body of an overlay
+  pub highlight: Attributes,    // the selected entry in an overlay
+  pub flash: Attributes,        // a watched line whose value just changed
+  pub sections: Vec<(String, Attributes)>, // laid over sections of documents tagged with these names
+}
+
+/// A style as what is drawn in it asks for it: attributes of its own, or
+/// the name of one of the theme's styles, such as `gutter` or the section
+/// style `muted`, which is looked up as it's drawn so that it follows the
+/// theme, however it has been configured.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Style {
+  Attributes(Attributes),
+  Named(String),
+}
+
+impl From<Attributes> for Style {
+  fn from(attrs: Attributes) -> Style {
+    Style::Attributes(attrs)
+  }
+}
+
+/// Names of the section styles every built-in theme has.
+pub const SECTIONS: [&str; 4] = ["warning", "note", "success", "muted"];
+
+/// Section styles, in the order of `SECTIONS`.
+fn sections(styles: [Attributes; 4]) -> Vec<(String, Attributes)> {
+  SECTIONS.iter().map(|s| s.to_string()).zip(styles).collect()
+}
+
+const fn style(bold: bool, invert: bool, color: Option<Color>, background: Option<Color>) -> Attributes {
+  Attributes{bold, underline: false, italic: false, strikethrough: false, dim: false, invert, color, background, link: None}
+}
+
+/// A style without color.
+const fn plain(bold: bool, underline: bool, invert: bool) -> Attributes {
+  Attributes{bold, underline, italic: false, strikethrough: false, dim: false, invert, color: None, background: None, link: None}
+}
+
+impl Theme {
+  /// The default theme, for terminals with a dark background.
+  pub fn dark() -> Theme {
+    Theme{
+      name: DARK.to_string(),
+      results: vec![
+        style(true, false, Some(Color::Yellow), None),
+        style(true, false, Some(Color::Magenta), None),
+        style(true, false, Some(Color::Cyan), None),
+        style(true, false, Some(Color::Green), None),
+        style(true, false, Some(Color::Blue), None),
+      ],
+      gutter: style(true, false, None, None),
+      current: style(false, false, None, Some(Color::Rgb{r: 10, g: 10, b: 10})),
+      selection: style(false, true, None, None),
+      title: style(true, false, None, Some(Color::DarkGrey)),
+      alert: style(true, false, None, Some(Color::DarkRed)),
+      panel: style(false, false, None, Some(Color::DarkGrey)),
+      highlight: style(true, true, None, None),
+      flash: style(true, false, Some(Color::Black), Some(Color::Yellow)),
+      sections: sections([
+        style(false, false, None, Some(Color::Rgb{r: 60, g: 15, b: 15})),
+        style(false, false, None, Some(Color::Rgb{r: 15, g: 30, b: 60})),
+        style(false, false, None, Some(Color::Rgb{r: 15, g: 45, b: 15})),
+        Attributes{dim: true, ..style(false, false, None, None)},
+      ]),
+    }
+  }
+
+  /// A theme for terminals with a light background.
+  pub fn light() -> Theme {
+    Theme{
+      name: LIGHT.to_string(),
+      results: vec![
+        style(true, false, Some(Color::DarkRed), None),
+        style(true, false, Some(Color::DarkMagenta), None),
+        style(true, false, Some(Color::DarkBlue), None),
+        style(true, false, Some(Color::DarkGreen), None),
+        style(true, false, Some(Color::DarkCyan), None),
+      ],
+      gutter: style(false, false, Some(Color::DarkGrey), None),
+      current: style(false, false, None, Some(Color::Rgb{r: 240, g: 240, b: 240})),
+      selection: style(false, true, None, None),
+      title: style(true, false, Some(Color::Black), Some(Color::Grey)),
+      alert: style(true, false, Some(Color::White), Some(Color::DarkRed)),
+      panel: style(false, false, Some(Color::Black), Some(Color::Grey)),
+      highlight: style(true, true, None, None),
+      flash: style(true, false, Some(Color::Black), Some(Color::Rgb{r: 255, g: 235, b: 130})),
+      sections: sections([
+        style(false, false, None, Some(Color::Rgb{r: 255, g: 225, b: 225})),
+        style(false, false, None, Some(Color::Rgb{r: 225, g: 235, b: 255})),
+        style(false, false, None, Some(Color::Rgb{r: 225, g: 250, b: 225})),
+        Attributes{dim: true, ..style(false, false, None, None)},
+      ]),
+    }
+  }
+
+  /// A theme without color, for terminals which can't show it or for
+  /// anyone who would rather not. Adjacent results alternate between bold
+  /// and underlined, and overlays are drawn in reverse video.
+  pub fn mono() -> Theme {
+    Theme{
+      name: MONO.to_string(),
+      results: vec![
+        plain(true, false, false),
+        plain(false, true, false),
+      ],
+      gutter: plain(false, false, false),
+      current: plain(false, false, false), // the cursor is enough
+      selection: plain(false, false, true),
+      title: plain(true, false, true),
+      alert: plain(true, true, true),
+      panel: plain(false, false, true),
+      highlight: plain(true, true, false),
+      flash: plain(true, false, true),
+      sections: sections([
+        plain(false, false, true),
+        plain(false, true, false),
+        plain(true, false, false),
+        Attributes{dim: true, ..plain(false, false, false)},
+      ]),
+    }
+  }
+
+  /// Produce a built-in theme by name.
+  pub fn builtin(name: &str) -> Option<Theme> {
+    match name {
+      DARK  => Some(Theme::dark()),
+      LIGHT => Some(Theme::light()),
+      MONO  => Some(Theme::mono()),
+      _     => None,
+    }
+  }
+
+  /// The style laid over sections tagged with a name, if there is one.
+  pub fn section(&self, name: &str) -> Option<&Attributes> {
+    self.sections.iter().find(|(n, _)| n == name).map(|(_, s)| s)
+  }
+
+  /// The style with a name, as a theme file sets it, or that of the
+  /// section with the name, if there is one.
+  pub fn named(&self, name: &str) -> Option<&Attributes> {
+    match name {
+      "gutter"    => Some(&self.gutter),
+      "current"   => Some(&self.current),
+      "selection" => Some(&self.selection),
+      "title"     => Some(&self.title),
+      "alert"     => Some(&self.alert),
+      "panel"     => Some(&self.panel),
+      "highlight" => Some(&self.highlight),
+      "flash"     => Some(&self.flash),
+      name        => self.section(name),
+    }
+  }
+
+  /// The attributes to draw in a style. A name the theme has no style for
+  /// is drawn plainly.
+  pub fn resolve(&self, style: &Style) -> Attributes {
+    match style {
+      Style::Attributes(attrs) => attrs.clone(),
+      Style::Named(name) => self.named(name).cloned().unwrap_or(plain(false, false, false)),
+    }
+  }
+}
+
+/// Determine which built-in theme suits the terminal: the monochrome theme
+/// if it can only show two colors or `NO_COLOR` is set, and otherwise the light or dark theme
+/// from the colors it reports when asked or, failing that, `COLORFGBG`.
+/// This must be called in raw mode, before anything else reads input.
+/// Produces none if it can't be determined, in which case the dark theme
+/// is a reasonable guess.
+pub fn detect() -> Option<&'static str> {
+  if env::var("TERM").is_ok_and(|t| is_monochrome(&t)) || env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()) {
+    return Some(MONO);
+  }
+  let light = match query_colors().and_then(|reply| is_light(&reply)) {
+    Some(light) => Some(light),
+    None => env::var("COLORFGBG").ok().and_then(|v| colorfgbg_is_light(&v)),
+  };
+  light.map(|light| if light { LIGHT } else { DARK })
+}
+
+/// Ask the terminal for its colors and collect whatever it replies with
+/// until it stops. Terminals which don't understand the query ignore it.
+#[cfg(unix)]
+fn query_colors() -> Option<String> {
+  use std::io::{Read, Write};
+  let mut out = io::stdout();
+  out.write_all(QUERY.as_bytes()).ok()?;
+  out.flush().ok()?;
+  let mut reply = Vec::new();
+  let mut buf = [0u8; 64];
+  let mut fds = libc::pollfd{fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0};
+  while unsafe { libc::poll(&mut fds, 1, QUERY_TIMEOUT.as_millis() as libc::c_int) } > 0 {
+    let n = io::stdin().lock().read(&mut buf).ok()?;
+    if n == 0 {
+      break;
+    }
+    reply.extend_from_slice(&buf[..n]);
+    let ends = reply.iter().filter(|b| **b == 0x07).count() + reply.windows(2).filter(|w| *w == b"\x1b\\").count();
+    if ends >= 2 {
+      break; // both answers are in
+    }
+  }
+  String::from_utf8(reply).ok()
+}
+
+#[cfg(not(unix))]
+fn query_colors() -> Option<String> {
+  None
+}
+
+/// Determine whether a terminal's reply to the color query describes a
+/// light background: one brighter than the foreground, or bright in itself
+/// when only one color is reported.
+fn is_light(reply: &str) -> Option<bool> {
+  let mut fg = None;
+  let mut bg = None;
+  for part in reply.split('\x1b').filter_map(|p| p.strip_prefix(']')) {
+    let (code, spec) = part.split_once(';')?;
+    let lum = luminance(spec.trim_end_matches(['\x07', '\\']));
+    match code {
+      "10" => fg = lum,
+      "11" => bg = lum,
+      _    => {},
+    };
+  }
+  match (fg, bg) {
+    (Some(fg), Some(bg)) => Some(bg > fg),
+    (None, Some(bg))     => Some(bg > 0.5),
+    (Some(fg), None)     => Some(fg < 0.5),
+    (None, None)         => None,
+  }
+}
+
+/// The relative luminance of a color reported as `rgb:RRRR/GGGG/BBBB`,
+/// where each component has from one to four hex digits.
+fn luminance(spec: &str) -> Option<f64> {
+  let mut rgb = [0.0; 3];
+  let comps: Vec<&str> = spec.strip_prefix("rgb:")?.split('/').collect();
+  if comps.len() != 3 {
+    return None;
+  }
+  for (i, c) in comps.iter().enumerate() {
+    if c.is_empty() || c.len() > 4 {
+      return None;
+    }
+    let max = (1u32 << (4 * c.len())) - 1;
+    rgb[i] = u32::from_str_radix(c, 16).ok()? as f64 / max as f64;
+  }
+  Some(0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2])
+}
+
+/// Interpret `COLORFGBG`, which is `fg;bg` or `fg;default;bg` in ANSI color
+/// numbers, where the background is light if it is white or light grey.
+fn colorfgbg_is_light(val: &str) -> Option<bool> {
+  let bg = val.rsplit(';').next()?.parse::<u8>().ok()?;
+  Some(bg == 7 || bg == 15)
+}
+
+/// Determine whether a terminal type describes a terminal that can only
+/// show two colors: old hardware terminals and the monochrome variants of
+/// others, which terminfo names with a `-mono` or `-m` suffix.
+fn is_monochrome(term: &str) -> bool {
+  matches!(term, "dumb" | "vt52" | "vt100" | "vt102" | "vt220") || term.ends_with("-mono") || term.ends_with("-m")
+}
+
+fn dir() -> Option<path::PathBuf> {
+  config::dir().map(|d| d.join(DIR))
+}
+
+/// The names of every theme that can be selected: the built-in themes and
+/// those defined by theme files in the configuration directory.
+pub fn names() -> Vec<String> {
+  let mut names = vec![DARK.to_string(), LIGHT.to_string(), MONO.to_string()];
+  if let Some(entries) = dir().and_then(|d| fs::read_dir(d).ok()) {
+    let mut user: Vec<String> = entries
+      .filter_map(|e| e.ok().map(|e| e.path()))
+      .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(EXTENSION))
+      .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()))
+      .filter(|n| !names.contains(n))
+      .collect();
+    user.sort();
+    names.append(&mut user);
+  }
+  names
+}
+
+/// Load a theme by name. User themes are loaded from the configuration
+/// directory as `themes/NAME.toml`, and take precedence over built-in
+/// themes with the same name.
+pub fn load(name: &str) -> Result<Theme, String> {
+  let file = dir().map(|d| d.join(format!("{}.{}", name, EXTENSION)));
+  match file.as_deref().map(fs::read_to_string) {
+    Some(Ok(text)) => parse(name, &text).map_err(|err| format!("{}.{}: {}", name, EXTENSION, err)),
+    Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => Err(format!("{}.{}: {}", name, EXTENSION, err)),
+    _ => Theme::builtin(name).ok_or_else(|| format!("No such theme: {}", name)),
+  }
+}
+
+/// Parse a theme file. A theme is based on a built-in theme, `dark` unless
+/// it says otherwise with `base`, and overrides any of its styles. A style
+/// is a table with any of `color`, `background`, `bold`, `underline`,
+/// `italic`, `strikethrough`, `dim`, and `invert`, or simply a color. Styles laid over tagged sections
+/// of documents are set in a `sections` table, by the name of the tag. Colors are names, such as
+/// `dark_red`, hex values, such as `#ff8800`, or ANSI color numbers.
+pub fn parse(name: &str, text: &str) -> Result<Theme, String> {
+  let table = match text.parse::<toml::Value>() {
+    Ok(toml::Value::Table(table)) => table,
+    Ok(_) => return Err("Expected a table".to_string()),
+    Err(err) => return Err(err.to_string()),
+  };
+  let base = match table.get("base") {
+    Some(base) => base.as_str().and_then(Theme::builtin).ok_or_else(|| format!("base: expected {}, {}, or {}, got {}", DARK, LIGHT, MONO, base))?,
+    None => Theme::dark(),
+  };
+  let mut theme = Theme{name: name.to_string(), ..base};
+  for (key, val) in &table {
+    let slot = match key.as_str() {
+      "base"      => continue,
+      "results"   => {
+        let vals = val.as_array().filter(|a| !a.is_empty()).ok_or_else(|| format!("results: expected a list of styles, got {}", val))?;
+        theme.results = vals.iter().map(parse_style).collect::<Result<_, _>>().map_err(|e| format!("results: {}", e))?;
+        continue;
+      },
+      "sections"  => {
+        let table = val.as_table().ok_or_else(|| format!("sections: expected a table of section styles, got {}", val))?;
+        for (name, val) in table {
+          let style = parse_style(val).map_err(|e| format!("sections.{}: {}", name, e))?;
+          match theme.sections.iter_mut().find(|(n, _)| n == name) {
+            Some(slot) => slot.1 = style,
+            None => theme.sections.push((name.to_lowercase(), style)),
+          };
+        }
+        continue;
+      },
+      "gutter"    => &mut theme.gutter,
+      "current"   => &mut theme.current,
+      "selection" => &mut theme.selection,
+      "title"     => &mut theme.title,
+      "alert"     => &mut theme.alert,
+      "panel"     => &mut theme.panel,
+      "highlight" => &mut theme.highlight,
+      "flash"     => &mut theme.flash,
+      _           => return Err(format!("{}: unknown style", key)),
+    };
+    *slot = parse_style(val).map_err(|e| format!("{}: {}", key, e))?;
+  }
+  Ok(theme)
+}
+
+fn parse_style(val: &toml::Value) -> Result<Attributes, String> {
+  let mut attrs = style(false, false, None, None);
+  match val {
+    toml::Value::String(color) => attrs.color = Some(color::parse(color)?),
+    toml::Value::Table(table) => for (key, val) in table {
+      match (key.as_str(), val) {
+        ("color", toml::Value::String(c))      => attrs.color = Some(color::parse(c)?),
+        ("background", toml::Value::String(c)) => attrs.background = Some(color::parse(c)?),
+        ("bold", toml::Value::Boolean(b))      => attrs.bold = *b,
+        ("underline", toml::Value::Boolean(b)) => attrs.underline = *b,
+        ("italic", toml::Value::Boolean(b))    => attrs.italic = *b,
+        ("strikethrough", toml::Value::Boolean(b)) => attrs.strikethrough = *b,
+        ("dim", toml::Value::Boolean(b))       => attrs.dim = *b,
+        ("invert", toml::Value::Boolean(b))    => attrs.invert = *b,
+        _ => return Err(format!("invalid attribute: {} = {}", key, val)),
+      };
+    },
+    _ => return Err(format!("expected a style, got {}", val)),
+  };
+  Ok(attrs)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_theme() {
+    let theme = parse("sunrise", "base = \"light\"\nresults = [\"#ff8800\", { color = \"dark-blue\", bold = true }]\ngutter = \"244\"\n[sections]\nwarning = \"red\"\nrisk = { background = \"dark-red\" }\n").unwrap();
+    assert_eq!("sunrise", &theme.name);
+    assert_eq!(vec![style(false, false, Some(Color::Rgb{r: 255, g: 136, b: 0}), None), style(true, false, Some(Color::DarkBlue), None)], theme.results);
+    assert_eq!(style(false, false, Some(Color::AnsiValue(244)), None), theme.gutter);
+    assert_eq!(Theme::light().panel, theme.panel);
+    assert_eq!(Some(&style(false, false, Some(Color::Red), None)), theme.section("warning"));
+    assert_eq!(Some(&style(false, false, None, Some(Color::DarkRed))), theme.section("risk"));
+    assert_eq!(Theme::light().section("note"), theme.section("note"));
+
+    assert_eq!(Err("gutter: invalid color: mauve".to_string()), parse("x", "gutter = \"mauve\""));
+    assert_eq!(Err("chrome: unknown style".to_string()), parse("x", "chrome = \"red\""));
+    assert!(parse("x", "base = \"solarized\"").is_err());
+
+    let theme = parse("stark", "base = \"mono\"\ngutter = { underline = true }\n").unwrap();
+    assert_eq!(plain(false, true, false), theme.gutter);
+    assert_eq!(Theme::mono().results, theme.results);
+    let theme = parse("slanted", "title = { italic = true, strikethrough = true }\n").unwrap();
+    assert!(theme.title.italic && theme.title.strikethrough && !theme.title.underline);
+  }
+
+  #[test]
+  fn resolve_styles() {
+    let theme = parse("sunrise", "gutter = \"244\"\n[sections]\nmuted = { italic = true }\n").unwrap();
+    assert_eq!(style(false, false, Some(Color::AnsiValue(244)), None), theme.resolve(&Style::Named("gutter".to_string())));
+    assert_eq!(Theme::dark().flash, theme.resolve(&Style::Named("flash".to_string())));
+    let muted = Style::Named("muted".to_string());
+    assert_eq!(Attributes{italic: true, ..plain(false, false, false)}, theme.resolve(&muted));
+    assert_eq!(Theme::dark().section("muted").cloned(), Some(Theme::dark().resolve(&muted))); // follows the theme it's drawn in
+    assert_eq!(Theme::dark().section("note").cloned(), Some(theme.resolve(&Style::Named("note".to_string()))));
+    assert_eq!(plain(false, false, false), theme.resolve(&Style::Named("number".to_string()))); // no such style
+    assert_eq!(plain(true, true, false), theme.resolve(&plain(true, true, false).into()));
+  }
+
+  #[test]
+  fn detect_light_background() {
+    assert_eq!(Some(true), is_light("\x1b]10;rgb:0000/0000/0000\x07\x1b]11;rgb:ffff/ffff/ffff\x07"));
+    assert_eq!(Some(false), is_light("\x1b]10;rgb:ffff/ffff/ffff\x1b\\\x1b]11;rgb:1e1e/1e1e/1e1e\x1b\\"));
+    assert_eq!(Some(true), is_light("\x1b]11;rgb:ee/ee/ee\x07"));
+    assert_eq!(None, is_light(""));
+    assert_eq!(None, is_light("\x1b]11;cmy:1/2/3\x07"));
+    assert_eq!(Some(true), colorfgbg_is_light("0;15"));
+    assert_eq!(Some(false), colorfgbg_is_light("15;default;0"));
+    assert_eq!(None, colorfgbg_is_light("15;default"));
+    assert!(is_monochrome("vt100"));
+    assert!(is_monochrome("xterm-mono"));
+    assert!(!is_monochrome("xterm-256color"));
+  }
+
+}
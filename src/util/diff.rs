@@ -0,0 +1,133 @@
+const OURS: &str = "<<<<<<< ours";
+const SEPARATOR: &str = "=======";
+const THEIRS: &str = ">>>>>>> theirs";
+
+/// Align the lines of two texts, in order, as a diff does: each line which
+/// is unchanged is paired with its counterpart, and each which was removed
+/// or added stands alone. Removed lines come before those added in their
+/// place.
+pub fn align(a: &[&str], b: &[&str]) -> Vec<(Option<usize>, Option<usize>)> {
+  let lcs = table(a, b);
+  let mut out = Vec::new();
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() || j < b.len() {
+    if i < a.len() && j < b.len() && a[i] == b[j] {
+      out.push((Some(i), Some(j)));
+      i += 1;
+      j += 1;
+    }else if i < a.len() && (j == b.len() || lcs[i+1][j] >= lcs[i][j+1]) {
+      out.push((Some(i), None));
+      i += 1;
+    }else{
+      out.push((None, Some(j)));
+      j += 1;
+    }
+  }
+  out
+}
+
+/// Merge two texts which were both derived from a common base, line by
+/// line. Changes made on only one side are taken as they are; where both
+/// sides changed the same lines differently, both versions are kept between
+/// conflict markers. Returns the merged text and whether there were any
+/// conflicts.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> (String, bool) {
+  let b: Vec<&str> = base.lines().collect();
+  let o: Vec<&str> = ours.lines().collect();
+  let t: Vec<&str> = theirs.lines().collect();
+  let mo = matches(&b, &o);
+  let mt = matches(&b, &t);
+  
+  let mut out: Vec<&str> = Vec::new();
+  let mut conflict = false;
+  let (mut ib, mut io, mut it) = (0, 0, 0);
+  loop {
+    // the next base line that is unchanged on both sides anchors a chunk
+    let next = (ib..b.len()).find_map(|k| match (mo[k], mt[k]) {
+      (Some(ko), Some(kt)) => Some((k, ko, kt)),
+      _ => None,
+    });
+    let (kb, ko, kt) = next.unwrap_or((b.len(), o.len(), t.len()));
+    let (cb, co, ct) = (&b[ib..kb], &o[io..ko], &t[it..kt]);
+    if co == cb || co == ct {
+      out.extend(ct);
+    }else if ct == cb {
+      out.extend(co);
+    }else{
+      conflict = true;
+      out.push(OURS);
+      out.extend(co);
+      out.push(SEPARATOR);
+      out.extend(ct);
+      out.push(THEIRS);
+    }
+    if next.is_none() {
+      break;
+    }
+    out.push(b[kb]);
+    (ib, io, it) = (kb + 1, ko + 1, kt + 1);
+  }
+  
+  let mut text = out.join("\n");
+  if !text.is_empty() && (theirs.ends_with('\n') || ours.ends_with('\n')) {
+    text.push('\n');
+  }
+  (text, conflict)
+}
+
+/// Compute the table of longest common subsequences: lcs[i][j] is the
+/// length of the longest common subsequence of a[i..] and b[j..].
+fn table(a: &[&str], b: &[&str]) -> Vec<Vec<usize>> {
+  let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      lcs[i][j] = if a[i] == b[j] { lcs[i+1][j+1] + 1 } else { lcs[i+1][j].max(lcs[i][j+1]) };
+    }
+  }
+  lcs
+}
+
+/// Match every line of a to its counterpart in b, if it has one, following
+/// the longest common subsequence.
+fn matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+  let lcs = table(a, b);
+  let mut res = vec![None; a.len()];
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() && j < b.len() {
+    if a[i] == b[j] {
+      res[i] = Some(j);
+      i += 1;
+      j += 1;
+    }else if lcs[i+1][j] >= lcs[i][j+1] {
+      i += 1;
+    }else{
+      j += 1;
+    }
+  }
+  res
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  #[test]
+  fn diff_lines() {
+    assert!(align(&[], &[]).is_empty());
+    assert_eq!(vec![(Some(0), Some(0)), (Some(1), None), (None, Some(1)), (Some(2), Some(2)), (None, Some(3))], align(&["a", "b", "c"], &["a", "B", "c", "d"]));
+    assert_eq!(vec![(Some(0), None), (Some(1), None)], align(&["a", "b"], &[]));
+  }
+  
+  #[test]
+  fn merge_lines() {
+    let base = "a\nb\nc\nd\n";
+    assert_eq!(("a\nB\nc\nD\n".to_string(), false), merge(base, "a\nB\nc\nd\n", "a\nb\nc\nD\n"));
+    assert_eq!(("a\nB\nc\nd\n".to_string(), false), merge(base, "a\nB\nc\nd\n", "a\nB\nc\nd\n"));
+    assert_eq!(("a\nc\nd\ne\n".to_string(), false), merge(base, "a\nc\nd\n", "a\nb\nc\nd\ne\n"));
+    assert_eq!(
+      ("a\n<<<<<<< ours\nX\n=======\nY\n>>>>>>> theirs\nc\nd\n".to_string(), true),
+      merge(base, "a\nX\nc\nd\n", "a\nY\nc\nd\n")
+    );
+  }
+  
+}
@@ -1,3 +1,5 @@
+pub mod diff;
+pub mod suggest;
 
 pub fn coalesce<T>(a: Option<T>, b: Option<T>) -> Option<T> {
   if let Some(a) = a {
@@ -0,0 +1,48 @@
+use std::cmp::{max, min};
+
+/// Find the candidate a mistyped word was most likely meant to be, if any
+/// is close enough to it to be worth suggesting. Case, and hyphens in
+/// place of underscores, are disregarded.
+pub fn closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+  let norm = |s: &str| s.to_lowercase().replace('-', "_");
+  let word = norm(word);
+  let limit = max(1, word.chars().count() / 3);
+  candidates.iter()
+    .map(|c| (distance(&word, &norm(c)), *c))
+    .filter(|(d, _)| *d <= limit)
+    .min_by_key(|(d, _)| *d)
+    .map(|(_, c)| c)
+}
+
+/// The number of single-character insertions, deletions, and substitutions
+/// needed to turn one string into another.
+fn distance(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let mut prev: Vec<usize> = (0..=b.len()).collect();
+  for (i, ca) in a.iter().enumerate() {
+    let mut row = vec![i + 1];
+    for (j, cb) in b.iter().enumerate() {
+      let sub = prev[j] + if ca == cb { 0 }else{ 1 };
+      row.push(min(sub, min(prev[j + 1], row[j]) + 1));
+    }
+    prev = row;
+  }
+  prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn suggest_words() {
+    assert_eq!(3, distance("kitten", "sitting"));
+    let keys = ["theme", "placement", "precision", "ambiguous_width"];
+    assert_eq!(Some("placement"), closest("placment", &keys));
+    assert_eq!(Some("ambiguous_width"), closest("Ambiguous-Width", &keys));
+    assert_eq!(Some("theme"), closest("them", &keys));
+    assert_eq!(None, closest("colour", &keys));
+  }
+
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::io;
+use std::path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::document;
+use crate::rdl;
+use crate::rdl::unit;
+use crate::rdl::exec;
+use crate::rdl::scan::{Scanner, TType};
+use crate::rdl::import::Imports;
+
+pub const EXTENSION: &str = "res";
+
+/// A directory of documents which can reference each other's variables
+/// as `@document.name`, where the document is named by its file stem.
+///
+/// Documents are evaluated in dependency order, so a document sees the
+/// variables of every document it references, directly or not. References
+/// which cannot be resolved, because the document or variable does not
+/// exist or because documents reference each other in a cycle, are stale.
+///
+/// Evaluated documents are cached until the workspace is reloaded.
+pub struct Workspace {
+  dir: path::PathBuf,
+  docs: BTreeMap<String, path::PathBuf>,
+  cache: HashMap<String, Vec<(String, unit::Value)>>,
+}
+
+impl Workspace {
+  /// Open the directory as a workspace. Every file in it with the document
+  /// extension is a member.
+  pub fn open(dir: &path::Path) -> io::Result<Workspace> {
+    let mut ws = Workspace{
+      dir: dir.to_owned(),
+      docs: BTreeMap::new(),
+      cache: HashMap::new(),
+    };
+    ws.scan()?;
+    Ok(ws)
+  }
+
+  /// Produce the paths of every document in the workspace, ordered by name.
+  pub fn docs(&self) -> Vec<path::PathBuf> {
+    self.docs.values().cloned().collect()
+  }
+
+  /// Determine the name by which a document in the workspace is referenced.
+  pub fn name(&self, doc: &path::Path) -> Option<&str> {
+    self.docs.iter().find(|(_, p)| *p == doc).map(|(n, _)| n.as_str())
+  }
+
+  /// Discard evaluated documents and look for documents that have been
+  /// added or removed since the workspace was opened.
+  pub fn reload(&mut self) -> io::Result<()> {
+    self.cache.clear();
+    self.scan()
+  }
+
+  fn scan(&mut self) -> io::Result<()> {
+    self.docs.clear();
+    for entry in fs::read_dir(&self.dir)? {
+      let path = entry?.path();
+      if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+        continue;
+      }
+      if let Some(name) = path.file_stem().and_then(|e| e.to_str()) {
+        self.docs.insert(name.to_string(), path.clone());
+      }
+    }
+    Ok(())
+  }
+
+  /// Produce a context in which to evaluate the named document, with the
+  /// variables of every document it depends on bound as references. The
+  /// document itself is never evaluated, since it is presumably being edited.
+  pub fn context(&mut self, name: Option<&str>, text: &str) -> exec::Context {
+    let mut cxt = exec::Context::new_with_stdlib();
+    let mut visiting: Vec<String> = name.map(|n| vec![n.to_string()]).unwrap_or_default();
+    for dep in references(text) {
+      self.bind(&mut cxt, &dep, &mut visiting);
+    }
+    cxt
+  }
+
+  /// Bind the variables of a document, evaluating it and its dependencies
+  /// if necessary. Documents already being evaluated form a cycle and are
+  /// not bound, so references to them are stale.
+  fn bind(&mut self, cxt: &mut exec::Context, name: &str, visiting: &mut Vec<String>) {
+    if visiting.iter().any(|n| n == name) {
+      return;
+    }
+    if !self.cache.contains_key(name) {
+      let text = match self.docs.get(name).map(|p| document::load(p)) {
+        Some(Ok(text)) => text,
+        _ => return, // missing or unreadable; references to it are stale
+      };
+      visiting.push(name.to_string());
+      let mut sub = exec::Context::new_with_stdlib();
+      for dep in references(&text) {
+        self.bind(&mut sub, &dep, visiting);
+      }
+      visiting.pop();
+      let mut imports = Imports::new(&self.dir);
+      for line in text.lines() {
+        rdl::import(&mut imports, &mut sub, line); // what it can't import is shown when it's opened itself
+        rdl::eval(&mut sub, line);
+      }
+      let vars = sub.vars().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+      self.cache.insert(name.to_string(), vars);
+    }
+    for (k, v) in &self.cache[name] {
+      cxt.bind_reference(name, k, *v);
+    }
+  }
+}
+
+/// Produce the names of every document referenced by the text.
+pub fn references(text: &str) -> BTreeSet<String> {
+  let mut refs = BTreeSet::new();
+  for line in text.lines() {
+    let mut scan = Scanner::new(line);
+    while let Ok(tok) = scan.token() {
+      if tok.ttype == TType::End {
+        break;
+      }
+      if tok.ttype == TType::Reference {
+        if let Some((doc, _)) = tok.ttext.split_once('.') {
+          refs.insert(doc.to_string());
+        }
+      }
+    }
+  }
+  refs
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn resolve_references() {
+    let dir = std::env::temp_dir().join(format!("resolver-workspace-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("rates.res"), "rate = 0.5\n").unwrap();
+    fs::write(dir.join("savings.res"), "total = 1000 * @rates.rate\n").unwrap();
+    fs::write(dir.join("a.res"), "x = @b.x\n").unwrap();
+    fs::write(dir.join("b.res"), "x = @a.x\n").unwrap();
+    fs::write(dir.join("notes.txt"), "not a document\n").unwrap();
+
+    let mut ws = Workspace::open(&dir).unwrap();
+    assert_eq!(vec!["a", "b", "rates", "savings"], ws.docs.keys().collect::<Vec<_>>());
+    assert_eq!(Some("savings"), ws.name(&dir.join("savings.res")));
+
+    let text = "@savings.total / 2, @savings.missing, @gone.total";
+    let mut cxt = ws.context(Some("budget"), text);
+    let res: Vec<_> = rdl::eval(&mut cxt, text).into_iter().map(|e| e.result).collect();
+    assert_eq!(Ok(unit::Value::raw(250.0)), res[0]);
+    assert_eq!(Err(rdl::error::Error::StaleReference("savings.missing".to_string())), res[1]);
+    assert_eq!(Err(rdl::error::Error::StaleReference("gone.total".to_string())), res[2]);
+
+    let mut cxt = ws.context(Some("a"), "y = @b.x");
+    assert!(rdl::eval(&mut cxt, "y = @b.x")[0].result.is_err()); // cyclic
+
+    fs::write(dir.join("rates.res"), "rate = 0.25\n").unwrap();
+    let mut cxt = ws.context(None, "@savings.total");
+    assert_eq!(Some(unit::Value::raw(500.0)), cxt.reference("savings.total")); // cached
+    ws.reload().unwrap();
+    cxt = ws.context(None, "@savings.total");
+    assert_eq!(Some(unit::Value::raw(250.0)), cxt.reference("savings.total"));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+}
@@ -0,0 +1,11 @@
+# Income [style: positive]
+salary = 3000 EUR
+bonus = 500 EUR
+
+# Costs [style: negative]
+rent = 1200 EUR
+food = 85 EUR * 4.3
+# [style: none]
+
+left = salary + bonus - rent - food
+left / 30 per day
@@ -0,0 +1,5 @@
+If we drive 340 km at 6.5 l per 100 km, the trip needs 340 * 6.5 / 100 litres of fuel, which at 1.85 EUR a litre comes to 340 * 6.5 / 100 * 1.85 EUR.
+
+Short lines stay put: 2 + 2, and (12 tbsp + 1 cup) in tsp.
+tax = 0.0875
+a purchase of 100 USD costs 100 USD * (1 + tax) with tax